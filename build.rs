@@ -0,0 +1,19 @@
+use std::process::Command;
+
+fn main() {
+    embed_resource::compile("pauza.rc", embed_resource::NONE);
+
+    // The commit goes into the About window and crash reports, to tell builds apart.
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=PAUZA_COMMIT={}", commit);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=pauza.rc");
+    println!("cargo:rerun-if-changed=pauza.ico");
+}
@@ -0,0 +1,131 @@
+"use strict";
+
+const STATUS_INTERVAL = 5000;
+const CHARTS_INTERVAL = 60000;
+const DAY = 24 * 60 * 60;
+
+const token = new URLSearchParams(location.search).get("token") || "";
+const labels = document.body.dataset;
+let status = null;
+let fetched = 0;
+
+async function get(path) {
+  const response = await fetch(path, { headers: { Authorization: "Bearer " + token } });
+  if (!response.ok) {
+    throw new Error(response.status);
+  }
+  return response.json();
+}
+
+function reachable(ok) {
+  document.getElementById("error").hidden = ok;
+}
+
+function clock(seconds) {
+  seconds = Math.max(0, Math.round(seconds));
+  const minutes = Math.floor(seconds / 60);
+  return minutes + ":" + String(seconds % 60).padStart(2, "0");
+}
+
+function element(tag, className, style) {
+  const node = document.createElement(tag);
+  if (className) {
+    node.className = className;
+  }
+  Object.assign(node.style, style || {});
+  return node;
+}
+
+function showStatus() {
+  if (!status) {
+    return;
+  }
+  const names = { working: labels.working, paused: labels.paused, on_break: labels.onBreak };
+  document.getElementById("state").textContent = names[status.state] || status.state;
+  // Counts down between fetches while working, where the time moves on its own.
+  const passed = status.state === "working" ? (Date.now() - fetched) / 1000 : 0;
+  document.getElementById("countdown").textContent =
+    status.state === "on_break" ? status.text : clock(status.remaining - passed);
+}
+
+async function refreshStatus() {
+  try {
+    status = (await get("status")).status;
+    fetched = Date.now();
+    reachable(true);
+  } catch (e) {
+    reachable(false);
+  }
+  showStatus();
+}
+
+function drawTimeline(today) {
+  const timeline = document.getElementById("timeline");
+  timeline.replaceChildren();
+  for (const interval of today.intervals) {
+    if (interval.kind !== "work" && interval.kind !== "break") {
+      continue;
+    }
+    const left = (interval.start - today.since) / DAY * 100;
+    const width = interval.duration / DAY * 100;
+    timeline.appendChild(element("div", interval.kind, { left: left + "%", width: Math.max(width, 0.1) + "%" }));
+  }
+}
+
+function drawDaily(summaries) {
+  const daily = document.getElementById("daily");
+  daily.replaceChildren();
+  if (summaries.length === 0) {
+    daily.textContent = labels.empty;
+    return;
+  }
+  const max = Math.max(1, ...summaries.map((day) => Math.max(day.work, day.rest)));
+  for (const summary of summaries) {
+    const day = element("div", "day");
+    const bars = element("div", "bars");
+    bars.appendChild(element("div", "work", { height: summary.work / max * 100 + "%" }));
+    bars.appendChild(element("div", "break", { height: summary.rest / max * 100 + "%" }));
+    const label = element("div");
+    label.textContent = summary.label;
+    day.append(bars, label);
+    daily.appendChild(day);
+  }
+}
+
+function drawHeatmap(minutes) {
+  const table = document.getElementById("heatmap");
+  table.replaceChildren();
+  const days = labels.days.split(/\s+/);
+  minutes.forEach((hours, i) => {
+    const row = element("tr");
+    const label = element("td");
+    label.textContent = days[i] || "";
+    row.appendChild(label);
+    hours.forEach((worked, hour) => {
+      const cell = element("td", "hour", {
+        background: "color-mix(in srgb, var(--accent) " + Math.min(100, worked / 60 * 100) + "%, var(--track))"
+      });
+      cell.title = hour + ":00 " + worked + " min";
+      row.appendChild(cell);
+    });
+    table.appendChild(row);
+  });
+}
+
+async function refreshCharts() {
+  try {
+    const [today, history, heatmap] = await Promise.all([get("today"), get("history?period=day&days=7"), get("heatmap")]);
+    drawTimeline(today);
+    drawDaily(history.summaries);
+    drawHeatmap(heatmap.minutes);
+    reachable(true);
+  } catch (e) {
+    reachable(false);
+  }
+}
+
+refreshStatus();
+refreshCharts();
+setInterval(refreshStatus, STATUS_INTERVAL);
+setInterval(refreshCharts, CHARTS_INTERVAL);
+setInterval(showStatus, 1000);
@@ -0,0 +1,132 @@
+tip-remote = Pauza (Remotesitzung)
+tip-private = Privatmodus: nichts wird aufgezeichnet
+tip-degraded = Pauza (Leerlauferkennung eingeschränkt, nur die Maus wird beobachtet)
+
+state-working = Arbeit
+state-paused = Angehalten
+state-on-break = Pause
+state-with-reason = { $state } ({ $reason })
+reason-low-power = Energiesparmodus
+reason-degraded = Leerlauferkennung eingeschränkt
+reason-game = Spiel
+reason-fullscreen = Vollbild-App
+
+starting = Wird gestartet...
+break-in = Pause in { $time }
+break-overdue = Pause überfällig seit { $time }
+time-worked = Gearbeitet: { $time }
+breaks-today = Pausen heute: { $count }
+progress-name = Arbeitszeit bis zur nächsten Pause
+duration-hours = { $hours } Std. { $minutes } Min.
+duration-minutes = { $minutes } Min.
+duration-seconds = { $seconds } Sek.
+
+menu-toggle = Anzeigen/Ausblenden
+menu-statistics = Statistik
+menu-dashboard = Dashboard im Browser
+menu-mini-timer = Mini-Timer
+menu-start-break = Ich mache jetzt Pause
+menu-undo-reset = Zurücksetzen rückgängig machen
+menu-log-folder = Protokollordner öffnen
+menu-check-updates = Nach Updates suchen
+menu-autostart = Bei der Anmeldung starten
+menu-privacy = Privatmodus
+menu-about = Über Pauza
+menu-quit = Beenden
+
+break-title = Zeit für eine Pause!
+break-message = Pausenzeit!
+break-acknowledge = Klicke hier, wenn du deine Pause beginnst.
+reset-title = Zurück an die Arbeit
+reset-message = Zurück an die Arbeit! Hier klicken, um das Zurücksetzen rückgängig zu machen, falls du nicht weg warst.
+budget-title = Zeitbudget erreicht
+budget-message = { $category }: heute { $time }
+daily-limit-title = Tageslimit
+daily-limit-approaching = Noch { $time } Computerzeit für heute
+daily-limit-reached = Das heutige Limit von { $time } am Computer ist erreicht
+daily-limit-over = { $time } über dem heutigen Limit, Zeit für Feierabend
+badge-title = Neues Abzeichen, gut gemacht!
+crash-message = Pauza ist beim letzten Mal abgestürzt. Den Absturzbericht öffnen?
+config-problems = Einige Einstellungen in { $path } sind ungültig, stattdessen gelten ihre Standardwerte:
+config-open = Die Konfigurationsdatei öffnen?
+autostart-failed = Der Start bei der Anmeldung konnte nicht geändert werden: { $error }
+
+update-title = Pauza { $version } ist verfügbar
+update-message = Hier klicken, um die Neuerungen zu sehen.
+update-download = Pauza { $version } ist verfügbar. Jetzt herunterladen?
+update-none = Du hast die neueste Version von Pauza.
+update-failed = Die Suche nach Updates ist fehlgeschlagen: { $error }
+
+about-title = Über Pauza
+about-copy = Diagnose kopieren
+about-open-folder = Datenordner öffnen
+about-export-settings = Einstellungen exportieren...
+about-import-settings = Einstellungen importieren...
+about-export-settings-title = Einstellungen exportieren
+about-import-settings-title = Einstellungen importieren
+about-export-settings-done = Einstellungen gespeichert in { $path }
+about-import-settings-done = Einstellungen importiert. Starte Pauza neu, damit alle gelten.
+about-settings-failed = Fehlgeschlagen: { $error }
+about-back-up = Jetzt sichern
+about-restore = Sicherung wiederherstellen...
+about-back-up-title = Sichern
+about-restore-title = Sicherung wiederherstellen
+about-back-up-done = Einstellungen und Verlauf wurden in { $path } gesichert
+about-restore-confirm = Einstellungen und Verlauf durch die Sicherung in { $path } ersetzen? Der jetzige Stand wird im Datenordner aufbewahrt.
+about-restore-done = Die Sicherung wird beim nächsten Start von Pauza eingespielt. Starte Pauza neu, um abzuschließen.
+about-delete-data = Alle meine Daten löschen...
+about-delete-data-title = Alle meine Daten löschen
+about-delete-data-confirm = Den ganzen Verlauf löschen: Pausen, Tagessummen, App-Nutzung und Eingaben? Seine Sicherungen und die per Synchronisierung geteilte Kopie werden mitgelöscht, und Timer und Tageszählung beginnen von vorn. Die Einstellungen bleiben. Das lässt sich nicht rückgängig machen.
+about-delete-data-done = Der Verlauf ist gelöscht.
+
+report-title = Deine Woche mit Pauza
+report-message =
+    Aktiv: { $active }
+    Pausen gemacht: { $breaks } von { $prompts } Erinnerungen
+    Durchschnittliche Zeit zwischen Pausen: { $average }
+    Zeit weg vom Bildschirm in den Pausen: { $quality }
+report-compliance = Pausen innerhalb von 10 Minuten nach einer Erinnerung: { $percent }
+
+stats-title = Pauza-Statistik
+stats-export = Exportieren...
+stats-export-title = Verlauf exportieren
+stats-export-done = { $count } Abschnitte nach { $path } exportiert
+stats-export-failed = Export fehlgeschlagen: { $error }
+stats-total = Letzte { $days } Tage: { $work } gearbeitet, { $rest } Pause
+stats-compliance = Pausen innerhalb von 10 Minuten nach einer Erinnerung: { $percent }, durchschnittliche Verzögerung { $delay }
+stats-longest = Längste Zeit ohne Pause: { $time }
+stats-quality = Zeit weg vom Bildschirm in den Pausen: { $percent }
+stats-streak = Pausenserie: { $days } Tage (Bestwert { $best })
+stats-badges = Abzeichen: { $badges }
+stats-apps = Apps heute: { $apps }
+stats-categories = Kategorien heute: { $categories }
+stats-no-badges = noch keine
+chart-daily = Täglich (Arbeit / Pause, Pausen pünktlich)
+chart-weekly = Wöchentlich (Arbeit / Pause, Pausen pünktlich)
+chart-empty = Noch kein Verlauf
+chart-row = { $label }: gearbeitet { $work }, Pause { $rest }, Pausen pünktlich { $percent }
+chart-intensity = Tastendrücke und Klicks pro Minute heute
+chart-intensity-row = { $hour }:00 Uhr: { $rate } pro Minute
+chart-heatmap = Gearbeitete Minuten pro Stunde in den letzten 7 Tagen
+chart-heatmap-days = Mo Di Mi Do Fr Sa So
+chart-heatmap-row = { $day }: { $hours }
+chart-heatmap-hour = { $hour }:00 Uhr { $time }
+
+badge-first-break = Erste Pause
+badge-first-break-description = Deine erste Pause gemacht
+badge-hourly-day = Stündlich
+badge-hourly-day-description = Einen Tag lang nie länger als eine Stunde ohne Pause gearbeitet
+badge-hourly-week = Stündliche Woche
+badge-hourly-week-description = Fünf Tage in Folge nie länger als eine Stunde ohne Pause gearbeitet
+badge-streak-3 = Drei in Folge
+badge-streak-3-description = Drei Tage in Folge jeder Pausenerinnerung gefolgt
+badge-streak-7 = Ganze Woche
+badge-streak-7-description = Sieben Tage in Folge jeder Pausenerinnerung gefolgt
+badge-streak-30 = Gewohnheit
+badge-streak-30-description = Dreißig Tage in Folge jeder Pausenerinnerung gefolgt
+
+dashboard-title = Pauza
+dashboard-today = Heute
+dashboard-work = Arbeit
+dashboard-break = Pause
+dashboard-unreachable = Pauza ist nicht erreichbar. Läuft es noch?
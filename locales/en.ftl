@@ -0,0 +1,135 @@
+# Messages shown by pauza, in Fluent syntax: `key = text`, with `{ $name }` filled in
+# and indented lines continuing the text on a new line.
+
+tip-remote = Pauza (remote session)
+tip-private = Privacy mode: nothing is recorded
+tip-degraded = Pauza (idle detection degraded, watching the mouse only)
+
+state-working = Working
+state-paused = Paused
+state-on-break = On break
+state-with-reason = { $state } ({ $reason })
+reason-low-power = low power
+reason-degraded = idle detection degraded
+reason-game = game
+reason-fullscreen = fullscreen app
+
+starting = Starting...
+break-in = Break in { $time }
+break-overdue = Break overdue by { $time }
+time-worked = Worked { $time }
+breaks-today = Breaks today: { $count }
+progress-name = Work time toward the next break
+duration-hours = { $hours } h { $minutes } m
+duration-minutes = { $minutes } m
+duration-seconds = { $seconds } s
+
+menu-toggle = Show/Hide
+menu-statistics = Statistics
+menu-dashboard = Dashboard in browser
+menu-mini-timer = Mini timer
+menu-start-break = Starting my break
+menu-undo-reset = Undo reset
+menu-log-folder = Open log folder
+menu-check-updates = Check for updates
+menu-autostart = Start at login
+menu-privacy = Privacy mode
+menu-about = About Pauza
+menu-quit = Quit
+
+break-title = Time to take a break!
+break-message = Break Time!
+break-acknowledge = Click here when you start your break.
+reset-title = Get back to work
+reset-message = Back to work! Click here to undo the reset if you weren't away.
+budget-title = Time budget reached
+budget-message = { $category }: { $time } today
+daily-limit-title = Daily limit
+daily-limit-approaching = { $time } left of today's computer time
+daily-limit-reached = You've reached today's limit of { $time } at the computer
+daily-limit-over = { $time } past today's limit, time to stop for the day
+badge-title = New badge, well done!
+crash-message = Pauza crashed the last time it ran. Open the crash report?
+config-problems = Some settings in { $path } couldn't be used, so their defaults apply instead:
+config-open = Open the config file?
+autostart-failed = Couldn't change starting at login: { $error }
+
+update-title = Pauza { $version } is available
+update-message = Click here to see what's new.
+update-download = Pauza { $version } is available. Download it now?
+update-none = You have the latest version of Pauza.
+update-failed = Couldn't check for updates: { $error }
+
+about-title = About Pauza
+about-copy = Copy diagnostics
+about-open-folder = Open data folder
+about-export-settings = Export settings...
+about-import-settings = Import settings...
+about-export-settings-title = Export settings
+about-import-settings-title = Import settings
+about-export-settings-done = Saved the settings to { $path }
+about-import-settings-done = Imported the settings. Restart Pauza to use all of them.
+about-settings-failed = Failed: { $error }
+about-back-up = Back up now
+about-restore = Restore backup...
+about-back-up-title = Back up
+about-restore-title = Restore backup
+about-back-up-done = Backed up the settings and the history to { $path }
+about-restore-confirm = Replace the settings and the history with the backup in { $path }? What they are now is kept aside in the data folder.
+about-restore-done = The backup is put in place the next time Pauza starts. Restart Pauza to finish.
+about-delete-data = Delete all my data...
+about-delete-data-title = Delete all my data
+about-delete-data-confirm = Delete the whole history: breaks, daily totals, app usage and input counts? Its backups and the copy shared through sync go too, and the timer and today's count start over. The settings are kept. This can't be undone.
+about-delete-data-done = Deleted the history.
+
+report-title = Your week with Pauza
+report-message =
+    Active: { $active }
+    Breaks taken: { $breaks } of { $prompts } reminders
+    Average time between breaks: { $average }
+    Time away from the screen during breaks: { $quality }
+report-compliance = Breaks within 10 minutes of a reminder: { $percent }
+
+stats-title = Pauza statistics
+stats-export = Export...
+stats-export-title = Export history
+stats-export-done = Exported { $count } intervals to { $path }
+stats-export-failed = Export failed: { $error }
+stats-total = Last { $days } days: worked { $work }, on break { $rest }
+stats-compliance = Breaks within 10 minutes of a reminder: { $percent }, average delay { $delay }
+stats-longest = Longest stretch without a break: { $time }
+stats-quality = Time away from the screen during breaks: { $percent }
+stats-streak = Break streak: { $days } days (best { $best })
+stats-badges = Badges: { $badges }
+stats-apps = Apps today: { $apps }
+stats-categories = Categories today: { $categories }
+stats-no-badges = none yet
+chart-daily = Daily (work / break, breaks on time)
+chart-weekly = Weekly (work / break, breaks on time)
+chart-empty = No history yet
+chart-row = { $label }: worked { $work }, on break { $rest }, breaks on time { $percent }
+chart-intensity = Key presses and clicks per minute today
+chart-intensity-row = { $hour }:00: { $rate } per minute
+chart-heatmap = Minutes worked by hour over the last 7 days
+chart-heatmap-days = Mon Tue Wed Thu Fri Sat Sun
+chart-heatmap-row = { $day }: { $hours }
+chart-heatmap-hour = { $hour }:00 { $time }
+
+badge-first-break = First break
+badge-first-break-description = Took your first break
+badge-hourly-day = Hourly
+badge-hourly-day-description = Never worked more than an hour without a break for a day
+badge-hourly-week = Hourly week
+badge-hourly-week-description = Never worked more than an hour without a break for five days running
+badge-streak-3 = Three in a row
+badge-streak-3-description = Followed every break reminder three days running
+badge-streak-7 = Full week
+badge-streak-7-description = Followed every break reminder seven days running
+badge-streak-30 = Habit formed
+badge-streak-30-description = Followed every break reminder thirty days running
+
+dashboard-title = Pauza
+dashboard-today = Today
+dashboard-work = Work
+dashboard-break = Break
+dashboard-unreachable = Pauza is not reachable. Is it still running?
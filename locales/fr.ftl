@@ -0,0 +1,132 @@
+tip-remote = Pauza (session à distance)
+tip-private = Mode confidentialité : rien n'est enregistré
+tip-degraded = Pauza (détection d'inactivité dégradée, seule la souris est suivie)
+
+state-working = Au travail
+state-paused = Suspendu
+state-on-break = En pause
+state-with-reason = { $state } ({ $reason })
+reason-low-power = économie d'énergie
+reason-degraded = détection d'inactivité dégradée
+reason-game = jeu
+reason-fullscreen = application plein écran
+
+starting = Démarrage...
+break-in = Pause dans { $time }
+break-overdue = Pause en retard de { $time }
+time-worked = Travail : { $time }
+breaks-today = Pauses aujourd'hui : { $count }
+progress-name = Temps de travail avant la prochaine pause
+duration-hours = { $hours } h { $minutes } min
+duration-minutes = { $minutes } min
+duration-seconds = { $seconds } s
+
+menu-toggle = Afficher/Masquer
+menu-statistics = Statistiques
+menu-dashboard = Tableau de bord dans le navigateur
+menu-mini-timer = Mini minuteur
+menu-start-break = Je commence ma pause
+menu-undo-reset = Annuler la remise à zéro
+menu-log-folder = Ouvrir le dossier des journaux
+menu-check-updates = Rechercher des mises à jour
+menu-autostart = Lancer à l'ouverture de session
+menu-privacy = Mode confidentialité
+menu-about = À propos de Pauza
+menu-quit = Quitter
+
+break-title = C'est l'heure d'une pause !
+break-message = Pause !
+break-acknowledge = Cliquez ici lorsque vous commencez votre pause.
+reset-title = Au travail
+reset-message = Au travail ! Cliquez ici pour annuler la remise à zéro si vous n'étiez pas absent.
+budget-title = Budget de temps atteint
+budget-message = { $category } : { $time } aujourd'hui
+daily-limit-title = Limite quotidienne
+daily-limit-approaching = Il reste { $time } de temps d'écran pour aujourd'hui
+daily-limit-reached = La limite du jour de { $time } devant l'ordinateur est atteinte
+daily-limit-over = { $time } au-delà de la limite du jour, il est temps d'arrêter
+badge-title = Nouveau badge, bravo !
+crash-message = Pauza a planté lors de sa dernière exécution. Ouvrir le rapport de plantage ?
+config-problems = Certains réglages de { $path } sont invalides, leurs valeurs par défaut s'appliquent à la place :
+config-open = Ouvrir le fichier de configuration ?
+autostart-failed = Impossible de modifier le lancement à l'ouverture de session : { $error }
+
+update-title = Pauza { $version } est disponible
+update-message = Cliquez ici pour voir les nouveautés.
+update-download = Pauza { $version } est disponible. Le télécharger maintenant ?
+update-none = Vous avez la dernière version de Pauza.
+update-failed = Impossible de rechercher des mises à jour : { $error }
+
+about-title = À propos de Pauza
+about-copy = Copier les diagnostics
+about-open-folder = Ouvrir le dossier des données
+about-export-settings = Exporter les réglages...
+about-import-settings = Importer les réglages...
+about-export-settings-title = Exporter les réglages
+about-import-settings-title = Importer les réglages
+about-export-settings-done = Réglages enregistrés dans { $path }
+about-import-settings-done = Réglages importés. Redémarrez Pauza pour tous les appliquer.
+about-settings-failed = Échec : { $error }
+about-back-up = Sauvegarder
+about-restore = Restaurer une sauvegarde...
+about-back-up-title = Sauvegarder
+about-restore-title = Restaurer une sauvegarde
+about-back-up-done = Réglages et historique sauvegardés dans { $path }
+about-restore-confirm = Remplacer les réglages et l'historique par la sauvegarde de { $path } ? L'état actuel est conservé dans le dossier de données.
+about-restore-done = La sauvegarde sera mise en place au prochain démarrage de Pauza. Redémarrez Pauza pour terminer.
+about-delete-data = Supprimer toutes mes données...
+about-delete-data-title = Supprimer toutes mes données
+about-delete-data-confirm = Supprimer tout l'historique : pauses, totaux quotidiens, utilisation des applications et saisies ? Ses sauvegardes et la copie partagée par la synchronisation sont aussi supprimées, et le minuteur et le décompte du jour repartent de zéro. Les réglages sont conservés. Cette action est irréversible.
+about-delete-data-done = L'historique a été supprimé.
+
+report-title = Votre semaine avec Pauza
+report-message =
+    Actif : { $active }
+    Pauses prises : { $breaks } sur { $prompts } rappels
+    Temps moyen entre les pauses : { $average }
+    Temps loin de l'écran pendant les pauses : { $quality }
+report-compliance = Pauses dans les 10 minutes après un rappel : { $percent }
+
+stats-title = Statistiques de Pauza
+stats-export = Exporter...
+stats-export-title = Exporter l'historique
+stats-export-done = { $count } intervalles exportés vers { $path }
+stats-export-failed = Échec de l'export : { $error }
+stats-total = { $days } derniers jours : { $work } de travail, { $rest } de pause
+stats-compliance = Pauses dans les 10 minutes après un rappel : { $percent }, retard moyen { $delay }
+stats-longest = Plus longue période sans pause : { $time }
+stats-quality = Temps loin de l'écran pendant les pauses : { $percent }
+stats-streak = Série de pauses : { $days } jours (record { $best })
+stats-badges = Badges : { $badges }
+stats-apps = Applications aujourd'hui : { $apps }
+stats-categories = Catégories aujourd'hui : { $categories }
+stats-no-badges = aucun pour l'instant
+chart-daily = Par jour (travail / pause, pauses à l'heure)
+chart-weekly = Par semaine (travail / pause, pauses à l'heure)
+chart-empty = Pas encore d'historique
+chart-row = { $label } : travail { $work }, pause { $rest }, pauses à l'heure { $percent }
+chart-intensity = Frappes et clics par minute aujourd'hui
+chart-intensity-row = { $hour } h : { $rate } par minute
+chart-heatmap = Minutes travaillées par heure sur les 7 derniers jours
+chart-heatmap-days = lun mar mer jeu ven sam dim
+chart-heatmap-row = { $day } : { $hours }
+chart-heatmap-hour = { $hour } h { $time }
+
+badge-first-break = Première pause
+badge-first-break-description = Vous avez pris votre première pause
+badge-hourly-day = Toutes les heures
+badge-hourly-day-description = Jamais plus d'une heure de travail sans pause pendant une journée
+badge-hourly-week = Semaine à l'heure
+badge-hourly-week-description = Jamais plus d'une heure de travail sans pause cinq jours de suite
+badge-streak-3 = Trois d'affilée
+badge-streak-3-description = Tous les rappels de pause suivis trois jours de suite
+badge-streak-7 = Semaine complète
+badge-streak-7-description = Tous les rappels de pause suivis sept jours de suite
+badge-streak-30 = Habitude prise
+badge-streak-30-description = Tous les rappels de pause suivis trente jours de suite
+
+dashboard-title = Pauza
+dashboard-today = Aujourd'hui
+dashboard-work = Travail
+dashboard-break = Pause
+dashboard-unreachable = Pauza est injoignable. Est-il toujours lancé ?
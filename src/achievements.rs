@@ -0,0 +1,111 @@
+use std::time::{Duration, UNIX_EPOCH};
+use crate::history::{History, Period, Summary};
+use crate::i18n::tr;
+
+/// The longest a work stretch may run for a day to count as hourly.
+pub const HOURLY_LIMIT: Duration = Duration::from_secs(60 * 60);
+/// So a short day doesn't count as hourly just for being short.
+const HOURLY_MIN_WORK: Duration = Duration::from_secs(3 * 60 * 60);
+const HOURLY_WEEK_DAYS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Badge {
+    FirstBreak,
+    HourlyDay,
+    HourlyWeek,
+    Streak3,
+    Streak7,
+    Streak30
+}
+
+impl Badge {
+    pub const ALL: [Badge; 6] = [
+        Badge::FirstBreak,
+        Badge::HourlyDay,
+        Badge::HourlyWeek,
+        Badge::Streak3,
+        Badge::Streak7,
+        Badge::Streak30,
+    ];
+
+    /// Stable name, as stored in the history's meta table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Badge::FirstBreak => "first_break",
+            Badge::HourlyDay => "hourly_day",
+            Badge::HourlyWeek => "hourly_week",
+            Badge::Streak3 => "streak_3",
+            Badge::Streak7 => "streak_7",
+            Badge::Streak30 => "streak_30"
+        }
+    }
+
+    fn message_key(&self) -> String {
+        format!("badge-{}", self.key().replace('_', "-"))
+    }
+
+    pub fn name(&self) -> String {
+        tr(&self.message_key())
+    }
+
+    pub fn description(&self) -> String {
+        tr(&format!("{}-description", self.message_key()))
+    }
+}
+
+/// Streaks and badges earned over the whole history. Days without any work are skipped,
+/// so weekends don't break a streak.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Achievements {
+    /// Work days in a row, up to the latest, on which every break reminder was followed.
+    pub streak: u32,
+    pub best_streak: u32,
+    pub badges: Vec<Badge>,
+}
+
+fn is_work_day(day: &Summary) -> bool {
+    day.stretches > 0 || day.work > Duration::from_secs(0)
+}
+
+fn is_compliant(day: &Summary) -> bool {
+    day.followed == day.prompts
+}
+
+fn is_hourly(day: &Summary) -> bool {
+    day.longest <= HOURLY_LIMIT && day.work >= HOURLY_MIN_WORK
+}
+
+impl Achievements {
+    /// Applies the streak and badge rules to per-day summaries in date order.
+    pub fn from_days(days: &[Summary]) -> Achievements {
+        let mut streak = 0;
+        let mut best_streak = 0;
+        let mut hourly_run = 0;
+        let mut best_hourly_run = 0;
+        let mut breaks = 0;
+        for day in days.iter().filter(|day| is_work_day(day)) {
+            streak = if is_compliant(day) { streak + 1 } else { 0 };
+            best_streak = best_streak.max(streak);
+            hourly_run = if is_hourly(day) { hourly_run + 1 } else { 0 };
+            best_hourly_run = best_hourly_run.max(hourly_run);
+            breaks += day.breaks;
+        }
+
+        let badges = Badge::ALL.iter()
+            .copied()
+            .filter(|badge| match badge {
+                Badge::FirstBreak => breaks > 0,
+                Badge::HourlyDay => best_hourly_run >= 1,
+                Badge::HourlyWeek => best_hourly_run >= HOURLY_WEEK_DAYS,
+                Badge::Streak3 => best_streak >= 3,
+                Badge::Streak7 => best_streak >= 7,
+                Badge::Streak30 => best_streak >= 30
+            })
+            .collect();
+        Achievements { streak, best_streak, badges }
+    }
+
+    pub fn load(history: &History) -> rusqlite::Result<Achievements> {
+        Ok(Achievements::from_days(&history.summaries(Period::Date, UNIX_EPOCH)?))
+    }
+}
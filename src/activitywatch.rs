@@ -0,0 +1,92 @@
+use crossbeam::channel::Receiver;
+use serde_json::json;
+use std::env;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::ActivityWatchConfig;
+use crate::format;
+use crate::{Event, State};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Heartbeats with the same state this close together are merged into one event.
+const PULSE_TIME: Duration = Duration::from_secs(60);
+const BUCKET_TYPE: &str = "pauza.state";
+
+/// A small client for the ActivityWatch REST API.
+struct Client {
+    url: String,
+    bucket: String,
+    hostname: String,
+    created: bool,
+}
+
+impl Client {
+    fn create_bucket(&mut self) -> Result<(), String> {
+        let result = ureq::post(&format!("{}/api/0/buckets/{}", self.url, self.bucket))
+            .timeout(TIMEOUT)
+            .send_json(json!({ "client": "pauza", "type": BUCKET_TYPE, "hostname": self.hostname }));
+        match result {
+            // An existing bucket is answered with 304.
+            Ok(_) | Err(ureq::Error::Status(304, _)) => {
+                self.created = true;
+                Ok(())
+            },
+            Err(e) => Err(e.to_string())
+        }
+    }
+
+    fn heartbeat(&mut self, state: State) -> Result<(), String> {
+        if !self.created {
+            self.create_bucket()?;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        ureq::post(&format!("{}/api/0/buckets/{}/heartbeat", self.url, self.bucket))
+            .timeout(TIMEOUT)
+            .query("pulsetime", &PULSE_TIME.as_secs().to_string())
+            .send_json(json!({
+                "timestamp": format::rfc3339(now),
+                "duration": 0,
+                "data": { "state": state.as_str() },
+            }))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Sends the timer state to ActivityWatch as heartbeats, so work and breaks show up as
+/// events in a `pauza_<hostname>` bucket.
+pub fn send_heartbeats(config: ActivityWatchConfig, r: Receiver<Event>) {
+    let hostname = env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string());
+    let mut client = Client {
+        url: config.url.trim_end_matches('/').to_string(),
+        bucket: format!("pauza_{}", hostname),
+        hostname,
+        created: false,
+    };
+    let mut state = State::Working;
+    let mut last_heartbeat: Option<Instant> = None;
+    let mut failing = false;
+    for event in r.iter() {
+        let due = match event {
+            Event::UpdateState(new_state) => {
+                state = new_state;
+                true
+            },
+            Event::UpdateTime(_) => last_heartbeat.is_none_or(|sent| sent.elapsed() >= HEARTBEAT_INTERVAL),
+            _ => false
+        };
+        if !due {
+            continue;
+        }
+        last_heartbeat = Some(Instant::now());
+        match client.heartbeat(state) {
+            Ok(()) => failing = false,
+            Err(e) => {
+                if !failing {
+                    log::warn!("Failed to reach ActivityWatch: {}", e);
+                }
+                failing = true;
+            }
+        }
+    }
+}
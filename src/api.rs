@@ -0,0 +1,310 @@
+use crossbeam::channel::{Receiver, Sender};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Method, Response, Server};
+use crate::auth;
+use crate::civil::{self, Civil};
+use crate::config::ApiConfig;
+use crate::export::{self, Format};
+use crate::format;
+use crate::history::{History, Period, Summary};
+use crate::i18n::tr;
+use crate::metrics::Metrics;
+use crate::schema::{self, CommandMessage, StateMessage};
+use crate::status::Status;
+use crate::{Event, Request};
+
+const DEFAULT_HISTORY_DAYS: u64 = 7;
+/// About ten years, more history than anyone keeps.
+const MAX_HISTORY_DAYS: u64 = 3660;
+const MAX_BODY: u64 = 4096;
+const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// The dashboard's page, served without the token, which it reads from its own address.
+const DASHBOARD: &str = include_str!("../dashboard/index.html");
+const DASHBOARD_FILES: [(&str, &str, &str); 2] = [
+    ("/dashboard.js", "application/javascript; charset=utf-8", include_str!("../dashboard/dashboard.js")),
+    ("/dashboard.css", "text/css; charset=utf-8", include_str!("../dashboard/dashboard.css")),
+];
+
+type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+fn json_response(code: u16, body: String) -> JsonResponse {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(body).with_status_code(code).with_header(content_type)
+}
+
+fn error(code: u16, message: &str) -> JsonResponse {
+    json_response(code, json!({ "ok": false, "error": message }).to_string())
+}
+
+fn ok() -> JsonResponse {
+    json_response(200, json!({ "ok": true }).to_string())
+}
+
+/// Splits `/path?a=1&b=2` into the path and its query parameters. Values used here never
+/// need percent decoding.
+fn split_url(url: &str) -> (&str, HashMap<&str, &str>) {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (url, "")
+    };
+    let params = query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    (path, params)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The dashboard page, each `{{key}}` in it translated.
+fn dashboard_page() -> String {
+    let mut page = String::new();
+    let mut rest = DASHBOARD;
+    while let Some((before, after)) = rest.split_once("{{") {
+        let (key, after) = after.split_once("}}").unwrap_or((after, ""));
+        page += before;
+        page += &escape(&tr(key));
+        rest = after;
+    }
+    page + rest
+}
+
+fn dashboard(path: &str) -> Option<JsonResponse> {
+    let (content_type, body) = match path {
+        "/" => ("text/html; charset=utf-8", dashboard_page()),
+        _ => DASHBOARD_FILES.iter()
+            .find(|(file, _, _)| *file == path)
+            .map(|(_, content_type, body)| (*content_type, body.to_string()))?
+    };
+    let content_type = Header::from_bytes("Content-Type", content_type).unwrap();
+    Some(Response::from_string(body).with_header(content_type))
+}
+
+fn is_authorized(request: &tiny_http::Request, params: &HashMap<&str, &str>, token: &str) -> bool {
+    let header = request.headers().iter()
+        .any(|header| header.field.equiv("Authorization") && auth::is_bearer(header.value.as_str(), token));
+    header || params.get("token").is_some_and(|given| auth::is_token(given, token))
+}
+
+fn summary_json(summary: &Summary) -> Value {
+    json!({
+        "label": summary.label,
+        "work": summary.work.as_secs(),
+        "rest": summary.rest.as_secs(),
+        "longest": summary.longest.as_secs(),
+        "breaks": summary.breaks,
+        "prompts": summary.prompts,
+        "compliance": summary.compliance(),
+        "average_overdue": summary.average_overdue().map(|d| d.as_secs()),
+    })
+}
+
+fn history(params: &HashMap<&str, &str>) -> JsonResponse {
+    let period = match params.get("period").copied().unwrap_or("day") {
+        "day" => Period::Day,
+        "week" => Period::Week,
+        other => return error(400, &format!("unknown period: {}", other))
+    };
+    let days = match params.get("days").map(|days| days.parse::<u64>()) {
+        Some(Ok(days)) if days <= MAX_HISTORY_DAYS => days,
+        Some(Ok(_)) => return error(400, &format!("days must be at most {}", MAX_HISTORY_DAYS)),
+        Some(Err(_)) => return error(400, "invalid days"),
+        None => DEFAULT_HISTORY_DAYS
+    };
+    let since = match days.checked_mul(24 * 60 * 60).and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs))) {
+        Some(since) => since,
+        None => return error(400, "invalid days")
+    };
+    match History::open().and_then(|history| history.summaries(period, since)) {
+        Ok(summaries) => {
+            let summaries: Vec<_> = summaries.iter().map(summary_json).collect();
+            json_response(200, json!({ "ok": true, "summaries": summaries }).to_string())
+        },
+        Err(e) => error(500, &e.to_string())
+    }
+}
+
+/// Today's intervals, from local midnight.
+fn today() -> JsonResponse {
+    let now = civil::now();
+    let midnight = civil::to_system_time(Civil::new(now.year, now.month, now.day, 0, 0, 0));
+    let secs = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+    match History::open().and_then(|history| history.intervals_since(midnight)) {
+        Ok(intervals) => {
+            let intervals: Vec<_> = intervals.iter()
+                .map(|interval| json!({
+                    "kind": interval.kind.as_str(),
+                    "start": secs(interval.start),
+                    "duration": interval.duration.as_secs(),
+                }))
+                .collect();
+            json_response(200, json!({ "ok": true, "since": secs(midnight), "intervals": intervals }).to_string())
+        },
+        Err(e) => error(500, &e.to_string())
+    }
+}
+
+fn heatmap() -> JsonResponse {
+    match History::open().and_then(|history| history.active_minutes(SystemTime::now() - WEEK)) {
+        Ok(minutes) => json_response(200, json!({ "ok": true, "minutes": minutes }).to_string()),
+        Err(e) => error(500, &e.to_string())
+    }
+}
+
+fn records(params: &HashMap<&str, &str>) -> JsonResponse {
+    let mut body = Vec::new();
+    let result = History::open()
+        .map_err(export::ExportError::from)
+        .and_then(|history| export::export(&history, Format::Json, params.get("from").copied(), &mut body));
+    match result {
+        Ok(_) => json_response(200, String::from_utf8_lossy(&body).into_owned()),
+        Err(e) => error(500, &e.to_string())
+    }
+}
+
+fn control(params: &HashMap<&str, &str>, path: &str, requests: &Sender<Request>) -> JsonResponse {
+    let request = match path {
+        "/pause" => match params.get("duration").map(|text| format::parse_pause(text)) {
+            Some(Ok(duration)) => Request::PauseTimer(Some(duration)),
+            Some(Err(e)) => return error(400, &e),
+            None => Request::PauseTimer(None)
+        },
+        "/resume" => Request::ResumeTimer,
+        "/break" => Request::BreakNow,
+        "/start-break" => Request::AcknowledgeBreak,
+        "/snooze" => Request::Snooze,
+        "/reset" => Request::ResetTimer,
+        _ => return error(404, "not found")
+    };
+    log::info!("API request: {}", path);
+    requests.send(request).unwrap();
+    ok()
+}
+
+fn command(request: &mut tiny_http::Request, requests: &Sender<Request>) -> JsonResponse {
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+        return error(400, &e.to_string());
+    }
+    match CommandMessage::parse(&body) {
+        Ok(command) => {
+            log::info!("API command: {:?}", command);
+            requests.send(command.request()).unwrap();
+            json_response(200, json!({ "version": schema::VERSION, "ok": true }).to_string())
+        },
+        Err(e) => error(400, &e)
+    }
+}
+
+fn metrics(status: &Mutex<Status>, metrics: &Mutex<Metrics>) -> JsonResponse {
+    let status = status.lock().unwrap().clone();
+    let body = metrics.lock().unwrap().render(&status);
+    let content_type = Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+    Response::from_string(body).with_header(content_type)
+}
+
+/// Who a listener serves: this machine, with full control, or the network, which may
+/// only read what the dashboard shows.
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    Local,
+    ReadOnly,
+}
+
+/// What the dashboard reads, all the network may ask for.
+const DASHBOARD_PATHS: &[&str] = &["/status", "/today", "/history", "/heatmap"];
+
+fn handle(request: &mut tiny_http::Request, status: &Mutex<Status>, counters: &Mutex<Metrics>, requests: &Sender<Request>, token: &str, access: Access) -> JsonResponse {
+    let url = request.url().to_string();
+    let (path, params) = split_url(&url);
+    if request.method() == &Method::Get {
+        if let Some(response) = dashboard(path) {
+            return response;
+        }
+    }
+    if !is_authorized(request, &params, token) {
+        return error(401, "missing or wrong token");
+    }
+    if access == Access::ReadOnly && (request.method() != &Method::Get || !DASHBOARD_PATHS.contains(&path)) {
+        return error(403, "only the dashboard is served on the network");
+    }
+    match (request.method(), path) {
+        (Method::Get, "/status") => {
+            let status = StateMessage::new(&status.lock().unwrap());
+            json_response(200, json!({ "ok": true, "version": schema::VERSION, "status": status }).to_string())
+        },
+        (Method::Post, "/command") => command(request, requests),
+        (Method::Get, "/history") => history(&params),
+        (Method::Get, "/records") => records(&params),
+        (Method::Get, "/today") => today(),
+        (Method::Get, "/heatmap") => heatmap(),
+        (Method::Get, "/metrics") => metrics(status, counters),
+        (Method::Post, _) => control(&params, path, requests),
+        _ => error(404, "not found")
+    }
+}
+
+/// Answers requests to `server` until it closes.
+fn listen(server: Server, status: &Mutex<Status>, counters: &Mutex<Metrics>, requests: &Sender<Request>, token: &str, access: Access) {
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut request, status, counters, requests, token, access);
+        if let Err(e) = request.respond(response) {
+            log::warn!("API: {}", e);
+        }
+    }
+}
+
+/// Serves the state, the history, control endpoints and the dashboard over HTTP on localhost,
+/// and the dashboard with what it reads to the whole network when `lan` is on.
+pub fn serve(config: ApiConfig, r: Receiver<Event>, requests: Sender<Request>) {
+    let token = match config.token {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            log::error!("The API needs a token");
+            return;
+        }
+    };
+    let status = Arc::new(Mutex::new(Status::default()));
+    let counters = Arc::new(Mutex::new(Metrics::default()));
+    {
+        let status = status.clone();
+        let counters = counters.clone();
+        thread::spawn(move || {
+            for event in r.iter() {
+                status.lock().unwrap().apply(&event);
+                counters.lock().unwrap().apply(&event);
+            }
+        });
+    }
+
+    match config.lan_token.filter(|token| !token.is_empty()) {
+        Some(lan_token) if config.lan => match Server::http(("0.0.0.0", config.lan_port)) {
+            Ok(server) => {
+                log::info!("Read-only API listening on the network at port {}", config.lan_port);
+                let status = status.clone();
+                let counters = counters.clone();
+                let requests = requests.clone();
+                thread::spawn(move || listen(server, &status, &counters, &requests, &lan_token, Access::ReadOnly));
+            },
+            Err(e) => log::error!("Failed to start the API on the network at port {}: {}", config.lan_port, e)
+        },
+        None if config.lan => log::error!("The API on the network needs a token of its own"),
+        _ => {}
+    }
+
+    let server = match Server::http(("127.0.0.1", config.port)) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to start the API on port {}: {}", config.port, e);
+            return;
+        }
+    };
+    log::info!("API listening on http://127.0.0.1:{}", config.port);
+    listen(server, &status, &counters, &requests, &token, Access::Local);
+}
@@ -0,0 +1,30 @@
+use std::hint;
+
+/// Whether `given` is `token`, compared in constant time so how long the answer takes
+/// doesn't give away how much of a guess was right. Only a wrong length can be told.
+pub fn is_token(given: &str, token: &str) -> bool {
+    if given.len() != token.len() {
+        return false;
+    }
+    let differ = given.bytes().zip(token.bytes()).fold(0, |differ, (a, b)| differ | (a ^ b));
+    hint::black_box(differ) == 0
+}
+
+/// Whether an `Authorization` header's `value` is `Bearer` with `token`.
+pub fn is_bearer(value: &str, token: &str) -> bool {
+    value.strip_prefix("Bearer ").is_some_and(|given| is_token(given, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_only_the_token() {
+        assert!(is_bearer("Bearer 0123abcd", "0123abcd"));
+        assert!(!is_bearer("Bearer 0123abce", "0123abcd"));
+        assert!(!is_bearer("Bearer 0123abc", "0123abcd"));
+        assert!(!is_bearer("0123abcd", "0123abcd"));
+        assert!(!is_token("", "0123abcd"));
+    }
+}
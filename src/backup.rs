@@ -0,0 +1,305 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task, time};
+use crate::config::{config_path, data_dir, BackupConfig};
+use crate::format::utc_civil;
+use crate::history::History;
+use crate::store::{self, StoreError};
+
+const PREFIX: &str = "pauza-";
+/// Where a backup being made is written, so an unfinished one is never taken for a backup.
+const PARTIAL: &str = ".partial";
+/// In the data folder, the backup to put in place on the next start.
+const PENDING: &str = "restore";
+/// In the data folder, what the last restore replaced.
+const REPLACED: &str = "before-restore";
+/// In the data folder, what a restore under way replaces, until the restore is known to be good.
+const REPLACING: &str = "before-restore.new";
+/// Marks that all of what a restore replaces is in `REPLACING`, so whatever else is in the
+/// data folder came from the backup.
+const MOVED_ASIDE: &str = ".moved";
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(5 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+pub enum BackupError {
+    Io(io::Error),
+    History(StoreError),
+    /// The folder chosen to restore holds none of pauza's files.
+    NotABackup(PathBuf),
+    /// The history in the backup doesn't open on this account.
+    Unreadable(PathBuf, StoreError),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackupError::Io(e) => write!(f, "{}", e),
+            BackupError::History(e) => write!(f, "{}", e),
+            BackupError::NotABackup(path) => write!(f, "{} is not a pauza backup", path.display()),
+            BackupError::Unreadable(path, e) => write!(f, "{} can't be restored here: {}", path.display(), e)
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> BackupError {
+        BackupError::Io(e)
+    }
+}
+
+impl From<StoreError> for BackupError {
+    fn from(e: StoreError) -> BackupError {
+        BackupError::History(e)
+    }
+}
+
+/// The files a backup holds, where they are kept in the data folder.
+fn data_files() -> Vec<PathBuf> {
+    let history = History::path();
+    vec![config_path(), store::encrypted_path(&history), store::key_path(&history), history]
+}
+
+fn is_backup(name: &str) -> bool {
+    name.starts_with(PREFIX) && !name.ends_with(PARTIAL)
+}
+
+/// The backups among `names` to delete to keep only the latest `keep`. The names sort
+/// by when the backups were made.
+fn expired(names: &[String], keep: usize) -> Vec<String> {
+    let mut backups: Vec<&String> = names.iter().filter(|name| is_backup(name)).collect();
+    backups.sort();
+    let expired = backups.len().saturating_sub(keep);
+    backups.into_iter().take(expired).cloned().collect()
+}
+
+fn backups(folder: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Copies the config and the history into a new folder in `folder`, deleting the oldest
+/// backups there beyond `keep`. Returns the new backup.
+pub fn back_up(folder: &Path, keep: usize) -> Result<PathBuf, BackupError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let (year, month, day, hour, minute, second) = utc_civil(now);
+    let name = format!("{}{:04}{:02}{:02}-{:02}{:02}{:02}", PREFIX, year, month, day, hour, minute, second);
+    let backup = folder.join(&name);
+    let partial = folder.join(name + PARTIAL);
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+    fs::create_dir_all(&partial)?;
+    let config = config_path();
+    if config.exists() {
+        fs::copy(&config, partial.join(config.file_name().unwrap_or_default()))?;
+    }
+    store::back_up(&History::path(), &partial)?;
+    fs::rename(&partial, &backup)?;
+    log::info!("Backed up to {}", backup.display());
+
+    for name in expired(&backups(folder)?, keep) {
+        log::info!("Deleting the old backup {}", name);
+        if let Err(e) = fs::remove_dir_all(folder.join(&name)) {
+            log::warn!("Failed to delete the old backup {}: {}", name, e);
+        }
+    }
+    Ok(backup)
+}
+
+/// How long ago the latest backup in `folder` was made, if there is one.
+fn since_latest(folder: &Path) -> io::Result<Option<Duration>> {
+    let latest = backups(folder)?.into_iter().filter(|name| is_backup(name)).max();
+    match latest {
+        Some(name) => {
+            let modified = fs::metadata(folder.join(name))?.modified()?;
+            Ok(Some(modified.elapsed().unwrap_or_default()))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Backs up every `interval_hours` while pauza runs, unless no folder is set.
+pub fn watch(config: BackupConfig) {
+    let folder = match config.folder {
+        Some(folder) => PathBuf::from(folder),
+        None => return
+    };
+    let interval = Duration::from_secs(config.interval_hours.max(1).saturating_mul(60 * 60));
+    let keep = config.keep;
+    tokio::spawn(async move {
+        time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            let folder = folder.clone();
+            let result = task::spawn_blocking(move || -> Result<(), BackupError> {
+                fs::create_dir_all(&folder)?;
+                if since_latest(&folder)?.is_none_or(|since| since >= interval) {
+                    back_up(&folder, keep)?;
+                }
+                Ok(())
+            }).await;
+            match result {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => log::error!("Failed to back up: {}", e),
+                Err(e) => log::error!("Failed to back up: {}", e)
+            }
+            time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Sets the backup in `backup` to replace the config and the history on the next start,
+/// when nothing has them open. Refuses a history that wouldn't open here, like one encrypted
+/// for another Windows account.
+pub fn restore(backup: &Path) -> Result<(), BackupError> {
+    let files: Vec<PathBuf> = data_files().iter()
+        .filter_map(|file| file.file_name())
+        .map(|name| backup.join(name))
+        .filter(|file| file.exists())
+        .collect();
+    if files.is_empty() {
+        return Err(BackupError::NotABackup(backup.to_path_buf()));
+    }
+    let history = backup.join(History::path().file_name().unwrap_or_default());
+    store::check(&history).map_err(|e| BackupError::Unreadable(backup.to_path_buf(), e))?;
+    let pending = data_dir().join(PENDING);
+    if pending.exists() {
+        fs::remove_dir_all(&pending)?;
+    }
+    fs::create_dir_all(&pending)?;
+    for file in files {
+        fs::copy(&file, pending.join(file.file_name().unwrap_or_default()))?;
+    }
+    log::info!("Restoring {} on the next start", backup.display());
+    Ok(())
+}
+
+/// Deletes the backups in the folder `config` has, and the copies restores keep in the
+/// data folder, along with a restore still to be put in place.
+pub fn delete_all(config: &BackupConfig) -> io::Result<()> {
+    if let Some(folder) = config.folder.as_deref().map(Path::new).filter(|folder| folder.exists()) {
+        for name in backups(folder)? {
+            if name.starts_with(PREFIX) {
+                fs::remove_dir_all(folder.join(name))?;
+            }
+        }
+    }
+    for name in [PENDING, REPLACED, REPLACING] {
+        let dir = data_dir().join(name);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts back what a restore that didn't finish replaced, removing what it had put in its place.
+fn roll_back(replacing: &Path) -> io::Result<()> {
+    let moved_aside = replacing.join(MOVED_ASIDE).exists();
+    for file in data_files() {
+        let kept = replacing.join(file.file_name().unwrap_or_default());
+        if kept.exists() {
+            fs::rename(&kept, &file)?;
+        } else if moved_aside && file.exists() {
+            fs::remove_file(&file)?;
+        }
+    }
+    fs::remove_dir_all(replacing)
+}
+
+/// Keeps what a finished restore replaced, in place of what the one before it replaced.
+fn keep_replaced(replacing: &Path) -> io::Result<()> {
+    let replaced = data_dir().join(REPLACED);
+    if replaced.exists() {
+        fs::remove_dir_all(&replaced)?;
+    }
+    fs::rename(replacing, replaced)
+}
+
+/// Copies the backup in `pending` in place of the data files, which are moved aside first.
+/// Until the restored history is known to open they can be put back, and only then is `pending`
+/// removed and what the last restore replaced let go.
+fn put_in_place(pending: &Path) -> Result<(), BackupError> {
+    let replacing = data_dir().join(REPLACING);
+    if replacing.exists() {
+        log::warn!("Undoing a restore that didn't finish");
+        roll_back(&replacing)?;
+    }
+    fs::create_dir_all(&replacing)?;
+    let files = data_files();
+    for file in &files {
+        if let (true, Some(name)) = (file.exists(), file.file_name()) {
+            fs::rename(file, replacing.join(name))?;
+        }
+    }
+    fs::write(replacing.join(MOVED_ASIDE), b"")?;
+
+    let restored = files.iter().try_for_each(|file| -> Result<(), BackupError> {
+        let restored = pending.join(file.file_name().unwrap_or_default());
+        if restored.exists() {
+            fs::copy(&restored, file)?;
+        }
+        Ok(())
+    }).and_then(|()| store::check(&History::path()).map_err(BackupError::from));
+    if let Err(e) = restored {
+        roll_back(&replacing)?;
+        fs::remove_dir_all(pending)?;
+        return Err(e);
+    }
+
+    // From here on, a stop leaves `replacing` to be kept on the next start, not rolled back.
+    fs::remove_file(replacing.join(MOVED_ASIDE))?;
+    fs::remove_dir_all(pending)?;
+    keep_replaced(&replacing)?;
+    Ok(())
+}
+
+/// Puts in place a backup set to be restored, before anything reads the config or the
+/// history. What it replaces is kept aside in the data folder, and put back if the restore fails.
+pub fn finish_restore() {
+    let pending = data_dir().join(PENDING);
+    let replacing = data_dir().join(REPLACING);
+    if !pending.exists() {
+        // Pauza stopped after the restore, but before it kept what the restore replaced.
+        if replacing.exists() {
+            if let Err(e) = keep_replaced(&replacing) {
+                log::error!("Failed to keep what the restore replaced: {}", e);
+            }
+        }
+        return;
+    }
+    match put_in_place(&pending) {
+        Ok(()) => log::info!("Restored the backup, keeping what it replaced in {}", data_dir().join(REPLACED).display()),
+        Err(e) => log::error!("Failed to restore the backup, keeping the config and history as they were: {}", e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletes_the_oldest_backups() {
+        let names: Vec<String> = [
+            "pauza-20240103-090000",
+            "pauza-20240101-090000",
+            "pauza-20240104-090000.partial",
+            "photos",
+            "pauza-20240102-090000",
+        ].iter().map(|name| name.to_string()).collect();
+        assert_eq!(expired(&names, 2), vec!["pauza-20240101-090000".to_string()]);
+        assert_eq!(expired(&names, 1).len(), 2);
+        assert!(expired(&names, 7).is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use crate::config::data_dir;
+use crate::privacy;
+
+const QUIETED_FILE: &str = "quieted.toml";
+/// How far the volume may be from what was set for it to count as untouched.
+const VOLUME_TOLERANCE: f32 = 0.01;
+
+/// What was done to the sound at the start of a break, to undo at its end. Kept on disk
+/// until then, so a crash or a shutdown during the break doesn't leave the sound down.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Quieted {
+    Ducked { before: f32, after: f32 },
+    Muted,
+    Paused,
+}
+
+/// The sound as it is when it comes to putting it back, with what can't be told `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sound {
+    pub level: Option<f32>,
+    pub muted: Option<bool>,
+    pub playing: Option<bool>,
+}
+
+/// What putting the sound back takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Restore {
+    SetLevel(f32),
+    Unmute,
+    PressPlay,
+    /// It was changed during the break, and is left the way it was changed to.
+    Nothing,
+}
+
+impl Quieted {
+    /// What undoing this takes with the sound as it is, or None when that can't be told.
+    pub fn restore(&self, sound: Sound) -> Option<Restore> {
+        let restore = match *self {
+            Quieted::Ducked { before, after } => {
+                if (sound.level? - after).abs() <= VOLUME_TOLERANCE {
+                    Restore::SetLevel(before)
+                } else {
+                    Restore::Nothing
+                }
+            },
+            Quieted::Muted => if sound.muted? { Restore::Unmute } else { Restore::Nothing },
+            // Whatever plays now was started again, and play/pause would stop it.
+            Quieted::Paused => if sound.playing? { Restore::Nothing } else { Restore::PressPlay }
+        };
+        Some(restore)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if privacy::guest() {
+            return Ok(());
+        }
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(QUIETED_FILE), text)
+    }
+
+    /// What a run that didn't get to undo it left done to the sound.
+    pub fn load() -> Option<Quieted> {
+        if privacy::guest() {
+            return None;
+        }
+        let text = fs::read_to_string(data_dir().join(QUIETED_FILE)).ok()?;
+        toml::from_str(&text).ok()
+    }
+
+    /// Once undone, or once there's no undoing it.
+    pub fn forget() {
+        match fs::remove_file(data_dir().join(QUIETED_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => log::error!("{}", e),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(level: f32, muted: bool, playing: bool) -> Sound {
+        Sound { level: Some(level), muted: Some(muted), playing: Some(playing) }
+    }
+
+    #[test]
+    fn puts_back_only_what_is_still_as_the_break_left_it() {
+        let ducked = Quieted::Ducked { before: 0.8, after: 0.2 };
+        assert_eq!(ducked.restore(sound(0.2, false, true)), Some(Restore::SetLevel(0.8)));
+        assert_eq!(ducked.restore(sound(0.205, false, true)), Some(Restore::SetLevel(0.8)));
+        assert_eq!(ducked.restore(sound(0.5, false, true)), Some(Restore::Nothing));
+        assert_eq!(Quieted::Muted.restore(sound(0.5, true, false)), Some(Restore::Unmute));
+        assert_eq!(Quieted::Muted.restore(sound(0.5, false, false)), Some(Restore::Nothing));
+        assert_eq!(Quieted::Paused.restore(sound(0.5, false, false)), Some(Restore::PressPlay));
+        // Play/pause would stop what was started again during the break.
+        assert_eq!(Quieted::Paused.restore(sound(0.5, false, true)), Some(Restore::Nothing));
+    }
+
+    #[test]
+    fn doesnt_guess_when_the_sound_cant_be_told() {
+        assert_eq!(Quieted::Ducked { before: 0.8, after: 0.2 }.restore(Sound::default()), None);
+        assert_eq!(Quieted::Muted.restore(Sound::default()), None);
+        assert_eq!(Quieted::Paused.restore(Sound::default()), None);
+    }
+
+    #[test]
+    fn survives_a_restart() {
+        for quieted in [Quieted::Ducked { before: 0.8, after: 0.2 }, Quieted::Muted, Quieted::Paused] {
+            let text = toml::to_string(&quieted).unwrap();
+            assert_eq!(toml::from_str::<Quieted>(&text).unwrap(), quieted);
+        }
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task, time};
+use crate::civil::{self, days_from_civil};
+use crate::format::utc_civil;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Only meetings this close to now are kept.
+const HORIZON: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+const DAY: i64 = 24 * 60 * 60;
+/// Enough for a daily meeting to repeat for centuries, and an end to a rule that
+/// never picks a date.
+const MAX_PERIODS: i64 = 100_000;
+/// Far enough into the future that no rule repeating past it matters, and near enough
+/// that dates up to it don't overflow.
+const MAX_REPEAT: i64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Meeting {
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+/// An iCalendar date-time in seconds since the epoch: of UTC, or of the wall clock of its
+/// time zone, counted as if that were UTC.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Time {
+    Utc(i64),
+    Wall(i64),
+}
+
+/// Parses an iCalendar date-time, `YYYYMMDDTHHMMSS` with a trailing `Z` for UTC. Whole
+/// days return `None`, since all-day entries aren't meetings.
+fn parse_time(value: &str) -> Option<Time> {
+    let field = |range: std::ops::Range<usize>| value.get(range).and_then(|text| text.parse::<i64>().ok());
+    if value.len() < 15 || value.as_bytes()[8] != b'T' {
+        return None;
+    }
+    let (hour, minute, second) = (field(9..11)?, field(11..13)?, field(13..15)?);
+    let secs = parse_date(value)? * DAY + hour * 3600 + minute * 60 + second;
+    Some(if value.ends_with('Z') { Time::Utc(secs) } else { Time::Wall(secs) })
+}
+
+/// Days since the epoch of the `YYYYMMDD` a date or date-time starts with.
+fn parse_date(value: &str) -> Option<i64> {
+    let field = |range: std::ops::Range<usize>| value.get(range).and_then(|text| text.parse::<i64>().ok());
+    Some(days_from_civil(field(0..4)?, field(4..6)?, field(6..8)?))
+}
+
+/// Parses a `DURATION` such as `PT1H30M` or `P1D` into seconds, or `None` if it is too
+/// long to count.
+fn parse_duration(value: &str) -> Option<i64> {
+    let mut secs: i64 = 0;
+    let mut number: i64 = 0;
+    for c in value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number = number.checked_mul(10)?.checked_add(c.to_digit(10)? as i64)?;
+                continue;
+            },
+            'T' => continue,
+            'W' => 7 * DAY,
+            'D' => DAY,
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ => return None
+        };
+        secs = number.checked_mul(unit).and_then(|length| secs.checked_add(length))?;
+        number = 0;
+    }
+    Some(secs)
+}
+
+/// Parses a `TZOFFSETFROM` or `TZOFFSETTO` such as `+0100` or `-0530` into seconds.
+fn parse_offset(value: &str) -> Option<i64> {
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None
+    };
+    let field = |range: std::ops::Range<usize>| value.get(range).and_then(|text| text.parse::<i64>().ok());
+    let secs = field(1..3)? * 3600 + field(3..5)? * 60 + field(5..7).unwrap_or(0);
+    Some(sign * secs)
+}
+
+/// Days since Sunday of a day counted from the epoch, which was a Thursday.
+fn weekday(day: i64) -> i64 {
+    (day + 4).rem_euclid(7)
+}
+
+fn parse_weekday(value: &str) -> Option<i64> {
+    ["SU", "MO", "TU", "WE", "TH", "FR", "SA"].iter().position(|name| *name == value).map(|day| day as i64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// How an `RRULE` repeats an event, for the parts of it calendars use for meetings and
+/// time zones for changing their clocks.
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<Time>,
+    /// Weekdays from Sunday, each with which of them in the month it is (`-1` for the
+    /// last), or 0 for all of them.
+    by_day: Vec<(i64, i64)>,
+    by_month_day: Vec<i64>,
+    by_month: Vec<i64>,
+    week_start: i64,
+}
+
+impl Rule {
+    /// `None` for rules that repeat more often than daily or pick dates some other way,
+    /// which would be read wrong.
+    fn parse(value: &str) -> Option<Rule> {
+        let numbers = |value: &str| value.split(',').map(|number| number.parse().ok()).collect::<Option<Vec<i64>>>();
+        let mut frequency = None;
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            week_start: 1,
+        };
+        for part in value.split(';') {
+            let (name, value) = part.split_once('=')?;
+            match name {
+                "FREQ" => frequency = Some(match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None
+                }),
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|interval| *interval > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                // A date alone repeats until the end of that day.
+                "UNTIL" => rule.until = Some(parse_time(value).or_else(|| Some(Time::Wall(parse_date(value)? * DAY + DAY - 1)))?),
+                "BYDAY" => rule.by_day = value.split(',').map(|day| {
+                    let (nth, name) = day.split_at(day.len().checked_sub(2)?);
+                    let nth = if nth.is_empty() { 0 } else { nth.parse().ok()? };
+                    Some((nth, parse_weekday(name)?))
+                }).collect::<Option<_>>()?,
+                "BYMONTHDAY" => rule.by_month_day = numbers(value).filter(|days| days.iter().all(|day| (-31..=31).contains(day)))?,
+                "BYMONTH" => rule.by_month = numbers(value).filter(|months| months.iter().all(|month| (1..=12).contains(month)))?,
+                "WKST" => rule.week_start = parse_weekday(value)?,
+                _ => return None
+            }
+        }
+        rule.frequency = frequency?;
+        Some(rule)
+    }
+
+    /// The days of a month the rule picks, with `day` the day of the month it started on.
+    fn days_in_month(&self, year: i64, month: i64, day: i64) -> Vec<i64> {
+        let first = days_from_civil(year, month, 1);
+        let length = if month == 12 { days_from_civil(year + 1, 1, 1) } else { days_from_civil(year, month + 1, 1) } - first;
+        let mut days: Vec<i64> = if !self.by_month_day.is_empty() {
+            self.by_month_day.iter()
+                .map(|&day| if day < 0 { length + 1 + day } else { day })
+                .filter(|day| (1..=length).contains(day))
+                .map(|day| first + day - 1)
+                .filter(|&day| self.by_day.is_empty() || self.by_day.iter().any(|&(_, weekday_)| weekday(day) == weekday_))
+                .collect()
+        } else if !self.by_day.is_empty() {
+            self.by_day.iter().flat_map(|&(nth, weekday_)| {
+                let all: Vec<i64> = (first + (weekday_ - weekday(first)).rem_euclid(7)..first + length).step_by(7).collect();
+                match nth {
+                    0 => all,
+                    nth if nth > 0 => all.get(nth as usize - 1).copied().into_iter().collect(),
+                    nth => all.len().checked_sub(nth.unsigned_abs() as usize).and_then(|i| all.get(i)).copied().into_iter().collect()
+                }
+            }).collect()
+        } else if day <= length {
+            vec![first + day - 1]
+        } else {
+            Vec::new()
+        };
+        days.sort_unstable();
+        days
+    }
+
+    /// The wall clock times the rule repeats an event starting at `start` at, up to
+    /// `until` as a wall clock time, that are within `within`. The count of repeats
+    /// runs from `start`, so it is followed even when `within` starts later.
+    fn times(&self, start: i64, until: Option<i64>, within: Range<i64>) -> Vec<i64> {
+        let (first_day, time) = (start.div_euclid(DAY), start.rem_euclid(DAY));
+        let (year, month, day, ..) = utc_civil(start);
+        let last = until.map_or(within.end, |until| within.end.min(until + 1));
+        let mut times = Vec::new();
+        let mut count = 0;
+        for period in 0..MAX_PERIODS {
+            // Past that a huge `INTERVAL` only gives dates too far off to count.
+            let n = match period.checked_mul(self.interval).filter(|n| *n <= MAX_REPEAT) {
+                Some(n) => n,
+                None => break
+            };
+            let (period_start, days) = match self.frequency {
+                Frequency::Daily => (first_day + n, vec![first_day + n]),
+                Frequency::Weekly => {
+                    let week = first_day - (weekday(first_day) - self.week_start).rem_euclid(7) + 7 * n;
+                    let days = if self.by_day.is_empty() {
+                        vec![first_day + 7 * n]
+                    } else {
+                        (week..week + 7).filter(|&day| self.by_day.iter().any(|&(_, weekday_)| weekday(day) == weekday_)).collect()
+                    };
+                    (week, days)
+                },
+                Frequency::Monthly => {
+                    let index = year * 12 + month - 1 + n;
+                    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+                    (days_from_civil(year, month, 1), self.days_in_month(year, month, day))
+                },
+                Frequency::Yearly => {
+                    let months = if self.by_month.is_empty() { vec![month] } else { self.by_month.clone() };
+                    let mut days: Vec<i64> = months.iter().flat_map(|&month| self.days_in_month(year + n, month, day)).collect();
+                    days.sort_unstable();
+                    (days_from_civil(year + n, 1, 1), days)
+                }
+            };
+            if period_start * DAY + time >= last {
+                break;
+            }
+            let picked = |&day: &i64| {
+                let (_, month, ..) = utc_civil(day * DAY);
+                (self.by_month.is_empty() || self.by_month.contains(&month))
+                    && (self.frequency != Frequency::Daily || self.by_day.is_empty()
+                        || self.by_day.iter().any(|&(_, weekday_)| weekday(day) == weekday_))
+            };
+            for day in days.iter().filter(|day| picked(day)) {
+                let secs = day * DAY + time;
+                if secs < start {
+                    continue;
+                }
+                if secs >= last || self.count.is_some_and(|limit| count >= limit) {
+                    return times;
+                }
+                count += 1;
+                if within.contains(&secs) {
+                    times.push(secs);
+                }
+            }
+        }
+        times
+    }
+}
+
+/// A change of the clocks in a `VTIMEZONE`: at `start` on the wall clock before it, and
+/// again whenever `rule` repeats it.
+#[derive(Clone, Debug, Default)]
+struct Onset {
+    start: Option<i64>,
+    rule: Option<Rule>,
+    from: i64,
+    to: i64,
+}
+
+/// A time zone as the calendar defines it, by when its clocks change.
+#[derive(Debug, Default)]
+struct Zone {
+    onsets: Vec<Onset>,
+}
+
+impl Zone {
+    /// The offset from UTC at unix time `secs`.
+    fn offset(&self, secs: i64) -> i64 {
+        let mut latest: Option<(i64, i64)> = None;
+        for onset in &self.onsets {
+            let start = match onset.start {
+                Some(start) => start,
+                None => continue
+            };
+            // Zones only change their clocks a few times a year.
+            let before = secs + onset.from + 1;
+            let wall = match &onset.rule {
+                Some(rule) => {
+                    let until = rule.until.map(|until| match until {
+                        Time::Utc(until) => until + onset.from,
+                        Time::Wall(until) => until
+                    });
+                    rule.times(start, until, before - 400 * DAY..before).last().copied()
+                },
+                None => Some(start).filter(|start| *start < before)
+            };
+            if let Some(at) = wall.map(|wall| wall - onset.from) {
+                if latest.is_none_or(|(latest, _)| at > latest) {
+                    latest = Some((at, onset.to));
+                }
+            }
+        }
+        match latest {
+            Some((_, offset)) => offset,
+            None => self.onsets.iter().filter(|onset| onset.start.is_some()).min_by_key(|onset| onset.start).map_or(0, |onset| onset.from)
+        }
+    }
+}
+
+/// The wall clock an event's times are on.
+#[derive(Clone, Copy)]
+enum Clock<'a> {
+    Utc,
+    Zone(&'a Zone),
+    Local,
+}
+
+impl Clock<'_> {
+    fn unix(&self, wall: i64) -> i64 {
+        match self {
+            Clock::Utc => wall,
+            Clock::Zone(zone) => civil::unix_in_zone(wall, |secs| zone.offset(secs)),
+            Clock::Local => civil::unix_local(wall)
+        }
+    }
+
+    fn wall(&self, secs: i64) -> i64 {
+        match self {
+            Clock::Utc => secs,
+            Clock::Zone(zone) => secs + zone.offset(secs),
+            Clock::Local => civil::wall_local(secs)
+        }
+    }
+}
+
+/// A date-time property, with the `TZID` it is in.
+#[derive(Clone, Copy, Debug)]
+struct Stamp<'a> {
+    time: Time,
+    zone: Option<&'a str>,
+}
+
+#[derive(Default)]
+struct Event<'a> {
+    uid: &'a str,
+    start: Option<Stamp<'a>>,
+    end: Option<Stamp<'a>>,
+    duration: Option<i64>,
+    rule: Option<Rule>,
+    except: Vec<Stamp<'a>>,
+    recurrence_id: Option<Stamp<'a>>,
+    free: bool,
+}
+
+/// Times with a `TZID` the calendar doesn't define are taken as local time.
+fn clock<'a>(zones: &'a HashMap<&str, Zone>, stamp: Stamp) -> Clock<'a> {
+    match (stamp.time, stamp.zone.and_then(|zone| zones.get(zone))) {
+        (Time::Utc(_), _) => Clock::Utc,
+        (Time::Wall(_), Some(zone)) => Clock::Zone(zone),
+        (Time::Wall(_), None) => Clock::Local
+    }
+}
+
+fn unix(zones: &HashMap<&str, Zone>, stamp: Stamp) -> i64 {
+    match stamp.time {
+        Time::Utc(secs) => secs,
+        Time::Wall(wall) => clock(zones, stamp).unix(wall)
+    }
+}
+
+fn secs(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64)
+    }
+}
+
+fn system_time(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+/// Reads the busy events between `from` and `to` out of an iCalendar file, with
+/// recurring events repeated and times in the time zones the file defines.
+pub fn parse(text: &str, from: SystemTime, to: SystemTime) -> Vec<Meeting> {
+    // Long lines are folded by starting the continuation with a space or tab.
+    let unfolded = text.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut zones = HashMap::new();
+    let mut events = Vec::new();
+    // The components the line is in, innermost last.
+    let mut within = Vec::new();
+    let mut zone = (None, Zone::default());
+    let mut onset = Onset::default();
+    let mut event = Event::default();
+    for line in unfolded.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue
+        };
+        let mut params = name.split(';');
+        let name = params.next().unwrap_or("");
+        let stamp = |value: &str| Some(Stamp {
+            time: parse_time(value)?,
+            zone: params.clone().find_map(|param| param.strip_prefix("TZID=")).map(|zone| zone.trim_matches('"')),
+        });
+        match name {
+            "BEGIN" => {
+                within.push(value);
+                match value {
+                    "VTIMEZONE" => zone = (None, Zone::default()),
+                    "STANDARD" | "DAYLIGHT" => onset = Onset::default(),
+                    "VEVENT" => event = Event::default(),
+                    _ => {}
+                }
+                continue;
+            },
+            "END" => {
+                match value {
+                    "VTIMEZONE" => if let (Some(id), zone) = std::mem::take(&mut zone) {
+                        zones.insert(id, zone);
+                    },
+                    "STANDARD" | "DAYLIGHT" => zone.1.onsets.push(std::mem::take(&mut onset)),
+                    "VEVENT" => events.push(std::mem::take(&mut event)),
+                    _ => {}
+                }
+                within.pop();
+                continue;
+            },
+            _ => {}
+        }
+        match (within.last().copied(), name) {
+            (Some("VTIMEZONE"), "TZID") => zone.0 = Some(value),
+            (Some("STANDARD" | "DAYLIGHT"), "DTSTART") => onset.start = parse_time(value).map(|time| match time {
+                Time::Utc(secs) | Time::Wall(secs) => secs
+            }),
+            (Some("STANDARD" | "DAYLIGHT"), "RRULE") => onset.rule = Rule::parse(value),
+            (Some("STANDARD" | "DAYLIGHT"), "TZOFFSETFROM") => onset.from = parse_offset(value).unwrap_or(0),
+            (Some("STANDARD" | "DAYLIGHT"), "TZOFFSETTO") => onset.to = parse_offset(value).unwrap_or(0),
+            (Some("VEVENT"), "UID") => event.uid = value,
+            (Some("VEVENT"), "DTSTART") => event.start = stamp(value),
+            (Some("VEVENT"), "DTEND") => event.end = stamp(value),
+            (Some("VEVENT"), "DURATION") => event.duration = parse_duration(value),
+            (Some("VEVENT"), "RRULE") => event.rule = Rule::parse(value),
+            (Some("VEVENT"), "EXDATE") => event.except.extend(value.split(',').filter_map(stamp)),
+            (Some("VEVENT"), "RECURRENCE-ID") => event.recurrence_id = stamp(value),
+            (Some("VEVENT"), "TRANSP") => event.free |= value == "TRANSPARENT",
+            (Some("VEVENT"), "STATUS") => event.free |= value == "CANCELLED",
+            _ => {}
+        }
+    }
+
+    // Occurrences moved or cancelled on their own come as events of their own.
+    let moved: HashSet<(&str, i64)> = events.iter()
+        .filter_map(|event| Some((event.uid, unix(&zones, event.recurrence_id?))))
+        .collect();
+    let (from, to) = (secs(from), secs(to));
+    let mut meetings = Vec::new();
+    for event in events.iter().filter(|event| !event.free) {
+        let start = match event.start {
+            Some(start) => start,
+            None => continue
+        };
+        let first = unix(&zones, start);
+        let length = match (event.end, event.duration) {
+            (Some(end), _) => unix(&zones, end) - first,
+            (None, Some(duration)) => duration,
+            (None, None) => continue
+        };
+        if length <= 0 {
+            continue;
+        }
+        let starts = match (&event.rule, event.recurrence_id, start.time) {
+            (Some(rule), None, Time::Utc(wall) | Time::Wall(wall)) => {
+                let clock = clock(&zones, start);
+                let until = rule.until.map(|until| match until {
+                    Time::Utc(until) => clock.wall(until),
+                    Time::Wall(until) => until
+                });
+                let except: HashSet<i64> = event.except.iter().map(|&stamp| unix(&zones, stamp)).collect();
+                // Wall clocks are less than a day off UTC.
+                // Nothing repeats before the first start, however long the event is.
+                rule.times(wall, until, clock.wall((from - length).max(first)) - DAY..clock.wall(to) + DAY).into_iter()
+                    .map(|wall| clock.unix(wall))
+                    .filter(|start| !except.contains(start) && !moved.contains(&(event.uid, *start)))
+                    .collect()
+            },
+            _ => vec![first]
+        };
+        meetings.extend(starts.into_iter()
+            .filter_map(|start| Some((start, start.checked_add(length)?)))
+            .filter(|&(start, end)| end > from && start < to)
+            .map(|(start, end)| Meeting { start: system_time(start), end: system_time(end) }));
+    }
+    meetings.sort_by_key(|meeting| meeting.start);
+    meetings
+}
+
+fn fetch(url: &str) -> Result<Vec<Meeting>, String> {
+    let text = ureq::get(url)
+        .timeout(FETCH_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let now = SystemTime::now();
+    Ok(parse(&text, now - HORIZON, now + HORIZON))
+}
+
+/// Meetings from a calendar feed, refreshed in the background, to plan breaks around.
+#[derive(Clone, Default)]
+pub struct Calendar {
+    meetings: Arc<Mutex<Vec<Meeting>>>,
+}
+
+impl Calendar {
+    pub fn watch(url: String, refresh: Duration) -> Calendar {
+        let calendar = Calendar::default();
+        let meetings = calendar.meetings.clone();
+        tokio::spawn(async move {
+            loop {
+                let url = url.clone();
+                match task::spawn_blocking(move || fetch(&url)).await.unwrap_or_else(|e| Err(e.to_string())) {
+                    Ok(fetched) => {
+                        log::info!("Calendar has {} upcoming meetings", fetched.len());
+                        *meetings.lock().unwrap() = fetched;
+                    },
+                    Err(e) => log::warn!("Failed to fetch the calendar: {}", e)
+                }
+                time::sleep(refresh).await;
+            }
+        });
+        calendar
+    }
+
+    /// When to prompt for a break that is due at `due`, so it doesn't land in a meeting.
+    /// A break needing `room` that would overlap a meeting is moved into the gap just
+    /// before it when that is at most `early` sooner, and otherwise to when the meeting
+    /// (and any meetings straight after it) ends.
+    pub fn plan(&self, due: SystemTime, now: SystemTime, room: Duration, early: Duration) -> SystemTime {
+        let meetings = self.meetings.lock().unwrap();
+        let free_after = |mut time: SystemTime| {
+            while let Some(meeting) = meetings.iter().find(|m| m.start < time + room && time < m.end) {
+                time = meeting.end;
+            }
+            time
+        };
+        if meetings.iter().any(|m| m.start <= now && now < m.end) {
+            return free_after(now).max(due);
+        }
+        let clash = meetings.iter().find(|m| m.start < due + room && due < m.end);
+        match clash {
+            Some(meeting) => {
+                let before = meeting.start.checked_sub(room).unwrap_or(meeting.start);
+                if before + early >= due && meeting.start >= now + room {
+                    before.max(now)
+                } else {
+                    free_after(meeting.end)
+                }
+            },
+            None => due
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BERLIN: &str = "BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n\
+        BEGIN:DAYLIGHT\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nDTSTART:19700329T020000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\nEND:DAYLIGHT\r\n\
+        BEGIN:STANDARD\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nDTSTART:19701025T030000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n";
+
+    fn utc(year: i64, month: i64, day: i64, hour: i64, minute: i64) -> SystemTime {
+        system_time(days_from_civil(year, month, day) * DAY + hour * 3600 + minute * 60)
+    }
+
+    fn starts(meetings: &[Meeting]) -> Vec<SystemTime> {
+        meetings.iter().map(|meeting| meeting.start).collect()
+    }
+
+    #[test]
+    fn repeats_meetings_in_their_time_zone() {
+        let text = format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:standup\r\n\
+            DTSTART;TZID=Europe/Berlin:20240108T090000\r\nDTEND;TZID=Europe/Berlin:20240108T091500\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,TH\r\nEXDATE;TZID=Europe/Berlin:20240704T090000\r\n\
+            END:VEVENT\r\n{}END:VCALENDAR\r\n", BERLIN);
+
+        // Summer time, with Thursday's skipped.
+        let meetings = parse(&text, utc(2024, 7, 1, 0, 0), utc(2024, 7, 7, 0, 0));
+        assert_eq!(meetings, vec![Meeting { start: utc(2024, 7, 1, 7, 0), end: utc(2024, 7, 1, 7, 15) }]);
+        // Across the clocks going back, on Sunday 2024-10-27.
+        let meetings = parse(&text, utc(2024, 10, 24, 0, 0), utc(2024, 10, 29, 0, 0));
+        assert_eq!(starts(&meetings), vec![utc(2024, 10, 24, 7, 0), utc(2024, 10, 28, 8, 0)]);
+    }
+
+    #[test]
+    fn follows_counts_and_moved_occurrences() {
+        let text = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\nUID:review\r\nDTSTART:20240126T150000Z\r\nDURATION:PT1H\r\n\
+            RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=3\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:review\r\nRECURRENCE-ID:20240223T150000Z\r\n\
+            DTSTART:20240222T100000Z\r\nDTEND:20240222T110000Z\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:lunch\r\nDTSTART:20240101T120000Z\r\nDTEND:20240101T130000Z\r\n\
+            RRULE:FREQ=DAILY;INTERVAL=2;UNTIL=20240303\r\nTRANSP:TRANSPARENT\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let meetings = parse(text, utc(2024, 1, 1, 0, 0), utc(2024, 12, 31, 0, 0));
+        assert_eq!(starts(&meetings), vec![utc(2024, 1, 26, 15, 0), utc(2024, 2, 22, 10, 0), utc(2024, 3, 29, 15, 0)]);
+        assert_eq!(meetings[0].end, utc(2024, 1, 26, 16, 0));
+    }
+
+    #[test]
+    fn picks_the_days_a_rule_says() {
+        let rule = Rule::parse("FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU").unwrap();
+        let start = days_from_civil(2023, 3, 26) * DAY;
+        assert_eq!(rule.times(start, None, start..start + 400 * DAY), vec![start, days_from_civil(2024, 3, 31) * DAY]);
+
+        let rule = Rule::parse("FREQ=MONTHLY;BYMONTHDAY=31").unwrap();
+        let start = days_from_civil(2024, 1, 31) * DAY;
+        let times = rule.times(start, Some(days_from_civil(2024, 5, 31) * DAY), start..i64::MAX);
+        assert_eq!(times, [(1, 31), (3, 31), (5, 31)].iter().map(|&(month, day)| days_from_civil(2024, month, day) * DAY).collect::<Vec<_>>());
+
+        let rule = Rule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,FR").unwrap();
+        let start = days_from_civil(2024, 9, 3) * DAY + 9 * 3600;
+        let times = rule.times(start, None, start..start + 14 * DAY);
+        assert_eq!(times, vec![start, start + 3 * DAY]);
+
+        assert_eq!(Rule::parse("FREQ=HOURLY"), None);
+        assert_eq!(Rule::parse("FREQ=MONTHLY;BYSETPOS=-1;BYDAY=MO,TU,WE,TH,FR"), None);
+    }
+
+    #[test]
+    fn survives_numbers_too_big_to_count() {
+        assert_eq!(parse_duration("PT99999999999999999999S"), None);
+        assert_eq!(parse_duration("P9999999999999999W"), None);
+        assert_eq!(Rule::parse("FREQ=YEARLY;BYMONTH=9223372036854775807"), None);
+
+        let text = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\nUID:forever\r\nDTSTART:20240101T120000Z\r\nDURATION:PT9223372036854775807S\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:rarely\r\nDTSTART:20240101T120000Z\r\nDURATION:PT1H\r\n\
+            RRULE:FREQ=YEARLY;INTERVAL=9223372036854775807\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let meetings = parse(text, utc(2024, 1, 1, 0, 0), utc(2024, 12, 31, 0, 0));
+        assert_eq!(starts(&meetings), vec![utc(2024, 1, 1, 12, 0)]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::format::utc_civil;
+
+#[cfg(windows)]
+use crate::windows::{refresh_time_zone, utc_offset};
+
+#[cfg(not(windows))]
+fn utc_offset(_secs: i64) -> Option<i64> {
+    Some(0)
+}
+
+#[cfg(not(windows))]
+fn refresh_time_zone() {}
+
+const DAY: i64 = 24 * 60 * 60;
+const UNKNOWN: i64 = i64::MIN;
+
+/// The UTC offset last seen, to notice the clocks moving.
+static OFFSET: AtomicI64 = AtomicI64::new(UNKNOWN);
+
+/// A wall clock time in the time zone the machine is in, for whatever goes by the
+/// clock: the day time counts for, the weekly report, meetings. How long something
+/// took is measured with `Instant` instead, which neither DST nor travel moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Civil {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+}
+
+impl Civil {
+    pub fn new(year: i64, month: i64, day: i64, hour: i64, minute: i64, second: i64) -> Civil {
+        Civil { year, month, day, hour, minute, second }
+    }
+
+    fn from_secs(secs: i64) -> Civil {
+        let (year, month, day, hour, minute, second) = utc_civil(secs);
+        Civil { year, month, day, hour, minute, second }
+    }
+
+    /// Seconds since the epoch of this wall clock time, as if it were UTC.
+    fn secs(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * DAY + self.hour * 3600 + self.minute * 60 + self.second
+    }
+
+    /// Days since Sunday, as `Weekday::number` counts them.
+    pub fn weekday(&self) -> u16 {
+        // 1970-01-01 was a Thursday.
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7) as u16
+    }
+
+    /// The date, as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// The time of day, as `HH:MM`.
+    pub fn clock(&self) -> String {
+        format!("{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// Days since 1970-01-01 for a civil date, after Howard Hinnant's `days_from_civil`.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn unix(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64)
+    }
+}
+
+fn offset(secs: i64) -> i64 {
+    utc_offset(secs).unwrap_or(0)
+}
+
+fn local_with(secs: i64, offset: impl Fn(i64) -> i64) -> Civil {
+    Civil::from_secs(secs + offset(secs))
+}
+
+/// The unix time `civil` stands for. Of a time that happens twice as the clocks go back,
+/// the first; a time skipped as they go forward is read with the offset from before.
+fn unix_with(civil: Civil, offset: impl Fn(i64) -> i64) -> i64 {
+    let wall = civil.secs();
+    // No time zone changes its offset twice within a day of a time.
+    let before = wall - offset(wall - DAY);
+    let after = wall - offset(wall + DAY);
+    let fits = |secs: i64| secs + offset(secs) == wall;
+    match (fits(before), fits(after)) {
+        (true, true) => before.min(after),
+        (false, true) => after,
+        _ => before
+    }
+}
+
+/// The wall clock time at `time`, with the offset from UTC in effect then.
+pub fn local(time: SystemTime) -> Civil {
+    local_with(unix(time), offset)
+}
+
+pub fn now() -> Civil {
+    local(SystemTime::now())
+}
+
+/// The wall clock time `after` from now, as `HH:MM`, past any change of the clocks.
+pub fn clock_after(after: Duration) -> String {
+    local(SystemTime::now() + after).clock()
+}
+
+/// When the wall clock shows `civil`.
+pub fn to_system_time(civil: Civil) -> SystemTime {
+    let secs = unix_with(civil, offset);
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+    }
+}
+
+/// When a clock `offset` from UTC shows `wall`, as `to_system_time`, for time zones other
+/// than the machine's. Both are in seconds since the epoch, `wall` counted as if it were UTC.
+pub fn unix_in_zone(wall: i64, offset: impl Fn(i64) -> i64) -> i64 {
+    unix_with(Civil::from_secs(wall), offset)
+}
+
+/// When the wall clock shows `wall`, in seconds since the epoch as if it were UTC.
+pub fn unix_local(wall: i64) -> i64 {
+    unix_in_zone(wall, offset)
+}
+
+/// The wall clock at unix time `secs`, in seconds since the epoch as if it were UTC.
+pub fn wall_local(secs: i64) -> i64 {
+    secs + offset(secs)
+}
+
+/// Notices the clocks moving, for DST or a new time zone, and has everything that
+/// reads the local time see it from now on.
+pub fn check_offset() {
+    let offset = offset(unix(SystemTime::now()));
+    let last = OFFSET.swap(offset, Ordering::Relaxed);
+    if last != offset && last != UNKNOWN {
+        log::info!("The UTC offset changed from {}s to {}s", last, offset);
+        refresh_time_zone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central European time, switching to summer time on 2024-03-31 at 01:00 UTC and
+    /// back on 2024-10-27 at 01:00 UTC.
+    fn berlin(secs: i64) -> i64 {
+        let spring = Civil::new(2024, 3, 31, 1, 0, 0).secs();
+        let autumn = Civil::new(2024, 10, 27, 1, 0, 0).secs();
+        if (spring..autumn).contains(&secs) { 7200 } else { 3600 }
+    }
+
+    #[test]
+    fn follows_the_clocks_through_dst() {
+        let civil = Civil::new(2024, 3, 31, 1, 30, 0);
+        assert_eq!(civil.weekday(), 0);
+        assert_eq!(civil.date(), "2024-03-31");
+
+        // An hour after 01:30 on the night the clocks go forward is 03:30.
+        let secs = unix_with(civil, berlin);
+        assert_eq!(local_with(secs + 3600, berlin), Civil::new(2024, 3, 31, 3, 30, 0));
+        // 02:30 never happens that night.
+        assert_eq!(unix_with(Civil::new(2024, 3, 31, 2, 30, 0), berlin), secs + 3600);
+
+        // 02:30 happens twice when they go back, from the first time.
+        let twice = Civil::new(2024, 10, 27, 2, 30, 0);
+        let first = unix_with(twice, berlin);
+        assert_eq!(local_with(first, berlin), twice);
+        assert_eq!(local_with(first + 3600, berlin), twice);
+    }
+}
@@ -0,0 +1,354 @@
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use crate::backup;
+use crate::email;
+use crate::export::{self, Format};
+use crate::format;
+use crate::history::History;
+use crate::ipc;
+use crate::report::WeeklyReport;
+use crate::settings;
+use crate::simulate;
+use crate::store;
+use crate::config::{ClickAction, Config, SoundEvent};
+use crate::windows::{activate_existing, disable_autostart, enable_autostart, play_sound, show_existing_stats};
+
+pub const USAGE: &str = "usage:
+    pauza
+    pauza export [--format csv|json|ics] [--from YYYY-MM-DD] [--output FILE]
+    pauza status
+    pauza pause [DURATION]     e.g. 30m, 1h, 1h30m
+    pauza resume
+    pauza break
+    pauza start-break    starts the break reminded of, with [idle] require_acknowledgment
+    pauza snooze    reminds of the break again after [reminders] snooze_minutes
+    pauza reset
+    pauza simulate [SCRIPT]    previews a workday with the current config
+    pauza decrypt FILE [--output FILE]    reads an export made with [storage] encryption
+    pauza play-sound break|reset|daily_limit
+    pauza export-settings FILE    writes the settings, without the history, for another machine
+    pauza import-settings FILE
+    pauza backup [FOLDER]    copies the settings and the history, to [backup] folder unless given
+    pauza restore FOLDER    puts a backup in place on the next start of pauza
+    pauza email-report    sends the weekly summary to [email] to now
+    pauza install-autostart    starts pauza at login
+    pauza uninstall-autostart";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Run,
+    Export {
+        format: Format,
+        from: Option<String>,
+        output: Option<PathBuf>,
+    },
+    /// Runs the timer through a script of activities, or a made up workday.
+    Simulate(Option<PathBuf>),
+    /// Starts pauza at login, or stops doing so.
+    Autostart(bool),
+    /// Writes out a file exported while the history was encrypted.
+    Decrypt {
+        path: PathBuf,
+        output: Option<PathBuf>,
+    },
+    /// Plays the sound set for an event, to try it.
+    PlaySound(SoundEvent),
+    ExportSettings(PathBuf),
+    ImportSettings(PathBuf),
+    /// Backs up into the folder given, or the one set in the config.
+    Backup(Option<PathBuf>),
+    Restore(PathBuf),
+    /// Sends the weekly summary by email now, to try the settings.
+    EmailReport,
+    /// A command line for the running instance's control pipe.
+    Control(String),
+    /// A click on a toast, passed on to the running instance.
+    Activate(ClickAction)
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("missing value for {}", flag))
+}
+
+fn parse_export<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut format = None;
+    let mut from = None;
+    let mut output: Option<PathBuf> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = value(&mut args, &arg)?;
+                format = Some(Format::parse(&name).ok_or_else(|| format!("unknown format: {}", name))?);
+            },
+            "--from" => from = Some(value(&mut args, &arg)?),
+            "--output" | "-o" => output = Some(PathBuf::from(value(&mut args, &arg)?)),
+            _ => return Err(format!("unexpected argument: {}", arg))
+        }
+    }
+    let format = format
+        .or_else(|| output.as_deref().map(Format::for_path))
+        .unwrap_or(Format::Csv);
+    Ok(Command::Export { format, from, output })
+}
+
+fn parse_pause<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let line = match args.next() {
+        Some(text) => {
+            let duration = format::parse_pause(&text)?;
+            format!("pause {}", duration.as_secs())
+        },
+        None => "pause".to_string()
+    };
+    no_more(args)?;
+    Ok(Command::Control(line))
+}
+
+fn parse_simulate<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let script = args.next().map(PathBuf::from);
+    no_more(args)?;
+    Ok(Command::Simulate(script))
+}
+
+fn parse_backup<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let folder = args.next().map(PathBuf::from);
+    no_more(args)?;
+    Ok(Command::Backup(folder))
+}
+
+fn parse_decrypt<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut path = None;
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(PathBuf::from(value(&mut args, &arg)?)),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument: {}", arg))
+        }
+    }
+    let path = path.ok_or_else(|| "missing file to decrypt".to_string())?;
+    Ok(Command::Decrypt { path, output })
+}
+
+fn parse_play_sound<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let name = args.next().ok_or_else(|| "missing event: break or reset".to_string())?;
+    let event = SoundEvent::parse(&name).ok_or_else(|| format!("unknown event: {}", name))?;
+    no_more(args)?;
+    Ok(Command::PlaySound(event))
+}
+
+fn parse_path<I: Iterator<Item = String>>(mut args: I) -> Result<PathBuf, String> {
+    let path = args.next().map(PathBuf::from).ok_or_else(|| "missing file".to_string())?;
+    no_more(args)?;
+    Ok(path)
+}
+
+fn no_more<I: Iterator<Item = String>>(mut args: I) -> Result<(), String> {
+    match args.next() {
+        Some(arg) => Err(format!("unexpected argument: {}", arg)),
+        None => Ok(())
+    }
+}
+
+fn control<I: Iterator<Item = String>>(args: I, line: &str) -> Result<Command, String> {
+    no_more(args)?;
+    Ok(Command::Control(line.to_string()))
+}
+
+/// A click on a toast, as `pauza:` to show pauza, starting it if need be, or `pauza:ACTION`.
+fn parse_activation<I: Iterator<Item = String>>(args: I, arguments: &str) -> Result<Command, String> {
+    no_more(args)?;
+    match arguments.trim_matches('/') {
+        "" | "show" => Ok(Command::Run),
+        other => ClickAction::parse(other)
+            .map(Command::Activate)
+            .ok_or_else(|| format!("unknown activation: {}", other))
+    }
+}
+
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    match args.next().as_deref() {
+        None => Ok(Command::Run),
+        Some("export") => parse_export(args),
+        Some("status") => control(args, "status"),
+        Some("pause") => parse_pause(args),
+        Some("resume") => control(args, "resume"),
+        Some("break") => control(args, "break-now"),
+        Some("start-break") => control(args, "start-break"),
+        Some("snooze") => control(args, "snooze"),
+        Some("reset") => control(args, "reset"),
+        Some("simulate") | Some("--simulate") => parse_simulate(args),
+        Some("decrypt") => parse_decrypt(args),
+        Some("play-sound") => parse_play_sound(args),
+        Some("export-settings") => parse_path(args).map(Command::ExportSettings),
+        Some("import-settings") => parse_path(args).map(Command::ImportSettings),
+        Some("backup") => parse_backup(args),
+        Some("restore") => parse_path(args).map(Command::Restore),
+        Some("email-report") => no_more(args).map(|()| Command::EmailReport),
+        Some("install-autostart") | Some("--install-autostart") => no_more(args).map(|()| Command::Autostart(true)),
+        Some("uninstall-autostart") | Some("--uninstall-autostart") => no_more(args).map(|()| Command::Autostart(false)),
+        Some(arg) if arg.starts_with("pauza:") => parse_activation(args, &arg["pauza:".len()..]),
+        Some(arg) => Err(format!("unknown command: {}", arg))
+    }
+}
+
+/// Runs a command that doesn't start the GUI, returning the process exit code.
+pub fn run(command: Command) -> i32 {
+    match command {
+        Command::Run => 0,
+        Command::Export { format, from, output } => {
+            store::set_encryption(Config::load().storage.encryption);
+            let result = match &output {
+                Some(path) => export::export_to_file(format, from.as_deref(), path),
+                None => export::export_to_stdout(format, from.as_deref())
+            };
+            match result {
+                Ok(count) => {
+                    if output.is_some() {
+                        println!("Exported {} intervals", count);
+                    }
+                    0
+                },
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    1
+                }
+            }
+        },
+        Command::Simulate(script) => simulate::run(script.as_deref()),
+        Command::Autostart(enable) => {
+            let result = if enable { enable_autostart() } else { disable_autostart() };
+            match result {
+                Ok(()) => {
+                    println!("{}", if enable { "Pauza will start at login" } else { "Pauza won't start at login" });
+                    0
+                },
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    1
+                }
+            }
+        },
+        Command::Decrypt { path, output } => match export::decrypt_file(&path, output.as_deref()) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                1
+            }
+        },
+        Command::PlaySound(event) => {
+            let config = Config::load();
+            if play_sound(config.sounds.get(event), true) { 0 } else { 1 }
+        },
+        Command::ExportSettings(path) => match settings::export(&path) {
+            Ok(()) => {
+                println!("Exported the settings to {}", path.display());
+                0
+            },
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                1
+            }
+        },
+        Command::ImportSettings(path) => match settings::import(&path) {
+            Ok((_, problems)) => {
+                for problem in problems {
+                    eprintln!("WARNING: {}", problem);
+                }
+                println!("Imported the settings, which apply from the next start of pauza");
+                0
+            },
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                1
+            }
+        },
+        Command::Backup(folder) => {
+            let config = Config::load();
+            store::set_encryption(config.storage.encryption);
+            let folder = match folder.or_else(|| config.backup.folder.clone().map(PathBuf::from)) {
+                Some(folder) => folder,
+                None => {
+                    eprintln!("ERROR: no folder given, and none set in [backup]");
+                    return 1;
+                }
+            };
+            let result = std::fs::create_dir_all(&folder)
+                .map_err(backup::BackupError::from)
+                .and_then(|()| backup::back_up(&folder, config.backup.keep));
+            match result {
+                Ok(backup) => {
+                    println!("Backed up to {}", backup.display());
+                    0
+                },
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    1
+                }
+            }
+        },
+        Command::Restore(folder) => match backup::restore(&folder) {
+            Ok(()) => {
+                println!("Restored the backup, which pauza puts in place on its next start");
+                0
+            },
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                1
+            }
+        },
+        Command::EmailReport => {
+            let config = Config::load();
+            store::set_encryption(config.storage.encryption);
+            let result = History::open()
+                .and_then(|history| WeeklyReport::generate(&history, SystemTime::now()))
+                .map_err(|e| e.to_string())
+                .and_then(|report| email::send_report(&config.email, &report).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => {
+                    println!("Sent the weekly summary to {}", config.email.to);
+                    0
+                },
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    1
+                }
+            }
+        },
+        Command::Activate(action) => {
+            let line = match action {
+                ClickAction::ShowWindow => {
+                    activate_existing();
+                    return 0;
+                },
+                ClickAction::Stats => {
+                    show_existing_stats();
+                    return 0;
+                },
+                ClickAction::StartBreak => "start-break",
+                ClickAction::Snooze => "snooze",
+                ClickAction::Nothing => return 0
+            };
+            match ipc::send_command(line) {
+                Ok(_) => 0,
+                Err(e) => {
+                    log::error!("Failed to pass on a click on a toast: {}", e);
+                    1
+                }
+            }
+        },
+        Command::Control(line) => match ipc::send_command(&line) {
+            Ok(response) => {
+                println!("{}", response);
+                let ok = serde_json::from_str::<serde_json::Value>(&response)
+                    .map(|value| value["ok"] == true)
+                    .unwrap_or(false);
+                if ok { 0 } else { 1 }
+            },
+            Err(e) => {
+                println!("{}", json!({ "ok": false, "error": format!("pauza is not running: {}", e) }));
+                1
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Time as the monitor sees it. `now` is monotonic and times everything; `wall` can jump
+/// when the clock is set or DST starts, so it only places things on the calendar.
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn wall(&self) -> SystemTime;
+    fn sleep(&self, duration: Duration);
+    /// Waits up to `timeout` for something to come through `r`.
+    fn wait<T>(&self, r: &Receiver<T>, timeout: Duration) -> Option<T>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn wait<T>(&self, r: &Receiver<T>, timeout: Duration) -> Option<T> {
+        match r.recv_timeout(timeout) {
+            Ok(value) => Some(value),
+            Err(RecvTimeoutError::Timeout) => None,
+            // Nothing more can come, but the caller still expects the time to pass.
+            Err(RecvTimeoutError::Disconnected) => {
+                thread::sleep(timeout);
+                None
+            }
+        }
+    }
+}
+
+/// A clock that only moves when slept on, and then straight away.
+pub struct SimulatedClock {
+    start: Instant,
+    /// The wall clock at `start`, as it reads now.
+    wall: Cell<SystemTime>,
+    elapsed: Cell<Duration>,
+}
+
+impl SimulatedClock {
+    pub fn new(wall: SystemTime) -> SimulatedClock {
+        SimulatedClock {
+            start: Instant::now(),
+            wall: Cell::new(wall),
+            elapsed: Cell::new(Duration::from_secs(0)),
+        }
+    }
+
+    /// Sets the wall clock to `wall`, as the user or NTP might, leaving the monotonic
+    /// clock be.
+    #[cfg(test)]
+    pub fn set_wall(&self, wall: SystemTime) {
+        self.wall.set(wall - self.elapsed.get());
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn wall(&self) -> SystemTime {
+        self.wall.get() + self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+
+    /// Whatever was sent is taken at the end of the wait, as if it had come just then.
+    fn wait<T>(&self, r: &Receiver<T>, timeout: Duration) -> Option<T> {
+        self.sleep(timeout);
+        r.try_recv().ok()
+    }
+}
@@ -0,0 +1,59 @@
+use crossbeam::channel::Receiver;
+use std::os::windows::process::CommandExt;
+use std::process::{Command, Stdio};
+use crate::config::CommandConfig;
+use crate::history::IntervalKind;
+use crate::status::Status;
+use crate::{Event, State};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+fn expand(line: &str, event: &str, status: &Status, duration: Option<u64>) -> String {
+    line.replace("{event}", event)
+        .replace("{elapsed}", &status.elapsed.to_string())
+        .replace("{remaining}", &status.remaining.to_string())
+        .replace("{duration}", &duration.map(|d| d.to_string()).unwrap_or_default())
+}
+
+/// Starts the command without a console window and without waiting for it.
+fn spawn(line: &str) {
+    let result = Command::new("cmd")
+        .arg("/C")
+        .raw_arg(line)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn();
+    match result {
+        Ok(child) => log::info!("Started `{}` as process {}", line, child.id()),
+        Err(e) => log::error!("Failed to run `{}`: {}", line, e)
+    }
+}
+
+/// Runs the configured commands for break prompts, resets, and breaks starting and ending.
+pub fn run_on_events(config: CommandConfig, r: Receiver<Event>) {
+    let mut status = Status::default();
+    let mut on_break = false;
+    for event in r.iter() {
+        status.apply(&event);
+        let (name, lines, duration) = match &event {
+            Event::NotifyBreak => ("break_prompt", &config.on_break_prompt, None),
+            Event::NotifyReset => ("reset", &config.on_reset, None),
+            Event::UpdateState(state) => {
+                let started = *state == State::OnBreak && !on_break;
+                on_break = *state == State::OnBreak;
+                if !started {
+                    continue;
+                }
+                ("break_start", &config.on_break_start, None)
+            },
+            Event::Interval(interval) if interval.kind == IntervalKind::Break =>
+                ("break_end", &config.on_break_end, Some(interval.duration.as_secs())),
+            _ => continue
+        };
+        for line in lines {
+            spawn(&expand(line, name, &status, duration));
+        }
+    }
+}
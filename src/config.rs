@@ -0,0 +1,1833 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::history::IdleCause;
+use crate::i18n;
+use crate::notifications::Topic;
+use crate::{BREAK_TIME, IDLE_PAUSE_TIME, IDLE_RESET_TIME};
+
+const CONFIG_FILE: &str = "config.toml";
+/// Longer than this after a call, a held back reminder is as good as dropped.
+const MAX_EXTRA_MINUTES: u64 = 120;
+const MAX_BREAK_MINUTES: u64 = 120;
+const MAX_SNOOZE_MINUTES: u64 = 120;
+const MAX_REPEAT_MINUTES: u64 = 120;
+const MAX_GRACE_MINUTES: u64 = 120;
+/// A day: an absence any longer is a break by any measure.
+const MAX_RESET_MINUTES: u64 = 24 * 60;
+/// A day, the longest the daily limit can be and its warnings can come ahead or apart.
+const MAX_DAILY_MINUTES: u64 = 24 * 60;
+/// A week, the longest between reading the calendar or syncing.
+const MAX_INTERVAL_MINUTES: u64 = 7 * 24 * 60;
+/// A year, the longest between backups or looking for updates.
+const MAX_INTERVAL_HOURS: u64 = 365 * 24;
+const MAX_INPUT_WEIGHT: f64 = 10.0;
+/// The least weight short of ignoring an input altogether, 0.
+const MIN_INPUT_WEIGHT: f64 = 0.05;
+/// Settings that give access to the user's accounts, calendar or machines, by dotted key.
+/// They are left out of crash reports and exported settings, and never synced.
+pub const SECRETS: &[&str] = &[
+    "meetings.calendar_url", "api.token", "api.lan_token", "mqtt.username", "mqtt.password",
+    "slack.token", "webhooks.urls", "toggl.api_token", "sync.username", "sync.password",
+    "devices.token", "handoff.token", "email.username", "email.password", "hue.username",
+    "storage.passphrase",
+];
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Loaded instead of the icon embedded in the executable.
+    pub icon_file: Option<String>,
+    /// A language code like `de` for the messages, instead of the Windows display language.
+    pub language: Option<String>,
+    /// Keeps just the break timer running, recording no history, app usage or input counts.
+    /// Switched from the tray menu or with its hotkey.
+    pub privacy: bool,
+    /// For an account several people share: keeps the history in memory only until pauza
+    /// quits, and starts the timer and the day's active time from zero at each start.
+    pub guest: bool,
+    pub window: WindowConfig,
+    pub mini: MiniConfig,
+    pub theme: ThemeConfig,
+    pub display: DisplayConfig,
+    pub report: ReportConfig,
+    pub messages: MessageConfig,
+    pub reminders: ReminderConfig,
+    pub notifications: NotificationConfig,
+    pub clicks: ClickConfig,
+    pub tips: TipConfig,
+    pub sounds: SoundConfig,
+    pub break_audio: BreakAudioConfig,
+    pub flash: FlashConfig,
+    pub fullscreen: FullscreenConfig,
+    pub meetings: MeetingConfig,
+    pub processes: ProcessConfig,
+    pub input: InputConfig,
+    pub idle: IdleConfig,
+    pub api: ApiConfig,
+    pub mqtt: MqttConfig,
+    pub hotkeys: HotkeyConfig,
+    pub slack: SlackConfig,
+    pub discord: DiscordConfig,
+    pub webhooks: WebhookConfig,
+    pub commands: CommandConfig,
+    pub toggl: TogglConfig,
+    pub activitywatch: ActivityWatchConfig,
+    pub usage: UsageConfig,
+    pub daily_limit: DailyLimitConfig,
+    pub intensity: IntensityConfig,
+    pub storage: StorageConfig,
+    pub sync: SyncConfig,
+    pub devices: DevicesConfig,
+    pub handoff: HandoffConfig,
+    pub backup: BackupConfig,
+    pub retention: RetentionConfig,
+    pub email: EmailConfig,
+    pub hue: HueConfig,
+    pub gaming: GamingConfig,
+    pub log: LogConfig,
+    pub updates: UpdateConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub visible: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            x: 300,
+            y: 300,
+            width: 300,
+            height: 150,
+            visible: true,
+        }
+    }
+}
+
+/// The always-on-top mini timer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MiniConfig {
+    pub x: i32,
+    pub y: i32,
+    pub visible: bool,
+}
+
+impl Default for MiniConfig {
+    fn default() -> MiniConfig {
+        MiniConfig {
+            x: 40,
+            y: 40,
+            visible: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    System,
+    Light,
+    Dark
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub mode: ThemeMode,
+    /// `#rrggbb`, used for the progress fill of the tray icon. High contrast themes use their own colors.
+    pub accent: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// `01:23:45`
+    #[default]
+    Clock,
+    /// `1 h 23 m`, in the language of the messages.
+    Words
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeShown {
+    /// `Break in 00:07:48`
+    #[default]
+    Remaining,
+    /// `Worked 00:37:12`
+    Elapsed
+}
+
+impl TimeShown {
+    pub fn toggled(self) -> TimeShown {
+        match self {
+            TimeShown::Remaining => TimeShown::Elapsed,
+            TimeShown::Elapsed => TimeShown::Remaining
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// How durations are shown in the window, the tray tooltip and notifications.
+    pub time_format: TimeFormat,
+    /// Whether the timer counts down to the break or up from the last one, everywhere
+    /// it's shown. Clicking the time in the window switches it.
+    pub time_shown: TimeShown,
+    /// Shows the time until the break in a large font, to read from across the room.
+    pub big_timer: bool,
+    /// The tray icon pulses for this many minutes before the break; 0 never.
+    pub countdown_minutes: u64,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> DisplayConfig {
+        DisplayConfig {
+            time_format: TimeFormat::default(),
+            time_shown: TimeShown::default(),
+            big_timer: false,
+            countdown_minutes: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Sunday,
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday
+}
+
+impl Weekday {
+    /// Days since Sunday, as Windows' `SYSTEMTIME::wDayOfWeek` counts them.
+    pub fn number(&self) -> u16 {
+        *self as u16
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    pub weekly: bool,
+    pub weekday: Weekday,
+    /// Local hour from which the weekly summary is shown.
+    pub hour: u16,
+}
+
+impl Default for ReportConfig {
+    fn default() -> ReportConfig {
+        ReportConfig {
+            weekly: true,
+            weekday: Weekday::Monday,
+            hour: 9,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageOrder {
+    #[default]
+    Sequential,
+    Random
+}
+
+/// Break reminders show these in turn instead of the built-in message.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageConfig {
+    pub breaks: Vec<String>,
+    pub order: MessageOrder,
+}
+
+/// How a break reminder is given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReminderStyle {
+    /// A notification from the tray icon.
+    #[default]
+    Balloon,
+    /// A notification that stays in the Action Center until dismissed, or a balloon
+    /// where Windows has no toasts.
+    Toast,
+    /// Flashing the window and its taskbar button until it is brought to the front.
+    Flash,
+    /// Bringing the window to the front.
+    Window
+}
+
+/// How often a break reminder is repeated while the break isn't taken, and how each
+/// one is given: the first reminder in the first style of `escalation`, each repeat in
+/// the next, staying with the last.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderConfig {
+    /// 0 reminds only once.
+    pub repeat_minutes: u64,
+    pub max_repeats: u32,
+    pub escalation: Vec<ReminderStyle>,
+    /// How long snoozing the reminder puts it off.
+    pub snooze_minutes: u64,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> ReminderConfig {
+        ReminderConfig {
+            repeat_minutes: 0,
+            max_repeats: 3,
+            escalation: vec![ReminderStyle::Balloon],
+            snooze_minutes: 10,
+        }
+    }
+}
+
+impl ReminderConfig {
+    /// The style of the reminder after `repeats` repeats.
+    pub fn style(&self, repeats: u32) -> ReminderStyle {
+        self.escalation.get(repeats as usize)
+            .or(self.escalation.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Where a notification is delivered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Delivery {
+    /// A notification from the tray icon.
+    #[default]
+    Balloon,
+    /// A notification that stays in the Action Center until dismissed.
+    Toast,
+    /// Covers the screen until clicked or a key is pressed.
+    Overlay,
+    /// Only written to the log, without a sound.
+    #[serde(rename = "none")]
+    Log
+}
+
+/// How each kind of notification is delivered.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Unset, break reminders are a balloon or a toast as `[reminders] escalation` says.
+    /// Set, the reminders it has as a balloon or a toast are delivered this way instead.
+    pub breaks: Option<Delivery>,
+    pub reset: Delivery,
+    pub daily_limit: Delivery,
+    pub report: Delivery,
+    pub update: Delivery,
+    pub badges: Delivery,
+    pub budgets: Delivery,
+}
+
+impl NotificationConfig {
+    pub fn get(&self, topic: Topic) -> Delivery {
+        match topic {
+            Topic::Reset => self.reset,
+            Topic::DailyLimit => self.daily_limit,
+            Topic::Report => self.report,
+            Topic::Update => self.update,
+            Topic::Badges => self.badges,
+            Topic::Budgets => self.budgets,
+            Topic::Break => self.breaks.unwrap_or_default()
+        }
+    }
+
+    pub fn uses(&self, delivery: Delivery) -> bool {
+        self.breaks == Some(delivery)
+            || [self.reset, self.daily_limit, self.report, self.update, self.badges, self.budgets].contains(&delivery)
+    }
+
+    /// Whether anything is delivered as a toast, with the break reminders as `reminders` has
+    /// them unless `breaks` is set.
+    pub fn uses_toasts(&self, reminders: &ReminderConfig) -> bool {
+        self.uses(Delivery::Toast) || (self.breaks.is_none() && reminders.escalation.contains(&ReminderStyle::Toast))
+    }
+}
+
+/// What clicking a notification or the tray icon does.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    ShowWindow,
+    Stats,
+    /// Starts the break reminded of, as acknowledging it does.
+    StartBreak,
+    /// Reminds of the break again after `[reminders] snooze_minutes`.
+    Snooze,
+    Nothing
+}
+
+impl ClickAction {
+    /// The action of a click on a toast, from what follows `pauza:` in its arguments.
+    pub fn parse(arguments: &str) -> Option<ClickAction> {
+        match arguments {
+            "" | "show" => Some(ClickAction::ShowWindow),
+            "stats" => Some(ClickAction::Stats),
+            "start-break" => Some(ClickAction::StartBreak),
+            "snooze" => Some(ClickAction::Snooze),
+            "nothing" => Some(ClickAction::Nothing),
+            _ => None
+        }
+    }
+
+    pub fn arguments(&self) -> &'static str {
+        match self {
+            ClickAction::ShowWindow => "show",
+            ClickAction::Stats => "stats",
+            ClickAction::StartBreak => "start-break",
+            ClickAction::Snooze => "snooze",
+            ClickAction::Nothing => "nothing"
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClickConfig {
+    /// Unset, clicking the break reminder starts the break with `[idle] require_acknowledgment`
+    /// and does nothing otherwise.
+    pub break_notification: Option<ClickAction>,
+    pub tray_double_click: ClickAction,
+}
+
+impl Default for ClickConfig {
+    fn default() -> ClickConfig {
+        ClickConfig {
+            break_notification: None,
+            tray_double_click: ClickAction::ShowWindow,
+        }
+    }
+}
+
+/// Wellness tips shown with break reminders and when a break is over. `tips.json` in
+/// the data directory, a list of `{"category": ..., "text": ...}`, replaces the built-in ones.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TipConfig {
+    pub enabled: bool,
+    /// Like `hydration`, `eyes`, `ergonomics`, `movement` or `breathing`; empty for all.
+    pub categories: Vec<String>,
+}
+
+impl Default for TipConfig {
+    fn default() -> TipConfig {
+        TipConfig {
+            enabled: true,
+            categories: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sound {
+    pub enabled: bool,
+    /// A `.wav` file, or empty for the Windows notification sound.
+    pub file: Option<String>,
+    /// From 0 to 100.
+    pub volume: u8,
+}
+
+impl Default for Sound {
+    fn default() -> Sound {
+        Sound {
+            enabled: false,
+            file: None,
+            volume: 100,
+        }
+    }
+}
+
+/// The events that can play a sound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoundEvent {
+    BreakPrompt,
+    Reset,
+    DailyLimit
+}
+
+impl SoundEvent {
+    pub fn parse(name: &str) -> Option<SoundEvent> {
+        match name {
+            "break" | "break_prompt" => Some(SoundEvent::BreakPrompt),
+            "reset" => Some(SoundEvent::Reset),
+            "daily_limit" => Some(SoundEvent::DailyLimit),
+            _ => None
+        }
+    }
+}
+
+/// Played instead of the sound of the notification, for each event that has one enabled.
+/// With it disabled, the event's notifications make no sound at all.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundConfig {
+    pub break_prompt: Sound,
+    pub reset: Sound,
+    pub daily_limit: Sound,
+}
+
+impl SoundConfig {
+    pub fn get(&self, event: SoundEvent) -> &Sound {
+        match event {
+            SoundEvent::BreakPrompt => &self.break_prompt,
+            SoundEvent::Reset => &self.reset,
+            SoundEvent::DailyLimit => &self.daily_limit
+        }
+    }
+
+    fn get_mut(&mut self, event: SoundEvent) -> &mut Sound {
+        match event {
+            SoundEvent::BreakPrompt => &mut self.break_prompt,
+            SoundEvent::Reset => &mut self.reset,
+            SoundEvent::DailyLimit => &mut self.daily_limit
+        }
+    }
+}
+
+/// What happens to the sound playing when a break starts, undone when the break ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakAudio {
+    #[default]
+    Nothing,
+    /// Turns the volume down to `duck_percent` of what it was.
+    Duck,
+    Mute,
+    /// Presses the media play/pause key, which most players answer, if sound is playing.
+    Pause
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakAudioConfig {
+    pub action: BreakAudio,
+    pub duck_percent: u32,
+}
+
+impl Default for BreakAudioConfig {
+    fn default() -> BreakAudioConfig {
+        BreakAudioConfig {
+            action: BreakAudio::Nothing,
+            duck_percent: 20,
+        }
+    }
+}
+
+/// Flashes the window and its taskbar button once the break is overdue by `grace_minutes`,
+/// until the window is brought to the front.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlashConfig {
+    pub enabled: bool,
+    pub grace_minutes: u64,
+}
+
+impl Default for FlashConfig {
+    fn default() -> FlashConfig {
+        FlashConfig {
+            enabled: true,
+            grace_minutes: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FullscreenConfig {
+    /// Hold back break reminders while a fullscreen app or presentation is in front.
+    pub defer_notifications: bool,
+    /// Stop the work timer too until the fullscreen app is gone.
+    pub pause_timer: bool,
+}
+
+impl Default for FullscreenConfig {
+    fn default() -> FullscreenConfig {
+        FullscreenConfig {
+            defer_notifications: true,
+            pause_timer: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeetingConfig {
+    /// Hold back break reminders while the microphone or webcam is in use.
+    pub defer_notifications: bool,
+    /// Extra minutes after a call ends before a held back reminder shows.
+    pub extra_minutes: u64,
+    /// An iCalendar feed, like Outlook's or Google's secret ICS address, to plan breaks around.
+    pub calendar_url: Option<String>,
+    pub calendar_refresh_minutes: u64,
+    /// How much sooner than due a break may be prompted to fit in before a meeting.
+    pub early_minutes: u64,
+}
+
+impl Default for MeetingConfig {
+    fn default() -> MeetingConfig {
+        MeetingConfig {
+            defer_notifications: true,
+            extra_minutes: 0,
+            calendar_url: None,
+            calendar_refresh_minutes: 15,
+            early_minutes: 15,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessConfig {
+    /// Executable names, like `powerpnt.exe`, that hold back break reminders while in front.
+    pub quiet: Vec<String>,
+    /// Executable names that also stop the work timer while in front.
+    pub pause: Vec<String>,
+}
+
+fn contains_name(names: &[String], name: &str) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+impl ProcessConfig {
+    pub fn is_empty(&self) -> bool {
+        self.quiet.is_empty() && self.pause.is_empty()
+    }
+
+    pub fn is_quiet(&self, name: &str) -> bool {
+        contains_name(&self.quiet, name) || self.pauses(name)
+    }
+
+    pub fn pauses(&self, name: &str) -> bool {
+        contains_name(&self.pause, name)
+    }
+}
+
+/// Activity besides keyboard and mouse input that keeps the timer from going idle.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub gamepad: bool,
+    /// Sound playing on the default output device.
+    pub audio: bool,
+    /// How much typing counts towards being active. The time since the last key press is
+    /// divided by it, so 2 keeps the timer working twice as long after typing, and 0 ignores it.
+    pub keyboard_weight: f64,
+    /// Like `keyboard_weight`, for moving, clicking and scrolling the mouse.
+    pub mouse_weight: f64,
+}
+
+impl Default for InputConfig {
+    fn default() -> InputConfig {
+        InputConfig {
+            gamepad: false,
+            audio: false,
+            keyboard_weight: 1.0,
+            mouse_weight: 1.0,
+        }
+    }
+}
+
+impl InputConfig {
+    /// True when keyboard and mouse input have to be told apart.
+    pub fn weighted(&self) -> bool {
+        self.keyboard_weight != 1.0 || self.mouse_weight != 1.0
+    }
+}
+
+/// How long each kind of absence lasts before it counts as a break and resets the timer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// No keyboard or mouse input.
+    pub inactive_reset_minutes: u64,
+    pub locked_reset_minutes: u64,
+    pub asleep_reset_minutes: u64,
+    /// Switched to another user or a remote session that ended.
+    pub disconnected_reset_minutes: u64,
+    /// Breaks start only when the user says so from the reminder. Until then being away
+    /// stops the timer instead of resetting it, so compliance counts real breaks.
+    pub require_acknowledgment: bool,
+}
+
+impl Default for IdleConfig {
+    fn default() -> IdleConfig {
+        let minutes = IDLE_RESET_TIME.as_secs() / 60;
+        IdleConfig {
+            inactive_reset_minutes: minutes,
+            locked_reset_minutes: minutes,
+            asleep_reset_minutes: minutes,
+            disconnected_reset_minutes: minutes,
+            require_acknowledgment: false,
+        }
+    }
+}
+
+impl IdleConfig {
+    pub fn reset_after(&self, cause: IdleCause) -> Duration {
+        let minutes = match cause {
+            IdleCause::Inactive => self.inactive_reset_minutes,
+            IdleCause::Locked => self.locked_reset_minutes,
+            IdleCause::Asleep => self.asleep_reset_minutes,
+            IdleCause::Disconnected => self.disconnected_reset_minutes
+        };
+        Duration::from_secs(minutes.saturating_mul(60))
+    }
+}
+
+/// The HTTP API on localhost, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// The WebSocket event stream, protected by the same token.
+    pub events_port: u16,
+    /// Needed as `Authorization: Bearer TOKEN` or `?token=TOKEN`. Generated when missing.
+    pub token: Option<String>,
+    /// Also serves the dashboard and what it reads, but nothing that controls the timer, on
+    /// every network interface at `lan_port`, to open the dashboard on a phone as
+    /// `http://<this machine>:<lan_port>/?token=<lan_token>`.
+    pub lan: bool,
+    pub lan_port: u16,
+    /// The token of the read-only API on the network. Generated when missing.
+    pub lan_token: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> ApiConfig {
+        ApiConfig {
+            enabled: false,
+            port: 7645,
+            events_port: 7646,
+            token: None,
+            lan: false,
+            lan_port: 7647,
+            lan_token: None,
+        }
+    }
+}
+
+fn ensure_token(token: &mut Option<String>) -> bool {
+    if token.as_deref().is_some_and(|token| !token.is_empty()) {
+        return false;
+    }
+    let mut bytes = [0u8; 16];
+    if let Err(e) = getrandom::getrandom(&mut bytes) {
+        log::error!("Failed to generate an API token: {}", e);
+        return false;
+    }
+    *token = Some(bytes.iter().map(|b| format!("{:02x}", b)).collect());
+    true
+}
+
+impl ApiConfig {
+    /// Generates random tokens where there are none, returning whether it did.
+    pub fn ensure_token(&mut self) -> bool {
+        let generated = ensure_token(&mut self.token);
+        let lan_generated = self.lan && ensure_token(&mut self.lan_token);
+        generated || lan_generated
+    }
+}
+
+/// Publishes the state and remaining time to an MQTT broker, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Prefix of the `state`, `remaining` and `availability` topics.
+    pub topic: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Announces the sensors and buttons to Home Assistant.
+    pub discovery: bool,
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> MqttConfig {
+        MqttConfig {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "pauza".to_string(),
+            topic: "pauza".to_string(),
+            username: None,
+            password: None,
+            discovery: true,
+            discovery_prefix: "homeassistant".to_string(),
+        }
+    }
+}
+
+/// System-wide key combinations like `Ctrl+Alt+B`; empty to leave one unset.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    pub break_now: String,
+    /// Pauses the timer, or resumes it when paused.
+    pub pause: String,
+    pub show_window: String,
+    /// Turns privacy mode on or off.
+    pub privacy: String,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> HotkeyConfig {
+        HotkeyConfig {
+            break_now: "Ctrl+Alt+B".to_string(),
+            pause: "Ctrl+Alt+P".to_string(),
+            show_window: "Ctrl+Alt+W".to_string(),
+            privacy: "Ctrl+Alt+H".to_string(),
+        }
+    }
+}
+
+/// Sets the Slack status during breaks, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlackConfig {
+    pub enabled: bool,
+    /// A user token with the `users.profile:write` scope.
+    pub token: Option<String>,
+    pub emoji: String,
+    /// `{time}` is replaced with when the break is expected to end.
+    pub text: String,
+    pub break_minutes: u64,
+}
+
+impl Default for SlackConfig {
+    fn default() -> SlackConfig {
+        SlackConfig {
+            enabled: false,
+            token: None,
+            emoji: ":person_in_lotus_position:".to_string(),
+            text: "On a break, back at {time}".to_string(),
+            break_minutes: 10,
+        }
+    }
+}
+
+/// Shows the state as Discord Rich Presence, off unless enabled.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    /// The id of a Discord application, whose name is shown as the activity.
+    pub client_id: String,
+}
+
+/// URLs that get a JSON POST when breaks are prompted, started, completed or skipped.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+}
+
+/// Command lines run through `cmd /C` on events. `{event}`, `{elapsed}`, `{remaining}`
+/// and, when a break ends, `{duration}` are replaced with the event's data in seconds.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    pub on_break_prompt: Vec<String>,
+    pub on_reset: Vec<String>,
+    pub on_break_start: Vec<String>,
+    pub on_break_end: Vec<String>,
+}
+
+impl CommandConfig {
+    pub fn is_empty(&self) -> bool {
+        self.on_break_prompt.is_empty()
+            && self.on_reset.is_empty()
+            && self.on_break_start.is_empty()
+            && self.on_break_end.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TogglMode {
+    /// Stop the running entry for the break, track the break, then resume the entry.
+    Switch,
+    /// Only tag the running entry with `break`.
+    Tag
+}
+
+/// Tracks breaks in Toggl Track, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TogglConfig {
+    pub enabled: bool,
+    pub api_token: Option<String>,
+    pub mode: TogglMode,
+    pub break_description: String,
+}
+
+impl Default for TogglConfig {
+    fn default() -> TogglConfig {
+        TogglConfig {
+            enabled: false,
+            api_token: None,
+            mode: TogglMode::Switch,
+            break_description: "Break".to_string(),
+        }
+    }
+}
+
+/// Sends the state to a local ActivityWatch server, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityWatchConfig {
+    pub enabled: bool,
+    pub url: String,
+}
+
+impl Default for ActivityWatchConfig {
+    fn default() -> ActivityWatchConfig {
+        ActivityWatchConfig {
+            enabled: false,
+            url: "http://localhost:5600".to_string(),
+        }
+    }
+}
+
+/// Records which app is in front while working, per app and day, for the stats window.
+/// Window titles are kept only as a hash. Off unless enabled.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    pub enabled: bool,
+    /// Groups of apps, like `Communication`, with an optional daily budget each.
+    pub categories: Vec<AppCategory>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppCategory {
+    pub name: String,
+    /// Executable names, like `slack.exe`. An app counts towards the first category listing it.
+    pub apps: Vec<String>,
+    /// Time in the category a day past which a notification says so, once a day.
+    pub budget_minutes: Option<u64>,
+}
+
+impl AppCategory {
+    pub fn contains(&self, app: &str) -> bool {
+        contains_name(&self.apps, app)
+    }
+}
+
+/// A cap on active computer time a day, apart from the break reminders, with warnings as
+/// it nears and passes. Off unless `limit_minutes` is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyLimitConfig {
+    pub limit_minutes: Option<u64>,
+    /// How long before the limit to give the first warning.
+    pub warn_minutes: u64,
+    /// How often to warn again once past the limit.
+    pub repeat_minutes: u64,
+}
+
+impl Default for DailyLimitConfig {
+    fn default() -> DailyLimitConfig {
+        DailyLimitConfig {
+            limit_minutes: None,
+            warn_minutes: 30,
+            repeat_minutes: 30,
+        }
+    }
+}
+
+/// Counts key presses and clicks per minute, never which keys or where, to chart how busy
+/// each hour was in the stats window. Off unless enabled.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntensityConfig {
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encryption {
+    #[default]
+    None,
+    /// With a key only the current Windows account can unlock.
+    Dpapi,
+    /// With a key derived from `storage.passphrase`, kept in the Windows credential store,
+    /// or from the `PAUZA_PASSPHRASE` environment variable.
+    Passphrase
+}
+
+/// Keeps the history, and files exported from it, unreadable to other accounts on the machine.
+/// Switching it converts the existing history.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub encryption: Encryption,
+    /// Moved into the Windows credential store on the next start, and taken out of here.
+    pub passphrase: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncService {
+    #[default]
+    WebDav,
+    /// A bucket of Amazon S3 or a service compatible with it, addressed by path.
+    S3
+}
+
+/// Keeps the settings, and the history too if `history` is on, the same across machines
+/// through a folder on a WebDAV or S3 server, off unless enabled. Each machine keeps working
+/// on its own copy while the server can't be reached; where two machines changed the same
+/// setting, the later change wins.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub enabled: bool,
+    pub service: SyncService,
+    /// The folder, like `https://dav.example.com/pauza` or `https://s3.example.com/bucket/pauza`.
+    pub url: String,
+    /// For S3, the access key ID.
+    pub username: Option<String>,
+    /// For S3, the secret access key.
+    pub password: Option<String>,
+    /// For S3, the region of the bucket.
+    pub region: String,
+    /// Shares the history as well. Not while the history is encrypted, which would give that away.
+    pub history: bool,
+    /// This machine's name in the shared history, the computer name unless set.
+    pub machine: Option<String>,
+    pub interval_minutes: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> SyncConfig {
+        SyncConfig {
+            enabled: false,
+            service: SyncService::WebDav,
+            url: String::new(),
+            username: None,
+            password: None,
+            region: "us-east-1".to_string(),
+            history: false,
+            machine: None,
+            interval_minutes: 15,
+        }
+    }
+}
+
+impl SyncConfig {
+    /// Whether there are credentials to send and `url` isn't HTTPS, so anyone on the way
+    /// could read them.
+    pub fn is_unencrypted(&self) -> bool {
+        let set = |value: &Option<String>| value.as_deref().is_some_and(|value| !value.is_empty());
+        let https = self.url.get(..8).is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"));
+        (set(&self.username) || set(&self.password)) && !https
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DevicesMode {
+    #[default]
+    Off,
+    /// The others report to this one.
+    Server,
+    Client
+}
+
+/// Counts input on any of several machines as work on each of them, so a break only counts
+/// once away from all of them. One of them is the server the others report to. Off unless set.
+/// Only idle time is shared: each machine keeps its own timer, which `handoff` can carry over.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevicesConfig {
+    pub mode: DevicesMode,
+    /// For the server, the port it listens on.
+    pub port: u16,
+    /// For the server, the address of the network interface it listens on, like
+    /// `192.168.1.20`, every interface unless set.
+    pub address: Option<IpAddr>,
+    /// For the others, the server, like `http://desktop:7647`.
+    pub server: String,
+    /// The same on the server and on every machine reporting to it.
+    pub token: Option<String>,
+    /// This machine's name, the computer name unless set.
+    pub name: Option<String>,
+}
+
+impl Default for DevicesConfig {
+    fn default() -> DevicesConfig {
+        DevicesConfig {
+            mode: DevicesMode::Off,
+            port: 7647,
+            address: None,
+            server: String::new(),
+            token: None,
+            name: None,
+        }
+    }
+}
+
+/// Finds the other pauzas on the local network, and takes over the work timer of the one
+/// the user just left, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HandoffConfig {
+    pub enabled: bool,
+    /// Where the timer is offered to the others.
+    pub port: u16,
+    /// The address of the network interface the timer is offered on, like `192.168.1.20`,
+    /// every interface unless set.
+    pub address: Option<IpAddr>,
+    /// The same on every machine handing the timer over.
+    pub token: Option<String>,
+    /// This machine's name, the computer name unless set.
+    pub name: Option<String>,
+}
+
+impl Default for HandoffConfig {
+    fn default() -> HandoffConfig {
+        HandoffConfig {
+            enabled: false,
+            port: 7648,
+            address: None,
+            token: None,
+            name: None,
+        }
+    }
+}
+
+/// Copies the config and the history to a folder of the user's choosing every
+/// `interval_hours`, keeping the latest `keep` copies, off unless a folder is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub folder: Option<String>,
+    pub interval_hours: u64,
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> BackupConfig {
+        BackupConfig {
+            folder: None,
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+/// How long the history keeps the detail of each day: its intervals, app usage and input
+/// counts. The daily totals the statistics need are kept for good.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// 0 keeps everything.
+    pub detail_days: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> RetentionConfig {
+        RetentionConfig {
+            detail_days: 90,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain at first, then TLS before anything is sent, as on port 587.
+    #[default]
+    StartTls,
+    /// TLS from the start, as on port 465.
+    Tls,
+    /// No encryption at all, for a relay on the same machine.
+    None
+}
+
+/// Sends the weekly summary by email too, when it is due, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub enabled: bool,
+    pub to: String,
+    /// The sender, the address sent to unless set.
+    pub from: Option<String>,
+    /// The SMTP server, like `smtp.example.com`.
+    pub server: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    /// Moved into the Windows credential store on the next start, and taken out of here.
+    pub password: Option<String>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> EmailConfig {
+        EmailConfig {
+            enabled: false,
+            to: String::new(),
+            from: None,
+            server: String::new(),
+            port: 587,
+            security: SmtpSecurity::StartTls,
+            username: None,
+            password: None,
+        }
+    }
+}
+
+/// Turns a Philips Hue light to a break color during breaks, off unless enabled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HueConfig {
+    pub enabled: bool,
+    /// The bridge's address on the local network.
+    pub bridge: String,
+    /// A username created on the bridge by pressing its link button.
+    pub username: String,
+    pub light: String,
+    /// Hue from 0 to 65535, saturation and brightness from 0 to 254.
+    pub hue: u16,
+    pub saturation: u8,
+    pub brightness: u8,
+}
+
+impl Default for HueConfig {
+    fn default() -> HueConfig {
+        HueConfig {
+            enabled: false,
+            bridge: String::new(),
+            username: String::new(),
+            light: "1".to_string(),
+            hue: 25500,
+            saturation: 254,
+            brightness: 200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GamingMode {
+    /// Games are treated like any other app.
+    Normal,
+    /// Hold back break reminders while gaming.
+    Gentle,
+    /// Stop the work timer too while gaming.
+    Pause
+}
+
+/// How to behave while a game is played: a Direct3D fullscreen app, the Game Bar open, or one
+/// of `games`. Whether Game Mode is on can only be asked by the game itself, so it isn't what
+/// is watched.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamingConfig {
+    pub mode: GamingMode,
+    /// Executable names of games that run windowed, like `minecraft.exe`.
+    pub games: Vec<String>,
+}
+
+impl Default for GamingConfig {
+    fn default() -> GamingConfig {
+        GamingConfig {
+            mode: GamingMode::Gentle,
+            games: Vec::new(),
+        }
+    }
+}
+
+impl GamingConfig {
+    pub fn is_game(&self, name: &str) -> bool {
+        self.games.iter().any(|game| game.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace
+}
+
+/// What goes into `pauza.log` in the data directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LogLevel,
+}
+
+/// Looks for new releases in the background, on unless turned off.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub check: bool,
+    /// Where the latest release is described, in the format of GitHub's releases API.
+    pub url: String,
+    pub interval_hours: u64,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> UpdateConfig {
+        UpdateConfig {
+            check: true,
+            url: "https://api.github.com/repos/tristan/pauza/releases/latest".to_string(),
+            interval_hours: 24,
+        }
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    match env::var_os("APPDATA") {
+        Some(dir) => PathBuf::from(dir).join("pauza"),
+        None => PathBuf::from(".")
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    data_dir().join(CONFIG_FILE)
+}
+
+/// A setting that couldn't be used, so its default applies instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// Like `meetings.extra_minutes`, or empty for the file as a whole.
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+fn is_color(text: &str) -> bool {
+    text.trim().strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Reads every setting of `text` that can be read, keeping the default for each one
+/// that can't, and for sections that aren't tables.
+fn parse(text: &str) -> (Config, Vec<Problem>) {
+    let mut problems = Vec::new();
+    let user = match text.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => table,
+        Ok(_) => toml::value::Table::new(),
+        Err(e) => {
+            problems.push(Problem { key: String::new(), message: e.to_string() });
+            return (Config::default(), problems);
+        }
+    };
+    let mut accepted = toml::value::Table::new();
+    let mut accept = |section: &str, key: Option<&str>, value: &toml::Value, problems: &mut Vec<Problem>| {
+        let mut candidate = accepted.clone();
+        match key {
+            Some(key) => {
+                let entries = candidate.entry(section.to_string())
+                    .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+                if let toml::Value::Table(entries) = entries {
+                    entries.insert(key.to_string(), value.clone());
+                }
+            },
+            None => {
+                candidate.insert(section.to_string(), value.clone());
+            }
+        }
+        match toml::Value::Table(candidate.clone()).try_into::<Config>() {
+            Ok(_) => accepted = candidate,
+            Err(e) => problems.push(Problem {
+                key: key.map_or_else(|| section.to_string(), |key| format!("{}.{}", section, key)),
+                message: e.to_string(),
+            })
+        }
+    };
+    for (section, value) in &user {
+        match value {
+            toml::Value::Table(entries) => {
+                for (key, value) in entries {
+                    accept(section, Some(key), value, &mut problems);
+                }
+            },
+            _ => accept(section, None, value, &mut problems)
+        }
+    }
+    let config: Config = toml::Value::Table(accepted).try_into().unwrap_or_default();
+
+    // Whatever didn't come back out of the config was ignored while reading it.
+    let read = toml::Value::try_from(&config).ok();
+    let known = |section: &str, key: Option<&str>| {
+        let value = read.as_ref().and_then(|read| read.get(section));
+        match key {
+            Some(key) => value.and_then(|value| value.get(key)).is_some(),
+            None => value.is_some()
+        }
+    };
+    for (section, value) in &user {
+        match value {
+            toml::Value::Table(entries) if known(section, None) => {
+                for key in entries.keys() {
+                    let name = format!("{}.{}", section, key);
+                    if !known(section, Some(key)) && !problems.iter().any(|problem| problem.key == name) {
+                        problems.push(Problem { key: name, message: "unknown setting".to_string() });
+                    }
+                }
+            },
+            _ if !known(section, None) && !problems.iter().any(|problem| problem.key == *section) => {
+                problems.push(Problem { key: section.clone(), message: "unknown setting".to_string() });
+            },
+            _ => {}
+        }
+    }
+    (config, problems)
+}
+
+impl Config {
+    pub fn load() -> Config {
+        Config::load_checked().0
+    }
+
+    /// Loads the config, with the problems of each setting that was left at its default.
+    pub fn load_checked() -> (Config, Vec<Problem>) {
+        let path = config_path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_e) => return (Config::default(), Vec::new())
+        };
+        let (config, problems) = Config::from_text(&text);
+        if problems.is_empty() {
+            log::info!("Loaded {}", path.display());
+        }
+        for problem in &problems {
+            log::error!("{}: {}", path.display(), problem);
+        }
+        (config, problems)
+    }
+
+    /// Reads a config file's text, with the problems of each setting left at its default.
+    pub fn from_text(text: &str) -> (Config, Vec<Problem>) {
+        let (mut config, mut problems) = parse(text);
+        problems.extend(config.validate());
+        (config, problems)
+    }
+
+    /// Puts back the default of each setting whose value makes no sense.
+    pub fn validate(&mut self) -> Vec<Problem> {
+        let defaults = Config::default();
+        let mut problems = Vec::new();
+        let mut problem = |key: &str, message: String| {
+            problems.push(Problem { key: key.to_string(), message });
+        };
+        if let Some(icon) = self.icon_file.as_deref().filter(|icon| !Path::new(icon).exists()) {
+            problem("icon_file", format!("{} doesn't exist", icon));
+            self.icon_file = None;
+        }
+        if let Some(language) = self.language.as_deref().filter(|language| !i18n::is_shipped(language)) {
+            problem("language", format!("there is no translation for {}, only for {}", language, i18n::shipped().join(", ")));
+            self.language = None;
+        }
+        if let Some(accent) = self.theme.accent.as_deref().filter(|accent| !is_color(accent)) {
+            problem("theme.accent", format!("{} is not a color like #0078d7", accent));
+            self.theme.accent = None;
+        }
+        if self.report.hour > 23 {
+            problem("report.hour", format!("{} is not an hour of the day, which go from 0 to 23", self.report.hour));
+            self.report.hour = defaults.report.hour;
+        }
+        if self.meetings.extra_minutes > MAX_EXTRA_MINUTES {
+            problem("meetings.extra_minutes",
+                    format!("{} minutes is longer than the {} a reminder can be held back after a call", self.meetings.extra_minutes, MAX_EXTRA_MINUTES));
+            self.meetings.extra_minutes = defaults.meetings.extra_minutes;
+        }
+        if self.meetings.calendar_refresh_minutes == 0 {
+            problem("meetings.calendar_refresh_minutes", "the calendar can't be read every 0 minutes".to_string());
+            self.meetings.calendar_refresh_minutes = defaults.meetings.calendar_refresh_minutes;
+        } else if self.meetings.calendar_refresh_minutes > MAX_INTERVAL_MINUTES {
+            problem("meetings.calendar_refresh_minutes",
+                    format!("{} minutes is longer than the week the calendar can go unread", self.meetings.calendar_refresh_minutes));
+            self.meetings.calendar_refresh_minutes = defaults.meetings.calendar_refresh_minutes;
+        }
+        if self.meetings.early_minutes.saturating_mul(60) >= BREAK_TIME.as_secs() {
+            problem("meetings.early_minutes",
+                    format!("{} minutes early is as long as a whole work period of {} minutes", self.meetings.early_minutes, BREAK_TIME.as_secs() / 60));
+            self.meetings.early_minutes = defaults.meetings.early_minutes;
+        }
+        if self.display.countdown_minutes > BREAK_TIME.as_secs() / 60 {
+            problem("display.countdown_minutes",
+                    format!("{} minutes is longer than a whole work period of {} minutes", self.display.countdown_minutes, BREAK_TIME.as_secs() / 60));
+            self.display.countdown_minutes = defaults.display.countdown_minutes;
+        }
+        if self.reminders.repeat_minutes > MAX_REPEAT_MINUTES {
+            problem("reminders.repeat_minutes",
+                    format!("{} minutes is longer than the {} a reminder can wait to repeat", self.reminders.repeat_minutes, MAX_REPEAT_MINUTES));
+            self.reminders.repeat_minutes = defaults.reminders.repeat_minutes;
+        }
+        if self.flash.grace_minutes > MAX_GRACE_MINUTES {
+            problem("flash.grace_minutes",
+                    format!("{} minutes is longer than the {} the window can wait to flash", self.flash.grace_minutes, MAX_GRACE_MINUTES));
+            self.flash.grace_minutes = defaults.flash.grace_minutes;
+        }
+        if self.reminders.snooze_minutes == 0 || self.reminders.snooze_minutes > MAX_SNOOZE_MINUTES {
+            problem("reminders.snooze_minutes",
+                    format!("{} minutes is not a snooze, which can take 1 to {}", self.reminders.snooze_minutes, MAX_SNOOZE_MINUTES));
+            self.reminders.snooze_minutes = defaults.reminders.snooze_minutes;
+        }
+        for (key, weight) in [("input.keyboard_weight", &mut self.input.keyboard_weight), ("input.mouse_weight", &mut self.input.mouse_weight)] {
+            if *weight != 0.0 && !(MIN_INPUT_WEIGHT..=MAX_INPUT_WEIGHT).contains(weight) {
+                problem(key, format!("{} is not 0 or a weight from {} to {}", weight, MIN_INPUT_WEIGHT, MAX_INPUT_WEIGHT));
+                *weight = 1.0;
+            }
+        }
+        if self.input.keyboard_weight == 0.0 && self.input.mouse_weight == 0.0 {
+            problem("input.mouse_weight", "with both weights 0, no input would count".to_string());
+            self.input.keyboard_weight = defaults.input.keyboard_weight;
+            self.input.mouse_weight = defaults.input.mouse_weight;
+        }
+        if let Some(limit) = self.daily_limit.limit_minutes.filter(|limit| *limit == 0 || *limit > MAX_DAILY_MINUTES) {
+            problem("daily_limit.limit_minutes", format!("{} minutes is not a limit within a day", limit));
+            self.daily_limit.limit_minutes = None;
+        }
+        if self.daily_limit.repeat_minutes == 0 {
+            problem("daily_limit.repeat_minutes", "warnings can't repeat every 0 minutes".to_string());
+            self.daily_limit.repeat_minutes = defaults.daily_limit.repeat_minutes;
+        } else if self.daily_limit.repeat_minutes > MAX_DAILY_MINUTES {
+            problem("daily_limit.repeat_minutes",
+                    format!("{} minutes is longer than a day, so warnings wouldn't repeat", self.daily_limit.repeat_minutes));
+            self.daily_limit.repeat_minutes = defaults.daily_limit.repeat_minutes;
+        }
+        if self.daily_limit.warn_minutes > MAX_DAILY_MINUTES {
+            problem("daily_limit.warn_minutes",
+                    format!("{} minutes is longer than a day, so the warning would come before the day starts", self.daily_limit.warn_minutes));
+            self.daily_limit.warn_minutes = defaults.daily_limit.warn_minutes;
+        }
+        for (key, minutes, default) in [
+            ("idle.inactive_reset_minutes", &mut self.idle.inactive_reset_minutes, defaults.idle.inactive_reset_minutes),
+            ("idle.locked_reset_minutes", &mut self.idle.locked_reset_minutes, defaults.idle.locked_reset_minutes),
+            ("idle.asleep_reset_minutes", &mut self.idle.asleep_reset_minutes, defaults.idle.asleep_reset_minutes),
+            ("idle.disconnected_reset_minutes", &mut self.idle.disconnected_reset_minutes, defaults.idle.disconnected_reset_minutes),
+        ] {
+            if *minutes > MAX_RESET_MINUTES {
+                problem(key, format!("{} minutes is longer than a day, the longest an absence can take to reset", minutes));
+                *minutes = default;
+            }
+        }
+        if self.idle.inactive_reset_minutes.saturating_mul(60) <= IDLE_PAUSE_TIME.as_secs() {
+            problem("idle.inactive_reset_minutes",
+                    format!("{} minutes is no longer than the {} idle before the timer pauses", self.idle.inactive_reset_minutes, IDLE_PAUSE_TIME.as_secs() / 60));
+            self.idle.inactive_reset_minutes = defaults.idle.inactive_reset_minutes;
+        }
+        if self.api.port == 0 || self.api.events_port == 0 || self.api.port == self.api.events_port {
+            problem("api.events_port",
+                    format!("the API needs two different ports, not {} and {}", self.api.port, self.api.events_port));
+            self.api.port = defaults.api.port;
+            self.api.events_port = defaults.api.events_port;
+        }
+        if self.api.lan_port == 0 || self.api.lan_port == self.api.port || self.api.lan_port == self.api.events_port {
+            problem("api.lan_port",
+                    format!("{} is not a port of its own, apart from {} and {}", self.api.lan_port, self.api.port, self.api.events_port));
+            self.api.lan_port = defaults.api.lan_port;
+        }
+        if self.mqtt.port == 0 {
+            problem("mqtt.port", "0 is not a port".to_string());
+            self.mqtt.port = defaults.mqtt.port;
+        }
+        if self.slack.break_minutes == 0 || self.slack.break_minutes > MAX_BREAK_MINUTES {
+            problem("slack.break_minutes",
+                    format!("{} minutes is not a break, which can take 1 to {}", self.slack.break_minutes, MAX_BREAK_MINUTES));
+            self.slack.break_minutes = defaults.slack.break_minutes;
+        }
+        for (key, value, default) in [("hue.saturation", &mut self.hue.saturation, defaults.hue.saturation),
+                                      ("hue.brightness", &mut self.hue.brightness, defaults.hue.brightness)] {
+            if *value > 254 {
+                problem(key, format!("{} is more than the highest value, 254", value));
+                *value = default;
+            }
+        }
+        for (key, event) in [("sounds.break_prompt", SoundEvent::BreakPrompt), ("sounds.reset", SoundEvent::Reset),
+                             ("sounds.daily_limit", SoundEvent::DailyLimit)] {
+            let sound = self.sounds.get_mut(event);
+            if let Some(file) = sound.file.as_deref().filter(|file| !Path::new(file).exists()) {
+                problem(&format!("{}.file", key), format!("{} doesn't exist", file));
+                sound.file = None;
+            }
+            if sound.volume > 100 {
+                problem(&format!("{}.volume", key), format!("{} is more than the highest volume, 100", sound.volume));
+                sound.volume = 100;
+            }
+        }
+        if self.break_audio.duck_percent > 100 {
+            problem("break_audio.duck_percent", format!("{} is more than the whole volume, 100", self.break_audio.duck_percent));
+            self.break_audio.duck_percent = defaults.break_audio.duck_percent;
+        }
+        for (index, category) in self.usage.categories.iter_mut().enumerate() {
+            if category.budget_minutes == Some(0) {
+                problem(&format!("usage.categories[{}].budget_minutes", index), "0 leaves no time at all".to_string());
+                category.budget_minutes = None;
+            } else if let Some(budget) = category.budget_minutes.filter(|budget| *budget > MAX_DAILY_MINUTES) {
+                problem(&format!("usage.categories[{}].budget_minutes", index), format!("{} minutes is more than a day has", budget));
+                category.budget_minutes = None;
+            }
+        }
+        if self.sync.enabled && self.sync.url.is_empty() {
+            problem("sync.url", "syncing needs the url of a folder to sync through".to_string());
+            self.sync.enabled = false;
+        } else if self.sync.enabled && self.sync.is_unencrypted() {
+            problem("sync.url", "the credentials would be sent unencrypted: use an https:// url".to_string());
+            self.sync.enabled = false;
+        }
+        if self.sync.interval_minutes == 0 {
+            problem("sync.interval_minutes", "syncing can't happen every 0 minutes".to_string());
+            self.sync.interval_minutes = defaults.sync.interval_minutes;
+        } else if self.sync.interval_minutes > MAX_INTERVAL_MINUTES {
+            problem("sync.interval_minutes",
+                    format!("{} minutes is longer than the week syncing can wait", self.sync.interval_minutes));
+            self.sync.interval_minutes = defaults.sync.interval_minutes;
+        }
+        if self.devices.mode != DevicesMode::Off && self.devices.token.as_deref().is_none_or(str::is_empty) {
+            problem("devices.token", "the machines need a token to tell each other apart from strangers".to_string());
+            self.devices.mode = DevicesMode::Off;
+        }
+        if self.devices.mode == DevicesMode::Client && self.devices.server.is_empty() {
+            problem("devices.server", "reporting needs the address of the server".to_string());
+            self.devices.mode = DevicesMode::Off;
+        }
+        if self.handoff.enabled && self.handoff.token.as_deref().is_none_or(str::is_empty) {
+            problem("handoff.token", "the machines need a token to tell each other apart from strangers".to_string());
+            self.handoff.enabled = false;
+        }
+        if self.handoff.port == 0 {
+            problem("handoff.port", "0 is not a port".to_string());
+            self.handoff.port = defaults.handoff.port;
+        }
+        if self.email.enabled && (self.email.server.is_empty() || self.email.to.is_empty()) {
+            problem("email.server", "sending the weekly summary needs an SMTP server and an address to send to".to_string());
+            self.email.enabled = false;
+        }
+        if self.email.port == 0 {
+            problem("email.port", "0 is not a port".to_string());
+            self.email.port = defaults.email.port;
+        }
+        if self.email.security == SmtpSecurity::None && self.email.username.is_some() {
+            problem("email.username", "the password would be sent unencrypted: set email.security to \"starttls\" or \"tls\"".to_string());
+            self.email.enabled = false;
+        }
+        if self.backup.interval_hours == 0 {
+            problem("backup.interval_hours", "backups need at least an hour between them".to_string());
+            self.backup.interval_hours = defaults.backup.interval_hours;
+        } else if self.backup.interval_hours > MAX_INTERVAL_HOURS {
+            problem("backup.interval_hours", format!("{} hours is longer than the year a backup can wait", self.backup.interval_hours));
+            self.backup.interval_hours = defaults.backup.interval_hours;
+        }
+        if self.backup.keep == 0 {
+            problem("backup.keep", "keeping no backups would delete each one as it is made".to_string());
+            self.backup.keep = defaults.backup.keep;
+        }
+        if self.devices.port == 0 {
+            problem("devices.port", "0 is not a port".to_string());
+            self.devices.port = defaults.devices.port;
+        }
+        if self.updates.interval_hours == 0 {
+            problem("updates.interval_hours", "updates can't be looked for every 0 hours".to_string());
+            self.updates.interval_hours = defaults.updates.interval_hours;
+        } else if self.updates.interval_hours > MAX_INTERVAL_HOURS {
+            problem("updates.interval_hours",
+                    format!("{} hours is longer than the year updates can go unchecked", self.updates.interval_hours));
+            self.updates.interval_hours = defaults.updates.interval_hours;
+        }
+        problems
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(CONFIG_FILE), text)?;
+        log::info!("Saved {}", dir.join(CONFIG_FILE).display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(|problem| problem.key.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_the_settings_that_can_be_read() {
+        let (config, problems) = parse("language = 5\n[meetings]\nextra_minutes = \"ten\"\nearly_minutes = 5\ncolour = 1\n\
+                                        [report]\nhour = 7\n[window]\nx = 10\n[nope]\na = 1\n[log]\nlevel = \"loud\"");
+        assert_eq!(config.meetings.extra_minutes, 0);
+        assert_eq!(config.meetings.early_minutes, 5);
+        assert_eq!(config.report.hour, 7);
+        assert_eq!(config.window.x, 10);
+        assert_eq!(config.language, None);
+        assert_eq!(config.log.level, LogLevel::Info);
+        let mut keys = keys(&problems);
+        keys.sort_unstable();
+        assert_eq!(keys, ["language", "log.level", "meetings.colour", "meetings.extra_minutes", "nope"]);
+    }
+
+    #[test]
+    fn reports_unreadable_files() {
+        let (config, problems) = parse("[window\nx = 1");
+        assert_eq!(config.window.x, WindowConfig::default().x);
+        assert_eq!(keys(&problems), [""]);
+    }
+
+    #[test]
+    fn puts_back_defaults_for_values_that_make_no_sense() {
+        let (mut config, problems) = parse("icon_file = \"/no/such/icon.ico\"\nlanguage = \"fr\"\n\
+                                            [theme]\naccent = \"blue\"\n[report]\nhour = 24\n[display]\ncountdown_minutes = 9999\n[reminders]\nsnooze_minutes = 0\n\
+                                            [api]\nport = 8000\nevents_port = 8000\n[slack]\nbreak_minutes = 0\n\
+                                            [sounds.reset]\nvolume = 150");
+        assert!(problems.is_empty());
+        let problems = config.validate();
+        assert_eq!(keys(&problems), ["icon_file", "theme.accent", "report.hour", "display.countdown_minutes", "reminders.snooze_minutes", "api.events_port", "slack.break_minutes", "sounds.reset.volume"]);
+        assert_eq!(config.icon_file, None);
+        assert_eq!(config.language.as_deref(), Some("fr"));
+        assert_eq!(config.theme.accent, None);
+        assert_eq!(config.report.hour, 9);
+        assert_eq!(config.reminders.snooze_minutes, 10);
+        assert_eq!(config.display.countdown_minutes, 5);
+        assert_eq!((config.api.port, config.api.events_port), (7645, 7646));
+        assert_eq!(config.slack.break_minutes, 10);
+        assert_eq!(config.sounds.get(SoundEvent::Reset).volume, 100);
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn doesnt_overflow_on_huge_minutes() {
+        let huge = i64::MAX;
+        let (mut config, problems) = parse(&format!(
+            "[meetings]\nearly_minutes = {0}\ncalendar_refresh_minutes = {0}\n[reminders]\nrepeat_minutes = {0}\n[flash]\ngrace_minutes = {0}\n\
+             [idle]\ninactive_reset_minutes = {0}\nasleep_reset_minutes = {0}\n\
+             [daily_limit]\nwarn_minutes = {0}\nrepeat_minutes = {0}\n\
+             [sync]\ninterval_minutes = {0}\n[backup]\ninterval_hours = {0}\n[updates]\ninterval_hours = {0}\n\
+             [[usage.categories]]\nname = \"Chat\"\nbudget_minutes = {0}", huge));
+        assert!(problems.is_empty());
+        let problems = config.validate();
+        let mut keys = keys(&problems);
+        keys.sort_unstable();
+        assert_eq!(keys, ["backup.interval_hours", "daily_limit.repeat_minutes", "daily_limit.warn_minutes",
+                          "flash.grace_minutes", "idle.asleep_reset_minutes", "idle.inactive_reset_minutes",
+                          "meetings.calendar_refresh_minutes", "meetings.early_minutes", "reminders.repeat_minutes", "sync.interval_minutes",
+                          "updates.interval_hours", "usage.categories[0].budget_minutes"]);
+        assert_eq!(config.idle.reset_after(IdleCause::Asleep), IDLE_RESET_TIME);
+    }
+
+    #[test]
+    fn doesnt_sync_credentials_unencrypted() {
+        let (mut config, _) = parse("[sync]\nenabled = true\nurl = \"http://dav.example.com/pauza\"\nusername = \"me\"");
+        assert_eq!(keys(&config.validate()), ["sync.url"]);
+        assert!(!config.sync.enabled);
+        let (mut config, _) = parse("[sync]\nenabled = true\nurl = \"HTTPS://dav.example.com/pauza\"\nusername = \"me\"");
+        assert!(config.validate().is_empty());
+        // Without credentials there is nothing to give away.
+        let (mut config, _) = parse("[sync]\nenabled = true\nurl = \"http://nas.local/pauza\"");
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn doesnt_sign_in_to_smtp_unencrypted() {
+        let (mut config, _) = parse("[email]\nenabled = true\nserver = \"localhost\"\nto = \"me@example.com\"\n\
+                                     security = \"none\"\nusername = \"me\"");
+        assert_eq!(keys(&config.validate()), ["email.username"]);
+        assert!(!config.email.enabled);
+    }
+
+    #[test]
+    fn the_network_gets_a_token_of_its_own() {
+        let mut api = ApiConfig::default();
+        assert!(api.ensure_token());
+        assert_eq!(api.lan_token, None);
+        api.lan = true;
+        assert!(api.ensure_token());
+        assert!(api.lan_token.is_some() && api.lan_token != api.token);
+        assert!(!api.ensure_token());
+    }
+
+    #[test]
+    fn escalates_reminders_and_stays_at_the_last_style() {
+        let (config, problems) = parse("[reminders]\nrepeat_minutes = 5\nescalation = [\"balloon\", \"flash\", \"window\"]");
+        assert!(problems.is_empty());
+        let styles: Vec<_> = (0..5).map(|repeats| config.reminders.style(repeats)).collect();
+        assert_eq!(styles, [ReminderStyle::Balloon, ReminderStyle::Flash, ReminderStyle::Window, ReminderStyle::Window, ReminderStyle::Window]);
+        assert_eq!(ReminderConfig { escalation: Vec::new(), ..ReminderConfig::default() }.style(1), ReminderStyle::Balloon);
+    }
+
+    #[test]
+    fn parses_click_actions_and_their_toast_arguments() {
+        let (config, problems) = parse("[clicks]\nbreak_notification = \"snooze\"\ntray_double_click = \"stats\"");
+        assert!(problems.is_empty());
+        assert_eq!(config.clicks.break_notification, Some(ClickAction::Snooze));
+        assert_eq!(config.clicks.tray_double_click, ClickAction::Stats);
+        assert_eq!(Config::default().clicks.tray_double_click, ClickAction::ShowWindow);
+        for action in [ClickAction::ShowWindow, ClickAction::Stats, ClickAction::StartBreak, ClickAction::Snooze, ClickAction::Nothing] {
+            assert_eq!(ClickAction::parse(action.arguments()), Some(action));
+        }
+    }
+
+    #[test]
+    fn delivers_each_kind_of_notification_as_set() {
+        let (config, problems) = parse("[notifications]\nreset = \"none\"\nupdate = \"toast\"");
+        assert!(problems.is_empty());
+        assert_eq!(config.notifications.get(Topic::Reset), Delivery::Log);
+        assert_eq!(config.notifications.get(Topic::Update), Delivery::Toast);
+        assert_eq!(config.notifications.get(Topic::Badges), Delivery::Balloon);
+        assert!(config.notifications.uses(Delivery::Toast));
+        assert_eq!(config.notifications.get(Topic::Break), Delivery::Balloon);
+        // Nothing is a toast unless asked for, so nothing is registered for them.
+        let config = Config::default();
+        assert!(!config.notifications.uses_toasts(&config.reminders));
+        let reminders = ReminderConfig { escalation: vec![ReminderStyle::Balloon, ReminderStyle::Toast], ..ReminderConfig::default() };
+        assert!(config.notifications.uses_toasts(&reminders));
+        let (config, problems) = parse("[notifications]\nbreaks = \"overlay\"");
+        assert!(problems.is_empty());
+        assert_eq!(config.notifications.get(Topic::Break), Delivery::Overlay);
+        assert!(config.notifications.uses(Delivery::Overlay));
+    }
+}
@@ -0,0 +1,164 @@
+use serde::Deserialize;
+use std::fs;
+use crate::config::{data_dir, MessageConfig, MessageOrder, TipConfig};
+use crate::i18n::{self, tr};
+
+/// Replaces the built-in tips when it exists in the data directory.
+const TIPS_FILE: &str = "tips.json";
+
+/// The built-in tips, by language. English is the fallback.
+const TIPS: &[(&str, &str)] = &[
+    ("en", include_str!("../tips/en.json")),
+    ("de", include_str!("../tips/de.json")),
+    ("fr", include_str!("../tips/fr.json")),
+];
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Tip {
+    /// Like `hydration`, `eyes`, `ergonomics` or `movement`.
+    #[serde(default)]
+    pub category: String,
+    pub text: String,
+}
+
+pub fn parse_tips(text: &str) -> Result<Vec<Tip>, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+fn builtin_tips() -> Vec<Tip> {
+    let language = i18n::language();
+    let text = TIPS.iter()
+        .find(|(code, _)| *code == language)
+        .unwrap_or(&TIPS[0]).1;
+    parse_tips(text).unwrap_or_else(|e| {
+        log::error!("tips/{}.json: {}", language, e);
+        Vec::new()
+    })
+}
+
+fn load_tips() -> Vec<Tip> {
+    let path = data_dir().join(TIPS_FILE);
+    match fs::read_to_string(&path) {
+        Ok(text) => match parse_tips(&text) {
+            Ok(tips) => {
+                log::info!("Loaded {} tips from {}", tips.len(), path.display());
+                tips
+            },
+            Err(e) => {
+                log::error!("{}: {}", path.display(), e);
+                builtin_tips()
+            }
+        },
+        Err(_e) => builtin_tips()
+    }
+}
+
+/// The index of the item to show after the one at `last`. In random order `random`
+/// picks it, never the same one twice in a row.
+pub fn next_index(order: MessageOrder, count: usize, last: Option<usize>, random: usize) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    Some(match (order, last) {
+        (MessageOrder::Sequential, Some(last)) => (last + 1) % count,
+        (MessageOrder::Sequential, None) => 0,
+        (MessageOrder::Random, Some(last)) if count > 1 => (last + 1 + random % (count - 1)) % count,
+        (MessageOrder::Random, _) => random % count
+    })
+}
+
+fn random() -> usize {
+    let mut bytes = [0u8; 8];
+    if let Err(e) = getrandom::getrandom(&mut bytes) {
+        log::error!("{}", e);
+    }
+    u64::from_le_bytes(bytes) as usize
+}
+
+/// What break reminders and the notification at the end of a break say, whichever
+/// part of the GUI shows them.
+#[derive(Debug, Default)]
+pub struct BreakContent {
+    messages: MessageConfig,
+    tips: Vec<Tip>,
+    last_message: Option<usize>,
+    last_tip: Option<usize>,
+}
+
+impl BreakContent {
+    pub fn new(messages: MessageConfig, tips: Vec<Tip>) -> BreakContent {
+        BreakContent { messages, tips, last_message: None, last_tip: None }
+    }
+
+    /// Loads the tips in the current language, or the user's own.
+    pub fn load(messages: &MessageConfig, config: &TipConfig) -> BreakContent {
+        let tips = if config.enabled {
+            load_tips().into_iter()
+                .filter(|tip| config.categories.is_empty() || config.categories.contains(&tip.category))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        BreakContent::new(messages.clone(), tips)
+    }
+
+    /// The next of the configured break messages, or the built-in one.
+    pub fn break_message(&mut self) -> String {
+        let count = self.messages.breaks.len();
+        match next_index(self.messages.order, count, self.last_message, random()) {
+            Some(index) => {
+                self.last_message = Some(index);
+                self.messages.breaks[index].clone()
+            },
+            None => tr("break-message")
+        }
+    }
+
+    /// A tip picked at random, if there are any.
+    pub fn tip(&mut self) -> Option<String> {
+        let index = next_index(MessageOrder::Random, self.tips.len(), self.last_tip, random())?;
+        self.last_tip = Some(index);
+        Some(self.tips[index].text.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates() {
+        assert_eq!(next_index(MessageOrder::Sequential, 3, None, 7), Some(0));
+        assert_eq!(next_index(MessageOrder::Sequential, 3, Some(1), 7), Some(2));
+        assert_eq!(next_index(MessageOrder::Sequential, 3, Some(2), 7), Some(0));
+        assert_eq!(next_index(MessageOrder::Random, 3, None, 7), Some(1));
+        for random in 0..10 {
+            assert_ne!(next_index(MessageOrder::Random, 3, Some(1), random), Some(1));
+        }
+        assert_eq!(next_index(MessageOrder::Random, 1, Some(0), 5), Some(0));
+        assert_eq!(next_index(MessageOrder::Random, 0, None, 0), None);
+    }
+
+    #[test]
+    fn builtin_tips_are_translated() {
+        let english = parse_tips(TIPS[0].1).unwrap();
+        for (language, text) in TIPS {
+            let tips = parse_tips(text).unwrap();
+            let categories: Vec<_> = tips.iter().map(|tip| &tip.category).collect();
+            assert_eq!(categories, english.iter().map(|tip| &tip.category).collect::<Vec<_>>(), "{}", language);
+        }
+    }
+
+    #[test]
+    fn shows_messages_in_turn() {
+        let messages = MessageConfig {
+            breaks: vec!["a".to_string(), "b".to_string()],
+            order: MessageOrder::Sequential,
+        };
+        let tip = Tip { category: String::new(), text: "Drink".to_string() };
+        let mut content = BreakContent::new(messages, vec![tip]);
+        assert_eq!([content.break_message(), content.break_message(), content.break_message()], ["a", "b", "a"]);
+        assert_eq!(content.tip().as_deref(), Some("Drink"));
+        assert_eq!(parse_tips(r#"[{"text": "Stretch"}]"#).unwrap()[0].category, "");
+    }
+}
@@ -0,0 +1,162 @@
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::thread;
+use crate::config::{data_dir, Config, SECRETS};
+use crate::logger;
+use crate::VERSION;
+use crate::windows::{local_timestamp, restore_sound, set_exception_handler};
+
+const REPORT_FILE: &str = "crash.txt";
+/// Where a report is moved once it's been offered, so it's only offered once.
+const LAST_REPORT_FILE: &str = "last-crash.txt";
+
+static CONFIG: OnceLock<String> = OnceLock::new();
+
+/// Redacts the secrets in `value`, the settings under the dotted key `prefix`.
+fn redact(prefix: &str, value: &mut toml::Value) {
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            if SECRETS.contains(&key.as_str()) {
+                *value = toml::Value::String("<redacted>".to_string());
+            } else {
+                redact(&key, value);
+            }
+        }
+    }
+}
+
+/// The config as it goes in a crash report, with secrets left out.
+pub fn snapshot(config: &Config) -> String {
+    let mut value = match toml::Value::try_from(config) {
+        Ok(value) => value,
+        Err(e) => return e.to_string()
+    };
+    redact("", &mut value);
+    toml::to_string(&value).unwrap_or_else(|e| e.to_string())
+}
+
+pub fn report(what: &str, backtrace: Option<&str>, recent: &[String], config: Option<&str>) -> String {
+    let mut text = format!("pauza {} crashed at {}\n\n{}\n", VERSION, local_timestamp(), what);
+    if let Some(backtrace) = backtrace {
+        text += &format!("\nBacktrace:\n{}\n", backtrace);
+    }
+    text += "\nRecent log:\n";
+    for line in recent {
+        text += line;
+        text += "\n";
+    }
+    text += &format!("\nConfig:\n{}", config.unwrap_or("not loaded yet\n"));
+    text
+}
+
+fn save(what: &str, backtrace: Option<&str>) {
+    let text = report(what, backtrace, &logger::recent(), CONFIG.get().map(String::as_str));
+    let path = data_dir().join(REPORT_FILE);
+    if let Err(e) = fs::write(&path, text) {
+        eprintln!("ERROR: {}: {}", path.display(), e);
+    }
+}
+
+/// Writes a crash report to the data directory when pauza panics or dies of an
+/// unhandled exception.
+pub fn install(config: &Config) {
+    let _ = CONFIG.set(snapshot(config));
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log::error!("{}", info);
+        let what = format!("thread '{}' {}", thread::current().name().unwrap_or("<unnamed>"), info);
+        save(&what, Some(&Backtrace::force_capture().to_string()));
+        // The process goes with the main thread, and with it the chance to end the break.
+        if thread::current().name() == Some("main") {
+            restore_sound(true);
+        }
+        default_hook(info);
+    }));
+    set_exception_handler(|what| {
+        save(what, None);
+        restore_sound(true);
+    });
+}
+
+/// The report of a crash since the last start, if there was one it hasn't offered yet.
+pub fn take_report() -> Option<PathBuf> {
+    let path = data_dir().join(REPORT_FILE);
+    if !path.exists() {
+        return None;
+    }
+    let last = data_dir().join(LAST_REPORT_FILE);
+    match fs::rename(&path, &last) {
+        Ok(()) => Some(last),
+        Err(e) => {
+            log::error!("{}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_leaves_every_secret_out() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        // Each secret is set, so a misspelt key, which sets nothing, shows.
+        for key in SECRETS {
+            let (section, name) = key.split_once('.').unwrap();
+            let table = value.get_mut(section).and_then(|section| section.as_table_mut()).unwrap();
+            let secret = if *key == "webhooks.urls" { toml::Value::Array(vec!["hunter2".into()]) } else { "hunter2".into() };
+            table.insert(name.to_string(), secret);
+        }
+        let config: Config = value.try_into().unwrap();
+        let snapshot = snapshot(&config);
+        assert!(!snapshot.contains("hunter2"), "{}", snapshot);
+        assert_eq!(snapshot.matches("<redacted>").count(), SECRETS.len());
+    }
+
+    #[test]
+    fn every_secret_looking_setting_is_listed() {
+        /// Server addresses, which give no access by themselves.
+        const NOT_SECRET: &[&str] = &["sync.url", "activitywatch.url", "updates.url"];
+        fn collect(prefix: &str, value: &serde_json::Value, keys: &mut Vec<String>) {
+            if let serde_json::Value::Object(map) = value {
+                for (key, value) in map {
+                    let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    collect(&key, value, keys);
+                }
+            } else {
+                keys.push(prefix.to_string());
+            }
+        }
+        // JSON keeps the settings that are unset, which TOML leaves out.
+        let mut all = Vec::new();
+        collect("", &serde_json::to_value(Config::default()).unwrap(), &mut all);
+        let looks_secret = |key: &str| {
+            let name = key.rsplit('.').next().unwrap_or(key);
+            ["token", "password", "passphrase", "username", "url"].iter().any(|word| name.contains(word))
+        };
+        for key in all.iter().filter(|key| looks_secret(key)) {
+            assert!(SECRETS.contains(&key.as_str()) || NOT_SECRET.contains(&key.as_str()), "{} isn't in SECRETS", key);
+        }
+    }
+
+    #[test]
+    fn snapshot_leaves_secrets_out() {
+        let mut config = Config::default();
+        config.api.token = Some("0123abcd".to_string());
+        config.mqtt.password = Some("hunter2".to_string());
+        config.meetings.calendar_url = Some("https://example.com/secret.ics".to_string());
+        config.webhooks.urls = vec!["https://example.com/hook/secret".to_string()];
+        config.storage.passphrase = Some("correct horse".to_string());
+        let snapshot = snapshot(&config);
+        for secret in ["0123abcd", "hunter2", "secret", "correct horse"] {
+            assert!(!snapshot.contains(secret), "{} in {}", secret, snapshot);
+        }
+        assert!(snapshot.contains("<redacted>"));
+        assert!(snapshot.contains("[api]"));
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+use crate::config::{data_dir, DailyLimitConfig};
+use crate::privacy;
+
+const DAILY_FILE: &str = "daily.toml";
+/// More than this between steps, as after sleep, only counts for this long.
+const MAX_STEP: Duration = Duration::from_secs(30);
+
+/// The active time of a day so far, saved so a restart keeps counting.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyTime {
+    /// The local date, `YYYY-MM-DD`.
+    pub date: String,
+    pub active_secs: u64,
+    /// The last warning level given that day.
+    pub warned: u32,
+}
+
+impl DailyTime {
+    pub fn save(&self) -> io::Result<()> {
+        if privacy::guest() {
+            return Ok(());
+        }
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(DAILY_FILE), text)
+    }
+
+    /// Once the data is deleted.
+    pub fn forget() {
+        match fs::remove_file(data_dir().join(DAILY_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => log::error!("{}", e),
+            _ => {}
+        }
+    }
+
+    pub fn load() -> Option<DailyTime> {
+        if privacy::guest() {
+            return None;
+        }
+        let text = fs::read_to_string(data_dir().join(DAILY_FILE)).ok()?;
+        toml::from_str(&text).ok()
+    }
+}
+
+/// A warning about the day's active time, more pressing at each level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DailyWarning {
+    /// This much is left before the limit.
+    Approaching(Duration),
+    /// The limit, just reached.
+    Reached(Duration),
+    /// This far past the limit.
+    Over(Duration)
+}
+
+/// How many warnings `active` is worth: one within `warn_minutes` of the limit, another at
+/// it, and one more every `repeat_minutes` past it.
+fn warning_level(active: Duration, config: &DailyLimitConfig) -> u32 {
+    let limit = match config.limit_minutes {
+        Some(minutes) => Duration::from_secs(minutes.saturating_mul(60)),
+        None => return 0
+    };
+    if active >= limit {
+        let over = (active - limit).as_secs() / config.repeat_minutes.max(1).saturating_mul(60);
+        2 + over as u32
+    } else if active.saturating_add(Duration::from_secs(config.warn_minutes.saturating_mul(60))) >= limit {
+        1
+    } else {
+        0
+    }
+}
+
+/// Adds up the time worked each day and says when it nears and passes the daily limit.
+pub struct DailyTracker {
+    config: DailyLimitConfig,
+    time: DailyTime,
+    /// `time.active_secs` with the fraction of a second steps leave, which would be
+    /// lost with each of them otherwise.
+    active: Duration,
+    last: Option<Instant>,
+}
+
+impl DailyTracker {
+    pub fn new(config: DailyLimitConfig, saved: Option<DailyTime>) -> DailyTracker {
+        let time = saved.unwrap_or_default();
+        DailyTracker {
+            config,
+            active: Duration::from_secs(time.active_secs),
+            time,
+            last: None,
+        }
+    }
+
+    pub fn time(&self) -> &DailyTime {
+        &self.time
+    }
+
+    /// Counts the time since the last step as active when `working`, on the local day
+    /// `date`, returning a warning when one is due. A day earlier than the one counted,
+    /// after flying west over midnight, keeps counting to that one.
+    pub fn step(&mut self, date: &str, working: bool, now: Instant) -> Option<DailyWarning> {
+        if self.time.date.as_str() < date {
+            self.time = DailyTime { date: date.to_string(), ..DailyTime::default() };
+            self.active = Duration::ZERO;
+            self.last = None;
+        }
+        if !working {
+            self.last = None;
+            return None;
+        }
+        if let Some(last) = self.last.replace(now) {
+            self.active += now.saturating_duration_since(last).min(MAX_STEP);
+            self.time.active_secs = self.active.as_secs();
+        }
+        let active = self.active;
+        let level = warning_level(active, &self.config);
+        if level <= self.time.warned {
+            return None;
+        }
+        self.time.warned = level;
+        let limit = Duration::from_secs(self.config.limit_minutes.unwrap_or(0).saturating_mul(60));
+        Some(match level {
+            1 => DailyWarning::Approaching(limit - active),
+            2 => DailyWarning::Reached(limit),
+            _ => DailyWarning::Over(active - limit)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_nearing_and_past_the_limit() {
+        let config = DailyLimitConfig { limit_minutes: Some(60), warn_minutes: 10, repeat_minutes: 15 };
+        let mut tracker = DailyTracker::new(config, None);
+        let start = Instant::now();
+        let mut warnings = Vec::new();
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        // Steps come more often than once a second.
+        let step = Duration::from_millis(250);
+        for n in 0..=90 * 60 * 4 {
+            // Away for a while, which doesn't count.
+            let working = !(minutes(20)..minutes(40)).contains(&(step * n));
+            warnings.extend(tracker.step("2024-05-06", working, start + step * n));
+        }
+        assert_eq!(warnings, [DailyWarning::Approaching(minutes(10)),
+                              DailyWarning::Reached(minutes(60))]);
+        // Less the step back from being away, which is counted as away.
+        assert_eq!(tracker.active, minutes(70) - step);
+        assert_eq!(tracker.time().active_secs, 70 * 60 - 1);
+        for n in 90 * 60 * 4..=106 * 60 * 4 {
+            warnings.extend(tracker.step("2024-05-06", true, start + step * n));
+        }
+        assert_eq!(warnings.last(), Some(&DailyWarning::Over(minutes(15))));
+        // A new day starts from nothing.
+        assert_eq!(tracker.step("2024-05-07", true, start + minutes(107)), None);
+        assert_eq!(tracker.active, Duration::ZERO);
+        // But not one the time zone moves back to.
+        tracker.step("2024-05-06", true, start + minutes(107) + Duration::from_secs(20));
+        assert_eq!(tracker.time().date, "2024-05-07");
+        assert_eq!(tracker.time().active_secs, 20);
+    }
+
+    #[test]
+    fn doesnt_overflow_on_huge_minutes() {
+        let config = DailyLimitConfig { limit_minutes: Some(60), warn_minutes: u64::MAX, repeat_minutes: 1 << 62 };
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        assert_eq!(warning_level(minutes(1), &config), 1);
+        assert_eq!(warning_level(minutes(600), &config), 2);
+    }
+}
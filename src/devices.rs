@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Response, Server};
+use crate::auth;
+use crate::config::DevicesConfig;
+use crate::idle::IdleSource;
+use crate::privacy;
+use crate::sync::machine_name;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// A machine not heard from for this long is taken to be off or asleep.
+const STALE_TIME: Duration = Duration::from_secs(60);
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BODY: u64 = 4096;
+
+/// The idle time of one machine, as it was reported.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct DeviceReport {
+    name: String,
+    idle_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeviceReports {
+    devices: Vec<DeviceReport>,
+}
+
+/// The idle times of this machine and the others it knows of, with when each was heard.
+#[derive(Default)]
+pub struct Devices {
+    local: Mutex<Option<(Instant, Duration)>>,
+    others: Mutex<HashMap<String, (Instant, Duration)>>,
+}
+
+impl Devices {
+    fn set_local(&self, idle: Duration, now: Instant) {
+        *self.local.lock().unwrap() = Some((now, idle));
+    }
+
+    fn local(&self, now: Instant) -> Option<Duration> {
+        self.local.lock().unwrap().map(|(heard, idle)| idle + now.saturating_duration_since(heard))
+    }
+
+    fn report(&self, name: &str, idle: Duration, now: Instant) {
+        self.others.lock().unwrap().insert(name.to_string(), (now, idle));
+    }
+
+    /// The idle time of each other machine heard from lately but `except`, as of `now`.
+    fn others(&self, except: &str, now: Instant) -> Vec<(String, Duration)> {
+        let mut others = self.others.lock().unwrap();
+        others.retain(|_, (heard, _)| now.saturating_duration_since(*heard) < STALE_TIME);
+        others.iter()
+            .filter(|(name, _)| name.as_str() != except)
+            .map(|(name, (heard, idle))| (name.clone(), *idle + now.saturating_duration_since(*heard)))
+            .collect()
+    }
+
+    /// The shortest idle time of the other machines as of `now`, if any were heard from lately.
+    fn idle_time(&self, now: Instant) -> Option<Duration> {
+        self.others("", now).into_iter().map(|(_, idle)| idle).min()
+    }
+}
+
+/// The idle time of `inner`, cut short by input on the other machines.
+pub struct SharedIdle<I> {
+    inner: I,
+    devices: Arc<Devices>,
+}
+
+impl<I> SharedIdle<I> {
+    pub fn new(inner: I, devices: Arc<Devices>) -> SharedIdle<I> {
+        SharedIdle { inner, devices }
+    }
+}
+
+impl<I: IdleSource> IdleSource for SharedIdle<I> {
+    fn idle_time(&mut self, now: Instant) -> Result<Duration, i32> {
+        let idle_time = self.inner.idle_time(now)?;
+        self.devices.set_local(idle_time, now);
+        Ok(self.devices.idle_time(now).map_or(idle_time, |others| idle_time.min(others)))
+    }
+
+    fn degraded(&self) -> bool {
+        self.inner.degraded()
+    }
+}
+
+fn json_response(code: u16, body: String) -> Response<Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(body).with_status_code(code).with_header(content_type)
+}
+
+/// Whether `request` carries `token`, compared in constant time so the answer's timing
+/// doesn't give away how much of a guess was right.
+fn is_authorized(request: &tiny_http::Request, token: &str) -> bool {
+    request.headers().iter()
+        .filter(|header| header.field.equiv("Authorization"))
+        .any(|header| auth::is_bearer(header.value.as_str(), token))
+}
+
+/// Takes the idle time one machine reports and answers with those of all the others.
+fn handle(request: &mut tiny_http::Request, devices: &Devices, name: &str, token: &str) -> Response<Cursor<Vec<u8>>> {
+    if !is_authorized(request, token) {
+        return json_response(401, json!({ "error": "missing or wrong token" }).to_string());
+    }
+    if request.method() != &Method::Post || request.url() != "/devices" {
+        return json_response(404, json!({ "error": "not found" }).to_string());
+    }
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+        return json_response(400, json!({ "error": e.to_string() }).to_string());
+    }
+    let report: DeviceReport = match serde_json::from_str(&body) {
+        Ok(report) => report,
+        Err(e) => return json_response(400, json!({ "error": e.to_string() }).to_string())
+    };
+    let now = Instant::now();
+    devices.report(&report.name, Duration::from_millis(report.idle_ms), now);
+    let mut others = devices.others(&report.name, now);
+    if let Some(idle) = devices.local(now) {
+        others.push((name.to_string(), idle));
+    }
+    let reports = DeviceReports {
+        devices: others.into_iter()
+            .map(|(name, idle)| DeviceReport { name, idle_ms: idle.as_millis() as u64 })
+            .collect(),
+    };
+    json_response(200, serde_json::to_string(&reports).unwrap_or_default())
+}
+
+/// Serves as the machine the others report their idle time to.
+pub fn serve(config: DevicesConfig, devices: Arc<Devices>) {
+    let token = config.token.unwrap_or_default();
+    let name = machine_name(config.name.as_deref());
+    let address = config.address.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let server = match Server::http((address, config.port)) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to listen for other machines on {}:{}: {}", address, config.port, e);
+            return;
+        }
+    };
+    log::info!("Listening for other machines on {}:{}", address, config.port);
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut request, &devices, &name, &token);
+        if let Err(e) = request.respond(response) {
+            log::warn!("Devices: {}", e);
+        }
+    }
+}
+
+/// Reports this machine's idle time to the server every few seconds, keeping what it
+/// answers about the others.
+pub fn report(config: DevicesConfig, devices: Arc<Devices>) {
+    let url = format!("{}/devices", config.server.trim_end_matches('/'));
+    let bearer = format!("Bearer {}", config.token.unwrap_or_default());
+    let name = machine_name(config.name.as_deref());
+    let mut failing = false;
+    loop {
+        thread::sleep(REPORT_INTERVAL);
+        if !privacy::sharing() {
+            continue;
+        }
+        let idle = match devices.local(Instant::now()) {
+            Some(idle) => idle,
+            None => continue
+        };
+        let result = ureq::post(&url)
+            .timeout(TIMEOUT)
+            .set("Authorization", &bearer)
+            .send_json(DeviceReport { name: name.clone(), idle_ms: idle.as_millis() as u64 })
+            .map_err(|e| e.to_string())
+            .and_then(|response| response.into_json::<DeviceReports>().map_err(|e| e.to_string()));
+        match result {
+            Ok(reports) => {
+                if failing {
+                    log::info!("Reaching {} again", config.server);
+                }
+                failing = false;
+                let now = Instant::now();
+                for report in reports.devices {
+                    devices.report(&report.name, Duration::from_millis(report.idle_ms), now);
+                }
+            },
+            Err(e) => {
+                if !failing {
+                    log::warn!("Failed to reach {}: {}", config.server, e);
+                }
+                failing = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Idle(Duration);
+
+    impl IdleSource for Idle {
+        fn idle_time(&mut self, _now: Instant) -> Result<Duration, i32> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn input_anywhere_counts() {
+        let secs = Duration::from_secs;
+        let start = Instant::now();
+        let devices = Arc::new(Devices::default());
+        let mut idle = SharedIdle::new(Idle(secs(600)), devices.clone());
+        assert_eq!(idle.idle_time(start), Ok(secs(600)));
+
+        // Typing on the laptop 10 seconds before it reported, 20 seconds ago.
+        devices.report("laptop", secs(10), start);
+        assert_eq!(idle.idle_time(start + secs(20)), Ok(secs(30)));
+        assert!(devices.others("laptop", start + secs(20)).is_empty());
+
+        // Until the laptop goes quiet for good.
+        assert_eq!(idle.idle_time(start + STALE_TIME), Ok(secs(600)));
+    }
+}
@@ -0,0 +1,274 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::{EmailConfig, SmtpSecurity};
+use crate::format::utc_civil;
+use crate::report::WeeklyReport;
+use crate::sync::machine_name;
+
+#[cfg(windows)]
+use crate::windows::read_credential;
+
+#[cfg(not(windows))]
+fn read_credential(_target: &str) -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+/// Where the SMTP password is kept in the Windows credential store.
+pub const CREDENTIAL: &str = "pauza/email";
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_LINE: usize = 4096;
+/// The longest line of base64 in a message.
+const LINE_LENGTH: usize = 76;
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+#[derive(Debug)]
+pub enum EmailError {
+    Io(io::Error),
+    Tls(rustls::Error),
+    /// The server answered `command` with an error.
+    Refused { command: String, reply: String },
+    Credential(String),
+    /// Signing in was asked for over a connection that isn't encrypted.
+    Unencrypted,
+}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmailError::Io(e) => write!(f, "{}", e),
+            EmailError::Tls(e) => write!(f, "TLS: {}", e),
+            EmailError::Refused { command, reply } => write!(f, "The server refused {}: {}", command, reply.trim()),
+            EmailError::Credential(e) => write!(f, "{}", e),
+            EmailError::Unencrypted => write!(f, "Not sending the password without TLS: set email.security to \"starttls\" or \"tls\"")
+        }
+    }
+}
+
+impl std::error::Error for EmailError {}
+
+impl From<io::Error> for EmailError {
+    fn from(e: io::Error) -> EmailError {
+        EmailError::Io(e)
+    }
+}
+
+impl From<rustls::Error> for EmailError {
+    fn from(e: rustls::Error) -> EmailError {
+        EmailError::Tls(e)
+    }
+}
+
+enum Connection {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf)
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush()
+        }
+    }
+}
+
+fn tls(stream: TcpStream, server: &str) -> Result<Connection, EmailError> {
+    let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let config = ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+    let name = ServerName::try_from(server.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let connection = ClientConnection::new(Arc::new(config), name)?;
+    Ok(Connection::Tls(Box::new(StreamOwned::new(connection, stream))))
+}
+
+struct Smtp {
+    connection: Connection,
+}
+
+impl Smtp {
+    fn line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while line.last() != Some(&b'\n') {
+            if self.connection.read(&mut byte)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the server hung up"));
+            }
+            if line.len() >= MAX_LINE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "the server's reply is too long"));
+            }
+            line.push(byte[0]);
+        }
+        Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+    }
+
+    /// Reads a reply, however many lines it takes, and checks it is of the same kind
+    /// as `expected`.
+    fn expect(&mut self, expected: u16, command: &str) -> Result<(), EmailError> {
+        let mut reply = String::new();
+        loop {
+            let line = self.line()?;
+            reply += &line;
+            reply.push('\n');
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+        let code: u16 = reply.get(..3).and_then(|code| code.parse().ok()).unwrap_or(0);
+        if code / 100 != expected / 100 {
+            return Err(EmailError::Refused { command: command.to_string(), reply });
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, line: &str, expected: u16) -> Result<(), EmailError> {
+        self.connection.write_all(format!("{}\r\n", line).as_bytes())?;
+        self.connection.flush()?;
+        // Only the verb, which keeps credentials out of errors.
+        self.expect(expected, line.split(' ').next().unwrap_or(line))
+    }
+}
+
+/// A unix time as RFC 5322 has it, like `Mon, 06 May 2024 09:00:00 +0000`.
+fn date(secs: i64) -> String {
+    let (year, month, day, hour, minute, second) = utc_civil(secs);
+    format!("{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+            DAYS[secs.div_euclid(86400).rem_euclid(7) as usize], day, MONTHS[month as usize - 1], year, hour, minute, second)
+}
+
+fn header_text(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(text))
+    }
+}
+
+fn base64_lines(text: &str) -> String {
+    let encoded = STANDARD.encode(text);
+    encoded.as_bytes()
+        .chunks(LINE_LENGTH)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// A message with both a plain text and an HTML version of its body, sent at `secs`.
+fn message(from: &str, to: &str, subject: &str, text: &str, html: &str, secs: i64) -> String {
+    let boundary = format!("pauza-{}", secs);
+    [
+        format!("From: {}", from),
+        format!("To: {}", to),
+        format!("Subject: {}", header_text(subject)),
+        format!("Date: {}", date(secs)),
+        "MIME-Version: 1.0".to_string(),
+        format!("Content-Type: multipart/alternative; boundary=\"{}\"", boundary),
+        String::new(),
+        format!("--{}", boundary),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: base64".to_string(),
+        String::new(),
+        base64_lines(text),
+        format!("--{}", boundary),
+        "Content-Type: text/html; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: base64".to_string(),
+        String::new(),
+        base64_lines(html),
+        format!("--{}--", boundary),
+    ].join("\r\n")
+}
+
+/// Sends a message over SMTP as `config` has it.
+pub fn send(config: &EmailConfig, subject: &str, text: &str, html: &str) -> Result<(), EmailError> {
+    let stream = TcpStream::connect((config.server.as_str(), config.port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let connection = match config.security {
+        SmtpSecurity::Tls => tls(stream, &config.server)?,
+        _ => Connection::Plain(stream)
+    };
+    let mut smtp = Smtp { connection };
+    smtp.expect(220, "the connection")?;
+    let hello = format!("EHLO {}", machine_name(None));
+    smtp.command(&hello, 250)?;
+    if config.security == SmtpSecurity::StartTls {
+        smtp.command("STARTTLS", 220)?;
+        if let Connection::Plain(stream) = smtp.connection {
+            smtp = Smtp { connection: tls(stream, &config.server)? };
+        }
+        smtp.command(&hello, 250)?;
+    }
+    if let Some(username) = &config.username {
+        if !matches!(smtp.connection, Connection::Tls(_)) {
+            return Err(EmailError::Unencrypted);
+        }
+        let password = match &config.password {
+            Some(password) => Some(password.clone()),
+            None => read_credential(CREDENTIAL).map_err(EmailError::Credential)?
+        };
+        let password = password.ok_or_else(|| EmailError::Credential("no password is set in email.password".to_string()))?;
+        smtp.command(&format!("AUTH PLAIN {}", STANDARD.encode(format!("\0{}\0{}", username, password))), 235)?;
+    }
+
+    let from = config.from.as_deref().unwrap_or(&config.to);
+    smtp.command(&format!("MAIL FROM:<{}>", from), 250)?;
+    smtp.command(&format!("RCPT TO:<{}>", config.to), 250)?;
+    smtp.command("DATA", 354)?;
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let message = message(from, &config.to, subject, text, html, secs).replace("\r\n.", "\r\n..");
+    smtp.connection.write_all(format!("{}\r\n.\r\n", message).as_bytes())?;
+    smtp.connection.flush()?;
+    smtp.expect(250, "the message")?;
+    if let Err(e) = smtp.command("QUIT", 221) {
+        log::debug!("SMTP: {}", e);
+    }
+    Ok(())
+}
+
+/// Sends the weekly summary to the address `config` has.
+pub fn send_report(config: &EmailConfig, report: &WeeklyReport) -> Result<(), EmailError> {
+    send(config, &report.title(), &report.text(), &report.html())?;
+    log::info!("Sent the weekly summary to {}", config.to);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_both_versions() {
+        assert_eq!(date(1714986000), "Mon, 06 May 2024 09:00:00 +0000");
+        let html = format!("<p>{}</p>", "x".repeat(200));
+        let message = message("me@example.com", "me@example.com", "Deine Woche mit Pauza", "Aktiv: 30 Min.", &html, 1714986000);
+        assert!(message.contains("Subject: Deine Woche mit Pauza\r\n"));
+        assert!(message.contains("\r\n--pauza-1714986000\r\nContent-Type: text/html; charset=utf-8\r\n"));
+        assert!(message.ends_with("\r\n--pauza-1714986000--"));
+        assert!(message.split("\r\n").all(|line| line.len() <= 100));
+        assert_eq!(header_text("Votre semaine avec Pauza ☕"), "=?UTF-8?B?Vm90cmUgc2VtYWluZSBhdmVjIFBhdXphIOKYlQ==?=");
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::Encryption;
+use crate::format;
+use crate::history::{History, Record};
+use crate::store::{self, StoreError};
+
+const ICAL_PRODID: &str = "-//pauza//pauza//EN";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    /// Work and break intervals as iCalendar events.
+    Ical
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Option<Format> {
+        match format.to_ascii_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            "ics" | "ical" => Some(Format::Ical),
+            _ => None
+        }
+    }
+
+    /// Picks the format from a file extension, defaulting to CSV.
+    pub fn for_path(path: &Path) -> Format {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::parse)
+            .unwrap_or(Format::Csv)
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    History(rusqlite::Error),
+    Store(StoreError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{}", e),
+            ExportError::History(e) => write!(f, "{}", e),
+            ExportError::Store(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> ExportError {
+        ExportError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for ExportError {
+    fn from(e: rusqlite::Error) -> ExportError {
+        ExportError::History(e)
+    }
+}
+
+impl From<StoreError> for ExportError {
+    fn from(e: StoreError) -> ExportError {
+        ExportError::Store(e)
+    }
+}
+
+/// Formats unix seconds as an iCalendar UTC date-time, `YYYYMMDDTHHMMSSZ`.
+fn ical_time(secs: i64) -> String {
+    let (year, month, day, hour, minute, second) = format::utc_civil(secs);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, hour, minute, second)
+}
+
+fn write_event(out: &mut dyn Write, record: &Record, stamp: &str) -> io::Result<()> {
+    let summary = match record.kind.as_str() {
+        "work" => "Work",
+        _ => "Break"
+    };
+    write!(out,
+           "BEGIN:VEVENT\r\nUID:{}-{}@pauza\r\nDTSTAMP:{}\r\nDTSTART:{}\r\nDTEND:{}\r\nSUMMARY:{}\r\nEND:VEVENT\r\n",
+           record.started_at,
+           record.kind,
+           stamp,
+           ical_time(record.started_at),
+           ical_time(record.started_at + record.duration),
+           summary)
+}
+
+/// Writes one record, returning whether the format includes it.
+fn write_record(out: &mut dyn Write, format: Format, record: &Record, first: bool, stamp: &str) -> io::Result<bool> {
+    match format {
+        Format::Csv => {
+            let response = record.response.map(|r| r.to_string()).unwrap_or_default();
+            let quality = record.quality.map(|q| q.to_string()).unwrap_or_default();
+            writeln!(out, "{},{},{},{},{},{},{}", record.kind, record.start, record.started_at, record.duration, response,
+                     record.cause.as_deref().unwrap_or_default(), quality)?;
+        },
+        Format::Json => {
+            if !first {
+                writeln!(out, ",")?;
+            }
+            write!(out, "  ")?;
+            serde_json::to_writer(&mut *out, record)?;
+        },
+        Format::Ical => {
+            if record.kind != "work" && record.kind != "break" {
+                return Ok(false);
+            }
+            write_event(out, record, stamp)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Streams the history to `out` row by row, so long histories never sit in memory.
+pub fn export(history: &History, format: Format, from: Option<&str>, out: &mut dyn Write) -> Result<u64, ExportError> {
+    match format {
+        Format::Csv => writeln!(out, "kind,start,started_at,duration,response,cause,quality")?,
+        Format::Json => writeln!(out, "[")?,
+        Format::Ical => write!(out, "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:{}\r\n", ICAL_PRODID)?
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let stamp = ical_time(now);
+    let mut count = 0;
+    history.each_record(from, |record| -> Result<(), ExportError> {
+        if write_record(out, format, &record, count == 0, &stamp)? {
+            count += 1;
+        }
+        Ok(())
+    })?;
+
+    match format {
+        Format::Csv => {},
+        Format::Json => {
+            if count > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "]")?;
+        },
+        Format::Ical => write!(out, "END:VCALENDAR\r\n")?
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Writes the export encrypted like the history, when the history is encrypted.
+pub fn export_to_file(format: Format, from: Option<&str>, path: &Path) -> Result<u64, ExportError> {
+    let history = History::open()?;
+    if store::encryption() == Encryption::None {
+        let mut out = BufWriter::new(File::create(path)?);
+        return export(&history, format, from, &mut out);
+    }
+    let mut out = Vec::new();
+    let count = export(&history, format, from, &mut out)?;
+    store::write_export(&History::path(), path, &out)?;
+    Ok(count)
+}
+
+/// Writes a file exported while the history was encrypted to `output`, or to stdout.
+pub fn decrypt_file(path: &Path, output: Option<&Path>) -> Result<(), ExportError> {
+    let data = store::read_export(&History::path(), path)?;
+    match output {
+        Some(output) => fs::write(output, data)?,
+        None => io::stdout().write_all(&data)?
+    }
+    Ok(())
+}
+
+pub fn export_to_stdout(format: Format, from: Option<&str>) -> Result<u64, ExportError> {
+    let history = History::open()?;
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    export(&history, format, from, &mut out)
+}
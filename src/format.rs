@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crate::config::{TimeFormat, TimeShown};
+use crate::i18n::tr_args;
+use crate::BREAK_TIME;
+
+/// Whether durations are shown in words rather than on a clock.
+static WORDS: AtomicBool = AtomicBool::new(false);
+/// Whether the timer shows the time worked rather than the time left.
+static ELAPSED: AtomicBool = AtomicBool::new(false);
+
+pub fn hms(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Like `1 h 23 m` in English, down to the second below a minute.
+pub fn words(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes) = (secs / 3600, secs / 60 % 60);
+    if hours > 0 {
+        tr_args("duration-hours", &[("hours", &hours), ("minutes", &minutes)])
+    } else if minutes > 0 {
+        tr_args("duration-minutes", &[("minutes", &minutes)])
+    } else {
+        tr_args("duration-seconds", &[("seconds", &secs)])
+    }
+}
+
+/// Shows durations in `format` from now on.
+pub fn set_time_format(format: TimeFormat) {
+    WORDS.store(format == TimeFormat::Words, Ordering::Relaxed);
+}
+
+/// A duration as the user wants to read it.
+pub fn duration(duration: Duration) -> String {
+    if WORDS.load(Ordering::Relaxed) {
+        words(duration)
+    } else {
+        hms(duration)
+    }
+}
+
+/// Shows the timer as `shown` from now on.
+pub fn set_time_shown(shown: TimeShown) {
+    ELAPSED.store(shown == TimeShown::Elapsed, Ordering::Relaxed);
+}
+
+pub fn time_shown() -> TimeShown {
+    if ELAPSED.load(Ordering::Relaxed) {
+        TimeShown::Elapsed
+    } else {
+        TimeShown::Remaining
+    }
+}
+
+/// The timer after `elapsed` of work, the same in the window, the tray and the API.
+pub fn timer(elapsed: Duration) -> String {
+    match time_shown() {
+        TimeShown::Elapsed => tr_args("time-worked", &[("time", &duration(elapsed))]),
+        TimeShown::Remaining if elapsed >= BREAK_TIME => tr_args("break-overdue", &[("time", &duration(elapsed - BREAK_TIME))]),
+        TimeShown::Remaining => tr_args("break-in", &[("time", &duration(BREAK_TIME - elapsed))])
+    }
+}
+
+/// The longest pause taken from the command line, the API or a controller: longer is
+/// surely a mistake, and stays well clear of overflowing the clock.
+pub const MAX_PAUSE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Parses durations like `30m`, `90s`, `1h` or `1h30m`. A bare number is minutes.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    if let Ok(minutes) = text.parse::<u64>() {
+        return minutes.checked_mul(60).map(Duration::from_secs);
+    }
+    let mut secs = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let value: u64 = number.parse().ok()?;
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1
+                };
+                secs = value.checked_mul(unit).and_then(|value| value.checked_add(secs))?;
+                number.clear();
+            },
+            _ => return None
+        }
+    }
+    if !number.is_empty() || text.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// A pause of `duration`, or why it can't be one.
+pub fn check_pause(duration: Duration) -> Result<Duration, String> {
+    if duration > MAX_PAUSE {
+        Err(format!("pause longer than {}", hms(MAX_PAUSE)))
+    } else {
+        Ok(duration)
+    }
+}
+
+/// Parses a pause duration as `parse_duration` does, up to `MAX_PAUSE`.
+pub fn parse_pause(text: &str) -> Result<Duration, String> {
+    parse_duration(text)
+        .ok_or_else(|| format!("invalid duration: {}", text))
+        .and_then(check_pause)
+}
+
+/// The UTC year, month, day, hour, minute and second of a unix time.
+pub fn utc_civil(secs: i64) -> (i64, i64, i64, i64, i64, i64) {
+    let days = secs.div_euclid(86400);
+    let time = secs.rem_euclid(86400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+/// Formats a unix time as an RFC 3339 UTC timestamp, `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn rfc3339(secs: i64) -> String {
+    let (year, month, day, hour, minute, second) = utc_civil(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
@@ -0,0 +1,198 @@
+use crossbeam::channel::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{Cursor, Read};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+use crate::auth;
+use crate::config::HandoffConfig;
+use crate::status::Status;
+use crate::sync::machine_name;
+use crate::{Event, Request, State};
+
+mod mdns;
+
+const DISCOVERY_TIME: Duration = Duration::from_secs(2);
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BODY: u64 = 1024;
+
+/// The work timer of one machine, as offered to another.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Offer {
+    name: String,
+    state: String,
+    elapsed: u64,
+}
+
+/// Sent with `POST /handoff` by the machine taking the timer: its own work time, so the
+/// timer is only handed over when it's worth taking there.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Claim {
+    elapsed: u64,
+}
+
+/// Whether this machine, at `elapsed`, should take over the timer of `offer`: only a
+/// longer one, so the same work is never counted twice, and not from a machine where the
+/// user has since taken a break, which counts wherever it was taken.
+fn worth_taking(offer: &Offer, elapsed: u64) -> bool {
+    offer.state != State::OnBreak.as_str() && offer.elapsed > elapsed
+}
+
+fn json_response(code: u16, body: String) -> Response<Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(body).with_status_code(code).with_header(content_type)
+}
+
+/// Offers the timer on `GET /handoff`, and hands it over on `POST /handoff`, resetting it
+/// here so the work is counted on one machine only.
+fn handle(request: &mut tiny_http::Request, status: &Mutex<Status>, requests: &Sender<Request>, name: &str, token: &str) -> Response<Cursor<Vec<u8>>> {
+    if !request.headers().iter().any(|header| header.field.equiv("Authorization") && auth::is_bearer(header.value.as_str(), token)) {
+        return json_response(401, json!({ "error": "missing or wrong token" }).to_string());
+    }
+    let offer = {
+        let status = status.lock().unwrap();
+        Offer { name: name.to_string(), state: status.state.to_string(), elapsed: status.elapsed }
+    };
+    match (request.method(), request.url()) {
+        (Method::Get, "/handoff") => {},
+        (Method::Post, "/handoff") => {
+            let mut body = String::new();
+            let claim = request.as_reader().take(MAX_BODY).read_to_string(&mut body).ok()
+                .and_then(|_| serde_json::from_str::<Claim>(&body).ok());
+            match claim {
+                Some(claim) if worth_taking(&offer, claim.elapsed) => {
+                    log::info!("Handing the work timer over at {}s", offer.elapsed);
+                    requests.send(Request::ResetTimer).unwrap();
+                },
+                Some(_) => return json_response(409, json!({ "error": "not worth taking" }).to_string()),
+                None => return json_response(400, json!({ "error": "invalid claim" }).to_string())
+            }
+        },
+        _ => return json_response(404, json!({ "error": "not found" }).to_string())
+    }
+    json_response(200, serde_json::to_string(&offer).unwrap_or_default())
+}
+
+fn serve(address: IpAddr, port: u16, status: Arc<Mutex<Status>>, requests: Sender<Request>, name: String, token: String) {
+    let server = match Server::http((address, port)) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to offer the timer on {}:{}: {}", address, port, e);
+            return;
+        }
+    };
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut request, &status, &requests, &name, &token);
+        if let Err(e) = request.respond(response) {
+            log::warn!("Handoff: {}", e);
+        }
+    }
+}
+
+/// Looks for pauzas on the network and takes over the longest timer worth taking.
+fn take_over(name: &str, token: &str, status: &Mutex<Status>, requests: &Sender<Request>) -> Result<(), String> {
+    let bearer = format!("Bearer {}", token);
+    let offer = |url: &str| -> Result<Offer, String> {
+        ureq::get(url)
+            .timeout(TIMEOUT)
+            .set("Authorization", &bearer)
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_json()
+            .map_err(|e| e.to_string())
+    };
+    let found = mdns::discover(DISCOVERY_TIME).map_err(|e| e.to_string())?;
+    let elapsed = status.lock().unwrap().elapsed;
+    let best: Option<(Offer, String)> = found.into_iter()
+        .filter(|(instance, _)| instance != name)
+        .filter_map(|(instance, address)| {
+            let url = format!("http://{}/handoff", address);
+            match offer(&url) {
+                Ok(offer) => Some((offer, url)),
+                Err(e) => {
+                    log::warn!("Failed to ask {} for its timer: {}", instance, e);
+                    None
+                }
+            }
+        })
+        .filter(|(offer, _)| worth_taking(offer, elapsed))
+        .max_by_key(|(offer, _)| offer.elapsed);
+    if let Some((_, url)) = best {
+        // The timer may have moved on since, or the user taken a break there, so the other
+        // machine decides again before handing it over.
+        let claim = Claim { elapsed: status.lock().unwrap().elapsed };
+        let result = ureq::post(&url)
+            .timeout(TIMEOUT)
+            .set("Authorization", &bearer)
+            .send_json(&claim);
+        match result {
+            Ok(response) => {
+                let offer: Offer = response.into_json().map_err(|e| e.to_string())?;
+                log::info!("Taking over the work timer of {} at {}s", offer.name, offer.elapsed);
+                requests.send(Request::Adopt(Duration::from_secs(offer.elapsed))).unwrap();
+            },
+            Err(ureq::Error::Status(409, _)) => log::info!("The timer at {} is no longer worth taking", url),
+            Err(e) => return Err(e.to_string())
+        }
+    }
+    Ok(())
+}
+
+/// Makes this pauza known on the local network and, on starting and whenever the user
+/// comes back, takes over the work timer of another one the user just left.
+pub fn run(config: HandoffConfig, r: Receiver<Event>, requests: Sender<Request>) {
+    let name = machine_name(config.name.as_deref());
+    let token = config.token.unwrap_or_default();
+    let status = Arc::new(Mutex::new(Status::default()));
+    {
+        let name = name.clone();
+        let port = config.port;
+        thread::spawn(move || {
+            if let Err(e) = mdns::respond(&name, port) {
+                log::error!("Failed to answer mDNS queries: {}", e);
+            }
+        });
+    }
+    {
+        let status = status.clone();
+        let name = name.clone();
+        let token = token.clone();
+        let requests = requests.clone();
+        let (address, port) = (config.address.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)), config.port);
+        thread::spawn(move || serve(address, port, status, requests, name, token));
+    }
+
+    let look = |status: &Arc<Mutex<Status>>| {
+        let (name, token, status, requests) = (name.clone(), token.clone(), status.clone(), requests.clone());
+        thread::spawn(move || {
+            if let Err(e) = take_over(&name, &token, &status, &requests) {
+                log::warn!("Failed to look for a timer to take over: {}", e);
+            }
+        });
+    };
+    look(&status);
+    for event in r.iter() {
+        let was_away = status.lock().unwrap().state != State::Working.as_str();
+        status.lock().unwrap().apply(&event);
+        if was_away && event == Event::UpdateState(State::Working) {
+            look(&status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_only_a_longer_timer_still_running() {
+        let offer = |state: State, elapsed| Offer { name: "desktop".to_string(), state: state.as_str().to_string(), elapsed };
+        assert!(worth_taking(&offer(State::Working, 1800), 0));
+        assert!(worth_taking(&offer(State::Paused, 1800), 600));
+        assert!(!worth_taking(&offer(State::Working, 600), 1800));
+        assert!(!worth_taking(&offer(State::OnBreak, 1800), 0));
+    }
+}
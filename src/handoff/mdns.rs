@@ -0,0 +1,221 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+const SERVICE: &str = "_pauza._tcp.local";
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set in a question's class to have the answer sent straight back rather than to the group.
+const UNICAST_RESPONSE: u16 = 0x8000;
+const FLAGS_RESPONSE: u16 = 0x8400;
+const TTL: u32 = 120;
+const MAX_PACKET: usize = 9000;
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label);
+    }
+    packet.push(0);
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]))
+}
+
+/// Reads the name at `pos`, following compression pointers, and moves past it.
+fn read_name(packet: &[u8], pos: &mut usize) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut at = *pos;
+    let mut end = None;
+    // Each pointer has to go back, which also keeps a loop of them from going on forever.
+    let mut limit = at;
+    loop {
+        let len = *packet.get(at)? as usize;
+        if len == 0 {
+            end.get_or_insert(at + 1);
+            break;
+        }
+        if len & 0xc0 == 0xc0 {
+            let target = (read_u16(packet, at)? & 0x3fff) as usize;
+            end.get_or_insert(at + 2);
+            if target >= limit {
+                return None;
+            }
+            limit = target;
+            at = target;
+            continue;
+        }
+        labels.push(String::from_utf8_lossy(packet.get(at + 1..at + 1 + len)?).into_owned());
+        at += 1 + len;
+    }
+    *pos = end?;
+    Some(labels.join("."))
+}
+
+fn header(packet: &mut Vec<u8>, flags: u16, questions: u16, answers: u16) {
+    for field in [0, flags, questions, answers, 0, 0] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+}
+
+/// A question for every pauza on the network, to be answered straight back.
+fn query() -> Vec<u8> {
+    let mut packet = Vec::new();
+    header(&mut packet, 0, 1, 0);
+    write_name(&mut packet, SERVICE);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&(CLASS_IN | UNICAST_RESPONSE).to_be_bytes());
+    packet
+}
+
+/// Whether `packet` is a query asking for pauzas.
+fn asks_for_service(packet: &[u8]) -> bool {
+    let (flags, questions) = match (read_u16(packet, 2), read_u16(packet, 4)) {
+        (Some(flags), Some(questions)) => (flags, questions),
+        _ => return false
+    };
+    if flags & 0x8000 != 0 {
+        return false;
+    }
+    let mut pos = 12;
+    for _ in 0..questions {
+        let name = match read_name(packet, &mut pos) {
+            Some(name) => name,
+            None => return false
+        };
+        let kind = read_u16(packet, pos);
+        pos += 4;
+        if name.eq_ignore_ascii_case(SERVICE) && matches!(kind, Some(TYPE_PTR) | Some(TYPE_ANY)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// The answer naming this pauza as `instance` and the port it hands the timer over on.
+fn answer(instance: &str, port: u16) -> Vec<u8> {
+    let full_name = format!("{}.{}", instance, SERVICE);
+    let mut packet = Vec::new();
+    header(&mut packet, FLAGS_RESPONSE, 0, 2);
+
+    write_name(&mut packet, SERVICE);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet.extend_from_slice(&TTL.to_be_bytes());
+    let mut data = Vec::new();
+    write_name(&mut data, &full_name);
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&data);
+
+    write_name(&mut packet, &full_name);
+    packet.extend_from_slice(&TYPE_SRV.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet.extend_from_slice(&TTL.to_be_bytes());
+    let mut data = Vec::new();
+    // Priority and weight.
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(&port.to_be_bytes());
+    write_name(&mut data, &format!("{}.local", instance));
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&data);
+    packet
+}
+
+/// The pauza instance and port an answer names, from its service record.
+fn read_answer(packet: &[u8]) -> Option<(String, u16)> {
+    if read_u16(packet, 2)? & 0x8000 == 0 {
+        return None;
+    }
+    let questions = read_u16(packet, 4)?;
+    let records = read_u16(packet, 6)? as usize + read_u16(packet, 8)? as usize + read_u16(packet, 10)? as usize;
+    let mut pos = 12;
+    for _ in 0..questions {
+        read_name(packet, &mut pos)?;
+        pos += 4;
+    }
+    for _ in 0..records {
+        let name = read_name(packet, &mut pos)?;
+        let kind = read_u16(packet, pos)?;
+        let len = read_u16(packet, pos + 8)? as usize;
+        let data = pos + 10;
+        pos = data + len;
+        // By bytes, as the name comes off the network and needn't split into characters there.
+        let instance = name.len().checked_sub(SERVICE.len() + 1)
+            .filter(|at| name.as_bytes()[at + 1..].eq_ignore_ascii_case(SERVICE.as_bytes()) && name.as_bytes()[*at] == b'.')
+            .and_then(|at| name.get(..at))
+            .map(str::to_string);
+        if let (TYPE_SRV, Some(instance)) = (kind, instance) {
+            return Some((instance, read_u16(packet, data + 4)?));
+        }
+    }
+    None
+}
+
+/// Answers every query for pauzas on the network with `instance` and `port`, for good.
+pub fn respond(instance: &str, port: u16) -> io::Result<()> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // Windows and other responders listen on the same port.
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, PORT)).into())?;
+    socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    let socket: UdpSocket = socket.into();
+    let answer = answer(instance, port);
+    let mut buffer = vec![0; MAX_PACKET];
+    loop {
+        let (len, from) = socket.recv_from(&mut buffer)?;
+        if !asks_for_service(&buffer[..len]) {
+            continue;
+        }
+        // Queries from other ports are one-shot and want the answer back where they came from.
+        let to = if from.port() == PORT { SocketAddr::V4(SocketAddrV4::new(GROUP, PORT)) } else { from };
+        if let Err(e) = socket.send_to(&answer, to) {
+            log::warn!("mDNS: {}", e);
+        }
+    }
+}
+
+/// The pauzas that answer within `wait`, by instance name, with where to reach them.
+pub fn discover(wait: Duration) -> io::Result<Vec<(String, SocketAddr)>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.send_to(&query(), (GROUP, PORT))?;
+    let deadline = Instant::now() + wait;
+    let mut found: Vec<(String, SocketAddr)> = Vec::new();
+    let mut buffer = vec![0; MAX_PACKET];
+    while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+        socket.set_read_timeout(Some(left))?;
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e)
+        };
+        if let Some((instance, port)) = read_answer(&buffer[..len]) {
+            if !found.iter().any(|(known, _)| *known == instance) {
+                found.push((instance, SocketAddr::new(from.ip(), port)));
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_its_own_query() {
+        assert!(asks_for_service(&query()));
+        let answer = answer("DESKTOP-1", 7648);
+        assert!(!asks_for_service(&answer));
+        assert_eq!(read_answer(&answer), Some(("DESKTOP-1".to_string(), 7648)));
+        assert_eq!(read_answer(&query()), None);
+        // A pointer to itself doesn't hang.
+        assert_eq!(read_name(&[0xc0, 0x00], &mut 0), None);
+    }
+}
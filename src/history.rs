@@ -0,0 +1,742 @@
+use crossbeam::channel::Receiver;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task, time};
+use crate::config::{data_dir, Encryption, RetentionConfig};
+use crate::{privacy, store, Event};
+
+const HISTORY_FILE: &str = "history.db";
+const FIRST_PRUNE_DELAY: Duration = Duration::from_secs(10 * 60);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// A break starting within this long after a reminder counts as following it.
+pub const COMPLIANCE_WINDOW: Duration = Duration::from_secs(600);
+
+/// Seconds from each reminder to the next break, NULL for other kinds or unanswered reminders.
+const RESPONSE_EXPR: &str =
+    "CASE WHEN i.kind = 'prompt' THEN
+         (SELECT MIN(b.started_at) FROM intervals b
+          WHERE b.kind = 'break' AND b.started_at >= i.started_at) - i.started_at
+     END";
+
+/// Days before this local date, `YYYY-MM-DD`, are only in `daily_totals`.
+const PRUNED_BEFORE: &str = "(SELECT COALESCE(MAX(value), '') FROM meta WHERE key = 'pruned_before')";
+
+/// The totals of each local day whose intervals are from `?1` on and after the pruned days,
+/// in the columns of `daily_totals`.
+const DAY_TOTALS: &str =
+    "SELECT date(started_at, 'unixepoch', 'localtime') AS day,
+            SUM(CASE WHEN kind = 'work' THEN duration ELSE 0 END) AS work,
+            SUM(CASE WHEN kind = 'break' THEN duration ELSE 0 END) AS rest,
+            MAX(CASE WHEN kind = 'work' THEN duration ELSE 0 END) AS longest,
+            SUM(CASE WHEN kind = 'work' THEN 1 ELSE 0 END) AS stretches,
+            SUM(CASE WHEN kind = 'break' THEN 1 ELSE 0 END) AS breaks,
+            SUM(CASE WHEN kind = 'prompt' THEN 1 ELSE 0 END) AS prompts,
+            SUM(CASE WHEN response <= ?2 THEN 1 ELSE 0 END) AS followed,
+            SUM(CASE WHEN response IS NOT NULL THEN 1 ELSE 0 END) AS answered,
+            TOTAL(response) AS overdue,
+            SUM(CASE WHEN kind = 'break' AND quality IS NOT NULL THEN 1 ELSE 0 END) AS rated,
+            TOTAL(CASE WHEN kind = 'break' THEN quality END) AS quality_total
+     FROM (SELECT i.*, {response} AS response FROM intervals i WHERE i.started_at >= ?1)
+     WHERE day >= {pruned}
+     GROUP BY day";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntervalKind {
+    Work,
+    Break,
+    /// The timer reset by hand past a break reminder, recorded with no duration.
+    Skip,
+    Snooze,
+    /// A break reminder, recorded with no duration.
+    Prompt,
+    /// The user starting the break they were reminded of, recorded with no duration.
+    Acknowledgment
+}
+
+impl IntervalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntervalKind::Work => "work",
+            IntervalKind::Break => "break",
+            IntervalKind::Skip => "skip",
+            IntervalKind::Snooze => "snooze",
+            IntervalKind::Prompt => "prompt",
+            IntervalKind::Acknowledgment => "acknowledgment"
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<IntervalKind> {
+        match kind {
+            "work" => Some(IntervalKind::Work),
+            "break" => Some(IntervalKind::Break),
+            "skip" => Some(IntervalKind::Skip),
+            "snooze" => Some(IntervalKind::Snooze),
+            "prompt" => Some(IntervalKind::Prompt),
+            "acknowledgment" => Some(IntervalKind::Acknowledgment),
+            _ => None
+        }
+    }
+}
+
+/// Why a break started: nobody at the keyboard, or the session locked, asleep or switched away.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdleCause {
+    Inactive,
+    Locked,
+    Asleep,
+    Disconnected
+}
+
+impl IdleCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdleCause::Inactive => "inactive",
+            IdleCause::Locked => "locked",
+            IdleCause::Asleep => "asleep",
+            IdleCause::Disconnected => "disconnected"
+        }
+    }
+
+    pub fn parse(cause: &str) -> Option<IdleCause> {
+        match cause {
+            "inactive" => Some(IdleCause::Inactive),
+            "locked" => Some(IdleCause::Locked),
+            "asleep" => Some(IdleCause::Asleep),
+            "disconnected" => Some(IdleCause::Disconnected),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub kind: IntervalKind,
+    pub start: SystemTime,
+    pub duration: Duration,
+    /// For breaks, what started them.
+    pub cause: Option<IdleCause>,
+    /// For breaks, out of 100, how much of the break was spent away from the screen.
+    pub quality: Option<u8>,
+}
+
+/// An interval from the columns `kind, started_at, duration, cause, quality`.
+fn interval_row(row: &rusqlite::Row) -> rusqlite::Result<Interval> {
+    let kind: String = row.get(0)?;
+    let cause: Option<String> = row.get(3)?;
+    Ok(Interval {
+        kind: IntervalKind::parse(&kind).unwrap_or(IntervalKind::Work),
+        start: UNIX_EPOCH + Duration::from_secs(row.get::<_, i64>(1)?.max(0) as u64),
+        duration: Duration::from_secs(row.get::<_, i64>(2)?.max(0) as u64),
+        cause: cause.as_deref().and_then(IdleCause::parse),
+        quality: row.get(4)?,
+    })
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Time spent working in one app.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppUsage {
+    pub app: String,
+    pub duration: Duration,
+}
+
+/// Key presses and clicks in one hour of the day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Intensity {
+    pub hour: u32,
+    pub keys: u32,
+    pub clicks: u32,
+    /// Minutes with any input.
+    pub minutes: u32,
+}
+
+impl Intensity {
+    /// Key presses and clicks per minute of input.
+    pub fn per_minute(&self) -> f32 {
+        if self.minutes == 0 {
+            0.0
+        } else {
+            (self.keys + self.clicks) as f32 / self.minutes as f32
+        }
+    }
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn path() -> PathBuf {
+        data_dir().join(HISTORY_FILE)
+    }
+
+    /// Where the history actually is, which is elsewhere when it is encrypted.
+    pub fn file() -> PathBuf {
+        match store::encryption() {
+            Encryption::None => History::path(),
+            _ => store::encrypted_path(&History::path())
+        }
+    }
+
+    pub fn open() -> rusqlite::Result<History> {
+        let dir = data_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("{}: {}", dir.display(), e);
+        }
+        History::with(store::open(&History::path())?)
+    }
+
+    /// The history in `conn`, with its tables made or brought up to date.
+    fn with(conn: Connection) -> rusqlite::Result<History> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS intervals (
+                 id INTEGER PRIMARY KEY,
+                 kind TEXT NOT NULL,
+                 started_at INTEGER NOT NULL,
+                 duration INTEGER NOT NULL,
+                 cause TEXT,
+                 quality INTEGER,
+                 machine TEXT
+             );
+             CREATE INDEX IF NOT EXISTS intervals_started_at ON intervals (started_at);
+             CREATE TABLE IF NOT EXISTS app_usage (
+                 day TEXT NOT NULL,
+                 app TEXT NOT NULL,
+                 title_hash INTEGER NOT NULL,
+                 duration INTEGER NOT NULL,
+                 PRIMARY KEY (day, app, title_hash)
+             );
+             CREATE TABLE IF NOT EXISTS input_rate (
+                 minute INTEGER PRIMARY KEY,
+                 keys INTEGER NOT NULL,
+                 clicks INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS meta (
+                 key TEXT PRIMARY KEY,
+                 value TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS daily_totals (
+                 day TEXT PRIMARY KEY,
+                 work INTEGER NOT NULL,
+                 rest INTEGER NOT NULL,
+                 longest INTEGER NOT NULL,
+                 stretches INTEGER NOT NULL,
+                 breaks INTEGER NOT NULL,
+                 prompts INTEGER NOT NULL,
+                 followed INTEGER NOT NULL,
+                 answered INTEGER NOT NULL,
+                 overdue REAL NOT NULL,
+                 rated INTEGER NOT NULL,
+                 quality_total REAL NOT NULL
+             );")?;
+        // Histories from before causes, quality and synced machines were recorded lack their columns.
+        for (column, kind) in [("cause", "TEXT"), ("quality", "INTEGER"), ("machine", "TEXT")] {
+            let exists: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('intervals') WHERE name = ?1", params![column], |row| row.get(0))?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE intervals ADD COLUMN {} {}", column, kind))?;
+                store::changed();
+            }
+        }
+        Ok(History { conn })
+    }
+
+    pub fn meta(&self, key: &str) -> rusqlite::Result<Option<String>> {
+        self.conn.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> rusqlite::Result<()> {
+        self.conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+        store::changed();
+        Ok(())
+    }
+
+    pub fn record(&self, interval: &Interval) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO intervals (kind, started_at, duration, cause, quality) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![interval.kind.as_str(), unix_secs(interval.start), interval.duration.as_secs() as i64,
+                    interval.cause.map(|cause| cause.as_str()), interval.quality])?;
+        store::changed();
+        Ok(())
+    }
+
+    /// Adds `duration` to the time spent in a window of `app` on the local day of `at`.
+    pub fn add_usage(&self, app: &str, title_hash: i64, at: SystemTime, duration: Duration) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO app_usage (day, app, title_hash, duration)
+             VALUES (date(?1, 'unixepoch', 'localtime'), ?2, ?3, ?4)
+             ON CONFLICT (day, app, title_hash) DO UPDATE SET duration = duration + excluded.duration",
+            params![unix_secs(at), app, title_hash, duration.as_secs() as i64])?;
+        store::changed();
+        Ok(())
+    }
+
+    /// The intervals that ended after `since`, the earliest first.
+    pub fn intervals_since(&self, since: SystemTime) -> rusqlite::Result<Vec<Interval>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, started_at, duration, cause, quality FROM intervals
+             WHERE started_at + duration > ?1
+             ORDER BY started_at")?;
+        let rows = stmt.query_map(params![unix_secs(since)], interval_row)?;
+        rows.collect()
+    }
+
+    /// Time in each app from the local day of `since` on, the longest first.
+    pub fn app_usage(&self, since: SystemTime) -> rusqlite::Result<Vec<AppUsage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT app, SUM(duration) AS total FROM app_usage
+             WHERE day >= date(?1, 'unixepoch', 'localtime')
+             GROUP BY app
+             ORDER BY total DESC")?;
+        let rows = stmt.query_map(params![unix_secs(since)], |row| {
+            Ok(AppUsage {
+                app: row.get(0)?,
+                duration: Duration::from_secs(row.get::<_, i64>(1)? as u64),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Records the key presses and clicks of the minute up to `at`.
+    pub fn record_input(&self, at: SystemTime, keys: u32, clicks: u32) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO input_rate (minute, keys, clicks) VALUES (?1, ?2, ?3)",
+            params![unix_secs(at) / 60, keys, clicks])?;
+        store::changed();
+        Ok(())
+    }
+
+    /// Input for each local hour from the local day of `since` on, in order.
+    pub fn input_intensity(&self, since: SystemTime) -> rusqlite::Result<Vec<Intensity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST(strftime('%H', minute * 60, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+                    SUM(keys), SUM(clicks), COUNT(*)
+             FROM input_rate
+             WHERE date(minute * 60, 'unixepoch', 'localtime') >= date(?1, 'unixepoch', 'localtime')
+             GROUP BY hour
+             ORDER BY hour")?;
+        let rows = stmt.query_map(params![unix_secs(since)], |row| {
+            Ok(Intensity {
+                hour: row.get(0)?,
+                keys: row.get(1)?,
+                clicks: row.get(2)?,
+                minutes: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Drops the work and break intervals recorded since `since`, after a reset was undone.
+    pub fn undo_reset(&self, since: SystemTime) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "DELETE FROM intervals WHERE kind IN ('work', 'break') AND started_at >= ?1 AND machine IS NULL",
+            params![unix_secs(since)])?;
+        store::changed();
+        Ok(())
+    }
+
+    /// Keeps only the daily totals of the days more than `days` before today, dropping
+    /// their intervals, app usage and input counts, and returns how many rows went.
+    pub fn prune(&mut self, days: u64) -> rusqlite::Result<usize> {
+        let cutoff: String = self.conn.query_row(
+            "SELECT date('now', 'localtime', ?1)", params![format!("-{} days", days)], |row| row.get(0))?;
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            &format!("INSERT OR REPLACE INTO daily_totals SELECT * FROM ({}) WHERE day < ?3", day_totals()),
+            params![0, COMPLIANCE_WINDOW.as_secs() as i64, cutoff])?;
+        let mut pruned = tx.execute(
+            "DELETE FROM intervals WHERE started_at < CAST(strftime('%s', ?1, 'utc') AS INTEGER)", params![cutoff])?;
+        pruned += tx.execute("DELETE FROM app_usage WHERE day < ?1", params![cutoff])?;
+        pruned += tx.execute(
+            "DELETE FROM input_rate WHERE minute < CAST(strftime('%s', ?1, 'utc') AS INTEGER) / 60", params![cutoff])?;
+        tx.execute(
+            &format!("INSERT OR REPLACE INTO meta (key, value) VALUES ('pruned_before', MAX(?1, {}))", PRUNED_BEFORE),
+            params![cutoff])?;
+        tx.commit()?;
+        if pruned > 0 {
+            self.conn.execute_batch("VACUUM")?;
+            store::changed();
+        }
+        Ok(pruned)
+    }
+
+    /// Empties the whole history, daily totals included.
+    pub fn delete_all(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch(
+            "DELETE FROM intervals;
+             DELETE FROM app_usage;
+             DELETE FROM input_rate;
+             DELETE FROM daily_totals;
+             DELETE FROM meta WHERE key = 'pruned_before';
+             VACUUM;")?;
+        store::changed();
+        store::save();
+        Ok(())
+    }
+}
+
+/// Once a day, drops the detail of the days more than `detail_days` back, unless it is 0.
+pub fn watch_retention(config: RetentionConfig) {
+    let days = config.detail_days;
+    if days == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        time::sleep(FIRST_PRUNE_DELAY).await;
+        loop {
+            let result = task::spawn_blocking(move || History::open()?.prune(days)).await;
+            match result {
+                Ok(Ok(0)) => {},
+                Ok(Ok(pruned)) => log::info!("Pruned {} rows of history older than {} days", pruned, days),
+                Ok(Err(e)) => log::error!("Failed to prune the history: {}", e),
+                Err(e) => log::error!("Failed to prune the history: {}", e)
+            }
+            time::sleep(PRUNE_INTERVAL).await;
+        }
+    });
+}
+
+/// Intervals as they are synced with other machines, a file for each machine and month.
+impl History {
+    pub fn last_interval_id(&self) -> rusqlite::Result<i64> {
+        self.conn.query_row("SELECT COALESCE(MAX(id), 0) FROM intervals WHERE machine IS NULL", [], |row| row.get(0))
+    }
+
+    /// The months, as `YYYY-MM` in UTC, of the intervals recorded here after the one with `id`.
+    pub fn months_after(&self, id: i64) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT strftime('%Y-%m', started_at, 'unixepoch') FROM intervals
+             WHERE id > ?1 AND machine IS NULL")?;
+        let rows = stmt.query_map(params![id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// The intervals recorded here in `month`.
+    pub fn month_intervals(&self, month: &str) -> rusqlite::Result<Vec<Interval>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, started_at, duration, cause, quality FROM intervals
+             WHERE strftime('%Y-%m', started_at, 'unixepoch') = ?1 AND machine IS NULL
+             ORDER BY started_at")?;
+        let rows = stmt.query_map(params![month], interval_row)?;
+        rows.collect()
+    }
+
+    /// Replaces what is known of `machine`'s intervals in `month` with `intervals`.
+    pub fn replace_month(&mut self, machine: &str, month: &str, intervals: &[Interval]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM intervals WHERE machine = ?1 AND strftime('%Y-%m', started_at, 'unixepoch') = ?2",
+            params![machine, month])?;
+        for interval in intervals {
+            tx.execute(
+                "INSERT INTO intervals (kind, started_at, duration, cause, quality, machine) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![interval.kind.as_str(), unix_secs(interval.start), interval.duration.as_secs() as i64,
+                        interval.cause.map(|cause| cause.as_str()), interval.quality, machine])?;
+        }
+        tx.commit()?;
+        store::changed();
+        Ok(())
+    }
+}
+
+/// Writes every interval the monitor reports to the history database.
+pub fn record_events(r: Receiver<Event>) {
+    let history = match History::open() {
+        Ok(history) => history,
+        Err(e) => {
+            log::error!("{}", e);
+            return;
+        }
+    };
+    for event in r.iter() {
+        if privacy::enabled() {
+            continue;
+        }
+        let interval = match event {
+            Event::Interval(interval) => interval,
+            Event::ResetUndone(since) => {
+                if let Err(e) = history.undo_reset(since) {
+                    log::error!("{}", e);
+                }
+                continue;
+            },
+            Event::NotifyBreak => Interval {
+                kind: IntervalKind::Prompt,
+                start: SystemTime::now(),
+                duration: Duration::from_secs(0),
+                cause: None,
+                quality: None,
+            },
+            _ => continue
+        };
+        if let Err(e) = history.record(&interval) {
+            log::error!("{}", e);
+        }
+    }
+    store::save();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day,
+    Week,
+    /// A day labelled with its year too, so labels stay unique across the whole history.
+    Date
+}
+
+impl Period {
+    fn group_expr(&self) -> &'static str {
+        match self {
+            Period::Day => "strftime('%m-%d', day)",
+            Period::Week => "strftime('W%W', day)",
+            Period::Date => "day"
+        }
+    }
+}
+
+/// Work and break totals for one day or week.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub label: String,
+    pub work: Duration,
+    pub rest: Duration,
+    pub longest: Duration,
+    pub stretches: u32,
+    pub breaks: u32,
+    pub prompts: u32,
+    /// Reminders followed by a break within `COMPLIANCE_WINDOW`.
+    pub followed: u32,
+    /// Reminders followed by a break at all, and the total time it took.
+    pub answered: u32,
+    pub overdue: Duration,
+    /// Breaks with a quality, and the sum of their qualities.
+    pub rated: u32,
+    pub quality_total: u32,
+}
+
+impl Summary {
+    /// The share of break reminders followed within `COMPLIANCE_WINDOW`.
+    pub fn compliance(&self) -> Option<f32> {
+        if self.prompts == 0 {
+            None
+        } else {
+            Some(self.followed as f32 / self.prompts as f32)
+        }
+    }
+
+    /// How long breaks were put off after a reminder, on average.
+    pub fn average_overdue(&self) -> Option<Duration> {
+        if self.answered == 0 {
+            None
+        } else {
+            Some(self.overdue / self.answered)
+        }
+    }
+
+    /// How much of their breaks were spent away from the screen, on average.
+    pub fn quality(&self) -> Option<f32> {
+        if self.rated == 0 {
+            None
+        } else {
+            Some(self.quality_total as f32 / self.rated as f32 / 100.0)
+        }
+    }
+
+    pub fn total(summaries: &[Summary]) -> Summary {
+        summaries.iter().fold(Summary::default(), |total, s| Summary {
+            label: String::new(),
+            work: total.work + s.work,
+            rest: total.rest + s.rest,
+            longest: total.longest.max(s.longest),
+            stretches: total.stretches + s.stretches,
+            breaks: total.breaks + s.breaks,
+            prompts: total.prompts + s.prompts,
+            followed: total.followed + s.followed,
+            answered: total.answered + s.answered,
+            overdue: total.overdue + s.overdue,
+            rated: total.rated + s.rated,
+            quality_total: total.quality_total + s.quality_total,
+        })
+    }
+}
+
+fn day_totals() -> String {
+    DAY_TOTALS.replace("{response}", RESPONSE_EXPR).replace("{pruned}", PRUNED_BEFORE)
+}
+
+impl History {
+    pub fn summaries(&self, period: Period, since: SystemTime) -> rusqlite::Result<Vec<Summary>> {
+        // The days still in the intervals, then those only in the daily totals.
+        let sql = format!(
+            "SELECT {group} AS label, SUM(work), SUM(rest), MAX(longest), SUM(stretches), SUM(breaks), SUM(prompts),
+                    SUM(followed), SUM(answered), TOTAL(overdue), SUM(rated), TOTAL(quality_total)
+             FROM ({totals}
+                   UNION ALL
+                   SELECT * FROM daily_totals
+                   WHERE day >= date(?1, 'unixepoch', 'localtime') AND day < {pruned})
+             GROUP BY label
+             ORDER BY MIN(day)",
+            group = period.group_expr(),
+            totals = day_totals(),
+            pruned = PRUNED_BEFORE);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![unix_secs(since), COMPLIANCE_WINDOW.as_secs() as i64], |row| {
+            Ok(Summary {
+                label: row.get(0)?,
+                work: Duration::from_secs(row.get::<_, i64>(1)? as u64),
+                rest: Duration::from_secs(row.get::<_, i64>(2)? as u64),
+                longest: Duration::from_secs(row.get::<_, i64>(3)? as u64),
+                stretches: row.get::<_, i64>(4)? as u32,
+                breaks: row.get::<_, i64>(5)? as u32,
+                prompts: row.get::<_, i64>(6)? as u32,
+                followed: row.get::<_, i64>(7)? as u32,
+                answered: row.get::<_, i64>(8)? as u32,
+                overdue: Duration::from_secs(row.get::<_, f64>(9)? as u64),
+                rated: row.get::<_, i64>(10)? as u32,
+                quality_total: row.get::<_, f64>(11)? as u32,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Minutes worked in each local hour of each weekday, Monday first.
+pub type Heatmap = [[u32; 24]; 7];
+
+impl History {
+    /// Work time from `since` on, split at the local hours it crossed.
+    pub fn active_minutes(&self, since: SystemTime) -> rusqlite::Result<Heatmap> {
+        // Each step cuts a piece off at the next local hour, which is whole seconds of the
+        // UTC offset away from a UTC hour.
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE
+                 offsets(start, finish, offset) AS (
+                     SELECT MAX(started_at, ?1), started_at + duration,
+                            CAST(strftime('%s', MAX(started_at, ?1), 'unixepoch', 'localtime') AS INTEGER) - MAX(started_at, ?1)
+                     FROM intervals WHERE kind = 'work' AND started_at + duration > ?1
+                 ),
+                 pieces(start, finish, offset, hour_end) AS (
+                     SELECT start, finish, offset, start - (start + offset) % 3600 + 3600 FROM offsets
+                     UNION ALL
+                     SELECT hour_end, finish, offset, hour_end + 3600 FROM pieces WHERE hour_end < finish
+                 )
+             SELECT (CAST(strftime('%w', start, 'unixepoch', 'localtime') AS INTEGER) + 6) % 7 AS weekday,
+                    CAST(strftime('%H', start, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+                    SUM(MIN(finish, hour_end) - start)
+             FROM pieces
+             GROUP BY weekday, hour")?;
+        let mut heatmap = [[0; 24]; 7];
+        let mut rows = stmt.query(params![unix_secs(since)])?;
+        while let Some(row) = rows.next()? {
+            let (weekday, hour, seconds): (usize, usize, i64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            if let Some(minutes) = heatmap.get_mut(weekday).and_then(|day| day.get_mut(hour)) {
+                *minutes = (seconds / 60) as u32;
+            }
+        }
+        Ok(heatmap)
+    }
+}
+
+/// One row of the history as it is exported.
+#[derive(Clone, Debug, Serialize)]
+pub struct Record {
+    pub kind: String,
+    pub start: String,
+    pub started_at: i64,
+    pub duration: i64,
+    /// For break reminders, seconds until the next break started.
+    pub response: Option<i64>,
+    /// For breaks, what started them: `inactive`, `locked`, `asleep` or `disconnected`.
+    pub cause: Option<String>,
+    /// For breaks, out of 100, how much of the break was spent away from the screen.
+    pub quality: Option<i64>,
+}
+
+impl History {
+    /// Calls `f` for every interval starting on or after the local date `from` (`YYYY-MM-DD`),
+    /// one row at a time, and returns how many rows were visited.
+    pub fn each_record<F, E>(&self, from: Option<&str>, mut f: F) -> Result<u64, E>
+        where F: FnMut(Record) -> Result<(), E>,
+              E: From<rusqlite::Error>
+    {
+        let since = match from {
+            Some(date) => self.conn.query_row(
+                "SELECT CAST(strftime('%s', ?1, 'utc') AS INTEGER)",
+                params![date],
+                |row| row.get::<_, Option<i64>>(0))?
+                .ok_or_else(|| rusqlite::Error::InvalidParameterName(format!("invalid date: {}", date)))?,
+            None => 0
+        };
+
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT i.kind, datetime(i.started_at, 'unixepoch', 'localtime'), i.started_at, i.duration, {}, i.cause, i.quality
+                      FROM intervals i
+                      WHERE i.started_at >= ?1
+                      ORDER BY i.started_at", RESPONSE_EXPR))?;
+        let mut rows = stmt.query(params![since])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            f(Record {
+                kind: row.get(0)?,
+                start: row.get(1)?,
+                started_at: row.get(2)?,
+                duration: row.get(3)?,
+                response: row.get(4)?,
+                cause: row.get(5)?,
+                quality: row.get(6)?,
+            })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn days_ago(days: u64) -> SystemTime {
+        // Noon, so the day doesn't change with the time zone.
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        UNIX_EPOCH + Duration::from_secs(now - now % DAY - days * DAY + DAY / 2)
+    }
+
+    fn interval(kind: IntervalKind, start: SystemTime, minutes: u64) -> Interval {
+        Interval { kind, start, duration: Duration::from_secs(minutes * 60), cause: None, quality: None }
+    }
+
+    #[test]
+    fn pruning_keeps_the_totals() {
+        let mut history = History::with(Connection::open_in_memory().unwrap()).unwrap();
+        for days in [200, 45, 44, 3] {
+            history.record(&interval(IntervalKind::Work, days_ago(days), 50)).unwrap();
+            history.record(&interval(IntervalKind::Prompt, days_ago(days) + Duration::from_secs(50 * 60), 0)).unwrap();
+            history.record(&interval(IntervalKind::Break, days_ago(days) + Duration::from_secs(51 * 60), 10)).unwrap();
+        }
+        let totals = |history: &History| -> Vec<Vec<Summary>> {
+            [Period::Day, Period::Week, Period::Date].iter()
+                .map(|&period| history.summaries(period, UNIX_EPOCH).unwrap())
+                .collect()
+        };
+        let before = totals(&history);
+        assert!(history.prune(30).unwrap() > 0);
+        assert_eq!(totals(&history), before);
+        assert_eq!(history.intervals_since(UNIX_EPOCH).unwrap().len(), 3);
+
+        // Pruning again, or less far back, changes nothing.
+        assert_eq!(history.prune(30).unwrap(), 0);
+        history.prune(100).unwrap();
+        assert_eq!(totals(&history), before);
+    }
+}
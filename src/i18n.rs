@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// The translations built into the executable. English comes first, as the fallback
+/// for anything missing from the others.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+
+static CATALOGS: OnceLock<Vec<HashMap<String, String>>> = OnceLock::new();
+/// The index in `LOCALES` of the language messages are shown in.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Reads the subset of Fluent the locale files use: `key = text` messages, with indented
+/// lines continuing the text on a new line, and `#` comments.
+pub fn parse(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for (number, line) in text.lines().enumerate() {
+        if line.starts_with(' ') {
+            let (_, value) = current.as_mut()
+                .ok_or_else(|| format!("line {}: continues no message", number + 1))?;
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(line.trim());
+            continue;
+        }
+        if let Some((key, value)) = current.take() {
+            messages.insert(key, value);
+        }
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = text: {}", number + 1, line))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("line {}: invalid key: {}", number + 1, key));
+        }
+        current = Some((key.to_string(), value.trim().to_string()));
+    }
+    if let Some((key, value)) = current {
+        messages.insert(key, value);
+    }
+    Ok(messages)
+}
+
+fn catalogs() -> &'static [HashMap<String, String>] {
+    CATALOGS.get_or_init(|| {
+        LOCALES.iter()
+            .map(|(language, text)| parse(text).unwrap_or_else(|e| {
+                log::error!("{}.ftl: {}", language, e);
+                HashMap::new()
+            }))
+            .collect()
+    })
+}
+
+/// The shipped locale for a language code like `de` or `fr-CA`.
+fn find(language: &str) -> Option<usize> {
+    let primary = language.split(['-', '_']).next().unwrap_or("");
+    LOCALES.iter().position(|(code, _)| code.eq_ignore_ascii_case(primary))
+}
+
+pub fn is_shipped(language: &str) -> bool {
+    find(language).is_some()
+}
+
+/// The codes of the languages there are translations for.
+pub fn shipped() -> Vec<&'static str> {
+    LOCALES.iter().map(|(code, _)| *code).collect()
+}
+
+/// Shows messages in `language` from now on, or in English if it isn't shipped.
+pub fn set_language(language: &str) {
+    let index = find(language).unwrap_or_else(|| {
+        log::warn!("No translation for {}, using English", language);
+        0
+    });
+    log::info!("Language: {}", LOCALES[index].0);
+    CURRENT.store(index, Ordering::Relaxed);
+}
+
+/// The code of the language messages are shown in.
+pub fn language() -> &'static str {
+    LOCALES[CURRENT.load(Ordering::Relaxed)].0
+}
+
+/// The message for `key` in the current language, with each `{ $name }` replaced with
+/// its value from `args`.
+pub fn tr_args(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let catalogs = catalogs();
+    let message = catalogs[CURRENT.load(Ordering::Relaxed)].get(key)
+        .or_else(|| catalogs[0].get(key));
+    let mut text = match message {
+        Some(message) => message.clone(),
+        None => {
+            log::warn!("Missing message: {}", key);
+            return key.to_string();
+        }
+    };
+    for (name, value) in args {
+        text = text.replace(&format!("{{ ${} }}", name), &value.to_string());
+    }
+    text
+}
+
+pub fn tr(key: &str) -> String {
+    tr_args(key, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeables(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split("{ $").skip(1)
+            .filter_map(|rest| rest.split(" }").next())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn parses_messages() {
+        let messages = parse("# comment\n\none = One\ntwo =\n    Line { $a }\n    Line two\nthree=3").unwrap();
+        assert_eq!(messages["one"], "One");
+        assert_eq!(messages["two"], "Line { $a }\nLine two");
+        assert_eq!(messages["three"], "3");
+        assert_eq!(parse("one = 1\nnope"), Err("line 2: expected key = text: nope".to_string()));
+        assert_eq!(parse("  stray"), Err("line 1: continues no message".to_string()));
+    }
+
+    #[test]
+    fn translations_match_english() {
+        let english = parse(LOCALES[0].1).unwrap();
+        for (language, text) in &LOCALES[1..] {
+            let messages = parse(text).unwrap();
+            for (key, message) in &messages {
+                let original = english.get(key).unwrap_or_else(|| panic!("{}: unknown key {}", language, key));
+                assert_eq!(placeables(message), placeables(original), "{}: {}", language, key);
+            }
+            for key in english.keys() {
+                assert!(messages.contains_key(key), "{}: missing {}", language, key);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_languages() {
+        assert_eq!(find("de"), Some(1));
+        assert_eq!(find("fr-CA"), Some(2));
+        assert_eq!(find("EN_us"), Some(0));
+        assert_eq!(find("pt-BR"), None);
+    }
+
+    #[test]
+    fn fills_in_arguments() {
+        assert_eq!(tr_args("breaks-today", &[("count", &3)]), "Breaks today: 3");
+        assert_eq!(tr("no-such-message"), "no-such-message");
+    }
+}
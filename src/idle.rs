@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+use crate::config::InputConfig;
+#[cfg(windows)]
+use crate::windows::{cursor_position, get_idle_time, AudioMonitor, GamepadMonitor, RawInputMonitor};
+
+#[cfg(windows)]
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+#[cfg(windows)]
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Input this much more recent than any key press or mouse input was something else, like a pen.
+const OTHER_INPUT_SLACK: Duration = Duration::from_secs(1);
+
+pub trait IdleSource {
+    /// How long there has been no input as of `now`, or the error code of a failed read.
+    fn idle_time(&mut self, now: Instant) -> Result<Duration, i32>;
+
+    /// True while the idle time is a guess, because the real source is failing.
+    fn degraded(&self) -> bool {
+        false
+    }
+}
+
+/// The idle time with the time since typing and since using the mouse each divided by its
+/// weight, and any other input counting as it is.
+pub fn weighted_idle(system: Duration, keyboard: Duration, mouse: Duration, config: &InputConfig) -> Duration {
+    let weigh = |idle: Duration, weight: f64| if weight > 0.0 {
+        Duration::try_from_secs_f64(idle.as_secs_f64() / weight).unwrap_or(Duration::MAX)
+    } else {
+        Duration::MAX
+    };
+    let idle = weigh(keyboard, config.keyboard_weight).min(weigh(mouse, config.mouse_weight));
+    if system.saturating_add(OTHER_INPUT_SLACK) < keyboard.min(mouse) {
+        idle.min(system)
+    } else {
+        idle
+    }
+}
+
+/// Idle time as seen from the mouse cursor alone, which only notices the mouse moving.
+#[cfg(windows)]
+struct CursorIdle {
+    position: Option<(i32, i32)>,
+    moved: Instant,
+}
+
+#[cfg(windows)]
+impl CursorIdle {
+    fn idle_time(&mut self, now: Instant) -> Result<Duration, i32> {
+        let position = cursor_position()?;
+        if self.position != Some(position) {
+            if self.position.is_some() {
+                self.moved = now;
+            }
+            self.position = Some(position);
+        }
+        Ok(now.saturating_duration_since(self.moved))
+    }
+}
+
+/// Reading the idle time fails: it's retried less and less often, with the cursor
+/// standing in meanwhile.
+#[cfg(windows)]
+struct Failing {
+    retry_at: Instant,
+    delay: Duration,
+    cursor: CursorIdle,
+}
+
+/// Input idle time, cut short by any activity the optional extra inputs noticed since.
+#[cfg(windows)]
+pub struct InputIdle {
+    gamepad: Option<GamepadMonitor>,
+    audio: Option<AudioMonitor>,
+    raw_input: Option<RawInputMonitor>,
+    weights: InputConfig,
+    last_idle: Duration,
+    failing: Option<Failing>,
+}
+
+#[cfg(windows)]
+impl InputIdle {
+    pub fn new(config: &InputConfig) -> InputIdle {
+        InputIdle {
+            gamepad: if config.gamepad { Some(GamepadMonitor::default()) } else { None },
+            audio: if config.audio { Some(AudioMonitor::new()) } else { None },
+            raw_input: if config.weighted() { Some(RawInputMonitor::new()) } else { None },
+            weights: config.clone(),
+            last_idle: Duration::from_secs(0),
+            failing: None,
+        }
+    }
+
+    fn input_idle_time(&mut self, now: Instant) -> Result<Duration, i32> {
+        if let Some(failing) = self.failing.as_mut() {
+            if now < failing.retry_at {
+                return failing.cursor.idle_time(now);
+            }
+        }
+        match get_idle_time() {
+            Ok(idle_time) => {
+                if self.failing.take().is_some() {
+                    log::info!("Reading the idle time works again");
+                }
+                self.last_idle = idle_time;
+                Ok(idle_time)
+            },
+            Err(errno) => {
+                let last_idle = self.last_idle;
+                let failing = self.failing.get_or_insert_with(|| {
+                    log::warn!("Failed to read the idle time ({}), watching the cursor instead", errno);
+                    Failing {
+                        retry_at: now,
+                        delay: FIRST_RETRY_DELAY,
+                        cursor: CursorIdle {
+                            position: None,
+                            moved: now.checked_sub(last_idle).unwrap_or(now),
+                        },
+                    }
+                });
+                failing.retry_at = now + failing.delay;
+                failing.delay = (failing.delay * 2).min(MAX_RETRY_DELAY);
+                failing.cursor.idle_time(now)
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl IdleSource for InputIdle {
+    fn idle_time(&mut self, now: Instant) -> Result<Duration, i32> {
+        let mut idle_time = self.input_idle_time(now)?;
+        if let Some(raw_input) = self.raw_input.as_ref().filter(|_| self.failing.is_none()) {
+            let (keyboard, mouse) = raw_input.idle_times();
+            idle_time = weighted_idle(idle_time, keyboard, mouse, &self.weights);
+        }
+        let extra = [
+            self.gamepad.as_mut().and_then(|gamepad| gamepad.idle_time()),
+            self.audio.as_mut().and_then(|audio| audio.idle_time()),
+        ];
+        for extra_idle in extra.iter().flatten() {
+            idle_time = idle_time.min(*extra_idle);
+        }
+        Ok(idle_time)
+    }
+
+    fn degraded(&self) -> bool {
+        self.failing.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighs_keyboard_and_mouse() {
+        let secs = Duration::from_secs;
+        let config = InputConfig { mouse_weight: 3.0, ..InputConfig::default() };
+        // Scrolling a minute ago counts as 20 seconds ago.
+        assert_eq!(weighted_idle(secs(60), secs(600), secs(60), &config), secs(20));
+        assert_eq!(weighted_idle(secs(30), secs(30), secs(600), &config), secs(30));
+        let config = InputConfig { mouse_weight: 0.0, ..InputConfig::default() };
+        assert_eq!(weighted_idle(secs(5), secs(600), secs(5), &config), secs(600));
+        // A pen was used since.
+        assert_eq!(weighted_idle(secs(5), secs(600), secs(300), &config), secs(5));
+        // A weight too small to divide by counts as idle for good, rather than panicking.
+        let config = InputConfig { keyboard_weight: 1e-300, mouse_weight: 1e-300, ..InputConfig::default() };
+        assert_eq!(weighted_idle(secs(600), secs(600), secs(600), &config), Duration::MAX);
+    }
+}
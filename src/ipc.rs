@@ -0,0 +1,98 @@
+use crossbeam::channel::{Receiver, Sender};
+use serde_json::json;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::format;
+use crate::status::{self, Status};
+use crate::windows::{connect_pipe, PipeServer};
+use crate::{Event, Request};
+
+const MAX_COMMAND_LENGTH: usize = 1024;
+
+/// Parses one command line: `status`, `pause [SECONDS]`, `resume`, `break-now`, `start-break`,
+/// `snooze` or `reset`.
+/// `status` needs no request, so it parses to `None`.
+pub fn parse(line: &str) -> Result<Option<Request>, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let request = match command {
+        "status" => None,
+        "pause" => {
+            let duration = match words.next() {
+                Some(secs) => {
+                    let secs = secs.parse().map_err(|_| format!("invalid duration: {}", secs))?;
+                    Some(format::check_pause(Duration::from_secs(secs))?)
+                },
+                None => None
+            };
+            Some(Request::PauseTimer(duration))
+        },
+        "resume" => Some(Request::ResumeTimer),
+        "break-now" => Some(Request::BreakNow),
+        "start-break" => Some(Request::AcknowledgeBreak),
+        "snooze" => Some(Request::Snooze),
+        "reset" => Some(Request::ResetTimer),
+        _ => return Err(format!("unknown command: {}", command))
+    };
+    if let Some(extra) = words.next() {
+        return Err(format!("unexpected argument: {}", extra));
+    }
+    Ok(request)
+}
+
+fn read_line(stream: &mut dyn Read) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    while line.len() < MAX_COMMAND_LENGTH && stream.read(&mut byte)? == 1 && byte[0] != b'\n' {
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&line).trim().to_string())
+}
+
+/// Answers one command with a line of JSON.
+fn handle_client<S: Read + Write>(stream: &mut S, status: &Mutex<Status>, requests: &Sender<Request>) -> io::Result<()> {
+    let line = read_line(stream)?;
+    let response = match parse(&line) {
+        Ok(None) => json!({ "ok": true, "status": *status.lock().unwrap() }),
+        Ok(Some(request)) => {
+            log::info!("Pipe command: {}", line);
+            requests.send(request).unwrap();
+            json!({ "ok": true })
+        },
+        Err(e) => json!({ "ok": false, "error": e })
+    };
+    writeln!(stream, "{}", response)?;
+    stream.flush()
+}
+
+/// Serves commands from other processes on a named pipe, turning them into requests for
+/// the monitor.
+pub fn serve(r: Receiver<Event>, requests: Sender<Request>) {
+    let status = Arc::new(Mutex::new(Status::default()));
+    let tracked = status.clone();
+    thread::spawn(move || status::track(r, tracked));
+
+    let mut server = match PipeServer::create() {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to create the control pipe: {}", e);
+            return;
+        }
+    };
+    loop {
+        let result = server.accept()
+            .and_then(|mut connection| handle_client(&mut connection, &status, &requests));
+        if let Err(e) = result {
+            log::warn!("Control pipe: {}", e);
+        }
+    }
+}
+
+/// Sends one command to the running instance and returns its JSON answer.
+pub fn send_command(line: &str) -> io::Result<String> {
+    let mut pipe = connect_pipe()?;
+    writeln!(pipe, "{}", line)?;
+    read_line(&mut pipe)
+}
@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::FormatTime;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+use crate::config::{data_dir, LogLevel};
+use crate::windows::local_timestamp;
+
+const LOG_FILE: &str = "pauza.log";
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+/// Rotated logs are kept as `pauza.log.1` (newest) to `pauza.log.3` (oldest).
+const KEPT_LOGS: u32 = 3;
+/// How many of the latest records are kept in memory for crash reports.
+const RECENT_RECORDS: usize = 200;
+
+/// Changes the level once the config is loaded.
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: &Path) -> io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { file, size })
+    }
+}
+
+/// Appends log records to `pauza.log` in the data directory, rotating it when it gets large.
+struct RotatingFile {
+    dir: PathBuf,
+    file: Mutex<Option<LogFile>>,
+}
+
+impl RotatingFile {
+    fn rotated(&self, n: u32) -> PathBuf {
+        self.dir.join(format!("{}.{}", LOG_FILE, n))
+    }
+
+    fn rotate(&self) -> io::Result<LogFile> {
+        for n in (1..KEPT_LOGS).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(LOG_FILE), self.rotated(1))?;
+        LogFile::open(&self.dir.join(LOG_FILE))
+    }
+
+    fn write(&self, line: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if file.is_none() {
+            *file = Some(LogFile::open(&self.dir.join(LOG_FILE))?);
+        }
+        let log = file.as_mut().unwrap();
+        log.file.write_all(line)?;
+        log.size += line.len() as u64;
+        if log.size >= MAX_LOG_SIZE {
+            *file = None;
+            *file = Some(self.rotate()?);
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(log) => log.file.flush(),
+            None => Ok(())
+        }
+    }
+}
+
+/// Writes one formatted record, which comes in a single write.
+struct RecordWriter<'a>(&'a RotatingFile);
+
+impl Write for RecordWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_RECORDS {
+            recent.pop_front();
+        }
+        recent.push_back(String::from_utf8_lossy(buf).trim_end().to_string());
+        drop(recent);
+        if let Err(e) = self.0.write(buf) {
+            eprintln!("ERROR: {}: {}", self.0.dir.join(LOG_FILE).display(), e);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RecordWriter<'a>;
+
+    fn make_writer(&'a self) -> RecordWriter<'a> {
+        RecordWriter(self)
+    }
+}
+
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+        write!(w, "{}", local_timestamp())
+    }
+}
+
+fn filters(level: LogLevel) -> (LevelFilter, log::LevelFilter) {
+    match level {
+        LogLevel::Error => (LevelFilter::ERROR, log::LevelFilter::Error),
+        LogLevel::Warn => (LevelFilter::WARN, log::LevelFilter::Warn),
+        LogLevel::Info => (LevelFilter::INFO, log::LevelFilter::Info),
+        LogLevel::Debug => (LevelFilter::DEBUG, log::LevelFilter::Debug),
+        LogLevel::Trace => (LevelFilter::TRACE, log::LevelFilter::Trace)
+    }
+}
+
+pub fn path() -> PathBuf {
+    data_dir().join(LOG_FILE)
+}
+
+/// Sends tracing events, and records from the `log` crate, to the log file at the info
+/// level until `set_level` says otherwise.
+pub fn init() {
+    let dir = data_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("ERROR: {}: {}", dir.display(), e);
+    }
+    let file = RotatingFile {
+        dir,
+        file: Mutex::new(None),
+    };
+    let (filter, handle) = reload::Layer::new(LevelFilter::INFO);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(file)
+        .with_timer(LocalTime)
+        .with_ansi(false);
+    match tracing_subscriber::registry().with(filter).with(layer).try_init() {
+        Ok(()) => {
+            let _ = LEVEL.set(handle);
+        },
+        Err(e) => eprintln!("ERROR: {}", e)
+    }
+}
+
+pub fn set_level(level: LogLevel) {
+    let (filter, log_filter) = filters(level);
+    if let Some(handle) = LEVEL.get() {
+        if let Err(e) = handle.reload(filter) {
+            log::error!("Failed to set the log level: {}", e);
+            return;
+        }
+        // Records from the `log` crate are filtered before they get to tracing.
+        log::set_max_level(log_filter);
+    }
+}
+
+/// The latest records logged, oldest first.
+pub fn recent() -> Vec<String> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+}
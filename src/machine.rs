@@ -0,0 +1,1106 @@
+use tracing::info;
+use std::mem;
+use std::time::{Duration, Instant, SystemTime};
+use crate::config::{Config, GamingMode, IdleConfig, ReminderConfig};
+use crate::format;
+use crate::history::{IdleCause, Interval, IntervalKind};
+use crate::{
+    Event,
+    Request,
+    State,
+    BREAK_TIME,
+    IDLE_PAUSE_TIME,
+    IDLE_RESET_TIME,
+    SAVE_INTERVAL,
+    UNDO_TIME
+};
+
+/// The timer as it was before a reset, kept until `UNDO_TIME` after the user returns.
+struct ResetSnapshot {
+    start: Instant,
+    before_start: Duration,
+    started_at: SystemTime,
+    has_break: bool,
+    returned: Option<Instant>,
+}
+
+/// Why nobody can be at this session. Monitoring stops until every reason is gone, so
+/// a session that is both locked and switched away from resumes only once it's back
+/// on the console and unlocked.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct AwayReasons {
+    suspended: bool,
+    locked: bool,
+    disconnected: bool,
+}
+
+impl AwayReasons {
+    fn set(&mut self, request: Request, away: bool) {
+        match request {
+            Request::Suspend | Request::Resume => self.suspended = away,
+            Request::Lock | Request::Unlock => self.locked = away,
+            Request::Disconnect | Request::Connect => self.disconnected = away,
+            _ => {}
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.suspended || self.locked || self.disconnected
+    }
+}
+
+impl IdleCause {
+    /// The cause of an absence starting with `request`.
+    fn of(request: Request) -> IdleCause {
+        match request {
+            Request::Suspend => IdleCause::Asleep,
+            Request::Lock => IdleCause::Locked,
+            Request::Disconnect => IdleCause::Disconnected,
+            _ => IdleCause::Inactive
+        }
+    }
+}
+
+/// What is going on around the timer, as of the next step.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Conditions {
+    /// Stop the work timer, e.g. for a fullscreen app.
+    pub pause: bool,
+    /// Hold back the break prompt, e.g. during a call.
+    pub defer: bool,
+    /// The screen still has the user's attention while idle, with sound playing or a
+    /// fullscreen app in front, so a break isn't much of one.
+    pub watching: bool,
+}
+
+/// What can be seen around the session, for the config to turn into conditions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Surroundings {
+    pub fullscreen: bool,
+    pub gaming: bool,
+    /// The foreground process, when it's one of the configured quiet ones.
+    pub quiet_process: Option<String>,
+    pub in_call: bool,
+    /// How long ago the last call ended.
+    pub since_call: Option<Duration>,
+    pub sound_playing: bool,
+}
+
+impl Conditions {
+    pub fn new(config: &Config, surroundings: &Surroundings) -> Conditions {
+        let Surroundings { fullscreen, gaming, quiet_process, in_call, since_call, sound_playing } = surroundings;
+        let call_grace = Duration::from_secs(config.meetings.extra_minutes * 60);
+        Conditions {
+            pause: (*fullscreen && config.fullscreen.pause_timer)
+                || (*gaming && config.gaming.mode == GamingMode::Pause)
+                || quiet_process.as_deref().is_some_and(|name| config.processes.pauses(name)),
+            defer: (*fullscreen && config.fullscreen.defer_notifications)
+                || quiet_process.is_some()
+                || (*gaming && config.gaming.mode != GamingMode::Normal)
+                || (config.meetings.defer_notifications
+                    && (*in_call || since_call.is_some_and(|since| since < call_grace))),
+            watching: *fullscreen || *sound_playing,
+        }
+    }
+}
+
+/// Out of 100, how much of a break was spent away from the screen: all of it for a break
+/// as long as the idle reset with nothing watched, less for shorter ones.
+fn break_quality(rested: Duration, watched: Duration) -> u8 {
+    let full = (rested.as_secs_f32() / IDLE_RESET_TIME.as_secs_f32()).min(1.0);
+    let away = 1.0 - (watched.as_secs_f32() / rested.as_secs_f32().max(1.0)).min(1.0);
+    (full * away * 100.0).round() as u8
+}
+
+/// The work timer and its breaks. It only knows what it's told: the monitor thread
+/// feeds it the idle time, requests and conditions, and passes on the events it returns.
+pub struct BreakStateMachine {
+    start: Instant,
+    /// Work time from before `start`, which the monotonic clock may not reach back to, as
+    /// just after booting.
+    before_start: Duration,
+    has_break: bool,
+    has_reset: bool,
+    break_start: Instant,
+    state: State,
+    snapshot: Option<ResetSnapshot>,
+    /// When the session was left, the work time then, and why.
+    away: Option<(Instant, Duration, IdleCause)>,
+    away_reasons: AwayReasons,
+    /// The idle time at the last step, to tell a lock that followed inactivity from one that didn't.
+    idle: Duration,
+    last_step: Instant,
+    /// How long the screen was watched while idle, since the last work.
+    watched: Duration,
+    break_cause: IdleCause,
+    reset_policy: IdleConfig,
+    reminders: ReminderConfig,
+    /// When the break was last prompted or reminded of, and how many times it was repeated.
+    last_prompt: Instant,
+    repeats: u32,
+    /// Until when the reminder is put off by a snooze.
+    snoozed_until: Option<Instant>,
+    /// A break was prompted and, with `require_acknowledgment`, not acknowledged yet.
+    unacknowledged: bool,
+    /// When the current break was acknowledged.
+    acknowledged: Option<Instant>,
+    /// Work time taken over from another machine, which records it itself.
+    adopted: Duration,
+    paused: bool,
+    paused_until: Option<Instant>,
+    frozen: Option<Duration>,
+    conditions: Conditions,
+    /// Given the work left before a break, says whether to prompt for it now.
+    schedule: Option<Box<dyn FnMut(Duration) -> bool>>,
+    last_save: Instant,
+    save: Option<(Duration, bool)>,
+    /// The wall-clock time at an instant, to place intervals in time. Only that goes by
+    /// the wall clock: how long anything took is measured on the monotonic one.
+    anchor: (Instant, SystemTime),
+    events: Vec<Event>,
+}
+
+impl BreakStateMachine {
+    pub fn new(elapsed: Duration, has_break: bool, now: Instant, wall: SystemTime) -> BreakStateMachine {
+        BreakStateMachine {
+            start: now,
+            before_start: elapsed,
+            has_break,
+            has_reset: false,
+            break_start: now,
+            state: State::Working,
+            snapshot: None,
+            away: None,
+            away_reasons: AwayReasons::default(),
+            idle: Duration::from_secs(0),
+            last_step: now,
+            watched: Duration::from_secs(0),
+            break_cause: IdleCause::Inactive,
+            reset_policy: IdleConfig::default(),
+            reminders: ReminderConfig::default(),
+            last_prompt: now,
+            repeats: 0,
+            snoozed_until: None,
+            unacknowledged: false,
+            acknowledged: None,
+            adopted: Duration::from_secs(0),
+            paused: false,
+            paused_until: None,
+            frozen: None,
+            conditions: Conditions::default(),
+            schedule: None,
+            last_save: now,
+            save: None,
+            anchor: (now, wall),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_conditions(&mut self, conditions: Conditions) {
+        self.conditions = conditions;
+    }
+
+    pub fn set_reset_policy(&mut self, policy: IdleConfig) {
+        self.reset_policy = policy;
+    }
+
+    pub fn set_reminders(&mut self, reminders: ReminderConfig) {
+        self.reminders = reminders;
+    }
+
+    pub fn set_schedule(&mut self, schedule: impl FnMut(Duration) -> bool + 'static) {
+        self.schedule = Some(Box::new(schedule));
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.start) + self.before_start
+    }
+
+    /// Sets the work timer to `elapsed` as of `now`.
+    fn set_elapsed(&mut self, elapsed: Duration, now: Instant) {
+        self.start = now;
+        self.before_start = elapsed;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_away(&self) -> bool {
+        self.away.is_some()
+    }
+
+    /// Follows the wall clock to where it is at `now`, so a clock set right, or moved
+    /// by DST, places the intervals recorded from then on.
+    pub fn set_wall(&mut self, now: Instant, wall: SystemTime) {
+        self.anchor = (now, wall);
+    }
+
+    /// The work time and break flag to save, when they have changed enough to be worth it.
+    pub fn take_save(&mut self) -> Option<(Duration, bool)> {
+        self.save.take()
+    }
+
+    fn wall(&self, now: Instant) -> SystemTime {
+        let (instant, wall) = self.anchor;
+        match now.checked_duration_since(instant) {
+            Some(since) => wall + since,
+            None => wall - instant.duration_since(now)
+        }
+    }
+
+    fn set_state(&mut self, state: State) {
+        if self.state != state {
+            info!(from = ?self.state, to = ?state, "State changed");
+            self.events.push(Event::UpdateState(state));
+            self.state = state;
+        }
+    }
+
+    fn send_interval(&mut self, kind: IntervalKind, start: SystemTime, duration: Duration) {
+        let (start, duration) = if kind == IntervalKind::Work {
+            let adopted = mem::take(&mut self.adopted).min(duration);
+            (start + adopted, duration - adopted)
+        } else {
+            (start, duration)
+        };
+        let is_break = kind == IntervalKind::Break;
+        let cause = is_break.then_some(self.break_cause);
+        let quality = is_break.then(|| break_quality(duration, self.watched));
+        self.events.push(Event::Interval(Interval { kind, start, duration, cause, quality }));
+    }
+
+    /// Records the work stretch that ended at `break_start` and zeroes the timer, keeping
+    /// what was thrown away so the reset can be undone.
+    fn reset_timer(&mut self, worked: Duration, break_start: Instant, cause: IdleCause) {
+        let wall = self.wall(break_start);
+        self.send_interval(IntervalKind::Work, wall - worked, worked);
+        self.events.push(Event::NotifyReset);
+        self.start_break(worked, break_start, cause);
+    }
+
+    fn start_break(&mut self, worked: Duration, break_start: Instant, cause: IdleCause) {
+        self.events.push(Event::UndoAvailable(true));
+        self.events.push(Event::UpdateTime(Duration::from_secs(0)));
+        self.save = Some((Duration::from_secs(0), false));
+        self.snapshot = Some(ResetSnapshot {
+            start: self.start,
+            before_start: self.before_start,
+            started_at: self.wall(break_start) - worked,
+            has_break: self.has_break,
+            returned: None,
+        });
+        self.break_start = break_start;
+        self.break_cause = cause;
+        self.has_reset = true;
+    }
+
+    pub fn handle(&mut self, request: Request, now: Instant) -> Vec<Event> {
+        match request {
+            Request::UndoReset => {
+                if let Some(undone) = self.snapshot.take() {
+                    self.start = undone.start;
+                    self.before_start = undone.before_start;
+                    info!(elapsed = %format::hms(self.elapsed(now)), "Reset undone");
+                    self.has_break = undone.has_break;
+                    self.has_reset = false;
+                    self.acknowledged = None;
+                    self.events.push(Event::ResetUndone(undone.started_at));
+                    self.events.push(Event::UpdateTime(self.elapsed(now)));
+                    self.save = Some((self.elapsed(now), self.has_break));
+                }
+            },
+            Request::PauseTimer(duration) => {
+                info!("Pausing the timer{}", duration.map(|d| format!(" for {}", format::hms(d))).unwrap_or_default());
+                self.paused = true;
+                // Too long to add is as good as no end at all.
+                self.paused_until = duration.and_then(|duration| now.checked_add(duration));
+            },
+            Request::ResumeTimer => {
+                info!("Resuming the timer");
+                self.paused = false;
+                self.paused_until = None;
+            },
+            Request::BreakNow => {
+                info!(elapsed = %format::hms(self.elapsed(now)), "Break prompted on request");
+                self.prompt(now);
+            },
+            Request::AcknowledgeBreak => {
+                if self.away.is_none() && !self.has_reset {
+                    let worked = self.elapsed(now);
+                    info!(worked = %format::hms(worked), "Break acknowledged");
+                    let wall = self.wall(now);
+                    self.send_interval(IntervalKind::Acknowledgment, wall, Duration::from_secs(0));
+                    self.send_interval(IntervalKind::Work, wall - worked, worked);
+                    self.events.push(Event::BreakAcknowledged);
+                    self.start_break(worked, now, IdleCause::Inactive);
+                    self.set_state(State::OnBreak);
+                    self.set_elapsed(Duration::from_secs(0), now);
+                    self.unacknowledged = false;
+                    self.acknowledged = Some(now);
+                }
+            },
+            Request::Snooze => {
+                if self.away.is_none() && !self.has_reset {
+                    info!(minutes = self.reminders.snooze_minutes, "Break reminder snoozed");
+                    let wall = self.wall(now);
+                    self.send_interval(IntervalKind::Snooze, wall, Duration::from_secs(0));
+                    self.snoozed_until = self.reminders.snooze_minutes.checked_mul(60)
+                        .and_then(|secs| now.checked_add(Duration::from_secs(secs)));
+                }
+            },
+            Request::ResetTimer => {
+                let worked = self.elapsed(now);
+                info!(worked = %format::hms(worked), "Timer reset on request");
+                if !self.has_reset {
+                    let wall = self.wall(now);
+                    // Starting over past a reminder instead of taking the break skips it.
+                    if self.has_break {
+                        self.send_interval(IntervalKind::Skip, wall, Duration::from_secs(0));
+                    }
+                    self.send_interval(IntervalKind::Work, wall - worked, worked);
+                }
+                self.set_elapsed(Duration::from_secs(0), now);
+                self.has_break = false;
+                self.snoozed_until = None;
+                self.unacknowledged = false;
+                self.events.push(Event::UpdateTime(Duration::from_secs(0)));
+                self.save = Some((Duration::from_secs(0), false));
+            },
+            Request::ForgetData => {
+                info!("Timer started over after the data was deleted");
+                if self.snapshot.take().is_some() {
+                    self.events.push(Event::UndoAvailable(false));
+                }
+                self.set_elapsed(Duration::from_secs(0), now);
+                self.has_break = false;
+                self.snoozed_until = None;
+                self.unacknowledged = false;
+                self.events.push(Event::UpdateTime(Duration::from_secs(0)));
+            },
+            Request::Adopt(elapsed) => {
+                let own = self.elapsed(now);
+                if self.away.is_none() && self.frozen.is_none() && !self.has_reset && elapsed > own {
+                    info!(own = %format::hms(own), adopted = %format::hms(elapsed), "Work time taken over");
+                    self.adopted += elapsed - own;
+                    self.set_elapsed(elapsed, now);
+                    self.events.push(Event::UpdateTime(elapsed));
+                    self.save = Some((elapsed, self.has_break));
+                }
+            },
+            Request::Shutdown => {
+                let elapsed = self.away.map(|(_, elapsed, _)| elapsed).unwrap_or_else(|| self.elapsed(now));
+                info!(elapsed = %format::hms(elapsed), has_break = self.has_break, "Shutting down");
+                self.save = Some((elapsed, self.has_break));
+            },
+            Request::Suspend | Request::Lock | Request::Disconnect => {
+                self.away_reasons.set(request, true);
+                if self.away.is_none() {
+                    // Locking or sleeping after a while without input, as a screen saver
+                    // does, leaves a break that began with the inactivity.
+                    let cause = if self.idle > IDLE_PAUSE_TIME { IdleCause::Inactive } else { IdleCause::of(request) };
+                    info!(?request, ?cause, elapsed = %format::hms(self.elapsed(now)), "Away");
+                    self.away = Some((now, self.elapsed(now), cause));
+                    self.save = Some((self.elapsed(now), self.has_break));
+                }
+                match request {
+                    Request::Lock => self.set_state(State::OnBreak),
+                    Request::Disconnect => self.set_state(State::Paused),
+                    _ => {}
+                }
+            },
+            Request::Resume | Request::Unlock | Request::Connect => {
+                // Time asleep, locked or disconnected is break time: a long one resets,
+                // a short one isn't counted as work.
+                self.away_reasons.set(request, false);
+                if self.away_reasons.any() {
+                    info!(?request, away_reasons = ?self.away_reasons, "Still away");
+                } else if let Some((left, elapsed, cause)) = self.away.take() {
+                    let gone = now.saturating_duration_since(left);
+                    info!(?request, ?cause, gone = %format::hms(gone), "Back");
+                    self.idle = Duration::from_secs(0);
+                    if self.has_reset {
+                        self.set_elapsed(Duration::from_secs(0), now);
+                    } else if gone > self.reset_policy.reset_after(cause) && !self.unacknowledged {
+                        self.reset_timer(elapsed, left, cause);
+                        self.set_state(State::OnBreak);
+                        self.set_elapsed(Duration::from_secs(0), now);
+                    } else {
+                        self.set_elapsed(elapsed, now);
+                        self.events.push(Event::UpdateTime(elapsed));
+                    }
+                }
+            }
+        }
+        mem::take(&mut self.events)
+    }
+
+    fn prompt(&mut self, now: Instant) {
+        self.events.push(Event::NotifyBreak);
+        self.has_break = true;
+        self.last_prompt = now;
+        self.repeats = 0;
+        self.unacknowledged = self.reset_policy.require_acknowledgment;
+    }
+
+    fn repeat_due(&self, now: Instant) -> bool {
+        let interval = Duration::from_secs(self.reminders.repeat_minutes.saturating_mul(60));
+        !interval.is_zero()
+            && self.repeats < self.reminders.max_repeats
+            && now.saturating_duration_since(self.last_prompt) >= interval
+    }
+
+    fn watch(&mut self, since_step: Duration) {
+        if self.conditions.watching {
+            self.watched += since_step;
+        }
+    }
+
+    /// Moves the timer on to `now`, with the user idle for `idle`.
+    pub fn step(&mut self, idle: Duration, now: Instant) -> Vec<Event> {
+        let since_step = now.saturating_duration_since(self.last_step);
+        self.last_step = now;
+        if self.away.is_some() {
+            return mem::take(&mut self.events);
+        }
+        self.idle = idle;
+        if self.paused_until.is_some_and(|until| now >= until) {
+            info!("Pause over");
+            self.paused = false;
+            self.paused_until = None;
+        }
+        let pause = self.paused || self.conditions.pause;
+        self.frozen = if pause { Some(self.frozen.unwrap_or_else(|| self.elapsed(now))) } else { None };
+        if let Some(elapsed) = self.frozen {
+            self.set_elapsed(elapsed, now);
+            self.set_state(State::Paused);
+            return mem::take(&mut self.events);
+        }
+        if self.snapshot.as_ref().and_then(|snapshot| snapshot.returned).is_some_and(|t| now.saturating_duration_since(t) >= UNDO_TIME) {
+            self.snapshot = None;
+            info!("Reset can no longer be undone");
+            self.events.push(Event::UndoAvailable(false));
+        }
+        if idle > self.reset_policy.reset_after(IdleCause::Inactive) && !self.unacknowledged {
+            if !self.has_reset {
+                let worked = self.elapsed(now).saturating_sub(idle);
+                info!(idle = %format::hms(idle), worked = %format::hms(worked), "Idle, resetting the timer");
+                let break_start = now.checked_sub(idle).unwrap_or(now);
+                self.reset_timer(worked, break_start, IdleCause::Inactive);
+            }
+            self.set_state(State::OnBreak);
+            self.set_elapsed(Duration::from_secs(0), now);
+            self.watch(since_step);
+        } else if self.has_reset && self.acknowledged.is_some_and(|at| now.saturating_duration_since(at) < idle + IDLE_PAUSE_TIME) {
+            // No input since just after acknowledging, on the way away from the screen.
+            self.set_elapsed(Duration::from_secs(0), now);
+            self.watch(since_step);
+        } else if idle > IDLE_PAUSE_TIME {
+            if self.state == State::Working {
+                info!(idle = %format::hms(idle), "Idle");
+            }
+            self.set_state(State::Paused);
+            if self.unacknowledged {
+                // Without an acknowledgment this is no break, and no work either.
+                self.start += since_step;
+            }
+            self.watch(since_step);
+        } else {
+            self.set_state(State::Working);
+            if self.has_reset {
+                let rested = now.saturating_duration_since(self.break_start);
+                info!(rested = %format::hms(rested), "Back from a break");
+                let wall = self.wall(self.break_start);
+                self.send_interval(IntervalKind::Break, wall, rested);
+                self.set_elapsed(Duration::from_secs(0), now);
+                self.has_reset = false;
+                self.has_break = false;
+                self.snoozed_until = None;
+                self.acknowledged = None;
+                if let Some(snapshot) = self.snapshot.as_mut() {
+                    snapshot.returned = Some(now);
+                }
+            }
+            self.watched = Duration::from_secs(0);
+            let elapsed = self.elapsed(now);
+            self.events.push(Event::UpdateTime(elapsed));
+            if self.snoozed_until.is_some_and(|until| now >= until) && !self.conditions.defer {
+                self.snoozed_until = None;
+                if self.has_break {
+                    info!(elapsed = %format::hms(elapsed), "Break reminded again after a snooze");
+                    self.prompt(now);
+                }
+            }
+            let snoozed = self.snoozed_until.is_some();
+            if !self.has_break && !self.conditions.defer && !snoozed {
+                let due = match self.schedule.as_mut() {
+                    Some(schedule) => schedule(BREAK_TIME.saturating_sub(elapsed)),
+                    None => elapsed >= BREAK_TIME
+                };
+                if due {
+                    info!(elapsed = %format::hms(elapsed), "Break prompted");
+                    self.prompt(now);
+                }
+            } else if self.has_break && !self.conditions.defer && !snoozed && self.repeat_due(now) {
+                self.repeats += 1;
+                self.last_prompt = now;
+                info!(elapsed = %format::hms(elapsed), repeats = self.repeats, "Break reminded again");
+                self.events.push(Event::RemindAgain(self.repeats));
+            }
+            if now.saturating_duration_since(self.last_save) >= SAVE_INTERVAL {
+                self.save = Some((elapsed, self.has_break));
+                self.last_save = now;
+            }
+        }
+        mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, SimulatedClock};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    struct Test {
+        machine: BreakStateMachine,
+        now: Instant,
+        wall: SystemTime,
+    }
+
+    impl Test {
+        fn new() -> Test {
+            Test::resumed(Duration::from_secs(0), false)
+        }
+
+        fn resumed(elapsed: Duration, has_break: bool) -> Test {
+            // Well clear of the start of the monotonic clock, so it can be wound back.
+            let now = Instant::now() + Duration::from_secs(24 * 60 * 60);
+            let wall = SystemTime::now();
+            Test {
+                machine: BreakStateMachine::new(elapsed, has_break, now, wall),
+                now,
+                wall,
+            }
+        }
+
+        fn wall(&self) -> SystemTime {
+            self.wall + self.now.duration_since(self.machine.anchor.0)
+        }
+
+        fn elapsed(&self) -> Duration {
+            self.machine.elapsed(self.now)
+        }
+
+        /// Steps once a second for `duration` with the user idle for `idle` and more.
+        fn run(&mut self, duration: Duration, idle: Duration) -> Vec<Event> {
+            let mut events = Vec::new();
+            for second in 1..=duration.as_secs() {
+                self.now += SECOND;
+                let idle = if idle.is_zero() { idle } else { idle + Duration::from_secs(second) };
+                events.extend(self.machine.step(idle, self.now));
+            }
+            events
+        }
+
+        fn work(&mut self, duration: Duration) -> Vec<Event> {
+            self.run(duration, Duration::from_secs(0))
+        }
+
+        /// Idles for `duration`, stepping as if the idle started right away.
+        fn idle(&mut self, duration: Duration) -> Vec<Event> {
+            let mut events = Vec::new();
+            for second in 1..=duration.as_secs() {
+                self.now += SECOND;
+                events.extend(self.machine.step(Duration::from_secs(second), self.now));
+            }
+            events
+        }
+
+        fn away(&mut self, leave: Request, back: Request, duration: Duration) -> Vec<Event> {
+            let mut events = self.machine.handle(leave, self.now);
+            self.now += duration;
+            events.extend(self.machine.step(Duration::from_secs(0), self.now));
+            events.extend(self.machine.handle(back, self.now));
+            events
+        }
+
+        fn handle(&mut self, request: Request) -> Vec<Event> {
+            self.machine.handle(request, self.now)
+        }
+    }
+
+    fn count(events: &[Event], matches: impl Fn(&Event) -> bool) -> usize {
+        events.iter().filter(|event| matches(event)).count()
+    }
+
+    fn prompts(events: &[Event]) -> usize {
+        count(events, |event| *event == Event::NotifyBreak)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn prompts_once_when_the_break_is_due() {
+        let mut test = Test::new();
+        assert_eq!(prompts(&test.work(BREAK_TIME - SECOND)), 0);
+        assert_eq!(test.work(SECOND), vec![Event::UpdateTime(BREAK_TIME), Event::NotifyBreak]);
+        assert_eq!(prompts(&test.work(minutes(30))), 0);
+        assert!(test.machine.has_break);
+        assert_eq!(test.machine.state, State::Working);
+    }
+
+    #[test]
+    fn repeats_the_reminder_up_to_the_limit() {
+        let mut test = Test::new();
+        test.machine.set_reminders(ReminderConfig { repeat_minutes: 5, max_repeats: 2, ..ReminderConfig::default() });
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 1);
+        let events = test.work(minutes(30));
+        assert_eq!(prompts(&events), 0);
+        assert_eq!(count(&events, |event| matches!(event, Event::RemindAgain(_))), 2);
+        assert!(events.contains(&Event::RemindAgain(2)));
+    }
+
+    #[test]
+    fn snoozing_puts_the_reminder_off() {
+        let mut test = Test::new();
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 1);
+        test.handle(Request::Snooze);
+        assert_eq!(prompts(&test.work(minutes(10) - SECOND)), 0);
+        assert_eq!(prompts(&test.work(SECOND)), 1);
+        // Snoozed before it is due, the reminder waits for the snooze too.
+        let mut test = Test::resumed(BREAK_TIME - minutes(1), false);
+        test.handle(Request::Snooze);
+        assert_eq!(prompts(&test.work(minutes(9))), 0);
+        assert_eq!(prompts(&test.work(minutes(1))), 1);
+        // A snooze too long for the clock doesn't take the timer down.
+        test.machine.set_reminders(ReminderConfig { snooze_minutes: u64::MAX, ..ReminderConfig::default() });
+        test.handle(Request::Snooze);
+    }
+
+    #[test]
+    fn resumes_saved_work_time() {
+        let mut test = Test::resumed(BREAK_TIME - minutes(1), false);
+        assert_eq!(prompts(&test.work(minutes(1))), 1);
+        let mut test = Test::resumed(BREAK_TIME, true);
+        assert_eq!(prompts(&test.work(minutes(1))), 0);
+    }
+
+    #[test]
+    fn short_idle_pauses_without_resetting() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        let events = test.idle(IDLE_PAUSE_TIME + SECOND);
+        assert_eq!(events.last(), Some(&Event::UpdateState(State::Paused)));
+        assert_eq!(test.machine.state, State::Paused);
+        let events = test.work(SECOND);
+        assert_eq!(events[0], Event::UpdateState(State::Working));
+        // Idle time short of a reset still counts as work.
+        assert_eq!(test.elapsed(), minutes(10) + IDLE_PAUSE_TIME + 2 * SECOND);
+    }
+
+    #[test]
+    fn long_idle_resets_and_records_the_break() {
+        let mut test = Test::new();
+        test.work(minutes(20));
+        let work_end = test.wall();
+        let events = test.idle(IDLE_RESET_TIME + SECOND);
+        let reset = events.iter().position(|event| *event == Event::NotifyReset).unwrap();
+        assert_eq!(events[reset - 1], Event::Interval(Interval {
+            kind: IntervalKind::Work,
+            start: work_end - minutes(20),
+            duration: minutes(20),
+            cause: None,
+            quality: None,
+        }));
+        assert_eq!(events[reset + 1..reset + 4], [
+            Event::UndoAvailable(true),
+            Event::UpdateTime(Duration::from_secs(0)),
+            Event::UpdateState(State::OnBreak),
+        ]);
+        assert_eq!(test.machine.take_save(), Some((Duration::from_secs(0), false)));
+        // Staying away doesn't reset again.
+        assert_eq!(count(&test.run(minutes(5), IDLE_RESET_TIME), |event| *event == Event::NotifyReset), 0);
+        let events = test.work(SECOND);
+        assert_eq!(events[0], Event::UpdateState(State::Working));
+        assert_eq!(events[1], Event::Interval(Interval {
+            kind: IntervalKind::Break,
+            start: work_end,
+            duration: IDLE_RESET_TIME + minutes(5) + 2 * SECOND,
+            cause: Some(IdleCause::Inactive),
+            quality: Some(100),
+        }));
+        assert_eq!(test.elapsed(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn breaks_wait_for_acknowledgment() {
+        let mut test = Test::new();
+        test.machine.set_reset_policy(IdleConfig { require_acknowledgment: true, ..IdleConfig::default() });
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 1);
+        // Being away without acknowledging is no break, and no work either.
+        let events = test.idle(IDLE_RESET_TIME * 2);
+        assert_eq!(count(&events, |event| *event == Event::NotifyReset), 0);
+        assert_eq!(test.machine.state, State::Paused);
+        assert_eq!(test.elapsed(), BREAK_TIME + IDLE_PAUSE_TIME);
+        test.work(SECOND);
+        let acknowledged = test.wall();
+        let events = test.handle(Request::AcknowledgeBreak);
+        assert_eq!(events[0], Event::Interval(Interval {
+            kind: IntervalKind::Acknowledgment,
+            start: acknowledged,
+            duration: Duration::from_secs(0),
+            cause: None,
+            quality: None,
+        }));
+        assert!(events.contains(&Event::BreakAcknowledged));
+        assert_eq!(test.machine.state, State::OnBreak);
+        // Input on the way away from the screen doesn't end the break.
+        test.work(SECOND * 10);
+        test.idle(minutes(3));
+        assert_eq!(test.machine.state, State::OnBreak);
+        let events = test.work(SECOND);
+        assert_eq!(events[1], Event::Interval(Interval {
+            kind: IntervalKind::Break,
+            start: acknowledged,
+            duration: minutes(3) + SECOND * 11,
+            cause: Some(IdleCause::Inactive),
+            quality: Some(64),
+        }));
+        assert_eq!(test.elapsed(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn reset_clears_the_prompt() {
+        let mut test = Test::new();
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 1);
+        test.idle(IDLE_RESET_TIME + SECOND);
+        test.work(SECOND);
+        assert!(!test.machine.has_break);
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 1);
+    }
+
+    #[test]
+    fn undoes_a_reset() {
+        let mut test = Test::new();
+        test.work(BREAK_TIME);
+        test.idle(IDLE_RESET_TIME + SECOND);
+        test.work(minutes(1));
+        let events = test.handle(Request::UndoReset);
+        assert!(matches!(events[0], Event::ResetUndone(_)));
+        assert_eq!(test.elapsed(), BREAK_TIME + IDLE_RESET_TIME + SECOND + minutes(1));
+        assert!(test.machine.has_break);
+        assert!(test.machine.take_save().is_some());
+        // There is only one reset to undo.
+        assert!(test.handle(Request::UndoReset).is_empty());
+    }
+
+    #[test]
+    fn undo_expires() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.idle(IDLE_RESET_TIME + SECOND);
+        assert!(!test.work(UNDO_TIME).contains(&Event::UndoAvailable(false)));
+        assert!(test.work(SECOND).contains(&Event::UndoAvailable(false)));
+        assert!(test.handle(Request::UndoReset).is_empty());
+        assert!(test.elapsed() < minutes(10));
+    }
+
+    #[test]
+    fn pause_request_freezes_the_timer() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.handle(Request::PauseTimer(None));
+        assert_eq!(test.work(minutes(60)), vec![Event::UpdateState(State::Paused)]);
+        // The timer stops at the first step after the request.
+        assert_eq!(test.elapsed(), minutes(10) + SECOND);
+        test.handle(Request::ResumeTimer);
+        assert_eq!(test.work(SECOND)[0], Event::UpdateState(State::Working));
+        assert_eq!(test.elapsed(), minutes(10) + 2 * SECOND);
+    }
+
+    #[test]
+    fn timed_pause_ends_by_itself() {
+        let mut test = Test::new();
+        test.handle(Request::PauseTimer(Some(minutes(5))));
+        test.work(minutes(5) - SECOND);
+        assert_eq!(test.machine.state, State::Paused);
+        test.work(SECOND);
+        assert_eq!(test.machine.state, State::Working);
+        assert_eq!(test.elapsed(), 2 * SECOND);
+        // Past the end of the clock, it doesn't end.
+        test.handle(Request::PauseTimer(Some(Duration::MAX)));
+        test.work(minutes(5));
+        assert_eq!(test.machine.state, State::Paused);
+    }
+
+    #[test]
+    fn pause_condition_freezes_the_timer() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.machine.set_conditions(Conditions { pause: true, ..Conditions::default() });
+        test.idle(IDLE_RESET_TIME * 2);
+        assert_eq!(test.machine.state, State::Paused);
+        assert_eq!(test.elapsed(), minutes(10) + SECOND);
+        test.machine.set_conditions(Conditions::default());
+        test.work(SECOND);
+        assert_eq!(test.elapsed(), minutes(10) + 2 * SECOND);
+    }
+
+    #[test]
+    fn defer_holds_the_prompt_back() {
+        let mut test = Test::new();
+        test.machine.set_conditions(Conditions { defer: true, ..Conditions::default() });
+        assert_eq!(prompts(&test.work(BREAK_TIME + minutes(5))), 0);
+        test.machine.set_conditions(Conditions::default());
+        assert_eq!(prompts(&test.work(SECOND)), 1);
+    }
+
+    #[test]
+    fn schedule_decides_when_to_prompt() {
+        let mut test = Test::new();
+        // Prompt ten minutes early, as if a meeting were coming up.
+        test.machine.set_schedule(|remaining| remaining <= minutes(10));
+        assert_eq!(prompts(&test.work(BREAK_TIME - minutes(10) - SECOND)), 0);
+        assert_eq!(prompts(&test.work(SECOND)), 1);
+    }
+
+    #[test]
+    fn break_now_prompts_once() {
+        let mut test = Test::new();
+        test.work(minutes(5));
+        assert_eq!(test.handle(Request::BreakNow), vec![Event::NotifyBreak]);
+        assert_eq!(prompts(&test.work(BREAK_TIME)), 0);
+    }
+
+    #[test]
+    fn reset_request_records_the_work() {
+        let mut test = Test::new();
+        test.work(BREAK_TIME);
+        let events = test.handle(Request::ResetTimer);
+        assert_eq!(events, vec![
+            Event::Interval(Interval { kind: IntervalKind::Skip, start: test.wall(), duration: Duration::from_secs(0), cause: None, quality: None }),
+            Event::Interval(Interval { kind: IntervalKind::Work, start: test.wall() - BREAK_TIME, duration: BREAK_TIME, cause: None, quality: None }),
+            Event::UpdateTime(Duration::from_secs(0)),
+        ]);
+        assert!(!test.machine.has_break);
+        assert_eq!(test.machine.take_save(), Some((Duration::from_secs(0), false)));
+    }
+
+    #[test]
+    fn only_resets_past_a_reminder_skip_the_break() {
+        let mut test = Test::new();
+        test.work(BREAK_TIME - SECOND);
+        let skips = |events: &[Event]| count(events, |event| matches!(event, Event::Interval(Interval { kind: IntervalKind::Skip, .. })));
+        assert_eq!(skips(&test.handle(Request::ResetTimer)), 0);
+        test.work(BREAK_TIME);
+        test.idle(IDLE_RESET_TIME + SECOND);
+        // The break was taken.
+        assert_eq!(skips(&test.handle(Request::ResetTimer)), 0);
+    }
+
+    #[test]
+    fn adopted_work_time_is_not_recorded_again() {
+        let mut test = Test::new();
+        test.work(minutes(5));
+        assert_eq!(test.handle(Request::Adopt(minutes(30))), vec![Event::UpdateTime(minutes(30))]);
+        // A shorter timer elsewhere is no reason to lose any work time here.
+        assert!(test.handle(Request::Adopt(minutes(20))).is_empty());
+        test.work(minutes(10));
+        let events = test.handle(Request::ResetTimer);
+        assert_eq!(events[0], Event::Interval(Interval {
+            kind: IntervalKind::Work, start: test.wall() - minutes(15), duration: minutes(15), cause: None, quality: None,
+        }));
+    }
+
+    #[test]
+    fn keeps_more_work_time_than_the_clock_goes_back() {
+        // Like just after booting, when the monotonic clock can't go back as far.
+        let now = Instant::now();
+        let long = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+        let mut machine = BreakStateMachine::new(long, false, now, SystemTime::now());
+        assert_eq!(machine.elapsed(now + SECOND), long + SECOND);
+        machine.handle(Request::Adopt(2 * long), now + SECOND);
+        assert_eq!(machine.elapsed(now + 2 * SECOND), 2 * long + SECOND);
+    }
+
+    #[test]
+    fn short_lock_keeps_the_work_time() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        let events = test.away(Request::Lock, Request::Unlock, minutes(2));
+        assert_eq!(events, vec![Event::UpdateState(State::OnBreak), Event::UpdateTime(minutes(10))]);
+        assert_eq!(test.elapsed(), minutes(10));
+        assert_eq!(test.work(SECOND)[0], Event::UpdateState(State::Working));
+    }
+
+    #[test]
+    fn long_lock_resets() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        let locked_at = test.wall();
+        let events = test.away(Request::Lock, Request::Unlock, IDLE_RESET_TIME + SECOND);
+        assert_eq!(events[1], Event::Interval(Interval {
+            kind: IntervalKind::Work,
+            start: locked_at - minutes(10),
+            duration: minutes(10),
+            cause: None,
+            quality: None,
+        }));
+        assert_eq!(count(&events, |event| *event == Event::NotifyReset), 1);
+        let events = test.work(SECOND);
+        assert_eq!(events[1], Event::Interval(Interval {
+            kind: IntervalKind::Break,
+            start: locked_at,
+            duration: IDLE_RESET_TIME + 2 * SECOND,
+            cause: Some(IdleCause::Locked),
+            quality: Some(100),
+        }));
+    }
+
+    fn break_cause(events: &[Event]) -> Option<IdleCause> {
+        events.iter().find_map(|event| match event {
+            Event::Interval(Interval { kind: IntervalKind::Break, cause, .. }) => *cause,
+            _ => None
+        })
+    }
+
+    #[test]
+    fn lock_after_inactivity_is_inactive() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.idle(IDLE_PAUSE_TIME + SECOND);
+        test.away(Request::Lock, Request::Unlock, IDLE_RESET_TIME + SECOND);
+        assert_eq!(break_cause(&test.work(SECOND)), Some(IdleCause::Inactive));
+        test.work(minutes(10));
+        test.away(Request::Suspend, Request::Resume, IDLE_RESET_TIME + SECOND);
+        assert_eq!(break_cause(&test.work(SECOND)), Some(IdleCause::Asleep));
+    }
+
+    #[test]
+    fn each_cause_has_its_own_reset_time() {
+        let mut test = Test::new();
+        test.machine.set_reset_policy(IdleConfig { asleep_reset_minutes: 0, locked_reset_minutes: 60, ..IdleConfig::default() });
+        test.work(minutes(10));
+        let events = test.away(Request::Lock, Request::Unlock, minutes(30));
+        assert_eq!(count(&events, |event| *event == Event::NotifyReset), 0);
+        let events = test.away(Request::Suspend, Request::Resume, minutes(1));
+        assert_eq!(count(&events, |event| *event == Event::NotifyReset), 1);
+    }
+
+    #[test]
+    fn nothing_is_counted_while_away() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.handle(Request::Suspend);
+        assert!(test.machine.is_away());
+        assert!(test.work(BREAK_TIME).is_empty());
+        assert_eq!(test.machine.take_save(), Some((minutes(10), false)));
+    }
+
+    #[test]
+    fn stays_away_until_every_reason_is_gone() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.handle(Request::Lock);
+        test.handle(Request::Disconnect);
+        test.now += minutes(1);
+        assert!(test.handle(Request::Connect).is_empty());
+        assert!(test.machine.is_away());
+        assert_eq!(test.handle(Request::Unlock), vec![Event::UpdateTime(minutes(10))]);
+        assert!(!test.machine.is_away());
+    }
+
+    #[test]
+    fn saves_periodically_while_working() {
+        let mut test = Test::new();
+        test.work(SAVE_INTERVAL - SECOND);
+        assert_eq!(test.machine.take_save(), None);
+        test.work(SECOND);
+        assert_eq!(test.machine.take_save(), Some((SAVE_INTERVAL, false)));
+    }
+
+    #[test]
+    fn shutdown_saves_the_work_time() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.handle(Request::Shutdown);
+        assert_eq!(test.machine.take_save(), Some((minutes(10), false)));
+        test.handle(Request::Lock);
+        test.machine.take_save();
+        test.now += minutes(2);
+        test.handle(Request::Shutdown);
+        assert_eq!(test.machine.take_save(), Some((minutes(10), false)));
+    }
+
+    #[test]
+    fn watching_the_screen_lowers_break_quality() {
+        let mut test = Test::new();
+        test.work(minutes(10));
+        test.idle(minutes(4));
+        test.machine.set_conditions(Conditions { watching: true, ..Conditions::default() });
+        test.run(minutes(4), minutes(4));
+        test.machine.set_conditions(Conditions::default());
+        let quality = test.work(SECOND).iter().find_map(|event| match event {
+            Event::Interval(Interval { kind: IntervalKind::Break, quality, .. }) => *quality,
+            _ => None
+        });
+        assert_eq!(quality, Some(50));
+        assert_eq!(break_quality(minutes(1), Duration::from_secs(0)), 20);
+    }
+
+    /// Steps once a second for `duration` as the monitor does, with the user idle all along
+    /// or not at all.
+    fn tick(clock: &SimulatedClock, machine: &mut BreakStateMachine, duration: Duration, idle: bool) -> Vec<Event> {
+        let mut events = Vec::new();
+        for second in 1..=duration.as_secs() {
+            clock.sleep(SECOND);
+            machine.set_wall(clock.now(), clock.wall());
+            let idle = if idle { Duration::from_secs(second) } else { Duration::from_secs(0) };
+            events.extend(machine.step(idle, clock.now()));
+        }
+        events
+    }
+
+    #[test]
+    fn clock_changes_leave_the_timer_alone() {
+        let clock = SimulatedClock::new(SystemTime::now());
+        let mut machine = BreakStateMachine::new(Duration::from_secs(0), false, clock.now(), clock.wall());
+        tick(&clock, &mut machine, minutes(10), false);
+        clock.set_wall(clock.wall() - minutes(60));
+        assert_eq!(prompts(&tick(&clock, &mut machine, BREAK_TIME - minutes(10) - SECOND, false)), 0);
+        clock.set_wall(clock.wall() + minutes(120));
+        assert_eq!(prompts(&tick(&clock, &mut machine, SECOND, false)), 1);
+        assert_eq!(machine.elapsed(clock.now()), BREAK_TIME);
+    }
+
+    #[test]
+    fn intervals_are_placed_by_the_clock_as_set() {
+        let clock = SimulatedClock::new(SystemTime::now());
+        let mut machine = BreakStateMachine::new(Duration::from_secs(0), false, clock.now(), clock.wall());
+        tick(&clock, &mut machine, minutes(20), false);
+        // Set right an hour ahead, as by NTP after a wrong start.
+        clock.set_wall(clock.wall() + minutes(60));
+        let work_end = clock.wall();
+        let events = tick(&clock, &mut machine, IDLE_RESET_TIME + SECOND, true);
+        assert!(events.contains(&Event::Interval(Interval {
+            kind: IntervalKind::Work,
+            start: work_end - minutes(20),
+            duration: minutes(20),
+            cause: None,
+            quality: None,
+        })));
+        clock.set_wall(clock.wall() - minutes(30));
+        let events = tick(&clock, &mut machine, SECOND, false);
+        assert_eq!(events[1], Event::Interval(Interval {
+            kind: IntervalKind::Break,
+            start: work_end - minutes(30),
+            duration: IDLE_RESET_TIME + 2 * SECOND,
+            cause: Some(IdleCause::Inactive),
+            quality: Some(100),
+        }));
+    }
+}
@@ -1,73 +1,564 @@
 #![windows_subsystem = "windows"]
 
+use std::env;
+use std::process;
+use std::sync::Arc;
 use std::thread;
 use std::time::{
     Duration,
-    Instant
+    Instant,
+    SystemTime
 };
 
+mod achievements;
+#[cfg(windows)]
+mod activitywatch;
+#[cfg(windows)]
+mod api;
+mod auth;
+mod backup;
+mod break_audio;
+#[cfg(windows)]
+mod calendar;
+mod civil;
+mod cli;
+mod clock;
+#[cfg(windows)]
+mod commands;
+mod config;
+mod content;
+#[cfg(windows)]
+mod crash;
+mod daily;
+mod email;
+mod devices;
+mod handoff;
+mod export;
+mod format;
+mod history;
+mod i18n;
+mod idle;
+#[cfg(windows)]
+mod ipc;
+#[cfg(windows)]
+mod logger;
+mod machine;
+#[cfg(windows)]
+mod metrics;
+#[cfg(windows)]
+mod mqtt;
+mod notifications;
+mod persist;
+#[cfg(windows)]
+mod presence;
+mod privacy;
+mod report;
+mod schema;
+mod settings;
+mod simulate;
+mod status;
+mod store;
+#[cfg(windows)]
+mod stream;
+mod sync;
+#[cfg(windows)]
+mod update;
+#[cfg(windows)]
+mod usage;
+#[cfg(windows)]
+mod webhook;
 #[cfg(windows)]
 mod windows;
 #[cfg(windows)]
-use windows::{get_idle_time, start};
+use windows::{
+    acquire_instance,
+    activate_existing,
+    attach_console,
+    AudioMonitor,
+    foreground_process,
+    foreground_window,
+    is_capturing,
+    is_fullscreen,
+    is_fullscreen_game,
+    is_game_bar,
+    is_low_power,
+    quiet_breaks,
+    record_input_rate,
+    restore_sound,
+    repair_autostart,
+    start,
+    user_language,
+    write_credential
+};
+#[cfg(windows)]
+use calendar::Calendar;
+#[cfg(windows)]
+use idle::InputIdle;
+
+use cli::Command;
+use config::{BreakAudio, Config, DevicesMode, GamingMode};
+use daily::{DailyTime, DailyTracker, DailyWarning};
+use devices::{Devices, SharedIdle};
+use history::Interval;
+use clock::{Clock, SystemClock};
+use idle::IdleSource;
+use machine::{BreakStateMachine, Conditions, Surroundings};
+use persist::TimerState;
+use privacy::Outputs;
 
 use crossbeam::channel::{
     unbounded,
+    Receiver,
     Sender
 };
+use tracing::{debug, error, info, trace, warn};
+use tokio::runtime::{Builder, Handle};
+use tokio::sync::mpsc;
+use tokio::task;
 
+/// The release and the commit it was built from.
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("PAUZA_COMMIT"), ")");
 const IDLE_PAUSE_TIME: Duration = Duration::from_secs(60);
 const IDLE_RESET_TIME: Duration = Duration::from_secs(300);
 const BREAK_TIME: Duration = Duration::from_secs(2700);
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Polling less often on battery saves wakeups, at the cost of a less lively display.
+const LOW_POWER_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Polling while the user is away from the keyboard, when nothing changes until they're
+/// back. The idle time says when the break began, so only noticing the return is later.
+const AWAY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const CALL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const UNDO_TIME: Duration = Duration::from_secs(180);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Working,
+    Paused,
+    OnBreak
+}
 
-#[derive(Debug)]
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Working => "working",
+            State::Paused => "paused",
+            State::OnBreak => "on_break"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Event {
     UpdateTime(Duration),
+    UpdateState(State),
     NotifyBreak,
-    NotifyReset
+    /// The break still isn't taken: the reminder is repeated, for this many times now.
+    RemindAgain(u32),
+    NotifyReset,
+    /// The user said they are starting the break they were reminded of.
+    BreakAcknowledged,
+    Interval(Interval),
+    LowPower(bool),
+    /// Reminders are held back, because of a fullscreen app or the named foreground process.
+    Quiet(Option<String>),
+    UndoAvailable(bool),
+    /// The reset was undone; the restored work stretch started at this time.
+    ResetUndone(SystemTime),
+    /// Reading the idle time fails, so it is guessed from cursor movement instead.
+    Degraded(bool),
+    /// The day's active time nears or passed the daily limit.
+    DailyLimit(DailyWarning)
+}
+
+/// Sent from the GUI back to the monitor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Request {
+    UndoReset,
+    /// The system is about to sleep.
+    Suspend,
+    Resume,
+    Lock,
+    Unlock,
+    /// The user disconnected from this session, e.g. closed a Remote Desktop client.
+    Disconnect,
+    Connect,
+    /// Stops the work timer, for good or for a while.
+    PauseTimer(Option<Duration>),
+    ResumeTimer,
+    BreakNow,
+    /// The user is starting their break, as `idle.require_acknowledgment` asks.
+    AcknowledgeBreak,
+    /// Reminds of the break again after `reminders.snooze_minutes`.
+    Snooze,
+    ResetTimer,
+    /// Takes over the work time of another machine the user moved from.
+    Adopt(Duration),
+    /// The user deleted their data: starts the timer and the day's count over, recording nothing.
+    ForgetData,
+    /// Pauza is exiting: save the timer and stop.
+    Shutdown
 }
 
-fn monitor_idle_time(s: Sender<Event>) {
-    let mut start = Instant::now();
-    let mut has_reset: bool = false;
-    let mut has_break: bool = false;
-    s.send(Event::UpdateTime(start.elapsed())).unwrap();
+fn save_timer(elapsed: Duration, has_break: bool) {
+    if let Err(e) = TimerState::new(elapsed, has_break).save() {
+        error!("{}", e);
+    }
+}
+
+/// Runs `f` on a thread of its own, for loops that last as long as pauza does and would
+/// otherwise hold on to the runtime's blocking threads. The runtime stays at hand for the
+/// tasks they start.
+fn spawn_thread<F: FnOnce() + Send + 'static>(f: F) {
+    let runtime = Handle::current();
+    thread::spawn(move || {
+        let _context = runtime.enter();
+        f()
+    });
+}
+
+fn save_daily(time: &DailyTime) {
+    if let Err(e) = time.save() {
+        error!("{}", e);
+    }
+}
+
+/// How long to sleep before polling again, given the idle time at the last poll.
+fn poll_interval(low_power: bool, idle: Duration) -> Duration {
+    if idle > IDLE_PAUSE_TIME {
+        AWAY_POLL_INTERVAL
+    } else if low_power {
+        LOW_POWER_POLL_INTERVAL
+    } else {
+        POLL_INTERVAL
+    }
+}
+
+fn monitor_idle_time(s: mpsc::UnboundedSender<Event>, requests: Receiver<Request>, config: Config, clock: impl Clock, mut idle: impl IdleSource) {
+    let _span = tracing::info_span!("monitor").entered();
+    let (elapsed, has_break) = TimerState::load()
+        .and_then(|saved| saved.resume())
+        .unwrap_or((Duration::from_secs(0), false));
+    info!(elapsed = %format::hms(elapsed), has_break, "Starting the monitor");
+    let mut machine = BreakStateMachine::new(elapsed, has_break, clock.now(), clock.wall());
+    machine.set_reset_policy(config.idle.clone());
+    machine.set_reminders(config.reminders.clone());
+    let mut daily = DailyTracker::new(config.daily_limit.clone(), DailyTime::load());
+    let mut sound = AudioMonitor::new();
+    let mut low_power = is_low_power();
+    let mut last_power_check = clock.now();
+    let mut quiet: Option<String> = None;
+    let mut degraded = false;
+    let mut last_idle = Duration::from_secs(0);
+    let mut in_call = false;
+    let mut call_ended: Option<Instant> = None;
+    let mut last_call_check = clock.now();
+    let early = Duration::from_secs(config.meetings.early_minutes * 60);
+    if let Some(url) = config.meetings.calendar_url.clone().filter(|url| !url.is_empty()) {
+        let calendar = Calendar::watch(url, Duration::from_secs(config.meetings.calendar_refresh_minutes.max(1).saturating_mul(60)));
+        machine.set_schedule(move |remaining| {
+            let now = SystemTime::now();
+            now >= calendar.plan(now + remaining, now, IDLE_RESET_TIME, early)
+        });
+    }
+    s.send(Event::UpdateTime(elapsed)).unwrap();
+    s.send(Event::LowPower(low_power)).unwrap();
     loop {
-        thread::sleep(Duration::from_secs(1));
-        match get_idle_time() {
-            Ok(idle_time) if idle_time > IDLE_RESET_TIME => {
-                if !has_reset {
-                    s.send(Event::NotifyReset).unwrap();
-                    s.send(Event::UpdateTime(Duration::from_secs(0))).unwrap();
-                    has_reset = true;
+        // Requests wake the monitor straight away, so shutting down or unlocking isn't
+        // held up by a long poll.
+        let woken = clock.wait(&requests, poll_interval(low_power, last_idle));
+        machine.set_wall(clock.now(), clock.wall());
+        if clock.now() - last_power_check >= POWER_CHECK_INTERVAL {
+            last_power_check = clock.now();
+            if is_low_power() != low_power {
+                low_power = !low_power;
+                info!(low_power, "Low power mode changed");
+                s.send(Event::LowPower(low_power)).unwrap();
+            }
+        }
+        for request in woken.into_iter().chain(requests.try_iter()) {
+            debug!(?request, "Request");
+            for event in machine.handle(request, clock.now()) {
+                s.send(event).unwrap();
+            }
+            if request == Request::ForgetData {
+                daily = DailyTracker::new(config.daily_limit.clone(), None);
+                DailyTime::forget();
+                TimerState::forget();
+            }
+            if request == Request::Shutdown {
+                if let Some((elapsed, has_break)) = machine.take_save() {
+                    save_timer(elapsed, has_break);
                 }
-                start = Instant::now();
-            },
-            Ok(idle_time) if idle_time > IDLE_PAUSE_TIME => {},
-            Ok(_idle_time) => {
-                if has_reset {
-                    start = Instant::now();
-                    has_reset = false;
-                    has_break = false;
+                save_daily(daily.time());
+                info!("Monitor stopped");
+                return;
+            }
+        }
+        if !machine.is_away() {
+            let fullscreen = (config.fullscreen.defer_notifications || config.fullscreen.pause_timer) && is_fullscreen();
+            let gaming_mode = config.gaming.mode != GamingMode::Normal;
+            let foreground = if config.processes.is_empty() && !gaming_mode { None } else { foreground_process() };
+            let gaming = gaming_mode && (is_fullscreen_game() || is_game_bar(foreground.as_deref())
+                || foreground.as_deref().is_some_and(|name| config.gaming.is_game(name)));
+            let quiet_process = foreground.filter(|name| config.processes.is_quiet(name));
+            let reason = match (&quiet_process, gaming, fullscreen) {
+                (Some(name), _, _) => Some(name.clone()),
+                (None, true, _) => Some("game".to_string()),
+                (None, false, true) => Some("fullscreen app".to_string()),
+                (None, false, false) => None
+            };
+            if reason != quiet {
+                info!(?reason, "Quiet changed");
+                s.send(Event::Quiet(reason.clone())).unwrap();
+                quiet = reason;
+            }
+            if config.meetings.defer_notifications && clock.now() - last_call_check >= CALL_CHECK_INTERVAL {
+                last_call_check = clock.now();
+                if is_capturing() != in_call {
+                    in_call = !in_call;
+                    info!(in_call, "Call changed");
+                    call_ended = if in_call { None } else { Some(clock.now()) };
                 }
-                s.send(Event::UpdateTime(start.elapsed())).unwrap();
-                if start.elapsed() >= BREAK_TIME {
-                    if !has_break {
-                        s.send(Event::NotifyBreak).unwrap();
-                        has_break = true;
+            }
+            // While idle, whether the screen is still being watched tells how good a break it is.
+            let resting = machine.state() != State::Working;
+            machine.set_conditions(Conditions::new(&config, &Surroundings {
+                fullscreen: fullscreen || (resting && is_fullscreen()),
+                gaming,
+                quiet_process,
+                in_call,
+                since_call: call_ended.map(|ended| clock.now() - ended),
+                sound_playing: resting && sound.idle_time().is_some_and(|silent| silent < POLL_INTERVAL),
+            }));
+            match idle.idle_time(clock.now()) {
+                Ok(idle_time) => {
+                    trace!(?idle_time, "Step");
+                    if (idle_time > IDLE_PAUSE_TIME) != (last_idle > IDLE_PAUSE_TIME) {
+                        debug!(interval = ?poll_interval(low_power, idle_time), "Polling interval changed");
+                    }
+                    last_idle = idle_time;
+                    for event in machine.step(idle_time, clock.now()) {
+                        s.send(event).unwrap();
                     }
+                },
+                Err(errno) => {
+                    warn!(errno, "Failed to read the idle time");
                 }
-            },
-            Err(_errno) => {
             }
+            if idle.degraded() != degraded {
+                degraded = !degraded;
+                s.send(Event::Degraded(degraded)).unwrap();
+            }
+        }
+        civil::check_offset();
+        let today = civil::now().date();
+        if let Some(warning) = daily.step(&today, machine.state() == State::Working, clock.now()) {
+            info!(?warning, "Daily limit");
+            s.send(Event::DailyLimit(warning)).unwrap();
+        }
+        if let Some((elapsed, has_break)) = machine.take_save() {
+            save_timer(elapsed, has_break);
+            save_daily(daily.time());
+        }
+    }
+}
+
+/// Hands every event from the monitor to each consumer, and to those that send it off
+/// the machine only while privacy allows.
+async fn broadcast(mut r: mpsc::UnboundedReceiver<Event>, outputs: Outputs<Sender<Event>>) {
+    while let Some(event) = r.recv().await {
+        for output in outputs.recipients(privacy::sharing()) {
+            let _ = output.send(event.clone());
         }
     }
 }
 
 fn main() {
 
-    let (s, r) = unbounded();
-    thread::spawn(|| monitor_idle_time(s));
-    start(r);
+    logger::init();
+
+    match cli::parse(env::args().skip(1)) {
+        Ok(Command::Run) => {},
+        Ok(command) => {
+            attach_console();
+            process::exit(cli::run(command));
+        },
+        Err(e) => {
+            attach_console();
+            eprintln!("ERROR: {}\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    }
+
+    let _instance = match acquire_instance() {
+        Some(lock) => lock,
+        None => {
+            info!("Already running, activating the running instance");
+            activate_existing();
+            return;
+        }
+    };
+
+    backup::finish_restore();
+    repair_autostart();
+    let (mut config, problems) = Config::load_checked();
+    logger::set_level(config.log.level);
+    if let Some(language) = config.language.clone().or_else(user_language) {
+        i18n::set_language(&language);
+    }
+    format::set_time_format(config.display.time_format);
+    format::set_time_shown(config.display.time_shown);
+    privacy::set(config.privacy);
+    privacy::set_guest(config.guest);
+    if let Some(passphrase) = config.storage.passphrase.take() {
+        match write_credential(store::PASSPHRASE_CREDENTIAL, &passphrase) {
+            Ok(()) => {
+                if let Err(e) = config.save() {
+                    error!("{}", e);
+                }
+            },
+            Err(e) => {
+                error!("Failed to keep the passphrase in the credential store: {}", e);
+                config.storage.passphrase = Some(passphrase);
+            }
+        }
+    }
+    store::set_encryption(config.storage.encryption);
+    if config.api.enabled && config.api.ensure_token() {
+        if let Err(e) = config.save() {
+            error!("{}", e);
+        }
+    }
+    if let Some(password) = config.email.password.take() {
+        match write_credential(email::CREDENTIAL, &password) {
+            Ok(()) => {
+                if let Err(e) = config.save() {
+                    error!("{}", e);
+                }
+            },
+            Err(e) => {
+                error!("Failed to keep the email password in the credential store: {}", e);
+                config.email.password = Some(password);
+            }
+        }
+    }
+    // Once the passwords are in the credential store, so the crash reports' config has less to hide.
+    crash::install(&config);
+    let runtime = Builder::new_multi_thread()
+        .enable_time()
+        .build()
+        .expect("Failed to start the runtime");
+    let context = runtime.enter();
+    let (s, r) = mpsc::unbounded_channel();
+    let (gui_s, gui_r) = unbounded();
+    let (history_s, history_r) = unbounded();
+    let (ipc_s, ipc_r) = unbounded();
+    let (request_s, request_r) = unbounded();
+    // Dropped by the monitor and the history recorder once they've saved everything.
+    let (stopped_s, stopped_r) = unbounded::<()>();
+    let devices = Arc::new(Devices::default());
+    let monitor_config = config.clone();
+    let monitor_stopped = stopped_s.clone();
+    let monitor_devices = devices.clone();
+    // The monitor keeps to one thread, where the COM objects for audio detection live.
+    spawn_thread(move || {
+        let _stopped = monitor_stopped;
+        let idle = SharedIdle::new(InputIdle::new(&monitor_config.input), monitor_devices);
+        monitor_idle_time(s, request_r, monitor_config, SystemClock, idle)
+    });
+    let mut outputs = Outputs { local: vec![gui_s, history_s, ipc_s], remote: Vec::new() };
+    spawn_thread(move || {
+        let _stopped = stopped_s;
+        history::record_events(history_r)
+    });
+    let ipc_requests = request_s.clone();
+    spawn_thread(move || ipc::serve(ipc_r, ipc_requests));
+    if config.api.enabled {
+        let (api_s, api_r) = unbounded();
+        outputs.local.push(api_s);
+        let api_config = config.api.clone();
+        let api_requests = request_s.clone();
+        spawn_thread(move || api::serve(api_config, api_r, api_requests));
+        let (stream_s, stream_r) = unbounded();
+        outputs.local.push(stream_s);
+        let stream_config = config.api.clone();
+        let stream_requests = request_s.clone();
+        spawn_thread(move || stream::serve(stream_config, stream_r, stream_requests));
+    }
+    if config.mqtt.enabled {
+        let (mqtt_s, mqtt_r) = unbounded();
+        outputs.remote.push(mqtt_s);
+        let mqtt_config = config.mqtt.clone();
+        let mqtt_requests = request_s.clone();
+        spawn_thread(move || mqtt::publish(mqtt_config, mqtt_r, mqtt_requests));
+    }
+    let publishers = presence::publishers(&config);
+    if !publishers.is_empty() {
+        let (presence_s, presence_r) = unbounded();
+        outputs.remote.push(presence_s);
+        spawn_thread(move || presence::run(publishers, presence_r));
+    }
+    if !config.webhooks.urls.is_empty() {
+        let (webhook_s, webhook_r) = unbounded();
+        outputs.remote.push(webhook_s);
+        let webhook_config = config.webhooks.clone();
+        spawn_thread(move || webhook::deliver(webhook_config, webhook_r));
+    }
+    if !config.commands.is_empty() {
+        let (commands_s, commands_r) = unbounded();
+        outputs.local.push(commands_s);
+        let commands_config = config.commands.clone();
+        spawn_thread(move || commands::run_on_events(commands_config, commands_r));
+    }
+    if config.activitywatch.enabled {
+        let (aw_s, aw_r) = unbounded();
+        outputs.remote.push(aw_s);
+        let aw_config = config.activitywatch.clone();
+        spawn_thread(move || activitywatch::send_heartbeats(aw_config, aw_r));
+    }
+    if config.handoff.enabled {
+        let (handoff_s, handoff_r) = unbounded();
+        outputs.remote.push(handoff_s);
+        let handoff_config = config.handoff.clone();
+        let handoff_requests = request_s.clone();
+        spawn_thread(move || handoff::run(handoff_config, handoff_r, handoff_requests));
+    }
+    // A break cut short by a crash or a shutdown may have left the sound down.
+    task::spawn_blocking(|| restore_sound(false));
+    if config.break_audio.action != BreakAudio::Nothing {
+        let (audio_s, audio_r) = unbounded();
+        outputs.local.push(audio_s);
+        let audio_config = config.break_audio.clone();
+        spawn_thread(move || quiet_breaks(audio_config, audio_r));
+    }
+    match config.devices.mode {
+        DevicesMode::Server => {
+            let devices_config = config.devices.clone();
+            spawn_thread(move || devices::serve(devices_config, devices));
+        },
+        DevicesMode::Client => {
+            let devices_config = config.devices.clone();
+            spawn_thread(move || devices::report(devices_config, devices));
+        },
+        DevicesMode::Off => {}
+    }
+    if config.intensity.enabled {
+        // The input hooks need a thread with a message loop of its own.
+        spawn_thread(record_input_rate);
+    }
+    if config.usage.enabled {
+        let (usage_s, usage_r) = unbounded();
+        outputs.local.push(usage_s);
+        spawn_thread(move || usage::record(usage_r, foreground_window));
+    }
+    tokio::spawn(broadcast(r, outputs));
+    backup::watch(config.backup.clone());
+    history::watch_retention(config.retention.clone());
+    start(gui_r, request_s, stopped_r, config, problems);
+    store::save();
+    drop(context);
+    runtime.shutdown_background();
 
 }
@@ -0,0 +1,90 @@
+use std::fmt::Write;
+use std::time::Instant;
+use crate::history::IntervalKind;
+use crate::status::Status;
+use crate::civil;
+use crate::windows::get_idle_time;
+use crate::{Event, State};
+
+const STATES: [State; 3] = [State::Working, State::Paused, State::OnBreak];
+
+/// Counters kept since the app started, for the Prometheus `/metrics` endpoint.
+pub struct Metrics {
+    notifications: u64,
+    skips: u64,
+    resets: u64,
+    breaks_today: u64,
+    break_day: String,
+    last_break: Instant,
+    on_break: bool,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics {
+            notifications: 0,
+            skips: 0,
+            resets: 0,
+            breaks_today: 0,
+            break_day: civil::now().date(),
+            last_break: Instant::now(),
+            on_break: false,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::NotifyBreak => self.notifications += 1,
+            Event::NotifyReset => self.resets += 1,
+            Event::UpdateState(state) => self.on_break = *state == State::OnBreak,
+            Event::Interval(interval) => match interval.kind {
+                IntervalKind::Skip => self.skips += 1,
+                IntervalKind::Break => {
+                    self.roll_day();
+                    self.breaks_today += 1;
+                    self.last_break = Instant::now();
+                },
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn roll_day(&mut self) {
+        let today = civil::now().date();
+        if self.break_day != today {
+            self.break_day = today;
+            self.breaks_today = 0;
+        }
+    }
+
+    fn seconds_since_break(&self) -> u64 {
+        if self.on_break { 0 } else { self.last_break.elapsed().as_secs() }
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&mut self, status: &Status) -> String {
+        self.roll_day();
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP pauza_{} {}\n# TYPE pauza_{} {}\npauza_{} {}", name, help, name, kind, name, value);
+        };
+        metric("seconds_since_break", "gauge", "Seconds since the last break ended.", self.seconds_since_break());
+        metric("breaks_today", "gauge", "Breaks taken today.", self.breaks_today);
+        metric("notifications_total", "counter", "Break reminders shown.", self.notifications);
+        metric("skips_total", "counter", "Breaks skipped.", self.skips);
+        metric("resets_total", "counter", "Timer resets after a long absence.", self.resets);
+        metric("idle_seconds", "gauge", "Seconds since the last input.", get_idle_time().map(|idle| idle.as_secs()).unwrap_or(0));
+        metric("elapsed_seconds", "gauge", "Work time counted towards the next break.", status.elapsed);
+        metric("remaining_seconds", "gauge", "Work time left until the next break.", status.remaining);
+
+        let _ = writeln!(out, "# HELP pauza_state The timer state.\n# TYPE pauza_state gauge");
+        for state in STATES.iter() {
+            let value = if status.state == state.as_str() { 1 } else { 0 };
+            let _ = writeln!(out, "pauza_state{{state=\"{}\"}} {}", state.as_str(), value);
+        }
+        out
+    }
+}
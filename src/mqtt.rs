@@ -0,0 +1,126 @@
+use crossbeam::channel::{Receiver, Sender};
+use rumqttc::{Client, Event as MqttEvent, LastWill, MqttOptions, Packet, QoS};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::config::MqttConfig;
+use crate::ipc;
+use crate::status::Status;
+use crate::{Event, Request};
+
+mod discovery;
+
+/// Messages waiting for the broker; more are dropped rather than holding up the events.
+const QUEUE: usize = 32;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const REMAINING_INTERVAL: Duration = Duration::from_secs(15);
+
+fn state_topic(config: &MqttConfig) -> String {
+    format!("{}/state", config.topic)
+}
+
+fn remaining_topic(config: &MqttConfig) -> String {
+    format!("{}/remaining", config.topic)
+}
+
+fn availability_topic(config: &MqttConfig) -> String {
+    format!("{}/availability", config.topic)
+}
+
+/// Takes the same commands as the control pipe, e.g. `pause` or `break-now`.
+fn command_topic(config: &MqttConfig) -> String {
+    format!("{}/command", config.topic)
+}
+
+fn send(client: &Client, topic: &str, payload: String) {
+    if let Err(e) = client.try_publish(topic, QoS::AtLeastOnce, true, payload) {
+        log::debug!("MQTT: dropped a message for {}: {}", topic, e);
+    }
+}
+
+fn on_connect(client: &Client, config: &MqttConfig) {
+    log::info!("Connected to the MQTT broker");
+    if let Err(e) = client.try_subscribe(command_topic(config), QoS::AtLeastOnce) {
+        log::warn!("MQTT: {}", e);
+    }
+    if config.discovery {
+        for (topic, payload) in discovery::messages(config) {
+            send(client, &topic, payload);
+        }
+    }
+    send(client, &availability_topic(config), "online".to_string());
+}
+
+fn on_command(payload: &[u8], requests: &Sender<Request>) {
+    let line = String::from_utf8_lossy(payload);
+    match ipc::parse(line.trim()) {
+        Ok(Some(request)) => {
+            log::info!("MQTT command: {}", line.trim());
+            requests.send(request).unwrap();
+        },
+        Ok(None) => {},
+        Err(e) => log::warn!("MQTT command: {}", e)
+    }
+}
+
+/// Drives the connection, reconnecting after errors and announcing itself on each connect.
+fn run_connection(mut connection: rumqttc::Connection, client: Client, config: MqttConfig, requests: Sender<Request>) {
+    let commands = command_topic(&config);
+    let mut failing = false;
+    for notification in connection.iter() {
+        match notification {
+            Ok(MqttEvent::Incoming(Packet::ConnAck(_))) => {
+                failing = false;
+                on_connect(&client, &config);
+            },
+            Ok(MqttEvent::Incoming(Packet::Publish(publish))) if publish.topic == commands => {
+                on_command(&publish.payload, &requests);
+            },
+            Ok(_) => {},
+            Err(e) => {
+                if !failing {
+                    log::warn!("MQTT: {}", e);
+                }
+                failing = true;
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Publishes state changes and the remaining time as retained messages under `config.topic`,
+/// and passes commands from `<topic>/command` on to the monitor.
+pub fn publish(config: MqttConfig, r: Receiver<Event>, requests: Sender<Request>) {
+    let state_topic = state_topic(&config);
+    let remaining_topic = remaining_topic(&config);
+
+    let mut options = MqttOptions::new(config.client_id.as_str(), config.host.as_str(), config.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_last_will(LastWill::new(availability_topic(&config), "offline", QoS::AtLeastOnce, true));
+    if let Some(username) = &config.username {
+        options.set_credentials(username.as_str(), config.password.clone().unwrap_or_default());
+    }
+    let (client, connection) = Client::new(options, QUEUE);
+    let connection_client = client.clone();
+    thread::spawn(move || run_connection(connection, connection_client, config, requests));
+
+    let mut status = Status::default();
+    let mut last_remaining: Option<(u64, Instant)> = None;
+    for event in r.iter() {
+        status.apply(&event);
+        let due = match (&event, last_remaining) {
+            (Event::UpdateState(_), _) => {
+                send(&client, &state_topic, status.state.to_string());
+                true
+            },
+            (Event::UpdateTime(_), None) => true,
+            (Event::UpdateTime(_), Some((remaining, sent))) =>
+                remaining != status.remaining && sent.elapsed() >= REMAINING_INTERVAL,
+            _ => false
+        };
+        if due {
+            send(&client, &remaining_topic, status.remaining.to_string());
+            last_remaining = Some((status.remaining, Instant::now()));
+        }
+    }
+}
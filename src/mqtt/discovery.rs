@@ -0,0 +1,64 @@
+use serde_json::{json, Value};
+use crate::config::MqttConfig;
+
+/// An entity Home Assistant creates from a retained config message.
+struct Entity {
+    component: &'static str,
+    id: &'static str,
+    name: &'static str,
+    fields: Value,
+}
+
+fn entities(config: &MqttConfig) -> Vec<Entity> {
+    let command_topic = super::command_topic(config);
+    let button = |id, name, payload: &str| Entity {
+        component: "button",
+        id,
+        name,
+        fields: json!({ "command_topic": command_topic, "payload_press": payload }),
+    };
+    vec![
+        Entity {
+            component: "sensor",
+            id: "state",
+            name: "State",
+            fields: json!({ "state_topic": super::state_topic(config), "icon": "mdi:timer-outline" }),
+        },
+        Entity {
+            component: "sensor",
+            id: "remaining",
+            name: "Time until break",
+            fields: json!({
+                "state_topic": super::remaining_topic(config),
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        },
+        button("break", "Start break", "break-now"),
+        button("pause", "Pause", "pause"),
+        button("resume", "Resume", "resume"),
+    ]
+}
+
+/// The retained discovery messages, as topic and payload, that make pauza show up as a device.
+pub fn messages(config: &MqttConfig) -> Vec<(String, String)> {
+    let device = json!({
+        "identifiers": [config.client_id],
+        "name": "Pauza",
+        "manufacturer": "pauza",
+    });
+    entities(config).into_iter().map(|entity| {
+        let unique_id = format!("{}_{}", config.client_id, entity.id);
+        let topic = format!("{}/{}/{}/config", config.discovery_prefix, entity.component, unique_id);
+        let mut payload = json!({
+            "name": entity.name,
+            "unique_id": unique_id,
+            "availability_topic": super::availability_topic(config),
+            "device": device,
+        });
+        if let (Some(payload), Value::Object(fields)) = (payload.as_object_mut(), entity.fields) {
+            payload.extend(fields);
+        }
+        (topic, payload.to_string())
+    }).collect()
+}
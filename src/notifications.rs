@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+/// How soon after a notification the next one is folded into it rather than shown on
+/// its own, like the update notification that comes right after a reset.
+const COALESCE_TIME: Duration = Duration::from_secs(10);
+
+/// What a notification is about. A group has at most one notification on each topic,
+/// the latest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topic {
+    Reset,
+    Break,
+    DailyLimit,
+    Report,
+    Update,
+    Badges,
+    Budgets
+}
+
+impl Topic {
+    /// Which click a group keeps when several of its notifications do something when
+    /// clicked: undoing a reset can't wait, while a release or a report can be found later.
+    fn priority(self) -> u8 {
+        match self {
+            Topic::Break => 6,
+            Topic::Reset => 5,
+            Topic::DailyLimit => 4,
+            Topic::Budgets => 3,
+            Topic::Update => 2,
+            Topic::Report => 1,
+            Topic::Badges => 0
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub topic: Topic,
+    pub title: String,
+    pub message: String,
+}
+
+/// Somewhere a notification can be delivered.
+pub trait NotificationBackend {
+    fn name(&self) -> &'static str;
+    fn show(&self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Only writes notifications to the log, for those who'd rather not be interrupted.
+pub struct LogOnly;
+
+impl NotificationBackend for LogOnly {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn show(&self, notification: &Notification) -> Result<(), String> {
+        log::info!("{}: {}", notification.title, notification.message.replace('\n', " / "));
+        Ok(())
+    }
+}
+
+/// Sits between what happens and the tray icon or toasts, so notifications that come
+/// close together update the one shown instead of stacking up. Each notification can
+/// come with what clicking it does, a `C`.
+pub struct Notifications<C> {
+    group: Vec<(Notification, Option<C>)>,
+    last: Option<Instant>,
+}
+
+impl<C> Default for Notifications<C> {
+    fn default() -> Notifications<C> {
+        Notifications { group: Vec::new(), last: None }
+    }
+}
+
+impl<C: Clone> Notifications<C> {
+    /// The notification to show in place of the one shown for `notification`, which
+    /// has the messages of the group it joins under the newest title, or None when it is
+    /// already shown as it is. It comes with the click of the topic in the group that
+    /// matters most, so a notification folded in doesn't take away one that came before.
+    pub fn add(&mut self, notification: Notification, click: Option<C>, now: Instant) -> Option<(Notification, Option<C>)> {
+        let recent = self.last.is_some_and(|last| now.saturating_duration_since(last) < COALESCE_TIME);
+        if !recent {
+            self.group.clear();
+        }
+        if self.group.iter().any(|(shown, _)| *shown == notification) {
+            return None;
+        }
+        self.group.retain(|(shown, _)| shown.topic != notification.topic);
+        let topic = notification.topic;
+        let title = notification.title.clone();
+        self.group.push((notification, click));
+        self.last = Some(now);
+        let message = self.group.iter()
+            .map(|(shown, _)| shown.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let click = self.group.iter()
+            .filter_map(|(shown, click)| click.as_ref().map(|click| (shown.topic.priority(), click)))
+            .max_by_key(|(priority, _)| *priority)
+            .map(|(_, click)| click.clone());
+        Some((Notification { topic, title, message }, click))
+    }
+
+    /// Starts over once the notification shown is dealt with, as by clicking it.
+    pub fn clear(&mut self) {
+        self.group.clear();
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(topic: Topic, title: &str, message: &str) -> Notification {
+        Notification { topic, title: title.to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn coalesces_notifications_close_together() {
+        let mut notifications = Notifications::<()>::default();
+        let start = Instant::now();
+        let reset = notification(Topic::Reset, "Reset", "Time for work");
+        let update = notification(Topic::Update, "Update", "Pauza 2.0 is out");
+        assert_eq!(notifications.add(reset.clone(), None, start), Some((reset.clone(), None)));
+        assert_eq!(notifications.add(update, None, start + Duration::from_secs(2)),
+                   Some((notification(Topic::Update, "Update", "Time for work\nPauza 2.0 is out"), None)));
+        // Already shown as it is.
+        assert_eq!(notifications.add(reset.clone(), None, start + Duration::from_secs(4)), None);
+        // A newer one on the same topic takes the place of the one before.
+        let again = notification(Topic::Reset, "Reset", "Time for work again");
+        assert_eq!(notifications.add(again, None, start + Duration::from_secs(6)),
+                   Some((notification(Topic::Reset, "Reset", "Pauza 2.0 is out\nTime for work again"), None)));
+        // Shown on its own once the others are a while ago.
+        assert_eq!(notifications.add(reset.clone(), None, start + Duration::from_secs(30)), Some((reset.clone(), None)));
+        notifications.clear();
+        assert_eq!(notifications.add(reset.clone(), None, start + Duration::from_secs(31)), Some((reset, None)));
+    }
+
+    #[test]
+    fn keeps_the_click_that_matters_most() {
+        let mut notifications = Notifications::default();
+        let start = Instant::now();
+        let reset = notification(Topic::Reset, "Reset", "Time for work");
+        let update = notification(Topic::Update, "Update", "Pauza 2.0 is out");
+        let report = notification(Topic::Report, "Report", "A good week");
+        assert_eq!(notifications.add(reset, Some("undo"), start).unwrap().1, Some("undo"));
+        // The release can wait; undoing the reset can't.
+        assert_eq!(notifications.add(update, Some("open"), start + Duration::from_secs(1)).unwrap().1, Some("undo"));
+        // One that does nothing when clicked doesn't take the click either.
+        let limit = notification(Topic::DailyLimit, "Limit", "Enough for today");
+        assert_eq!(notifications.add(limit, None, start + Duration::from_secs(2)).unwrap().1, Some("undo"));
+        // On its own, a notification has its own click.
+        assert_eq!(notifications.add(report, None, start + Duration::from_secs(30)).unwrap().1, None);
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::data_dir;
+use crate::privacy;
+use crate::IDLE_RESET_TIME;
+
+const TIMER_FILE: &str = "timer.toml";
+
+/// The work timer as last seen, so a restart doesn't start the count from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimerState {
+    pub elapsed_secs: u64,
+    pub has_break: bool,
+    pub saved_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl TimerState {
+    pub fn new(elapsed: Duration, has_break: bool) -> TimerState {
+        TimerState {
+            elapsed_secs: elapsed.as_secs(),
+            has_break,
+            saved_at: now(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if privacy::guest() {
+            return Ok(());
+        }
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(TIMER_FILE), text)
+    }
+
+    /// Once the data is deleted.
+    pub fn forget() {
+        match fs::remove_file(data_dir().join(TIMER_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => log::error!("{}", e),
+            _ => {}
+        }
+    }
+
+    pub fn load() -> Option<TimerState> {
+        if privacy::guest() {
+            return None;
+        }
+        let text = fs::read_to_string(data_dir().join(TIMER_FILE)).ok()?;
+        toml::from_str(&text).ok()
+    }
+
+    /// Returns the elapsed work time to resume from, counting the time pauza wasn't
+    /// running as work like the idle pause does. A gap long enough for the idle reset,
+    /// or a save from the future, means the state is stale.
+    pub fn resume(&self) -> Option<(Duration, bool)> {
+        let gap = Duration::from_secs(now().checked_sub(self.saved_at)?);
+        if gap > IDLE_RESET_TIME {
+            return None;
+        }
+        Some((Duration::from_secs(self.elapsed_secs) + gap, self.has_break))
+    }
+}
@@ -0,0 +1,93 @@
+use crossbeam::channel::Receiver;
+use std::time::{Duration, SystemTime};
+use crate::config::Config;
+use crate::{Event, State};
+
+mod discord;
+mod hue;
+mod slack;
+mod toggl;
+
+/// How far the start of the work stretch may drift before it is published again.
+const START_TOLERANCE: Duration = Duration::from_secs(60);
+
+/// What other apps are told the user is doing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Presence {
+    /// Working since the end of the last break.
+    Working(SystemTime),
+    Paused,
+    OnBreak
+}
+
+impl Presence {
+    fn is_close_to(&self, other: &Presence) -> bool {
+        match (self, other) {
+            (Presence::Working(a), Presence::Working(b)) => {
+                let drift = a.duration_since(*b).or_else(|_| b.duration_since(*a)).unwrap_or_default();
+                drift < START_TOLERANCE
+            },
+            _ => self == other
+        }
+    }
+}
+
+/// Somewhere to show the presence, like a chat status.
+pub trait Publisher {
+    fn name(&self) -> &'static str;
+    fn publish(&mut self, presence: Presence) -> Result<(), String>;
+}
+
+/// The publishers enabled in the config.
+pub fn publishers(config: &Config) -> Vec<Box<dyn Publisher + Send>> {
+    let mut publishers: Vec<Box<dyn Publisher + Send>> = Vec::new();
+    if config.slack.enabled {
+        if let Some(slack) = slack::Slack::new(config.slack.clone()) {
+            publishers.push(Box::new(slack));
+        }
+    }
+    if config.discord.enabled {
+        if let Some(discord) = discord::Discord::new(&config.discord) {
+            publishers.push(Box::new(discord));
+        }
+    }
+    if config.hue.enabled {
+        if let Some(hue) = hue::Hue::new(config.hue.clone()) {
+            publishers.push(Box::new(hue));
+        }
+    }
+    if config.toggl.enabled {
+        if let Some(toggl) = toggl::Toggl::new(&config.toggl) {
+            publishers.push(Box::new(toggl));
+        }
+    }
+    publishers
+}
+
+/// Follows the timer and passes each change of presence to every publisher.
+pub fn run(mut publishers: Vec<Box<dyn Publisher + Send>>, r: Receiver<Event>) {
+    let mut state = State::Working;
+    let mut elapsed = Duration::from_secs(0);
+    let mut published: Option<Presence> = None;
+    for event in r.iter() {
+        match event {
+            Event::UpdateState(new_state) => state = new_state,
+            Event::UpdateTime(new_elapsed) => elapsed = new_elapsed,
+            _ => continue
+        }
+        let presence = match state {
+            State::Working => Presence::Working(SystemTime::now() - elapsed),
+            State::Paused => Presence::Paused,
+            State::OnBreak => Presence::OnBreak
+        };
+        if published.is_some_and(|published| published.is_close_to(&presence)) {
+            continue;
+        }
+        published = Some(presence);
+        for publisher in publishers.iter_mut() {
+            if let Err(e) = publisher.publish(presence) {
+                log::error!("Failed to update the {} status: {}", publisher.name(), e);
+            }
+        }
+    }
+}
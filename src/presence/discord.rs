@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::UNIX_EPOCH;
+use crate::config::DiscordConfig;
+use super::{Presence, Publisher};
+
+/// Discord listens on the first free one of `discord-ipc-0` to `discord-ipc-9`.
+const PIPE_COUNT: u32 = 10;
+const MAX_FRAME: usize = 64 * 1024;
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+fn write_frame(pipe: &mut File, op: u32, payload: &Value) -> io::Result<()> {
+    let payload = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    pipe.write_all(&frame)
+}
+
+fn read_frame(pipe: &mut File) -> io::Result<(u32, Value)> {
+    let mut header = [0u8; 8];
+    pipe.read_exact(&mut header)?;
+    let op = u32::from_le_bytes(header[..4].try_into().unwrap());
+    let len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut payload = vec![0u8; len];
+    pipe.read_exact(&mut payload)?;
+    let payload = serde_json::from_slice(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok((op, payload))
+}
+
+fn activity(presence: Presence) -> Value {
+    match presence {
+        Presence::Working(since) => json!({
+            "details": "Working",
+            "timestamps": { "start": since.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) },
+        }),
+        Presence::Paused => json!({ "details": "Paused" }),
+        Presence::OnBreak => json!({ "details": "On a break" })
+    }
+}
+
+/// Shows the presence as the activity of a Discord application, over Discord's local pipe.
+pub struct Discord {
+    client_id: String,
+    pipe: Option<File>,
+    nonce: u64,
+}
+
+impl Discord {
+    pub fn new(config: &DiscordConfig) -> Option<Discord> {
+        if config.client_id.is_empty() {
+            log::error!("Discord needs an application client id");
+            return None;
+        }
+        Some(Discord { client_id: config.client_id.clone(), pipe: None, nonce: 0 })
+    }
+
+    fn connect(&self) -> io::Result<File> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "Discord is not running");
+        for i in 0..PIPE_COUNT {
+            let mut pipe = match OpenOptions::new().read(true).write(true).open(format!(r"\\.\pipe\discord-ipc-{}", i)) {
+                Ok(pipe) => pipe,
+                Err(e) => {
+                    last_error = e;
+                    continue;
+                }
+            };
+            write_frame(&mut pipe, OP_HANDSHAKE, &json!({ "v": 1, "client_id": self.client_id }))?;
+            let (op, payload) = read_frame(&mut pipe)?;
+            if op != OP_FRAME || payload["evt"] != "READY" {
+                return Err(io::Error::other(format!("handshake refused: {}", payload)));
+            }
+            log::info!("Connected to Discord");
+            return Ok(pipe);
+        }
+        Err(last_error)
+    }
+
+    fn set_activity(&mut self, activity: Value) -> io::Result<()> {
+        let mut pipe = match self.pipe.take() {
+            Some(pipe) => pipe,
+            None => self.connect()?
+        };
+        self.nonce += 1;
+        write_frame(&mut pipe, OP_FRAME, &json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": process::id(), "activity": activity },
+            "nonce": self.nonce.to_string(),
+        }))?;
+        let (_, response) = read_frame(&mut pipe)?;
+        if response["evt"] == "ERROR" {
+            return Err(io::Error::other(response["data"]["message"].to_string()));
+        }
+        self.pipe = Some(pipe);
+        Ok(())
+    }
+}
+
+impl Publisher for Discord {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    fn publish(&mut self, presence: Presence) -> Result<(), String> {
+        let activity = activity(presence);
+        // A pipe left from before Discord restarted fails once; a fresh connection may not.
+        let connected = self.pipe.is_some();
+        let result = match self.set_activity(activity.clone()) {
+            Err(_) if connected => self.set_activity(activity),
+            result => result
+        };
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::debug!("Discord: {}", e);
+                Ok(())
+            },
+            result => result.map_err(|e| e.to_string())
+        }
+    }
+}
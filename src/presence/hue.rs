@@ -0,0 +1,91 @@
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+use crate::config::HueConfig;
+use super::{Presence, Publisher};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Switches a light to the break color over the Hue bridge's local API, and back afterwards.
+pub struct Hue {
+    config: HueConfig,
+    saved: Option<Map<String, Value>>,
+}
+
+impl Hue {
+    pub fn new(config: HueConfig) -> Option<Hue> {
+        if config.bridge.is_empty() || config.username.is_empty() {
+            log::error!("Hue needs the bridge address and a username");
+            return None;
+        }
+        Some(Hue { config, saved: None })
+    }
+
+    fn light_url(&self) -> String {
+        format!("http://{}/api/{}/lights/{}", self.config.bridge, self.config.username, self.config.light)
+    }
+
+    fn light_state(&self) -> Result<Map<String, Value>, String> {
+        let light: Value = ureq::get(&self.light_url())
+            .timeout(TIMEOUT)
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_json()
+            .map_err(|e| e.to_string())?;
+        match light.get("state") {
+            Some(Value::Object(state)) => Ok(state.clone()),
+            _ => Err(format!("unexpected answer: {}", light))
+        }
+    }
+
+    fn set_state(&self, state: Value) -> Result<(), String> {
+        // The bridge answers 200 with a list of errors when something is wrong.
+        let response: Value = ureq::put(&format!("{}/state", self.light_url()))
+            .timeout(TIMEOUT)
+            .send_json(state)
+            .map_err(|e| e.to_string())?
+            .into_json()
+            .map_err(|e| e.to_string())?;
+        let errors: Vec<_> = response.as_array().into_iter().flatten()
+            .filter_map(|item| item["error"]["description"].as_str())
+            .collect();
+        if errors.is_empty() { Ok(()) } else { Err(errors.join(", ")) }
+    }
+}
+
+impl Publisher for Hue {
+    fn name(&self) -> &'static str {
+        "Hue"
+    }
+
+    fn publish(&mut self, presence: Presence) -> Result<(), String> {
+        match presence {
+            Presence::OnBreak if self.saved.is_none() => {
+                let mut saved = self.light_state()?;
+                // Only the fields of the light's color mode, since the others would override it.
+                let color: &[&str] = match saved.get("colormode").and_then(Value::as_str) {
+                    Some("xy") => &["xy"],
+                    Some("ct") => &["ct"],
+                    Some("hs") => &["hue", "sat"],
+                    _ => &[]
+                };
+                saved.retain(|key, _| key == "on" || key == "bri" || color.contains(&key.as_str()));
+                self.set_state(json!({
+                    "on": true,
+                    "hue": self.config.hue,
+                    "sat": self.config.saturation,
+                    "bri": self.config.brightness,
+                }))?;
+                self.saved = Some(saved);
+                log::info!("Hue light {} set to the break color", self.config.light);
+            },
+            Presence::Working(_) => {
+                if let Some(saved) = self.saved.take() {
+                    self.set_state(Value::Object(saved))?;
+                    log::info!("Hue light {} restored", self.config.light);
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+}
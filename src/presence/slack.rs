@@ -0,0 +1,80 @@
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::civil;
+use crate::config::SlackConfig;
+use super::{Presence, Publisher};
+
+const PROFILE_URL: &str = "https://slack.com/api/users.profile.set";
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// The status outlives the expected break by this much, and then clears itself in case
+/// pauza isn't around to clear it.
+const EXPIRATION_MARGIN: Duration = Duration::from_secs(30 * 60);
+
+fn set_profile(token: &str, profile: Value) -> Result<(), String> {
+    let response: Value = ureq::post(PROFILE_URL)
+        .timeout(TIMEOUT)
+        .set("Authorization", &format!("Bearer {}", token))
+        .send_json(json!({ "profile": profile }))
+        .map_err(|e| e.to_string())?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+    if response["ok"] == true {
+        Ok(())
+    } else {
+        Err(response["error"].as_str().unwrap_or("unknown error").to_string())
+    }
+}
+
+fn break_profile(config: &SlackConfig) -> Value {
+    let length = Duration::from_secs(config.break_minutes * 60);
+    let expiration = (SystemTime::now() + length + EXPIRATION_MARGIN)
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    json!({
+        "status_text": config.text.replace("{time}", &civil::clock_after(length)),
+        "status_emoji": config.emoji,
+        "status_expiration": expiration,
+    })
+}
+
+fn clear_profile() -> Value {
+    json!({ "status_text": "", "status_emoji": "", "status_expiration": 0 })
+}
+
+/// Sets the Slack status when a break starts and clears it when work resumes.
+pub struct Slack {
+    config: SlackConfig,
+    token: String,
+    on_break: bool,
+}
+
+impl Slack {
+    pub fn new(config: SlackConfig) -> Option<Slack> {
+        match config.token.clone() {
+            Some(token) if !token.is_empty() => Some(Slack { config, token, on_break: false }),
+            _ => {
+                log::error!("Slack needs a user token");
+                None
+            }
+        }
+    }
+}
+
+impl Publisher for Slack {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    fn publish(&mut self, presence: Presence) -> Result<(), String> {
+        let profile = match presence {
+            Presence::OnBreak if !self.on_break => break_profile(&self.config),
+            Presence::Working(_) if self.on_break => clear_profile(),
+            _ => return Ok(())
+        };
+        set_profile(&self.token, profile)?;
+        self.on_break = !self.on_break;
+        log::info!("Slack status {}", if self.on_break { "set" } else { "cleared" });
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::{TogglConfig, TogglMode};
+use crate::format;
+use super::{Presence, Publisher};
+
+const API_URL: &str = "https://api.track.toggl.com/api/v9";
+const TIMEOUT: Duration = Duration::from_secs(10);
+const BREAK_TAG: &str = "break";
+
+#[derive(Clone, Debug, Deserialize)]
+struct TimeEntry {
+    id: u64,
+    workspace_id: u64,
+    project_id: Option<u64>,
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Stops the running Toggl entry for breaks and picks it up again afterwards, or just
+/// tags it, depending on the mode.
+pub struct Toggl {
+    mode: TogglMode,
+    description: String,
+    token: String,
+    /// The entry that was running when the break started.
+    interrupted: Option<TimeEntry>,
+    /// The entry tracking the break itself.
+    break_entry: Option<TimeEntry>,
+}
+
+fn now_rfc3339() -> String {
+    format::rfc3339(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0))
+}
+
+impl Toggl {
+    pub fn new(config: &TogglConfig) -> Option<Toggl> {
+        match config.api_token.clone() {
+            Some(token) if !token.is_empty() => Some(Toggl {
+                mode: config.mode,
+                description: config.break_description.clone(),
+                token,
+                interrupted: None,
+                break_entry: None,
+            }),
+            _ => {
+                log::error!("Toggl needs an API token");
+                None
+            }
+        }
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<Value>) -> Result<Value, String> {
+        let request = ureq::request(method, &format!("{}{}", API_URL, path))
+            .timeout(TIMEOUT)
+            .set("Authorization", &format!("Basic {}", basic_auth(&self.token)));
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call()
+        };
+        response.map_err(|e| e.to_string())?
+            .into_json()
+            .map_err(|e| e.to_string())
+    }
+
+    fn current(&self) -> Result<Option<TimeEntry>, String> {
+        let entry = self.request("GET", "/me/time_entries/current", None)?;
+        if entry.is_null() {
+            return Ok(None);
+        }
+        serde_json::from_value(entry).map(Some).map_err(|e| e.to_string())
+    }
+
+    fn stop(&self, entry: &TimeEntry) -> Result<(), String> {
+        self.request("PATCH", &format!("/workspaces/{}/time_entries/{}/stop", entry.workspace_id, entry.id), None)?;
+        Ok(())
+    }
+
+    fn start(&self, workspace_id: u64, project_id: Option<u64>, description: &str, tags: &[String]) -> Result<TimeEntry, String> {
+        let entry = self.request("POST", &format!("/workspaces/{}/time_entries", workspace_id), Some(json!({
+            "created_with": "pauza",
+            "workspace_id": workspace_id,
+            "project_id": project_id,
+            "description": description,
+            "tags": tags,
+            "start": now_rfc3339(),
+            "duration": -1,
+        })))?;
+        serde_json::from_value(entry).map_err(|e| e.to_string())
+    }
+
+    fn set_tags(&self, entry: &TimeEntry, tags: &[String]) -> Result<(), String> {
+        self.request("PUT", &format!("/workspaces/{}/time_entries/{}", entry.workspace_id, entry.id), Some(json!({ "tags": tags })))?;
+        Ok(())
+    }
+
+    fn start_break(&mut self) -> Result<(), String> {
+        let current = match self.current()? {
+            Some(current) => current,
+            // Without a running entry there is no workspace to track the break in.
+            None => return Ok(())
+        };
+        match self.mode {
+            TogglMode::Switch => {
+                self.stop(&current)?;
+                let entry = self.start(current.workspace_id, None, &self.description, &[BREAK_TAG.to_string()])?;
+                self.break_entry = Some(entry);
+                self.interrupted = Some(current);
+            },
+            TogglMode::Tag => {
+                if !current.tags.iter().any(|tag| tag == BREAK_TAG) {
+                    let mut tags = current.tags.clone();
+                    tags.push(BREAK_TAG.to_string());
+                    self.set_tags(&current, &tags)?;
+                }
+            }
+        }
+        log::info!("Toggl break started");
+        Ok(())
+    }
+
+    fn end_break(&mut self) -> Result<(), String> {
+        if let Some(entry) = self.break_entry.take() {
+            self.stop(&entry)?;
+        }
+        if let Some(entry) = self.interrupted.take() {
+            let description = entry.description.as_deref().unwrap_or("");
+            self.start(entry.workspace_id, entry.project_id, description, &entry.tags)?;
+            log::info!("Toggl entry resumed after the break");
+        }
+        Ok(())
+    }
+}
+
+fn basic_auth(token: &str) -> String {
+    STANDARD.encode(format!("{}:api_token", token))
+}
+
+impl Publisher for Toggl {
+    fn name(&self) -> &'static str {
+        "Toggl"
+    }
+
+    fn publish(&mut self, presence: Presence) -> Result<(), String> {
+        match presence {
+            Presence::OnBreak if self.break_entry.is_none() && self.interrupted.is_none() => self.start_break(),
+            Presence::Working(_) => self.end_break(),
+            _ => Ok(())
+        }
+    }
+}
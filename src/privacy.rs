@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static GUEST: AtomicBool = AtomicBool::new(false);
+
+/// True while nothing but the break timer runs: no history, app usage or input counts
+/// are recorded.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Turns privacy mode on or off, noting each change in the log.
+pub fn set(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::Relaxed) == enabled {
+        return;
+    }
+    if enabled {
+        log::info!("Privacy mode on: not recording history, app usage or input counts");
+    } else {
+        log::info!("Privacy mode off: recording as configured again");
+    }
+}
+
+/// True when nothing of this run outlives it: the history is kept in memory and the
+/// timer starts fresh.
+pub fn guest() -> bool {
+    GUEST.load(Ordering::Relaxed)
+}
+
+/// Turns guest mode on, before anything opens the history.
+pub fn set_guest(guest: bool) {
+    GUEST.store(guest, Ordering::Relaxed);
+    if guest {
+        log::info!("Guest mode: keeping no history past this run");
+    }
+}
+
+/// Whether what happens here may leave the machine, for integrations, other devices,
+/// sync and the emailed report. Not in privacy mode, nor for a guest.
+pub fn sharing() -> bool {
+    !enabled() && !guest()
+}
+
+/// Where the monitor's events go, split by whether they stay on this machine.
+pub struct Outputs<T> {
+    pub local: Vec<T>,
+    /// Integrations that send events elsewhere, and other machines.
+    pub remote: Vec<T>,
+}
+
+impl<T> Outputs<T> {
+    /// Those an event goes to, the remote ones only while `sharing`.
+    pub fn recipients(&self, sharing: bool) -> impl Iterator<Item = &T> {
+        self.local.iter().chain(self.remote.iter().filter(move |_| sharing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_events_on_the_machine_unless_sharing() {
+        let outputs = Outputs { local: vec!["gui", "history"], remote: vec!["mqtt", "webhooks"] };
+        assert_eq!(outputs.recipients(true).collect::<Vec<_>>(), [&"gui", &"history", &"mqtt", &"webhooks"]);
+        assert_eq!(outputs.recipients(false).collect::<Vec<_>>(), [&"gui", &"history"]);
+    }
+}
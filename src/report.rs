@@ -0,0 +1,99 @@
+use std::time::{Duration, SystemTime};
+use crate::format;
+use crate::i18n::{tr, tr_args};
+use crate::history::{Heatmap, History, Period, Summary};
+
+const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// The heatmap's colors in the email, from an hour not worked to a whole one.
+const HEAT_NONE: [u8; 3] = [0xd8, 0xd8, 0xd8];
+const HEAT_FULL: [u8; 3] = [0x2e, 0x9e, 0x44];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn heat(minutes: u32) -> String {
+    let amount = (minutes as f32 / 60.0).clamp(0.0, 1.0);
+    let mix = |none: u8, full: u8| (none as f32 + (full as f32 - none as f32) * amount).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", mix(HEAT_NONE[0], HEAT_FULL[0]), mix(HEAT_NONE[1], HEAT_FULL[1]), mix(HEAT_NONE[2], HEAT_FULL[2]))
+}
+
+/// What happened over the last seven days.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeeklyReport {
+    pub work: Duration,
+    pub breaks: u32,
+    pub prompts: u32,
+    pub average_stretch: Duration,
+    /// How much of their breaks were spent away from the screen, on average.
+    pub quality: Option<f32>,
+    /// How many of the reminders were followed by a break soon enough.
+    pub compliance: Option<f32>,
+    pub heatmap: Heatmap,
+}
+
+impl WeeklyReport {
+    pub fn generate(history: &History, now: SystemTime) -> rusqlite::Result<WeeklyReport> {
+        let total = Summary::total(&history.summaries(Period::Day, now - WEEK)?);
+        let average = total.work.as_secs().checked_div(total.stretches as u64).unwrap_or(0);
+        Ok(WeeklyReport {
+            work: total.work,
+            breaks: total.breaks,
+            prompts: total.prompts,
+            average_stretch: Duration::from_secs(average),
+            quality: total.quality(),
+            compliance: total.compliance(),
+            heatmap: history.active_minutes(now - WEEK)?,
+        })
+    }
+
+    pub fn title(&self) -> String {
+        tr("report-title")
+    }
+
+    pub fn message(&self) -> String {
+        tr_args("report-message", &[
+            ("active", &format::duration(self.work)),
+            ("breaks", &self.breaks),
+            ("prompts", &self.prompts),
+            ("average", &format::duration(self.average_stretch)),
+            ("quality", &self.quality.map(|quality| format!("{:.0}%", quality * 100.0)).unwrap_or_else(|| "-".to_string())),
+        ])
+    }
+
+    fn compliance_line(&self) -> Option<String> {
+        self.compliance.map(|compliance| tr_args("report-compliance", &[("percent", &format!("{:.0}%", compliance * 100.0))]))
+    }
+
+    /// The summary as plain text, for an email.
+    pub fn text(&self) -> String {
+        match self.compliance_line() {
+            Some(line) => format!("{}\n{}", self.message(), line),
+            None => self.message()
+        }
+    }
+
+    /// The summary as an HTML page, for an email, with the hours worked as a heatmap.
+    pub fn html(&self) -> String {
+        let mut html = "<!DOCTYPE html>\n<html><body style=\"font-family: 'Segoe UI', sans-serif\">".to_string();
+        html += &format!("<h2>{}</h2>", escape(&self.title()));
+        let lines: Vec<String> = self.text().lines().map(escape).collect();
+        html += &format!("<p>{}</p>", lines.join("<br>"));
+        html += &format!("<h3>{}</h3>", escape(&tr("chart-heatmap")));
+        html += "<table style=\"border-collapse: separate; border-spacing: 2px\">";
+        for (day, hours) in tr("chart-heatmap-days").split_whitespace().zip(&self.heatmap) {
+            html += &format!("<tr><td style=\"padding-right: 8px\">{}</td>", escape(day));
+            for (hour, minutes) in hours.iter().enumerate() {
+                let title = tr_args("chart-heatmap-hour", &[("hour", &hour), ("time", &format::duration(Duration::from_secs(*minutes as u64 * 60)))]);
+                html += &format!("<td title=\"{}\" style=\"width: 14px; height: 14px; background: {}\"></td>", escape(&title), heat(*minutes));
+            }
+            html += "</tr>";
+        }
+        html += "<tr><td></td>";
+        for hour in (0..24).step_by(6) {
+            html += &format!("<td colspan=\"6\">{}</td>", hour);
+        }
+        html += "</tr></table></body></html>\n";
+        html
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use crate::config::TimeShown;
+use crate::format;
+use crate::status::Status;
+use crate::Request;
+
+/// The version of the JSON messages below, sent as `version` with each of them. It goes up
+/// when a field changes meaning or goes away; new fields may appear within a version.
+///
+/// Version 1, over the HTTP API (`GET /status`, `POST /command`) and the event stream:
+///
+/// - state: `{"version": 1, "state": "working" | "paused" | "on_break", "elapsed": secs,
+///   "remaining": secs, "remaining_minutes": minutes rounded up, "shown": "remaining" |
+///   "elapsed", "text": the timer as the window shows it}`
+/// - command: `{"command": "break"}`, `{"command": "pause", "duration": secs}` with the
+///   duration optional and at most a day, `{"command": "resume"}` or `{"command": "reset"}`
+/// - result: `{"version": 1, "ok": true}` or `{"version": 1, "ok": false, "error": text}`
+///
+/// The event stream wraps the state as `{"type": "status", "status": state}` on connecting,
+/// sends each event as `{"version": 1, "type": ...}`, and answers commands with a result
+/// of `"type": "result"`.
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StateMessage {
+    pub version: u32,
+    pub state: &'static str,
+    pub elapsed: u64,
+    pub remaining: u64,
+    /// Rounded up, for displays with room for just a number, like a Stream Deck key.
+    pub remaining_minutes: u64,
+    /// Which of the two times the user has chosen to see.
+    pub shown: TimeShown,
+    pub text: String,
+}
+
+impl StateMessage {
+    pub fn new(status: &Status) -> StateMessage {
+        StateMessage {
+            version: VERSION,
+            state: status.state,
+            elapsed: status.elapsed,
+            remaining: status.remaining,
+            remaining_minutes: status.remaining.div_ceil(60),
+            shown: format::time_shown(),
+            text: status.text.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum CommandMessage {
+    Break,
+    StartBreak,
+    Snooze,
+    Pause {
+        #[serde(default)]
+        duration: Option<u64>
+    },
+    Resume,
+    Reset
+}
+
+impl CommandMessage {
+    pub fn parse(text: &str) -> Result<CommandMessage, String> {
+        let command = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if let CommandMessage::Pause { duration: Some(secs) } = command {
+            format::check_pause(Duration::from_secs(secs))?;
+        }
+        Ok(command)
+    }
+
+    pub fn request(&self) -> Request {
+        match self {
+            CommandMessage::Break => Request::BreakNow,
+            CommandMessage::StartBreak => Request::AcknowledgeBreak,
+            CommandMessage::Snooze => Request::Snooze,
+            CommandMessage::Pause { duration } => Request::PauseTimer(duration.map(Duration::from_secs)),
+            CommandMessage::Resume => Request::ResumeTimer,
+            CommandMessage::Reset => Request::ResetTimer
+        }
+    }
+}
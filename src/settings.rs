@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::config::{Config, Problem, SECRETS};
+use crate::VERSION;
+
+/// The layout of the settings file. New settings don't change it, since settings a
+/// pauza doesn't know are reported and skipped; only a change older ones would misread does.
+const SETTINGS_VERSION: u32 = 1;
+
+/// Everything needed to set pauza up the same way on another machine, without the history.
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    /// The pauza that wrote the file.
+    pauza: String,
+    config: toml::Value,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Format(String),
+    /// Written by a newer pauza, in a layout this one can't read.
+    TooNew(u32)
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "{}", e),
+            SettingsError::Format(e) => write!(f, "not a settings file: {}", e),
+            SettingsError::TooNew(version) =>
+                write!(f, "the settings are from a newer version of pauza (format {}, this one reads up to {})", version, SETTINGS_VERSION)
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> SettingsError {
+        SettingsError::Io(e)
+    }
+}
+
+/// `config` as TOML with its secrets replaced by those of `secrets`, or left out when it has none.
+fn with_secrets(config: &Config, secrets: Option<&Config>) -> Result<toml::Value, SettingsError> {
+    let format = |e: toml::ser::Error| SettingsError::Format(e.to_string());
+    let mut value = toml::Value::try_from(config).map_err(format)?;
+    let secrets = secrets.map(toml::Value::try_from).transpose().map_err(format)?;
+    for key in SECRETS {
+        let (section, name) = key.split_once('.').unwrap_or(("", key));
+        let secret = secrets.as_ref().and_then(|secrets| secrets.get(section)?.get(name)).cloned();
+        if let Some(table) = value.get_mut(section).and_then(|table| table.as_table_mut()) {
+            match secret {
+                Some(secret) => table.insert(name.to_string(), secret),
+                None => table.remove(name)
+            };
+        }
+    }
+    Ok(value)
+}
+
+/// The settings file for `config`, leaving out the secrets, which belong to this machine.
+fn to_text(config: &Config) -> Result<String, SettingsError> {
+    let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        pauza: VERSION.to_string(),
+        config: with_secrets(config, None)?,
+    };
+    toml::to_string(&file).map_err(|e| SettingsError::Format(e.to_string()))
+}
+
+/// The config in a settings file, with the problems of each setting that couldn't be used.
+fn from_text(text: &str) -> Result<(Config, Vec<Problem>), SettingsError> {
+    let file: SettingsFile = toml::from_str(text).map_err(|e| SettingsError::Format(e.to_string()))?;
+    if file.version > SETTINGS_VERSION {
+        return Err(SettingsError::TooNew(file.version));
+    }
+    let config = toml::to_string(&file.config).map_err(|e| SettingsError::Format(e.to_string()))?;
+    Ok(Config::from_text(&config))
+}
+
+/// Writes the current config to `path`.
+pub fn export(path: &Path) -> Result<(), SettingsError> {
+    fs::write(path, to_text(&Config::load())?)?;
+    log::info!("Exported the settings to {}", path.display());
+    Ok(())
+}
+
+/// Replaces the config with the one in the settings file at `path`, keeping this machine's
+/// secrets. Most of it applies from the next start.
+pub fn import(path: &Path) -> Result<(Config, Vec<Problem>), SettingsError> {
+    let (config, problems) = from_text(&fs::read_to_string(path)?)?;
+    for problem in &problems {
+        log::warn!("{}: {}", path.display(), problem);
+    }
+    let config: Config = with_secrets(&config, Some(&Config::load()))?.try_into()
+        .map_err(|e: toml::de::Error| SettingsError::Format(e.to_string()))?;
+    config.save()?;
+    log::info!("Imported the settings from {}", path.display());
+    Ok((config, problems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let mut config = Config { privacy: true, ..Config::default() };
+        config.api.token = Some("secret".to_string());
+        config.daily_limit.limit_minutes = Some(600);
+        let text = to_text(&config).unwrap();
+        assert!(!text.contains("secret"));
+        let (read, problems) = from_text(&text).unwrap();
+        assert!(problems.is_empty());
+        assert!(read.privacy);
+        assert_eq!(read.daily_limit.limit_minutes, Some(600));
+
+        // Settings only a newer pauza knows are skipped, a newer layout isn't read at all.
+        let newer = text.replace("privacy = true", "privacy = true\nfuture_setting = 1");
+        assert_eq!(from_text(&newer).unwrap().1.len(), 1);
+        let newer = text.replace("version = 1", "version = 2");
+        assert!(matches!(from_text(&newer), Err(SettingsError::TooNew(2))));
+    }
+
+    #[test]
+    fn keeps_this_machines_secrets() {
+        let mut imported = Config::default();
+        imported.mqtt.password = Some("theirs".to_string());
+        let mut current = Config::default();
+        current.mqtt.password = Some("mine".to_string());
+        current.webhooks.urls = vec!["https://example.com/hook".to_string()];
+        let config: Config = with_secrets(&imported, Some(&current)).unwrap().try_into().unwrap();
+        assert_eq!(config.mqtt.password.as_deref(), Some("mine"));
+        assert_eq!(config.webhooks.urls, current.webhooks.urls);
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use crate::clock::{Clock, SimulatedClock};
+use crate::config::{Config, GamingMode};
+use crate::format;
+use crate::history::IntervalKind;
+use crate::idle::IdleSource;
+use crate::machine::{BreakStateMachine, Conditions, Surroundings};
+use crate::{Event, Request, POLL_INTERVAL};
+
+/// The day simulated when no script is given.
+pub const WORKDAY: &str = "\
+# One activity per line with how long it lasts: work, idle, lock, sleep, call,
+# fullscreen, game, or app NAME for time spent in that program.
+work 1h
+idle 2m
+work 40m
+call 30m
+work 20m
+lock 45m
+work 1h30m
+idle 10m
+work 1h
+fullscreen 30m
+work 30m
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Activity {
+    Work,
+    Idle,
+    Lock,
+    Sleep,
+    Call,
+    Fullscreen,
+    Game,
+    App(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub activity: Activity,
+    pub duration: Duration,
+}
+
+pub fn parse(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (activity, duration) = match words.as_slice() {
+            ["app", name, duration] => (Activity::App(name.to_string()), duration),
+            [activity, duration] => (match *activity {
+                "work" => Activity::Work,
+                "idle" => Activity::Idle,
+                "lock" => Activity::Lock,
+                "sleep" => Activity::Sleep,
+                "call" => Activity::Call,
+                "fullscreen" => Activity::Fullscreen,
+                "game" => Activity::Game,
+                _ => return Err(format!("line {}: unknown activity: {}", number + 1, activity))
+            }, duration),
+            _ => return Err(format!("line {}: expected an activity and a duration: {}", number + 1, line))
+        };
+        let duration = format::parse_duration(duration)
+            .ok_or_else(|| format!("line {}: invalid duration: {}", number + 1, duration))?;
+        steps.push(Step { activity, duration });
+    }
+    Ok(steps)
+}
+
+/// The steps of a script laid out in time, from `start`.
+pub struct Timeline {
+    steps: Vec<Step>,
+    start: Instant,
+}
+
+impl Timeline {
+    pub fn new(steps: Vec<Step>, start: Instant) -> Timeline {
+        Timeline { steps, start }
+    }
+
+    /// The step under way at `now`, and when it started.
+    fn at(&self, now: Instant) -> Option<(usize, Instant)> {
+        let mut start = self.start;
+        for (index, step) in self.steps.iter().enumerate() {
+            if now < start + step.duration {
+                return if now >= start { Some((index, start)) } else { None };
+            }
+            start += step.duration;
+        }
+        None
+    }
+
+    pub fn activity(&self, now: Instant) -> Option<&Activity> {
+        self.at(now).map(|(index, _)| &self.steps[index].activity)
+    }
+}
+
+impl IdleSource for Timeline {
+    fn idle_time(&mut self, now: Instant) -> Result<Duration, i32> {
+        let (mut index, mut start) = match self.at(now) {
+            Some((index, start)) if self.steps[index].activity == Activity::Idle => (index, start),
+            _ => return Ok(Duration::from_secs(0))
+        };
+        while index > 0 && self.steps[index - 1].activity == Activity::Idle {
+            index -= 1;
+            start -= self.steps[index].duration;
+        }
+        Ok(now - start)
+    }
+}
+
+/// Runs the break timer through the steps as fast as it can, with the given config,
+/// returning the events with how far into the script they happened.
+pub fn simulate(config: &Config, steps: Vec<Step>) -> Vec<(Duration, Event)> {
+    let clock = SimulatedClock::new(SystemTime::now());
+    let mut timeline = Timeline::new(steps, clock.now());
+    let mut machine = BreakStateMachine::new(Duration::from_secs(0), false, clock.now(), clock.wall());
+    machine.set_reset_policy(config.idle.clone());
+    machine.set_reminders(config.reminders.clone());
+    let mut events = Vec::new();
+    let mut away: Option<Request> = None;
+    let mut in_call = false;
+    let mut call_ended: Option<Instant> = None;
+    let gaming_mode = config.gaming.mode != GamingMode::Normal;
+    loop {
+        clock.sleep(POLL_INTERVAL);
+        let now = clock.now();
+        machine.set_wall(now, clock.wall());
+        let activity = match timeline.activity(now) {
+            Some(activity) => activity.clone(),
+            None => break
+        };
+        let mut happened = Vec::new();
+        let leave = match activity {
+            Activity::Lock => Some(Request::Lock),
+            Activity::Sleep => Some(Request::Suspend),
+            _ => None
+        };
+        if leave != away {
+            match away {
+                Some(Request::Lock) => happened.extend(machine.handle(Request::Unlock, now)),
+                Some(_) => happened.extend(machine.handle(Request::Resume, now)),
+                None => {}
+            }
+            if let Some(request) = leave {
+                happened.extend(machine.handle(request, now));
+            }
+            away = leave;
+        }
+        if in_call && activity != Activity::Call {
+            call_ended = Some(now);
+        }
+        in_call = activity == Activity::Call;
+        let app = match &activity {
+            Activity::App(name) => Some(name.as_str()),
+            _ => None
+        };
+        machine.set_conditions(Conditions::new(config, &Surroundings {
+            fullscreen: matches!(activity, Activity::Fullscreen | Activity::Game),
+            gaming: gaming_mode && (activity == Activity::Game || app.is_some_and(|name| config.gaming.is_game(name))),
+            quiet_process: app.filter(|name| config.processes.is_quiet(name)).map(str::to_string),
+            in_call,
+            since_call: call_ended.map(|ended| now - ended),
+            sound_playing: false,
+        }));
+        let idle = timeline.idle_time(now).unwrap_or_default();
+        happened.extend(machine.step(idle, now));
+        events.extend(happened.into_iter().map(|event| (clock.elapsed(), event)));
+    }
+    events
+}
+
+fn describe(event: &Event) -> Option<String> {
+    match event {
+        Event::UpdateState(state) => Some(format!("now {}", state.as_str().replace('_', " "))),
+        Event::NotifyBreak => Some("break reminder".to_string()),
+        Event::RemindAgain(repeats) => Some(format!("break reminder, repeat {}", repeats)),
+        Event::NotifyReset => Some("timer reset".to_string()),
+        Event::BreakAcknowledged => Some("break acknowledged".to_string()),
+        Event::Interval(interval) => match interval.kind {
+            IntervalKind::Work => Some(format!("worked {}", format::hms(interval.duration))),
+            IntervalKind::Break => Some(format!("rested {}", format::hms(interval.duration))),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Simulates the script at `path`, or a made up workday, with the current config and
+/// prints what the timer would do. Returns the process exit code.
+pub fn run(path: Option<&Path>) -> i32 {
+    let text = match path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("ERROR: {}: {}", path.display(), e);
+                return 1;
+            }
+        },
+        None => WORKDAY.to_string()
+    };
+    let steps = match parse(&text) {
+        Ok(steps) => steps,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    let events = simulate(&Config::load(), steps);
+    for (at, event) in &events {
+        if let Some(text) = describe(event) {
+            println!("{}  {}", format::hms(*at), text);
+        }
+    }
+    let reminders = events.iter().filter(|(_, event)| *event == Event::NotifyBreak).count();
+    let resets = events.iter().filter(|(_, event)| *event == Event::NotifyReset).count();
+    println!("{} break reminders, {} resets", reminders, resets);
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BREAK_TIME;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn reminders(config: &Config, script: &str) -> Vec<Duration> {
+        simulate(config, parse(script).unwrap()).into_iter()
+            .filter(|(_, event)| *event == Event::NotifyBreak)
+            .map(|(at, _)| at)
+            .collect()
+    }
+
+    fn resets(config: &Config, script: &str) -> usize {
+        simulate(config, parse(script).unwrap()).iter()
+            .filter(|(_, event)| *event == Event::NotifyReset)
+            .count()
+    }
+
+    #[test]
+    fn parses_scripts() {
+        assert_eq!(parse("# a comment\nwork 1h30m\n\nidle 5 # minutes\napp code.exe 90s").unwrap(), vec![
+            Step { activity: Activity::Work, duration: minutes(90) },
+            Step { activity: Activity::Idle, duration: minutes(5) },
+            Step { activity: Activity::App("code.exe".to_string()), duration: Duration::from_secs(90) },
+        ]);
+        assert_eq!(parse("work 1h\nnap 20m"), Err("line 2: unknown activity: nap".to_string()));
+        assert_eq!(parse("work"), Err("line 1: expected an activity and a duration: work".to_string()));
+        assert_eq!(parse("work soon"), Err("line 1: invalid duration: soon".to_string()));
+        assert!(parse(WORKDAY).is_ok());
+    }
+
+    #[test]
+    fn idle_time_runs_across_idle_steps() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(parse("work 1m\nidle 1m\nidle 1m\nwork 1m").unwrap(), start);
+        assert_eq!(timeline.idle_time(start + Duration::from_secs(30)), Ok(Duration::from_secs(0)));
+        assert_eq!(timeline.idle_time(start + minutes(2) + Duration::from_secs(30)), Ok(Duration::from_secs(90)));
+        assert_eq!(timeline.idle_time(start + minutes(3)), Ok(Duration::from_secs(0)));
+        assert_eq!(timeline.activity(start + minutes(4)), None);
+    }
+
+    #[test]
+    fn reminds_when_the_break_is_due() {
+        assert_eq!(reminders(&Config::default(), "work 50m"), vec![BREAK_TIME]);
+    }
+
+    #[test]
+    fn long_lock_resets() {
+        let config = Config::default();
+        assert_eq!(resets(&config, "work 30m\nlock 10m\nwork 30m"), 1);
+        assert!(reminders(&config, "work 30m\nlock 10m\nwork 30m").is_empty());
+        assert_eq!(resets(&config, "work 30m\nlock 2m\nwork 30m"), 0);
+        assert_eq!(reminders(&config, "work 30m\nlock 2m\nwork 30m").len(), 1);
+    }
+
+    #[test]
+    fn long_idle_resets() {
+        let config = Config::default();
+        assert_eq!(resets(&config, "work 30m\nidle 10m\nwork 30m"), 1);
+        assert_eq!(resets(&config, "work 30m\nidle 3m\nwork 30m"), 0);
+    }
+
+    #[test]
+    fn calls_hold_the_reminder_back() {
+        let mut config = Config::default();
+        assert_eq!(reminders(&config, "work 40m\ncall 30m\nwork 10m"), vec![minutes(70)]);
+        config.meetings.extra_minutes = 5;
+        assert_eq!(reminders(&config, "work 40m\ncall 30m\nwork 10m"), vec![minutes(75)]);
+        config.meetings.defer_notifications = false;
+        assert_eq!(reminders(&config, "work 40m\ncall 30m\nwork 10m"), vec![BREAK_TIME]);
+    }
+
+    #[test]
+    fn configured_apps_pause_the_timer() {
+        let mut config = Config::default();
+        config.processes.pause = vec!["movie.exe".to_string()];
+        // The poll that notices the app still counts as work.
+        assert_eq!(reminders(&config, "work 30m\napp movie.exe 1h\nwork 30m"), vec![minutes(105) - POLL_INTERVAL]);
+        assert_eq!(reminders(&config, "work 30m\napp code.exe 1h\nwork 30m"), vec![BREAK_TIME]);
+    }
+
+    #[test]
+    fn gaming_follows_the_mode() {
+        let mut config = Config::default();
+        assert_eq!(reminders(&config, "work 30m\ngame 1h\nwork 10m"), vec![minutes(90)]);
+        config.gaming.mode = GamingMode::Pause;
+        assert!(reminders(&config, "work 30m\ngame 1h\nwork 10m").is_empty());
+        config.gaming.mode = GamingMode::Normal;
+        config.fullscreen.defer_notifications = false;
+        assert_eq!(reminders(&config, "work 30m\ngame 1h\nwork 10m"), vec![BREAK_TIME]);
+    }
+}
@@ -0,0 +1,46 @@
+use crossbeam::channel::Receiver;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::{format, Event, State, BREAK_TIME};
+
+/// The timer as other processes see it, kept up to date from the monitor's events.
+#[derive(Clone, Debug, Serialize)]
+pub struct Status {
+    pub state: &'static str,
+    pub elapsed: u64,
+    pub remaining: u64,
+    /// The timer as the window shows it, counting down or up.
+    pub text: String,
+}
+
+impl Default for Status {
+    fn default() -> Status {
+        Status {
+            state: State::Working.as_str(),
+            elapsed: 0,
+            remaining: BREAK_TIME.as_secs(),
+            text: format::timer(Duration::ZERO),
+        }
+    }
+}
+
+impl Status {
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::UpdateTime(elapsed) => {
+                self.elapsed = elapsed.as_secs();
+                self.remaining = BREAK_TIME.saturating_sub(*elapsed).as_secs();
+                self.text = format::timer(*elapsed);
+            },
+            Event::UpdateState(state) => self.state = state.as_str(),
+            _ => {}
+        }
+    }
+}
+
+pub fn track(r: Receiver<Event>, status: Arc<Mutex<Status>>) {
+    for event in r.iter() {
+        status.lock().unwrap().apply(&event);
+    }
+}
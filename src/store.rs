@@ -0,0 +1,492 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use rusqlite::backup::Backup;
+use rusqlite::serialize::OwnedData;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroU32;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use crate::config::Encryption;
+use crate::privacy;
+
+/// Starts encrypted files, followed by the nonce and the AES-256-GCM ciphertext.
+const MAGIC: &[u8] = b"PAUZAENC1";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 600_000;
+const PASSPHRASE_VAR: &str = "PAUZA_PASSPHRASE";
+/// Where the passphrase is kept in the Windows credential store.
+pub const PASSPHRASE_CREDENTIAL: &str = "pauza/passphrase";
+/// The decrypted history, shared by every connection in the process.
+const MEMORY_DB: &str = "file:/pauza-history?vfs=memdb";
+/// A guest's history, gone when pauza quits.
+const GUEST_DB: &str = "file:/pauza-guest?vfs=memdb";
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// The first byte of the key file: the key itself protected with DPAPI follows, or the
+/// salt to derive it from the passphrase with.
+const DPAPI_KEY: u8 = b'D';
+const PASSPHRASE_KEY: u8 = b'P';
+
+static ENCRYPTION: OnceLock<Encryption> = OnceLock::new();
+static STORE: OnceLock<Result<Mutex<Store>, String>> = OnceLock::new();
+/// Keeps the guest's history alive between the connections to it.
+static GUEST: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Key(String),
+    /// The key is wrong, or the file was damaged or isn't encrypted.
+    Decrypt,
+    History(rusqlite::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{}", e),
+            StoreError::Key(e) => write!(f, "{}", e),
+            StoreError::Decrypt => write!(f, "Failed to decrypt, the key is wrong or the file is damaged"),
+            StoreError::History(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> StoreError {
+        StoreError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> StoreError {
+        StoreError::History(e)
+    }
+}
+
+impl From<StoreError> for rusqlite::Error {
+    fn from(e: StoreError) -> rusqlite::Error {
+        match e {
+            StoreError::History(e) => e,
+            // Shown as the error itself, like any boxed error.
+            e => rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+        }
+    }
+}
+
+#[cfg(windows)]
+use crate::windows::{protect, read_credential, unprotect};
+
+#[cfg(not(windows))]
+fn protect(_data: &[u8]) -> Result<Vec<u8>, String> {
+    Err("DPAPI needs Windows".to_string())
+}
+
+#[cfg(not(windows))]
+fn unprotect(_data: &[u8]) -> Result<Vec<u8>, String> {
+    Err("DPAPI needs Windows".to_string())
+}
+
+#[cfg(not(windows))]
+fn read_credential(_target: &str) -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+/// Encrypts the history and exports from now on, before anything opens the history.
+pub fn set_encryption(encryption: Encryption) {
+    let _ = ENCRYPTION.set(encryption);
+}
+
+pub fn encryption() -> Encryption {
+    ENCRYPTION.get().copied().unwrap_or_default()
+}
+
+fn random(bytes: &mut [u8]) -> Result<(), StoreError> {
+    getrandom::getrandom(bytes).map_err(|e| StoreError::Key(e.to_string()))
+}
+
+pub fn encrypt(key: &[u8; KEY_LEN], plain: &[u8]) -> Result<Vec<u8>, StoreError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    random(&mut nonce)?;
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).map_err(|_| StoreError::Decrypt)?);
+    let mut sealed = plain.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(MAGIC), &mut sealed)
+        .map_err(|_| StoreError::Key("Failed to encrypt".to_string()))?;
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&nonce);
+    data.extend(sealed);
+    Ok(data)
+}
+
+pub fn decrypt(key: &[u8; KEY_LEN], data: &[u8]) -> Result<Vec<u8>, StoreError> {
+    let data = data.strip_prefix(MAGIC).ok_or(StoreError::Decrypt)?;
+    if data.len() < NONCE_LEN {
+        return Err(StoreError::Decrypt);
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| StoreError::Decrypt)?;
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).map_err(|_| StoreError::Decrypt)?);
+    let mut sealed = sealed.to_vec();
+    let plain = key.open_in_place(nonce, Aad::from(MAGIC), &mut sealed).map_err(|_| StoreError::Decrypt)?;
+    Ok(plain.to_vec())
+}
+
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    let rounds = NonZeroU32::new(PBKDF2_ROUNDS).unwrap();
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, rounds, salt, passphrase.as_bytes(), &mut key);
+    key
+}
+
+/// The passphrase from the environment, for scripts, or else from the credential store.
+fn passphrase() -> Result<String, StoreError> {
+    if let Some(passphrase) = env::var(PASSPHRASE_VAR).ok().filter(|passphrase| !passphrase.is_empty()) {
+        return Ok(passphrase);
+    }
+    read_credential(PASSPHRASE_CREDENTIAL)
+        .map_err(StoreError::Key)?
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or_else(|| StoreError::Key(format!("No passphrase: set storage.passphrase or {}", PASSPHRASE_VAR)))
+}
+
+pub fn key_path(path: &Path) -> PathBuf {
+    path.with_extension("key")
+}
+
+/// A new key waits here until the history is encrypted with it, so the key in use always
+/// opens the history, whenever pauza stops.
+fn pending_key_path(path: &Path) -> PathBuf {
+    path.with_extension("key.new")
+}
+
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    path.with_extension("db.enc")
+}
+
+/// Writes `data` to a file beside `path` first, so `path` has either all of the old or all
+/// of the new.
+fn write_replacing(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, data)?;
+    fs::rename(&temporary, path)
+}
+
+fn read_key_file(file: &Path) -> Result<[u8; KEY_LEN], StoreError> {
+    let data = fs::read(file)?;
+    let key = match data.split_first() {
+        Some((&DPAPI_KEY, protected)) => unprotect(protected).map_err(StoreError::Key)?,
+        Some((&PASSPHRASE_KEY, salt)) => derive_key(&passphrase()?, salt).to_vec(),
+        _ => return Err(StoreError::Key(format!("{} is damaged", file.display())))
+    };
+    key.try_into().map_err(|_| StoreError::Key(format!("{} is damaged", file.display())))
+}
+
+fn read_key(path: &Path) -> Result<[u8; KEY_LEN], StoreError> {
+    read_key_file(&key_path(path))
+}
+
+/// The encrypted history at `path` decrypted, with its key. If pauza stopped while switching
+/// keys, the switch is finished when the history is already under the new key, and
+/// dropped when it isn't.
+fn read_encrypted(path: &Path) -> Result<([u8; KEY_LEN], Vec<u8>), StoreError> {
+    let data = fs::read(encrypted_path(path))?;
+    let pending = pending_key_path(path);
+    if pending.exists() {
+        if let Some((key, plain)) = read_key_file(&pending).ok().and_then(|key| Some((key, decrypt(&key, &data).ok()?))) {
+            log::info!("Finishing the switch to a new key");
+            fs::rename(&pending, key_path(path))?;
+            return Ok((key, plain));
+        }
+    }
+    let key = read_key(path)?;
+    let plain = decrypt(&key, &data)?;
+    if pending.exists() {
+        fs::remove_file(&pending)?;
+    }
+    Ok((key, plain))
+}
+
+/// A new key of the kind `encryption` asks for, with the key file for it.
+fn new_key(encryption: Encryption) -> Result<([u8; KEY_LEN], Vec<u8>), StoreError> {
+    let (key, file) = match encryption {
+        Encryption::Passphrase => {
+            let mut salt = [0u8; SALT_LEN];
+            random(&mut salt)?;
+            let key = derive_key(&passphrase()?, &salt);
+            (key, [&[PASSPHRASE_KEY], &salt[..]].concat())
+        },
+        _ => {
+            let mut key = [0u8; KEY_LEN];
+            random(&mut key)?;
+            let protected = protect(&key).map_err(StoreError::Key)?;
+            (key, [&[DPAPI_KEY], &protected[..]].concat())
+        }
+    };
+    Ok((key, file))
+}
+
+fn key_kind(path: &Path) -> Option<Encryption> {
+    match fs::read(key_path(path)).ok()?.first() {
+        Some(&DPAPI_KEY) => Some(Encryption::Dpapi),
+        Some(&PASSPHRASE_KEY) => Some(Encryption::Passphrase),
+        _ => None
+    }
+}
+
+/// Checks that the history at `path` opens on this account, as before restoring it: a key
+/// protected with DPAPI opens only for the Windows account that made it, and one from a
+/// passphrase only with that passphrase.
+pub fn check(path: &Path) -> Result<(), StoreError> {
+    let encrypted = encrypted_path(path);
+    if encrypted.exists() {
+        let key = read_key(path).map_err(|e| match key_kind(path) {
+            Some(Encryption::Dpapi) => StoreError::Key(format!("The history is encrypted for another Windows account: {}", e)),
+            _ => e
+        })?;
+        decrypt(&key, &fs::read(encrypted)?)?;
+    }
+    if path.exists() {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let result: String = connection.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if result != "ok" {
+            return Err(StoreError::Key(format!("{} is damaged: {}", path.display(), result)));
+        }
+    }
+    Ok(())
+}
+
+/// Loads a serialized database into `into`.
+fn restore(plain: &[u8], into: &mut Connection) -> Result<(), StoreError> {
+    let mut loaded = Connection::open_in_memory()?;
+    let data = unsafe {
+        let ptr = rusqlite::ffi::sqlite3_malloc64(plain.len().max(1) as u64) as *mut u8;
+        let ptr = NonNull::new(ptr).ok_or_else(|| StoreError::Key("Out of memory".to_string()))?;
+        ptr.as_ptr().copy_from_nonoverlapping(plain.as_ptr(), plain.len());
+        OwnedData::from_raw_nonnull(ptr, plain.len())
+    };
+    loaded.deserialize(DatabaseName::Main, data, false)?;
+    copy(&loaded, into)
+}
+
+fn copy(from: &Connection, into: &mut Connection) -> Result<(), StoreError> {
+    Backup::new(from, into)?.run_to_completion(256, Duration::from_millis(0), None)?;
+    Ok(())
+}
+
+/// The decrypted history, written back encrypted now and then.
+struct Store {
+    path: PathBuf,
+    key: [u8; KEY_LEN],
+    keeper: Connection,
+    saved: Instant,
+    changed: bool,
+}
+
+impl Store {
+    fn load(path: &Path, encryption: Encryption) -> Result<Store, StoreError> {
+        let mut keeper = Connection::open(MEMORY_DB)?;
+        let encrypted = encrypted_path(path);
+        let mut changed = false;
+        let mut new_key_file = None;
+        let key = if encrypted.exists() {
+            let (key, plain) = read_encrypted(path)?;
+            restore(&plain, &mut keeper)?;
+            if key_kind(path) == Some(encryption) {
+                key
+            } else {
+                log::info!("Encrypting the history with a {:?} key", encryption);
+                changed = true;
+                let (key, file) = new_key(encryption)?;
+                new_key_file = Some(file);
+                key
+            }
+        } else {
+            let (key, file) = new_key(encryption)?;
+            new_key_file = Some(file);
+            if path.exists() {
+                log::info!("Encrypting the history in {}", path.display());
+                copy(&Connection::open(path)?, &mut keeper)?;
+                changed = true;
+            }
+            key
+        };
+        // The old key stays until the history is saved under the new one.
+        if let Some(file) = &new_key_file {
+            write_replacing(&pending_key_path(path), file)?;
+        }
+        let mut store = Store { path: path.to_path_buf(), key, keeper, saved: Instant::now(), changed };
+        if changed {
+            store.save()?;
+        }
+        if new_key_file.is_some() {
+            fs::rename(pending_key_path(path), key_path(path))?;
+        }
+        if changed && path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(store)
+    }
+
+    fn save(&mut self) -> Result<(), StoreError> {
+        let data = encrypt(&self.key, &self.keeper.serialize(DatabaseName::Main)?)?;
+        write_replacing(&encrypted_path(&self.path), &data)?;
+        self.saved = Instant::now();
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// Puts back the plain history after encryption was turned off.
+fn decrypt_leftover(path: &Path) -> Result<(), StoreError> {
+    let encrypted = encrypted_path(path);
+    if path.exists() || !encrypted.exists() {
+        return Ok(());
+    }
+    log::info!("Decrypting the history to {}", path.display());
+    let (_, data) = read_encrypted(path)?;
+    let mut plain = Connection::open(path)?;
+    restore(&data, &mut plain)?;
+    drop(plain);
+    fs::remove_file(&encrypted)?;
+    fs::remove_file(key_path(path))?;
+    Ok(())
+}
+
+fn store(path: &Path) -> Result<&'static Mutex<Store>, StoreError> {
+    STORE.get_or_init(|| {
+        Store::load(path, encryption()).map(Mutex::new).map_err(|e| {
+            log::error!("Failed to open the encrypted history: {}", e);
+            e.to_string()
+        })
+    }).as_ref().map_err(|e| StoreError::Key(e.clone()))
+}
+
+/// A connection to the history at `path`, decrypted in memory when it is encrypted,
+/// or to a history only in memory in guest mode.
+pub fn open(path: &Path) -> Result<Connection, StoreError> {
+    if privacy::guest() {
+        if GUEST.get().is_none() {
+            let _ = GUEST.set(Mutex::new(Connection::open(GUEST_DB)?));
+        }
+        return Ok(Connection::open(GUEST_DB)?);
+    }
+    if encryption() == Encryption::None {
+        decrypt_leftover(path)?;
+        return Ok(Connection::open(path)?);
+    }
+    store(path)?;
+    Ok(Connection::open(MEMORY_DB)?)
+}
+
+/// Notes that the history changed, saving the encrypted copy if it wasn't for a while.
+pub fn changed() {
+    if let Some(Ok(store)) = STORE.get() {
+        let mut store = store.lock().unwrap();
+        store.changed = true;
+        if store.saved.elapsed() >= SAVE_INTERVAL {
+            if let Err(e) = store.save() {
+                log::error!("Failed to save the encrypted history: {}", e);
+            }
+        }
+    }
+}
+
+/// Saves the encrypted copy of the history, if there's anything new.
+pub fn save() {
+    if let Some(Ok(store)) = STORE.get() {
+        let mut store = store.lock().unwrap();
+        if store.changed {
+            if let Err(e) = store.save() {
+                log::error!("Failed to save the encrypted history: {}", e);
+            }
+        }
+    }
+}
+
+/// Copies the history at `path` into the folder `into` the way it is kept: the plain
+/// database, or the encrypted one and its key.
+pub fn back_up(path: &Path, into: &Path) -> Result<(), StoreError> {
+    let copy_into = |file: &Path| -> Result<(), StoreError> {
+        if let Some(name) = file.file_name() {
+            fs::copy(file, into.join(name))?;
+        }
+        Ok(())
+    };
+    if encryption() == Encryption::None {
+        if path.exists() {
+            // Through SQLite, which copies a consistent database while it is being written.
+            let mut copied = Connection::open(into.join(path.file_name().unwrap_or_default()))?;
+            copy(&Connection::open(path)?, &mut copied)?;
+        }
+        return Ok(());
+    }
+    let encrypted = encrypted_path(path);
+    match STORE.get() {
+        Some(Ok(store)) => {
+            let mut store = store.lock().unwrap();
+            if store.changed {
+                store.save()?;
+            }
+            copy_into(&encrypted)?;
+        },
+        _ if encrypted.exists() => copy_into(&encrypted)?,
+        _ => return Ok(())
+    }
+    copy_into(&key_path(path))
+}
+
+/// Writes `data` to `path`, encrypted with the history's key when the history is encrypted.
+pub fn write_export(history_path: &Path, path: &Path, data: &[u8]) -> Result<(), StoreError> {
+    if encryption() == Encryption::None {
+        fs::write(path, data)?;
+        return Ok(());
+    }
+    let key = store(history_path)?.lock().unwrap().key;
+    fs::write(path, encrypt(&key, data)?)?;
+    Ok(())
+}
+
+/// Reads a file exported while the history was encrypted.
+pub fn read_export(history_path: &Path, path: &Path) -> Result<Vec<u8>, StoreError> {
+    let key = read_key(history_path)?;
+    decrypt(&key, &fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_decrypts() {
+        let key = [1u8; KEY_LEN];
+        let data = encrypt(&key, b"work,2024-01-01").unwrap();
+        assert!(data.starts_with(MAGIC));
+        assert_eq!(decrypt(&key, &data).unwrap(), b"work,2024-01-01");
+        let wrong = [2u8; KEY_LEN];
+        assert!(matches!(decrypt(&wrong, &data), Err(StoreError::Decrypt)));
+        assert!(matches!(decrypt(&key, b"work,2024-01-01"), Err(StoreError::Decrypt)));
+    }
+
+    #[test]
+    fn keeps_the_database_through_encryption() {
+        let plain = Connection::open_in_memory().unwrap();
+        plain.execute_batch("CREATE TABLE t (x); INSERT INTO t VALUES (42);").unwrap();
+        let key = [7u8; KEY_LEN];
+        let data = encrypt(&key, &plain.serialize(DatabaseName::Main).unwrap()).unwrap();
+        plain.execute_batch("DROP TABLE t").unwrap();
+        let mut restored = Connection::open_in_memory().unwrap();
+        restore(&decrypt(&key, &data).unwrap(), &mut restored).unwrap();
+        let x: i64 = restored.query_row("SELECT x FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(x, 42);
+    }
+}
@@ -0,0 +1,229 @@
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tungstenite::handshake::server::{ErrorResponse, Request as HandshakeRequest, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Error, Message, WebSocket};
+use crate::auth;
+use crate::config::ApiConfig;
+use crate::daily::DailyWarning;
+use crate::schema::{self, CommandMessage, StateMessage};
+use crate::status::Status;
+use crate::{Event, Request};
+
+/// Messages a client may fall behind by before it is dropped. It can reconnect and start
+/// again from a fresh status message.
+const CLIENT_QUEUE: usize = 64;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+type Clients = Arc<Mutex<Vec<Sender<String>>>>;
+
+fn event_json(event: &Event) -> Value {
+    let mut message = match event {
+        Event::UpdateTime(elapsed) => json!({ "type": "time", "elapsed": elapsed.as_secs() }),
+        Event::UpdateState(state) => json!({ "type": "state", "state": state.as_str() }),
+        Event::NotifyBreak => json!({ "type": "break_prompt" }),
+        Event::RemindAgain(repeats) => json!({ "type": "break_reminder", "repeats": repeats }),
+        Event::NotifyReset => json!({ "type": "reset" }),
+        Event::BreakAcknowledged => json!({ "type": "break_acknowledged" }),
+        Event::Interval(interval) => json!({
+            "type": "interval",
+            "kind": interval.kind.as_str(),
+            "start": interval.start.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            "duration": interval.duration.as_secs(),
+            "cause": interval.cause.map(|cause| cause.as_str()),
+            "quality": interval.quality,
+        }),
+        Event::LowPower(low_power) => json!({ "type": "low_power", "low_power": low_power }),
+        Event::Quiet(reason) => json!({ "type": "quiet", "reason": reason }),
+        Event::Degraded(degraded) => json!({ "type": "degraded", "degraded": degraded }),
+        Event::DailyLimit(warning) => match warning {
+            DailyWarning::Approaching(left) => json!({ "type": "daily_limit", "level": "approaching", "left": left.as_secs() }),
+            DailyWarning::Reached(limit) => json!({ "type": "daily_limit", "level": "reached", "limit": limit.as_secs() }),
+            DailyWarning::Over(over) => json!({ "type": "daily_limit", "level": "over", "over": over.as_secs() })
+        },
+        Event::UndoAvailable(available) => json!({ "type": "undo_available", "available": available }),
+        Event::ResetUndone(since) => json!({
+            "type": "reset_undone",
+            "since": since.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        })
+    };
+    message["version"] = json!(schema::VERSION);
+    message
+}
+
+fn status_json(status: &Status) -> String {
+    json!({ "version": schema::VERSION, "type": "status", "status": StateMessage::new(status) }).to_string()
+}
+
+/// Queues the message for every client, dropping those that went away or can't keep up.
+fn publish(clients: &Clients, message: String) {
+    clients.lock().unwrap().retain(|client| match client.try_send(message.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            log::warn!("Dropping an event stream client that fell behind");
+            false
+        },
+        Err(TrySendError::Disconnected(_)) => false
+    });
+}
+
+fn is_authorized(request: &HandshakeRequest, token: &str) -> bool {
+    let header = request.headers().get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| auth::is_bearer(value, token));
+    let query = request.uri().query().unwrap_or("").split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .any(|(key, value)| key == "token" && auth::is_token(value, token));
+    header || query
+}
+
+fn unauthorized() -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some("missing or wrong token".to_string()));
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+    response
+}
+
+fn is_timeout(e: &Error) -> bool {
+    match e {
+        Error::Io(e) => e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut,
+        _ => false
+    }
+}
+
+/// Runs a command a client sent, answering with whether it was understood.
+fn command_result(text: &str, requests: &Sender<Request>) -> String {
+    let result = match CommandMessage::parse(text) {
+        Ok(command) => {
+            log::info!("Event stream command: {:?}", command);
+            requests.send(command.request()).unwrap();
+            json!({ "version": schema::VERSION, "type": "result", "ok": true })
+        },
+        Err(e) => json!({ "version": schema::VERSION, "type": "result", "ok": false, "error": e })
+    };
+    result.to_string()
+}
+
+/// Writes queued messages to the client, pinging it when quiet, until either side hangs up.
+#[allow(clippy::result_large_err)]
+fn run_client(mut socket: WebSocket<TcpStream>, queue: Receiver<String>, requests: &Sender<Request>) -> Result<(), Error> {
+    let mut last_sent = Instant::now();
+    loop {
+        match queue.recv_timeout(POLL_INTERVAL) {
+            Ok(message) => {
+                socket.send(Message::Text(message))?;
+                last_sent = Instant::now();
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                if last_sent.elapsed() >= PING_INTERVAL {
+                    socket.send(Message::Ping(Vec::new()))?;
+                    last_sent = Instant::now();
+                }
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                socket.close(None)?;
+                return socket.flush();
+            }
+        }
+        // Reading also answers pings and notices the client closing.
+        match socket.read() {
+            Ok(Message::Text(text)) => socket.send(Message::Text(command_result(&text, requests)))?,
+            Ok(_) => {},
+            Err(e) if is_timeout(&e) => {},
+            Err(e) => return Err(e)
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn accept(stream: TcpStream, token: &str, status: &Mutex<Status>, clients: &Clients, requests: &Sender<Request>) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    if let Err(e) = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)) {
+        log::warn!("Event stream {}: {}", peer, e);
+        return;
+    }
+    let socket = tungstenite::accept_hdr(stream, |request: &HandshakeRequest, response: Response| {
+        if is_authorized(request, token) { Ok(response) } else { Err(unauthorized()) }
+    });
+    let socket = match socket {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::warn!("Event stream handshake with {} failed: {}", peer, e);
+            return;
+        }
+    };
+    if let Err(e) = socket.get_ref().set_read_timeout(Some(READ_TIMEOUT)) {
+        log::warn!("Event stream {}: {}", peer, e);
+        return;
+    }
+
+    // The status goes first so a client that reconnects catches up without a gap.
+    let (s, queue) = bounded(CLIENT_QUEUE);
+    {
+        let status = status.lock().unwrap();
+        let _ = s.try_send(status_json(&status));
+        clients.lock().unwrap().push(s);
+    }
+    log::info!("Event stream client {} connected", peer);
+    match run_client(socket, queue, requests) {
+        Ok(()) | Err(Error::ConnectionClosed) | Err(Error::AlreadyClosed) => {},
+        Err(e) => log::warn!("Event stream {}: {}", peer, e)
+    }
+    log::info!("Event stream client {} disconnected", peer);
+}
+
+/// Streams every event as JSON to WebSocket clients on localhost, and takes commands
+/// from them, as described in `schema`.
+pub fn serve(config: ApiConfig, r: Receiver<Event>, requests: Sender<Request>) {
+    let token = match config.token {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            log::error!("The event stream needs a token");
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(("127.0.0.1", config.events_port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to start the event stream on port {}: {}", config.events_port, e);
+            return;
+        }
+    };
+    log::info!("Event stream listening on ws://127.0.0.1:{}", config.events_port);
+
+    let status = Arc::new(Mutex::new(Status::default()));
+    let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+    {
+        let status = status.clone();
+        let clients = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let token = token.clone();
+                        let status = status.clone();
+                        let clients = clients.clone();
+                        let requests = requests.clone();
+                        thread::spawn(move || accept(stream, &token, &status, &clients, &requests));
+                    },
+                    Err(e) => log::warn!("Event stream: {}", e)
+                }
+            }
+        });
+    }
+
+    for event in r.iter() {
+        // Holding the status while publishing keeps a new client's status and first event in order.
+        let message = event_json(&event).to_string();
+        let mut current = status.lock().unwrap();
+        current.apply(&event);
+        publish(&clients, message);
+    }
+}
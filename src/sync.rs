@@ -0,0 +1,555 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::{digest, hmac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task, time};
+use crate::config::{data_dir, Config, Encryption, SyncConfig, SyncService, SECRETS};
+use crate::format;
+use crate::history::{History, IdleCause, Interval, IntervalKind};
+use crate::privacy;
+use crate::store;
+
+const STATE_FILE: &str = "sync.json";
+const SETTINGS_FILE: &str = "settings.json";
+const HISTORY_INDEX_FILE: &str = "history.json";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Keeps the first sync out of the way of starting up.
+const FIRST_SYNC_DELAY: Duration = Duration::from_secs(30);
+/// Times a shared file is read again when another machine wrote it in between.
+const WRITE_ATTEMPTS: usize = 3;
+
+/// A value and when it was last changed, in seconds since the epoch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    value: Value,
+    modified: i64,
+}
+
+type Entries = BTreeMap<String, Entry>;
+
+/// What this machine knew at the end of its last sync.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SyncState {
+    /// The settings as they were synced, to tell which of them changed here since.
+    settings: Option<Entries>,
+    /// The last interval recorded here that was shared.
+    uploaded_id: i64,
+    /// When the history of each other machine and month was last read, by `machine/month`.
+    downloaded: BTreeMap<String, i64>,
+}
+
+impl SyncState {
+    fn save(&self) -> io::Result<()> {
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+        let text = serde_json::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(STATE_FILE), text)
+    }
+
+    fn load() -> SyncState {
+        fs::read_to_string(data_dir().join(STATE_FILE)).ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// A synced interval, with its time in seconds since the epoch.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SyncedInterval {
+    kind: String,
+    started_at: i64,
+    duration: u64,
+    cause: Option<String>,
+    quality: Option<u8>,
+}
+
+impl SyncedInterval {
+    fn new(interval: &Interval) -> SyncedInterval {
+        SyncedInterval {
+            kind: interval.kind.as_str().to_string(),
+            started_at: unix_secs(interval.start),
+            duration: interval.duration.as_secs(),
+            cause: interval.cause.map(|cause| cause.as_str().to_string()),
+            quality: interval.quality,
+        }
+    }
+
+    fn interval(&self) -> Option<Interval> {
+        Some(Interval {
+            kind: IntervalKind::parse(&self.kind)?,
+            start: UNIX_EPOCH + Duration::from_secs(self.started_at.max(0) as u64),
+            duration: Duration::from_secs(self.duration),
+            cause: self.cause.as_deref().and_then(IdleCause::parse),
+            quality: self.quality,
+        })
+    }
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// Settings that stay on each machine: how it syncs with, reports to and hands the timer
+/// over to other machines, where its windows are, paths to its files, and whether it runs
+/// for guests. The secrets of its accounts stay too, so they never reach the sync server.
+fn is_local(key: &str) -> bool {
+    const SECTIONS: &[&str] = &["sync.", "devices.", "handoff.", "backup.", "email."];
+    const KEYS: &[&str] = &[
+        "guest", "icon_file", "tips.file",
+        "window.x", "window.y", "window.width", "window.height", "mini.x", "mini.y",
+    ];
+    SECTIONS.iter().any(|section| key.starts_with(section))
+        || KEYS.contains(&key)
+        || SECRETS.contains(&key)
+        || (key.starts_with("sounds.") && key.ends_with(".file"))
+}
+
+/// The settings in `value` by dotted key, like `report.hour`. Lists are kept whole.
+fn flatten(prefix: &str, value: &toml::Value, entries: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, entries);
+            }
+        },
+        _ => {
+            entries.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// The settings of `config` shared with other machines.
+fn settings_of(config: &Config) -> Result<BTreeMap<String, Value>, String> {
+    let mut settings = BTreeMap::new();
+    flatten("", &toml::Value::try_from(config).map_err(|e| e.to_string())?, &mut settings);
+    settings.into_iter()
+        .filter(|(key, _)| !is_local(key))
+        .map(|(key, value)| serde_json::to_value(value).map(|value| (key, value)).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Puts `value` at its dotted `key` in `table`.
+fn unflatten(table: &mut toml::value::Table, key: &str, value: toml::Value) {
+    match key.split_once('.') {
+        Some((first, rest)) => {
+            let inner = table.entry(first.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+            if let toml::Value::Table(inner) = inner {
+                unflatten(inner, rest, value);
+            }
+        },
+        None => {
+            table.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// The entries for `settings`, dated `now` where they differ from the last sync, with a null
+/// value for a setting gone back to its default. Before the first sync, nothing counts as
+/// changed here, so the shared settings win.
+fn changed_entries(settings: &BTreeMap<String, Value>, last: Option<&Entries>, now: i64) -> Entries {
+    let empty = Entries::new();
+    let previous = last.unwrap_or(&empty);
+    let modified = if last.is_some() { now } else { 0 };
+    let keys: BTreeSet<&String> = settings.keys().chain(previous.keys()).collect();
+    keys.into_iter()
+        .map(|key| {
+            let value = settings.get(key).cloned().unwrap_or(Value::Null);
+            let modified = match previous.get(key) {
+                Some(old) if old.value == value => old.modified,
+                _ => modified
+            };
+            (key.clone(), Entry { value, modified })
+        })
+        .collect()
+}
+
+/// Each key with the later of its two entries, the shared one when both are as old.
+/// Settings that are local are dropped, so those an older version shared are taken back.
+fn merge(local: &Entries, shared: &Entries) -> Entries {
+    let mut merged = shared.clone();
+    merged.retain(|key, _| !is_local(key));
+    for (key, entry) in local {
+        if merged.get(key).is_none_or(|other| entry.modified > other.modified) {
+            merged.insert(key.clone(), entry.clone());
+        }
+    }
+    merged
+}
+
+/// The config with `entries` in place of the shared settings of `config`.
+fn apply(config: &Config, entries: &Entries) -> Result<Config, String> {
+    let mut current = BTreeMap::new();
+    flatten("", &toml::Value::try_from(config).map_err(|e| e.to_string())?, &mut current);
+    let mut table = toml::value::Table::new();
+    for (key, value) in current.into_iter().filter(|(key, _)| is_local(key)) {
+        unflatten(&mut table, &key, value);
+    }
+    for (key, entry) in entries.iter().filter(|(key, entry)| !entry.value.is_null() && !is_local(key)) {
+        match toml::Value::try_from(&entry.value) {
+            Ok(value) => unflatten(&mut table, key, value),
+            Err(e) => log::warn!("Failed to sync {}: {}", key, e)
+        }
+    }
+    let text = toml::to_string(&toml::Value::Table(table)).map_err(|e| e.to_string())?;
+    let (config, problems) = Config::from_text(&text);
+    for problem in problems {
+        log::warn!("Synced settings: {}", problem);
+    }
+    Ok(config)
+}
+
+/// A shared file of entries.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SharedEntries {
+    entries: Entries,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The folder files are synced through.
+struct Remote {
+    config: SyncConfig,
+}
+
+impl Remote {
+    /// Refuses to send the credentials over plain HTTP, as email refuses without TLS.
+    fn new(config: &SyncConfig) -> Result<Remote, String> {
+        if config.is_unencrypted() {
+            return Err("Not sending the credentials without TLS: sync.url needs to be https://".to_string());
+        }
+        Ok(Remote { config: config.clone() })
+    }
+
+    fn url(&self, name: &str) -> String {
+        format!("{}/{}", self.config.url.trim_end_matches('/'), name)
+    }
+
+    fn request(&self, method: &str, name: &str) -> ureq::Request {
+        let url = self.url(name);
+        let request = ureq::request(method, &url).timeout(TIMEOUT);
+        let username = self.config.username.as_deref().unwrap_or("");
+        let password = self.config.password.as_deref().unwrap_or("");
+        match self.config.service {
+            SyncService::WebDav if !username.is_empty() => {
+                let credentials = STANDARD.encode(format!("{}:{}", username, password));
+                request.set("Authorization", &format!("Basic {}", credentials))
+            },
+            SyncService::WebDav => request,
+            SyncService::S3 => self.sign(request, method, &url, username, password)
+        }
+    }
+
+    /// Signs an S3 request with AWS signature version 4, leaving the body unsigned.
+    fn sign(&self, request: ureq::Request, method: &str, url: &str, key_id: &str, secret: &str) -> ureq::Request {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let (host, path) = rest.split_once('/').map_or((rest, String::new()), |(host, path)| (host, format!("/{}", path)));
+        let now = format::rfc3339(unix_secs(SystemTime::now())).replace(['-', ':'], "");
+        let date = &now[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let canonical = format!("{}\n{}\n\nhost:{}\nx-amz-content-sha256:UNSIGNED-PAYLOAD\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\nUNSIGNED-PAYLOAD",
+                                method, if path.is_empty() { "/" } else { &path }, host, now);
+        let to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", now, scope, hex(digest::digest(&digest::SHA256, canonical.as_bytes()).as_ref()));
+        let mut key = format!("AWS4{}", secret).into_bytes();
+        for part in [date, &self.config.region, "s3", "aws4_request"] {
+            key = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes()).as_ref().to_vec();
+        }
+        let signature = hex(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), to_sign.as_bytes()).as_ref());
+        request
+            .set("x-amz-date", &now)
+            .set("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+            .set("Authorization", &format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                                           key_id, scope, signature))
+    }
+
+    /// The file called `name` and its ETag, nothing when there is no such file yet.
+    fn get(&self, name: &str) -> Result<Option<(String, Option<String>)>, String> {
+        match self.request("GET", name).call() {
+            Ok(response) => {
+                let etag = response.header("ETag").map(str::to_string);
+                let text = response.into_string().map_err(|e| e.to_string())?;
+                Ok(Some((text, etag)))
+            },
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(e.to_string())
+        }
+    }
+
+    /// Writes the file called `name`, unless it changed since it had `etag`, or was created
+    /// when it had none; returns whether it was written.
+    fn put(&self, name: &str, text: &str, etag: Option<&str>) -> Result<bool, String> {
+        let request = self.request("PUT", name).set("Content-Type", "application/json");
+        let request = match etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*")
+        };
+        match request.send_string(text) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(412, _)) => Ok(false),
+            Err(e) => Err(e.to_string())
+        }
+    }
+
+    /// Merges `local` into the shared entries in the file called `name`, returning them all.
+    fn update(&self, name: &str, local: &Entries) -> Result<Entries, String> {
+        for _ in 0..WRITE_ATTEMPTS {
+            let (shared, etag) = match self.get(name)? {
+                Some((text, etag)) => {
+                    let shared: SharedEntries = serde_json::from_str(&text).map_err(|e| format!("{}: {}", name, e))?;
+                    (shared.entries, etag)
+                },
+                None => (Entries::new(), None)
+            };
+            let merged = merge(local, &shared);
+            if merged == shared && etag.is_some() {
+                return Ok(merged);
+            }
+            let text = serde_json::to_string(&SharedEntries { entries: merged.clone() }).map_err(|e| e.to_string())?;
+            if self.put(name, &text, etag.as_deref())? {
+                return Ok(merged);
+            }
+        }
+        Err(format!("{} kept changing on the server", name))
+    }
+}
+
+/// This machine's name as `name` sets it, or else the computer name, fit for file names and URLs.
+pub fn machine_name(name: Option<&str>) -> String {
+    let name = name.map(str::to_string)
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string());
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn history_file(machine: &str, month: &str) -> String {
+    format!("history-{}-{}.json", machine, month)
+}
+
+/// Shares the settings, returning the config when it changed here.
+fn sync_settings(remote: &Remote, state: &mut SyncState, now: i64) -> Result<Option<Config>, String> {
+    let config = Config::load();
+    let settings = settings_of(&config)?;
+    let local = changed_entries(&settings, state.settings.as_ref(), now);
+    let merged = remote.update(SETTINGS_FILE, &local)?;
+    state.settings = Some(merged.clone());
+    let shared: BTreeMap<String, Value> = merged.iter()
+        .filter(|(_, entry)| !entry.value.is_null())
+        .map(|(key, entry)| (key.clone(), entry.value.clone()))
+        .collect();
+    if shared == settings {
+        return Ok(None);
+    }
+    let config = apply(&config, &merged)?;
+    config.save().map_err(|e| e.to_string())?;
+    // What the config reads back as is what was synced.
+    state.settings = Some(changed_entries(&settings_of(&config)?, Some(&merged), now));
+    Ok(Some(config))
+}
+
+/// Shares the intervals recorded here since the last sync and reads those of other machines.
+fn sync_history(remote: &Remote, state: &mut SyncState, machine: &str, now: i64) -> Result<(), String> {
+    let mut history = History::open().map_err(|e| e.to_string())?;
+    let last_id = history.last_interval_id().map_err(|e| e.to_string())?;
+    let mut local = Entries::new();
+    for month in history.months_after(state.uploaded_id).map_err(|e| e.to_string())? {
+        let intervals: Vec<SyncedInterval> = history.month_intervals(&month).map_err(|e| e.to_string())?
+            .iter()
+            .map(SyncedInterval::new)
+            .collect();
+        let text = serde_json::to_string(&intervals).map_err(|e| e.to_string())?;
+        let name = history_file(machine, &month);
+        let etag = remote.get(&name)?.and_then(|(_, etag)| etag);
+        if !remote.put(&name, &text, etag.as_deref())? {
+            return Err(format!("{} changed on the server meanwhile", name));
+        }
+        local.insert(format!("{}/{}", machine, month), Entry { value: Value::from(intervals.len()), modified: now });
+    }
+    let index = remote.update(HISTORY_INDEX_FILE, &local)?;
+    state.uploaded_id = last_id;
+
+    for (key, entry) in index {
+        let (other, month) = match key.split_once('/') {
+            Some((other, month)) if other != machine => (other, month),
+            _ => continue
+        };
+        if state.downloaded.get(&key).is_some_and(|read| *read >= entry.modified) {
+            continue;
+        }
+        let intervals: Vec<SyncedInterval> = match remote.get(&history_file(other, month))? {
+            Some((text, _)) => serde_json::from_str(&text).map_err(|e| format!("{}: {}", key, e))?,
+            None => continue
+        };
+        let intervals: Vec<Interval> = intervals.iter().filter_map(SyncedInterval::interval).collect();
+        history.replace_month(other, month, &intervals).map_err(|e| e.to_string())?;
+        state.downloaded.insert(key, entry.modified);
+    }
+    Ok(())
+}
+
+/// Empties the history this machine shared, so the others drop their copies on their next
+/// sync, and shares it again from what is recorded next.
+pub fn forget_history(config: &SyncConfig) -> Result<(), String> {
+    let mut state = SyncState::load();
+    state.uploaded_id = 0;
+    state.save().map_err(|e| format!("{}: {}", STATE_FILE, e))?;
+    if !config.enabled || !config.history {
+        return Ok(());
+    }
+    let remote = Remote::new(config)?;
+    let machine = machine_name(config.machine.as_deref());
+    let index: SharedEntries = match remote.get(HISTORY_INDEX_FILE)? {
+        Some((text, _)) => serde_json::from_str(&text).map_err(|e| format!("{}: {}", HISTORY_INDEX_FILE, e))?,
+        None => return Ok(())
+    };
+    let now = unix_secs(SystemTime::now());
+    let mut local = Entries::new();
+    for key in index.entries.keys() {
+        let month = match key.split_once('/') {
+            Some((owner, month)) if owner == machine => month,
+            _ => continue
+        };
+        let name = history_file(&machine, month);
+        let etag = remote.get(&name)?.and_then(|(_, etag)| etag);
+        if !remote.put(&name, "[]", etag.as_deref())? {
+            return Err(format!("{} changed on the server meanwhile", name));
+        }
+        local.insert(key.clone(), Entry { value: Value::from(0), modified: now });
+    }
+    remote.update(HISTORY_INDEX_FILE, &local)?;
+    Ok(())
+}
+
+/// Syncs once, returning the config when the settings changed.
+fn sync(config: &SyncConfig, state: &mut SyncState) -> Result<Option<Config>, String> {
+    let remote = Remote::new(config)?;
+    let now = unix_secs(SystemTime::now());
+    let synced = sync_settings(&remote, state, now)?;
+    if config.history {
+        sync_history(&remote, state, &machine_name(config.machine.as_deref()), now)?;
+    }
+    Ok(synced)
+}
+
+/// Syncs every `interval_minutes` while enabled, handing settings changed elsewhere to `synced`.
+/// They are saved right away and take full effect from the next start.
+pub fn watch(config: SyncConfig, synced: impl Fn(Config) + Send + Sync + 'static) {
+    if !config.enabled {
+        return;
+    }
+    let mut config = config;
+    if config.history && store::encryption() != Encryption::None {
+        log::warn!("The history is encrypted, so it isn't synced");
+        config.history = false;
+    }
+    let interval = Duration::from_secs(config.interval_minutes.max(1).saturating_mul(60));
+    tokio::spawn(async move {
+        time::sleep(FIRST_SYNC_DELAY).await;
+        let mut failing = false;
+        loop {
+            if !privacy::sharing() {
+                time::sleep(interval).await;
+                continue;
+            }
+            let sync_config = config.clone();
+            let result = task::spawn_blocking(move || {
+                let mut state = SyncState::load();
+                let result = sync(&sync_config, &mut state);
+                if let Err(e) = state.save() {
+                    log::error!("{}: {}", STATE_FILE, e);
+                }
+                result
+            }).await.unwrap_or_else(|e| Err(e.to_string()));
+            match result {
+                Ok(Some(config)) => {
+                    log::info!("Synced settings changed elsewhere");
+                    failing = false;
+                    synced(config);
+                },
+                Ok(None) => failing = false,
+                Err(e) => {
+                    if !failing {
+                        log::warn!("Failed to sync: {}", e);
+                    }
+                    failing = true;
+                }
+            }
+            time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pairs: &[(&str, Value)]) -> BTreeMap<String, Value> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn later_changes_win_per_setting() {
+        // The first sync here takes what is shared over the defaults.
+        let shared = changed_entries(&settings(&[("report.hour", Value::from(9)), ("privacy", Value::from(true))]), Some(&Entries::new()), 100);
+        let here = changed_entries(&settings(&[("report.hour", Value::from(17))]), None, 200);
+        let merged = merge(&here, &shared);
+        assert_eq!(merged["report.hour"].value, Value::from(9));
+        assert_eq!(merged["privacy"].value, Value::from(true));
+
+        // Then a change here wins over an older one elsewhere, each key on its own.
+        let here = changed_entries(&settings(&[("report.hour", Value::from(8)), ("privacy", Value::from(true))]), Some(&merged), 300);
+        let mut shared = merged.clone();
+        shared.insert("privacy".to_string(), Entry { value: Value::from(false), modified: 250 });
+        let merged = merge(&here, &shared);
+        assert_eq!(merged["report.hour"], Entry { value: Value::from(8), modified: 300 });
+        assert_eq!(merged["privacy"], Entry { value: Value::from(false), modified: 250 });
+
+        // A setting left out now was taken back to its default.
+        let here = changed_entries(&settings(&[("report.hour", Value::from(8))]), Some(&merged), 400);
+        assert_eq!(here["privacy"], Entry { value: Value::Null, modified: 400 });
+    }
+
+    #[test]
+    fn keeps_the_settings_of_this_machine() {
+        let mut config = Config::default();
+        config.sync.url = "https://dav.example.com/pauza".to_string();
+        config.api.token = Some("secret".to_string());
+        config.slack.token = Some("xoxp-secret".to_string());
+        config.hue.username = "bridge-user".to_string();
+        config.storage.passphrase = Some("correct horse".to_string());
+        config.meetings.calendar_url = Some("https://calendar.example.com/private.ics".to_string());
+        config.sounds.reset.file = Some("C:\\Sounds\\chime.wav".to_string());
+        config.window.x = 1200;
+        let shared = settings_of(&config).unwrap();
+        assert!(!shared.keys().any(|key| is_local(key)));
+        for key in ["slack.token", "hue.username", "meetings.calendar_url", "storage.passphrase", "sounds.reset.file", "window.x"] {
+            assert!(!shared.contains_key(key), "{} was shared", key);
+        }
+        let mut entries: Entries = shared.into_iter().map(|(key, value)| (key, Entry { value, modified: 0 })).collect();
+        entries.insert("report.hour".to_string(), Entry { value: Value::from(7), modified: 1 });
+        entries.insert("window.x".to_string(), Entry { value: Value::from(0), modified: 1 });
+        let applied = apply(&config, &entries).unwrap();
+        assert_eq!(applied.report.hour, 7);
+        assert_eq!(applied.sync.url, config.sync.url);
+        assert_eq!(applied.api.token, config.api.token);
+        assert_eq!(applied.window.x, 1200);
+        assert_eq!(applied.slack.token, config.slack.token);
+        assert_eq!(applied.storage.passphrase, config.storage.passphrase);
+        // What an older version shared of them is taken back.
+        let mut old = Entries::new();
+        old.insert("slack.token".to_string(), Entry { value: Value::from("xoxp-old"), modified: 5 });
+        assert!(!merge(&Entries::new(), &old).contains_key("slack.token"));
+    }
+}
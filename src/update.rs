@@ -0,0 +1,173 @@
+use serde_json::Value;
+use std::time::Duration;
+use tokio::{task, time};
+use crate::config::UpdateConfig;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+/// Keeps the first check out of the way of starting up.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    /// The release page.
+    pub url: String,
+    /// The installer or executable, when the release has one.
+    pub download: Option<String>,
+}
+
+/// What a check for updates found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Checked {
+    Newer(Release),
+    UpToDate,
+    Failed(String)
+}
+
+/// The numbers of a version like `v1.2.3` or `1.2.3-beta`, ignoring the suffix.
+fn numbers(version: &str) -> Vec<u64> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let version = version.split(['-', '+']).next().unwrap_or("");
+    let mut numbers: Vec<u64> = version.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+    while numbers.last() == Some(&0) {
+        numbers.pop();
+    }
+    numbers
+}
+
+pub fn is_newer(version: &str, current: &str) -> bool {
+    numbers(version) > numbers(current)
+}
+
+/// Whether `url` is a web page served over HTTPS, and so safe to hand to the shell: a
+/// feed that was tampered with could otherwise open a program on a share or this machine.
+fn is_https(url: &str) -> bool {
+    let rest = match url.get(..8) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("https://") => &url[8..],
+        _ => return false
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    !host.is_empty() && !url.chars().any(|c| c.is_whitespace() || c.is_control() || c == '\\' || c == '"')
+}
+
+/// Reads a release in the format of GitHub's releases API, with its addresses only when
+/// they are HTTPS.
+pub fn parse_release(release: &Value) -> Option<Release> {
+    if release["draft"] == true || release["prerelease"] == true {
+        return None;
+    }
+    let download = release["assets"].as_array()
+        .and_then(|assets| assets.iter().find(|asset| {
+            asset["name"].as_str().is_some_and(|name| name.ends_with(".exe") || name.ends_with(".msi") || name.ends_with(".zip"))
+        }))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .filter(|url| is_https(url))
+        .map(str::to_string);
+    Some(Release {
+        version: release["tag_name"].as_str()?.trim_start_matches(['v', 'V']).to_string(),
+        url: release["html_url"].as_str().filter(|url| is_https(url))?.to_string(),
+        download,
+    })
+}
+
+/// Asks the release endpoint at `url` whether there is something newer than this build.
+pub fn check(url: &str) -> Checked {
+    let response = ureq::get(url)
+        .timeout(CHECK_TIMEOUT)
+        .set("Accept", "application/json")
+        .call()
+        .map_err(|e| e.to_string())
+        .and_then(|response| response.into_json::<Value>().map_err(|e| e.to_string()));
+    match response {
+        Ok(release) => match parse_release(&release) {
+            Some(release) if is_newer(&release.version, env!("CARGO_PKG_VERSION")) => Checked::Newer(release),
+            Some(_) => Checked::UpToDate,
+            None => Checked::Failed("no release in the response".to_string())
+        },
+        Err(e) => Checked::Failed(e)
+    }
+}
+
+/// Checks right away in the background, for a check asked for from the tray.
+pub fn check_now(url: String, found: impl FnOnce(Checked) + Send + 'static) {
+    task::spawn_blocking(move || found(check(&url)));
+}
+
+/// Checks for updates every `interval_hours`, unless checking is turned off.
+pub fn watch(config: UpdateConfig, found: impl Fn(Checked) + Send + Sync + 'static) {
+    if !config.check {
+        return;
+    }
+    let interval = Duration::from_secs(config.interval_hours.max(1).saturating_mul(60 * 60));
+    tokio::spawn(async move {
+        time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            let url = config.url.clone();
+            let checked = task::spawn_blocking(move || check(&url)).await
+                .unwrap_or_else(|e| Checked::Failed(e.to_string()));
+            match &checked {
+                Checked::Newer(release) => log::info!("Pauza {} is available", release.version),
+                Checked::UpToDate => log::debug!("Pauza is up to date"),
+                Checked::Failed(e) => log::warn!("Failed to check for updates: {}", e)
+            }
+            found(checked);
+            time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.10", "0.9.9"));
+        assert!(is_newer("1.0.1-beta", "1.0.0"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+    }
+
+    #[test]
+    fn parses_github_releases() {
+        let release = json!({
+            "tag_name": "v0.3.1",
+            "html_url": "https://example.com/releases/v0.3.1",
+            "draft": false,
+            "prerelease": false,
+            "assets": [
+                { "name": "checksums.txt", "browser_download_url": "https://example.com/checksums.txt" },
+                { "name": "pauza.exe", "browser_download_url": "https://example.com/pauza.exe" }
+            ]
+        });
+        assert_eq!(parse_release(&release), Some(Release {
+            version: "0.3.1".to_string(),
+            url: "https://example.com/releases/v0.3.1".to_string(),
+            download: Some("https://example.com/pauza.exe".to_string()),
+        }));
+        let mut prerelease = release.clone();
+        prerelease["prerelease"] = json!(true);
+        assert_eq!(parse_release(&prerelease), None);
+        assert_eq!(parse_release(&json!({ "message": "Not Found" })), None);
+        let mut local = release.clone();
+        local["assets"][1]["browser_download_url"] = json!("file://C:/Windows/System32/calc.exe");
+        assert_eq!(parse_release(&local).unwrap().download, None);
+        local["html_url"] = json!("\\\\server\\share\\setup.exe");
+        assert_eq!(parse_release(&local), None);
+    }
+
+    #[test]
+    fn opens_only_https_addresses() {
+        assert!(is_https("https://example.com/releases"));
+        assert!(is_https("HTTPS://example.com"));
+        assert!(!is_https("http://example.com"));
+        assert!(!is_https("https://"));
+        assert!(!is_https("https:///etc"));
+        assert!(!is_https("file:///C:/Windows/System32/calc.exe"));
+        assert!(!is_https("https://example.com/\\..\\calc.exe"));
+        assert!(!is_https("htt\u{e9}://example.com"));
+    }
+}
@@ -0,0 +1,142 @@
+use crossbeam::channel::Receiver;
+use std::time::{Duration, Instant, SystemTime};
+use crate::config::AppCategory;
+use crate::history::{AppUsage, History};
+use crate::{privacy, Event, State};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// More than this between samples, as after sleep, only counts for this long.
+const MAX_SAMPLE_TIME: Duration = Duration::from_secs(30);
+
+/// A hash of a window title, FNV-1a, so titles can be told apart without being stored.
+pub fn title_hash(title: &str) -> i64 {
+    let hash = title.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    hash as i64
+}
+
+/// The time spent in the apps of one category.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategoryTotal {
+    pub name: String,
+    pub duration: Duration,
+    pub budget: Option<Duration>,
+}
+
+impl CategoryTotal {
+    pub fn over_budget(&self) -> bool {
+        self.budget.is_some_and(|budget| self.duration >= budget)
+    }
+}
+
+/// Adds up the time in each app by category, in the order the categories are configured.
+/// Apps in no category are left out.
+pub fn category_totals(categories: &[AppCategory], apps: &[AppUsage]) -> Vec<CategoryTotal> {
+    let mut totals: Vec<_> = categories.iter()
+        .map(|category| CategoryTotal {
+            name: category.name.clone(),
+            duration: Duration::ZERO,
+            budget: category.budget_minutes.map(|minutes| Duration::from_secs(minutes.saturating_mul(60))),
+        })
+        .collect();
+    for usage in apps {
+        if let Some(index) = categories.iter().position(|category| category.contains(&usage.app)) {
+            totals[index].duration += usage.duration;
+        }
+    }
+    totals
+}
+
+/// Decides when to look at the foreground window, and how much work time it gets.
+#[derive(Debug, Default)]
+struct Sampler {
+    last: Option<Instant>,
+}
+
+impl Sampler {
+    /// The time to put down to the app in front at `now`, once a sample is due.
+    fn due(&mut self, now: Instant, working: bool) -> Option<Duration> {
+        if !working {
+            self.last = None;
+            return None;
+        }
+        match self.last {
+            Some(last) if now - last < SAMPLE_INTERVAL => None,
+            Some(last) => {
+                self.last = Some(now);
+                Some((now - last).min(MAX_SAMPLE_TIME))
+            },
+            None => {
+                self.last = Some(now);
+                None
+            }
+        }
+    }
+}
+
+/// Records which app is in front while the timer is working, as told by `foreground`.
+pub fn record(r: Receiver<Event>, foreground: impl Fn() -> Option<(String, String)>) {
+    let history = match History::open() {
+        Ok(history) => history,
+        Err(e) => {
+            log::error!("{}", e);
+            return;
+        }
+    };
+    let mut state = State::Working;
+    let mut sampler = Sampler::default();
+    for event in r.iter() {
+        match event {
+            Event::UpdateState(new_state) => state = new_state,
+            Event::UpdateTime(_) => {},
+            _ => continue
+        }
+        let duration = match sampler.due(Instant::now(), state == State::Working && !privacy::enabled()) {
+            Some(duration) => duration,
+            None => continue
+        };
+        if let Some((app, title)) = foreground() {
+            if let Err(e) = history.add_usage(&app, title_hash(&title), SystemTime::now(), duration) {
+                log::error!("{}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_only_while_working() {
+        let start = Instant::now();
+        let secs = |s| start + Duration::from_secs(s);
+        let mut sampler = Sampler::default();
+        assert_eq!(sampler.due(secs(0), true), None);
+        assert_eq!(sampler.due(secs(5), true), None);
+        assert_eq!(sampler.due(secs(12), true), Some(Duration::from_secs(12)));
+        assert_eq!(sampler.due(secs(20), false), None);
+        assert_eq!(sampler.due(secs(40), true), None);
+        assert_eq!(sampler.due(secs(500), true), Some(MAX_SAMPLE_TIME));
+        assert_eq!(title_hash("Inbox"), title_hash("Inbox"));
+        assert_ne!(title_hash("Inbox"), title_hash("Inbox (1)"));
+    }
+
+    #[test]
+    fn adds_up_categories() {
+        let category = |name: &str, apps: &[&str], budget_minutes| AppCategory {
+            name: name.to_string(),
+            apps: apps.iter().map(|app| app.to_string()).collect(),
+            budget_minutes,
+        };
+        let categories = [category("Communication", &["slack.exe", "outlook.exe"], Some(30)),
+                          category("Browsing", &["firefox.exe", "slack.exe"], None)];
+        let usage = |app: &str, minutes: u64| AppUsage { app: app.to_string(), duration: Duration::from_secs(minutes.saturating_mul(60)) };
+        let totals = category_totals(&categories, &[usage("Slack.exe", 20), usage("outlook.exe", 10),
+                                                    usage("firefox.exe", 90), usage("code.exe", 60)]);
+        assert_eq!(totals.iter().map(|total| total.duration.as_secs() / 60).collect::<Vec<_>>(), [30, 90]);
+        assert!(totals[0].over_budget());
+        assert!(!totals[1].over_budget());
+    }
+}
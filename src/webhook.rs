@@ -0,0 +1,152 @@
+use crossbeam::channel::Receiver;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{self, error::TrySendError, Sender};
+use tokio::{task, time};
+use crate::config::WebhookConfig;
+use crate::history::IntervalKind;
+use crate::status::Status;
+use crate::{Event, State};
+
+/// Payloads waiting for one URL; more are dropped while it is down.
+const QUEUE: usize = 32;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const ATTEMPTS: u32 = 5;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// The webhook payload for an event, if it is one that webhooks are sent for.
+fn payload(event: &Event, status: &Status, on_break: bool) -> Option<Value> {
+    let (name, extra) = match event {
+        Event::NotifyBreak => ("break_prompted", json!({})),
+        Event::UpdateState(State::OnBreak) if !on_break => ("break_started", json!({})),
+        Event::Interval(interval) => match interval.kind {
+            IntervalKind::Break => ("break_completed", json!({
+                "started_at": unix_secs(interval.start),
+                "duration": interval.duration.as_secs(),
+            })),
+            IntervalKind::Skip => ("break_skipped", json!({
+                "skipped_at": unix_secs(interval.start),
+            })),
+            _ => return None
+        },
+        _ => return None
+    };
+    let mut payload = json!({
+        "event": name,
+        "timestamp": unix_secs(SystemTime::now()),
+        "elapsed": status.elapsed,
+        "remaining": status.remaining,
+    });
+    if let (Some(payload), Value::Object(extra)) = (payload.as_object_mut(), extra) {
+        payload.extend(extra);
+    }
+    Some(payload)
+}
+
+/// Whether the request is worth trying again: network errors, rate limits and server errors.
+fn is_retryable(e: &ureq::Error) -> bool {
+    match e {
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+        ureq::Error::Transport(_) => true
+    }
+}
+
+/// What went wrong, without the URL ureq puts in its errors: it holds the hook's secret.
+fn describe(e: &ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, _) => format!("status {}", code),
+        ureq::Error::Transport(transport) => transport.kind().to_string()
+    }
+}
+
+/// Posts `payload` to `url`, logging it as `name` since the URL is a secret.
+async fn post(name: &str, url: &str, payload: &Value) {
+    let mut delay = FIRST_RETRY_DELAY;
+    for attempt in 1..=ATTEMPTS {
+        let (request_url, body) = (url.to_string(), payload.clone());
+        let result = task::spawn_blocking(move || {
+            ureq::post(&request_url).timeout(TIMEOUT).send_json(body)
+                .map(|_| ())
+                .map_err(|e| (is_retryable(&e), describe(&e)))
+        }).await.unwrap_or_else(|e| Err((false, e.to_string())));
+        match result {
+            Ok(()) => return,
+            Err((true, e)) if attempt < ATTEMPTS => {
+                log::warn!("{} failed, retrying in {}s: {}", name, delay.as_secs(), e);
+                time::sleep(delay).await;
+                delay *= 2;
+            },
+            Err((_, e)) => {
+                log::error!("{} failed: {}", name, e);
+                return;
+            }
+        }
+    }
+}
+
+/// Starts a task posting to one URL in order, so a slow URL only holds up its own payloads.
+fn spawn_sender(name: String, url: String) -> Sender<Value> {
+    let (s, mut r) = mpsc::channel::<Value>(QUEUE);
+    tokio::spawn(async move {
+        while let Some(payload) = r.recv().await {
+            post(&name, &url, &payload).await;
+        }
+    });
+    s
+}
+
+/// Posts a JSON payload to every configured URL when a break is prompted, started,
+/// completed or skipped.
+pub fn deliver(config: WebhookConfig, r: Receiver<Event>) {
+    // Known by their place in `webhooks.urls` in the log.
+    let senders: Vec<(String, Sender<Value>)> = config.urls.into_iter().enumerate()
+        .map(|(index, url)| {
+            let name = format!("Webhook #{}", index + 1);
+            (name.clone(), spawn_sender(name, url))
+        })
+        .collect();
+    let mut status = Status::default();
+    let mut on_break = false;
+    for event in r.iter() {
+        status.apply(&event);
+        let payload = payload(&event, &status, on_break);
+        if let Event::UpdateState(state) = event {
+            on_break = state == State::OnBreak;
+        }
+        let payload = match payload {
+            Some(payload) => payload,
+            None => continue
+        };
+        for (name, sender) in &senders {
+            if let Err(TrySendError::Full(_)) = sender.try_send(payload.clone()) {
+                log::warn!("{} is behind, dropping a payload", name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Interval;
+
+    #[test]
+    fn reports_skipped_breaks() {
+        let skip = Event::Interval(Interval {
+            kind: IntervalKind::Skip,
+            start: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            duration: Duration::ZERO,
+            cause: None,
+            quality: None,
+        });
+        let status = Status { elapsed: 3000, ..Status::default() };
+        let payload = payload(&skip, &status, false).unwrap();
+        assert_eq!(payload["event"], "break_skipped");
+        assert_eq!(payload["skipped_at"], 1_700_000_000);
+        assert_eq!(payload["elapsed"], 3000);
+    }
+}
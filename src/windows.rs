@@ -1,17 +1,48 @@
+use winapi::shared::windef::{POINT, RECT};
 use winapi::um::winuser::{
+    GetCursorPos,
+    GetDoubleClickTime,
     GetLastInputInfo,
+    MonitorFromRect,
     LASTINPUTINFO,
+    MONITOR_DEFAULTTONULL,
+    PBT_APMRESUMEAUTOMATIC,
+    PBT_APMSUSPEND,
+    SW_SHOWNORMAL,
 };
-use winapi::um::sysinfoapi::GetTickCount;
-use std::mem::{zeroed, size_of};
+use winapi::shared::minwindef::FILETIME;
+use winapi::shared::ntdef::LONG;
+use winapi::um::minwinbase::SYSTEMTIME;
+use winapi::um::timezoneapi::{
+    FileTimeToSystemTime, GetDynamicTimeZoneInformation, SystemTimeToFileTime, SystemTimeToTzSpecificLocalTimeEx,
+    DYNAMIC_TIME_ZONE_INFORMATION, TIME_ZONE_ID_INVALID
+};
+use winapi::um::errhandlingapi::{GetLastError, SetUnhandledExceptionFilter};
+use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+use winapi::um::sysinfoapi::{GetLocalTime, GetTickCount};
+use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+use winapi::um::winnls::{GetUserDefaultUILanguage, LCIDToLocaleName};
+use winapi::um::winnt::{EXCEPTION_POINTERS, LOCALE_NAME_MAX_LENGTH};
+use winapi::um::shellapi::{
+    SHQueryUserNotificationState,
+    ShellExecuteW,
+    QUNS_BUSY,
+    QUNS_PRESENTATION_MODE,
+    QUNS_RUNNING_D3D_FULL_SCREEN,
+};
+use std::mem::{self, zeroed, size_of};
+use std::path::Path;
+use std::ptr::null_mut;
+use std::sync::OnceLock;
 use std::time::Duration;
 
+#[tracing::instrument(level = "trace", ret, err)]
 pub fn get_idle_time() -> Result<Duration, i32> {
     let mut info: LASTINPUTINFO = unsafe { zeroed() };
     info.cbSize = size_of::<LASTINPUTINFO>() as u32;
     let result = unsafe { GetLastInputInfo(&mut info) };
     if result == 0 {
-        Err(result)
+        Err(unsafe { GetLastError() } as i32)
     } else {
         let tick_count = unsafe { GetTickCount() };
         let elapsed_millis = tick_count - info.dwTime;
@@ -20,75 +51,1269 @@ pub fn get_idle_time() -> Result<Duration, i32> {
     }
 }
 
+/// Where the mouse cursor is on the screen.
+pub fn cursor_position() -> Result<(i32, i32), i32> {
+    let mut point: POINT = unsafe { zeroed() };
+    if unsafe { GetCursorPos(&mut point) } == 0 {
+        return Err(unsafe { GetLastError() } as i32);
+    }
+    Ok((point.x, point.y))
+}
+
+/// True on battery power or with Battery Saver on.
+#[tracing::instrument(level = "trace", ret)]
+pub fn is_low_power() -> bool {
+    let mut status: SYSTEM_POWER_STATUS = unsafe { zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return false;
+    }
+    // `Reserved1` is `SystemStatusFlag`, 1 while Battery Saver is on.
+    status.ACLineStatus == 0 || status.Reserved1 == 1
+}
+
+/// True while a fullscreen app, game or presentation is in front.
+#[tracing::instrument(level = "trace", ret)]
+pub fn is_fullscreen() -> bool {
+    let mut state = 0;
+    if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+        return false;
+    }
+    matches!(state, QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE)
+}
+
+/// True while a Direct3D app runs exclusive fullscreen, which is what games do.
+#[tracing::instrument(level = "trace", ret)]
+pub fn is_fullscreen_game() -> bool {
+    let mut state = 0;
+    unsafe { SHQueryUserNotificationState(&mut state) == 0 && state == QUNS_RUNNING_D3D_FULL_SCREEN }
+}
+
+/// True while the Game Bar is open, from its presence API. Where that can't be asked,
+/// the Game Bar's own process being `foreground` is taken instead.
+#[tracing::instrument(level = "trace", ret)]
+pub fn is_game_bar(foreground: Option<&str>) -> bool {
+    match game_bar::is_visible() {
+        Ok(visible) => visible,
+        Err(_) => foreground.is_some_and(|process| {
+            process.eq_ignore_ascii_case("gamebar.exe") || process.eq_ignore_ascii_case("gamebarftserver.exe")
+        })
+    }
+}
+
+fn to_wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Opens a file with its program, a folder in File Explorer or a link in the browser.
+pub fn open(target: &str) {
+    let operation = to_wide("open");
+    let file = to_wide(target);
+    let result = unsafe { ShellExecuteW(null_mut(), operation.as_ptr(), file.as_ptr(), null_mut(), null_mut(), SW_SHOWNORMAL) };
+    if result as usize <= 32 {
+        // The query can hold a token, as the dashboard's does.
+        let shown = target.split('?').next().unwrap_or(target);
+        error!("Failed to open {}: {}", shown, result as usize);
+    }
+}
+
+pub fn open_path(path: &Path) {
+    open(&path.to_string_lossy());
+}
+
+const EXCEPTION_CONTINUE_SEARCH: LONG = 0;
+
+static EXCEPTION_HANDLER: OnceLock<fn(&str)> = OnceLock::new();
+
+unsafe extern "system" fn on_unhandled_exception(info: *mut EXCEPTION_POINTERS) -> LONG {
+    if let Some(handler) = EXCEPTION_HANDLER.get() {
+        let record = &*(*info).ExceptionRecord;
+        handler(&format!("Unhandled exception {:#010x} at {:?}", record.ExceptionCode, record.ExceptionAddress));
+    }
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Has `handler` told about an exception nothing else handled, before the process dies of it.
+pub fn set_exception_handler(handler: fn(&str)) {
+    let _ = EXCEPTION_HANDLER.set(handler);
+    unsafe { SetUnhandledExceptionFilter(Some(on_unhandled_exception)) };
+}
+
+fn load_embedded_icon(icon: &mut nwg::Icon) -> Result<(), nwg::NwgError> {
+    let embed = nwg::EmbedResource::load(None)?;
+    nwg::Icon::builder()
+        .source_embed(Some(&embed))
+        .source_embed_id(ICON_RESOURCE_ID)
+        .build(icon)
+}
+
+fn load_icon(icon: &mut nwg::Icon, file: Option<&str>) -> Result<(), nwg::NwgError> {
+    if let Some(file) = file {
+        match nwg::Icon::builder().source_file(Some(file)).build(icon) {
+            Ok(()) => return Ok(()),
+            Err(e) => error!("{}: {}", file, e)
+        }
+    }
+    load_embedded_icon(icon)
+}
+
+/// Whether the title bar of a window at the given position would land on any monitor.
+fn is_on_screen(x: i32, y: i32, width: u32) -> bool {
+    let rect = RECT {
+        left: x,
+        top: y,
+        right: x + width as i32,
+        bottom: y + TITLE_BAR_HEIGHT,
+    };
+    !unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONULL) }.is_null()
+}
+
+/// Lets command line output reach the console pauza was started from,
+/// which a `windows_subsystem = "windows"` binary doesn't get by default.
+pub fn attach_console() {
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+fn local_time() -> SYSTEMTIME {
+    let mut time: SYSTEMTIME = unsafe { zeroed() };
+    unsafe { GetLocalTime(&mut time) };
+    time
+}
+
+pub fn local_timestamp() -> String {
+    let t = local_time();
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            t.wYear, t.wMonth, t.wDay, t.wHour, t.wMinute, t.wSecond, t.wMilliseconds)
+}
+
+fn unix_to_file_time(secs: i64) -> FILETIME {
+    // FILETIME counts 100ns intervals since 1601-01-01.
+    let intervals = (secs + 11_644_473_600) * 10_000_000;
+    FILETIME { dwLowDateTime: intervals as u32, dwHighDateTime: (intervals >> 32) as u32 }
+}
+
+fn file_time_to_unix(file_time: &FILETIME) -> i64 {
+    let intervals = (file_time.dwHighDateTime as i64) << 32 | file_time.dwLowDateTime as i64;
+    intervals / 10_000_000 - 11_644_473_600
+}
+
+/// The offset from UTC of the time zone the machine is in now at the unix time `secs`,
+/// by the DST rules of that year.
+pub fn utc_offset(secs: i64) -> Option<i64> {
+    let mut zone: DYNAMIC_TIME_ZONE_INFORMATION = unsafe { zeroed() };
+    let mut utc: SYSTEMTIME = unsafe { zeroed() };
+    let mut local: SYSTEMTIME = unsafe { zeroed() };
+    let mut file_time: FILETIME = unsafe { zeroed() };
+    unsafe {
+        if GetDynamicTimeZoneInformation(&mut zone) == TIME_ZONE_ID_INVALID
+            || FileTimeToSystemTime(&unix_to_file_time(secs), &mut utc) == 0
+            || SystemTimeToTzSpecificLocalTimeEx(&zone, &utc, &mut local) == 0
+            || SystemTimeToFileTime(&local, &mut file_time) == 0 {
+            return None;
+        }
+    }
+    Some(file_time_to_unix(&file_time) - secs)
+}
+
+extern "C" {
+    fn _tzset();
+}
+
+/// Has the C runtime, and SQLite's `localtime` with it, read the time zone again.
+pub fn refresh_time_zone() {
+    unsafe { _tzset() };
+}
+
+/// The Windows display language, like `de-DE`.
+pub fn user_language() -> Option<String> {
+    let mut name = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let length = unsafe { LCIDToLocaleName(GetUserDefaultUILanguage() as u32, name.as_mut_ptr(), name.len() as i32, 0) };
+    if length <= 1 {
+        return None;
+    }
+    Some(String::from_utf16_lossy(&name[..length as usize - 1]))
+}
+
+pub use audio::{quiet_breaks, restore_sound, AudioMonitor};
+pub use autostart::{disable as disable_autostart, enable as enable_autostart, repair as repair_autostart};
+pub use capture::is_capturing;
+pub use credential::{read_credential, write_credential};
+pub use dpapi::{protect, unprotect};
+pub use foreground::{foreground_process, foreground_window};
+pub use gamepad::GamepadMonitor;
+pub use input::record_input_rate;
+pub use instance::{acquire as acquire_instance, activate_existing, show_existing_stats};
+pub use pipe::PipeServer;
+pub use pipe::connect as connect_pipe;
+pub use raw_input::RawInputMonitor;
+pub use sound::play as play_sound;
+
 // WINDOWS GUI
 
+mod about;
+mod accessibility;
+mod audio;
+mod autostart;
+mod balloon;
+mod capture;
+mod credential;
+mod dpapi;
+mod dpi;
+mod foreground;
+mod game_bar;
+mod gamepad;
+mod hotkeys;
+mod input;
+mod instance;
+mod mini;
+mod overlay;
+mod pipe;
+mod raw_input;
+mod session;
+mod sound;
+mod stats;
+mod taskbar;
+mod theme;
+mod toast;
+mod tray_icon;
+
 use nwg::NativeUi;
-use std::thread;
-use crossbeam::channel::{unbounded, Receiver, TryRecvError};
-use crate::Event;
+use tokio::task;
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use tracing::{error, info, trace, warn};
+use crate::{civil, crash, format, privacy, Event, Request, State, BREAK_TIME};
+use crate::achievements::Achievements;
+use crate::content::BreakContent;
+use crate::daily::DailyWarning;
+use crate::email;
+use crate::config::{config_path, data_dir, AppCategory, ClickAction, Config, Delivery, EmailConfig, MiniConfig, Problem, ReminderStyle, SoundEvent, WindowConfig};
+use crate::history::History;
+use crate::i18n::{tr, tr_args};
+use crate::notifications::{LogOnly, Notification, NotificationBackend, Notifications, Topic};
+use crate::persist::TimerState;
+use crate::report::WeeklyReport;
+use crate::sync;
+use crate::update::{self, Checked, Release};
+use crate::usage;
+use std::time::{Instant, SystemTime};
 use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::thread;
+use hotkeys::{Action, Hotkeys};
+use about::AboutWindow;
+use mini::MiniTimer;
+use overlay::Overlay;
+use accessibility::Accessibility;
+use balloon::Balloon;
+use stats::StatsWindow;
+use taskbar::TaskbarProgress;
+use session::SessionKind;
+use theme::{Theme, ThemePainter};
+use toast::Toast;
+use tray_icon::{IconState, StateIcon, PULSE_FRAMES, PULSE_INTERVAL_MS};
+
+const ICON_RESOURCE_ID: usize = 1;
+const WEEKLY_REPORT_KEY: &str = "weekly_report_shown";
+const BADGES_KEY: &str = "badges_announced";
+const UPDATE_KEY: &str = "update_announced";
+const ACHIEVEMENTS_INTERVAL: Duration = Duration::from_secs(600);
+const BUDGETS_KEY: &str = "budgets_announced";
+const BUDGETS_INTERVAL: Duration = Duration::from_secs(60);
+const PROGRESS_RANGE: u32 = 1000;
+const TITLE_BAR_HEIGHT: i32 = 30;
+const FONT_SIZE: u32 = 16;
+const BIG_FONT_SIZE: u32 = 48;
+/// Rows of the layout the time takes up with the big timer.
+const BIG_TIMER_ROWS: u32 = 3;
+const LAYOUT_MARGIN: u32 = 5;
+/// How long exiting waits for the monitor to save the timer, within what Windows allows
+/// at the end of a session.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// What clicking the last tray notification does.
+#[derive(Clone, Debug, PartialEq)]
+enum Click {
+    Nothing,
+    UndoReset,
+    Action(ClickAction),
+    Open(String)
+}
 
 pub struct BasicApp {
     window: nwg::Window,
-    label: nwg::Label,
+    layout: nwg::GridLayout,
+    state_label: nwg::Label,
+    time_label: nwg::Label,
+    progress_bar: nwg::ProgressBar,
+    breaks_label: nwg::Label,
     icon: nwg::Icon,
     tray: nwg::TrayNotification,
+    tray_menu: nwg::Menu,
+    tray_toggle: nwg::MenuItem,
+    tray_stats: nwg::MenuItem,
+    tray_dashboard: nwg::MenuItem,
+    tray_mini: nwg::MenuItem,
+    tray_undo: nwg::MenuItem,
+    tray_start_break: nwg::MenuItem,
+    tray_logs: nwg::MenuItem,
+    tray_update: nwg::MenuItem,
+    tray_autostart: nwg::MenuItem,
+    tray_privacy: nwg::MenuItem,
+    tray_about: nwg::MenuItem,
+    tray_exit: nwg::MenuItem,
     notice: nwg::Notice,
+    update_notice: nwg::Notice,
+    sync_notice: nwg::Notice,
+    /// Runs only while the tray icon pulses before the break.
+    pulse_timer: nwg::Timer,
+    /// Runs from a click on the tray icon until it is too late for a double-click, so the
+    /// first click of one doesn't toggle the window too.
+    tray_click_timer: nwg::Timer,
+    tray_click_pending: Cell<bool>,
+    stats: StatsWindow,
+    mini: MiniTimer,
+    overlay: Overlay,
+    about: AboutWindow,
+    taskbar: Option<TaskbarProgress>,
+    /// The window was flashed for the overdue break, which it isn't again until the next one.
+    flashed: Cell<bool>,
+    accessibility: Option<Accessibility>,
+    theme: Cell<Theme>,
+    theme_painters: RefCell<Vec<ThemePainter>>,
+    font: RefCell<nwg::Font>,
+    big_font: RefCell<nwg::Font>,
+    dpi: Cell<u32>,
+
+    elapsed: Cell<Duration>,
+    state: Cell<State>,
+    has_break: Cell<bool>,
+    breaks_today: Cell<u32>,
+    break_day: RefCell<String>,
+    report_checked: RefCell<Option<String>>,
+    achievements_checked: Cell<Option<Instant>>,
+    budgets_checked: Cell<Option<Instant>>,
+    icon_state: Cell<Option<IconState>>,
+    low_power: Cell<bool>,
+    degraded: Cell<bool>,
+    session_kind: Cell<SessionKind>,
+    quiet: RefCell<Option<String>>,
+    state_icon: RefCell<Option<StateIcon>>,
+    undo_available: Cell<bool>,
+    content: RefCell<BreakContent>,
+    notifications: RefCell<Notifications<Click>>,
+    on_click: RefCell<Click>,
+    hotkeys: RefCell<Option<Hotkeys>>,
 
-    r: Receiver<Event>
+    config: RefCell<Config>,
+    r: Receiver<Event>,
+    requests: Sender<Request>,
+    /// Disconnects once the monitor and the history recorder have saved everything.
+    stopped: Receiver<()>,
+    /// Update checks, with whether they were asked for from the tray.
+    update_s: Sender<(Checked, bool)>,
+    update_r: Receiver<(Checked, bool)>,
+    /// Settings synced from other machines.
+    sync_s: Sender<Config>,
+    sync_r: Receiver<Config>
 }
 
 impl BasicApp {
 
-    fn new(r: Receiver<Event>) -> BasicApp {
+    fn new(r: Receiver<Event>, requests: Sender<Request>, stopped: Receiver<()>, config: Config) -> BasicApp {
+        let (update_s, update_r) = unbounded();
+        let (sync_s, sync_r) = unbounded();
         BasicApp {
             window: nwg::Window::default(),
-            label: nwg::Label::default(),
+            layout: nwg::GridLayout::default(),
+            state_label: nwg::Label::default(),
+            time_label: nwg::Label::default(),
+            progress_bar: nwg::ProgressBar::default(),
+            breaks_label: nwg::Label::default(),
             icon: nwg::Icon::default(),
             tray: nwg::TrayNotification::default(),
+            tray_menu: nwg::Menu::default(),
+            tray_toggle: nwg::MenuItem::default(),
+            tray_stats: nwg::MenuItem::default(),
+            tray_dashboard: nwg::MenuItem::default(),
+            tray_mini: nwg::MenuItem::default(),
+            tray_undo: nwg::MenuItem::default(),
+            tray_start_break: nwg::MenuItem::default(),
+            tray_logs: nwg::MenuItem::default(),
+            tray_update: nwg::MenuItem::default(),
+            tray_autostart: nwg::MenuItem::default(),
+            tray_privacy: nwg::MenuItem::default(),
+            tray_about: nwg::MenuItem::default(),
+            tray_exit: nwg::MenuItem::default(),
             notice: nwg::Notice::default(),
-            r
+            update_notice: nwg::Notice::default(),
+            sync_notice: nwg::Notice::default(),
+            pulse_timer: nwg::Timer::default(),
+            tray_click_timer: nwg::Timer::default(),
+            tray_click_pending: Cell::new(false),
+            stats: StatsWindow::default(),
+            mini: MiniTimer::default(),
+            overlay: Overlay::default(),
+            about: AboutWindow::default(),
+            taskbar: None,
+            flashed: Cell::new(false),
+            accessibility: None,
+            theme: Cell::new(Theme::light()),
+            theme_painters: RefCell::new(Vec::new()),
+            font: RefCell::new(nwg::Font::default()),
+            big_font: RefCell::new(nwg::Font::default()),
+            dpi: Cell::new(dpi::DEFAULT_DPI),
+            elapsed: Cell::new(Duration::from_secs(0)),
+            state: Cell::new(State::Working),
+            has_break: Cell::new(false),
+            breaks_today: Cell::new(0),
+            break_day: RefCell::new(civil::now().date()),
+            report_checked: RefCell::new(None),
+            achievements_checked: Cell::new(None),
+            budgets_checked: Cell::new(None),
+            icon_state: Cell::new(None),
+            low_power: Cell::new(false),
+            degraded: Cell::new(false),
+            session_kind: Cell::new(SessionKind::Console),
+            quiet: RefCell::new(None),
+            state_icon: RefCell::new(None),
+            undo_available: Cell::new(false),
+            content: RefCell::new(BreakContent::load(&config.messages, &config.tips)),
+            notifications: RefCell::new(Notifications::default()),
+            on_click: RefCell::new(Click::Nothing),
+            hotkeys: RefCell::new(None),
+            config: RefCell::new(config),
+            r,
+            requests,
+            stopped,
+            update_s,
+            update_r,
+            sync_s,
+            sync_r
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        self.elapsed.get().as_secs_f32() / BREAK_TIME.as_secs_f32()
+    }
+
+    fn update_progress(&self) {
+        let elapsed = self.elapsed.get();
+        let progress = self.progress();
+        let (icon_state, taskbar_state, bar_state) = if self.state.get() != State::Working || self.quiet.borrow().is_some() {
+            (IconState::paused(progress), taskbar::TBPF_PAUSED, nwg::ProgressBarState::Paused)
+        } else if elapsed >= BREAK_TIME {
+            (IconState::Overdue, taskbar::TBPF_ERROR, nwg::ProgressBarState::Error)
+        } else {
+            (IconState::working(progress), taskbar::TBPF_NORMAL, nwg::ProgressBarState::Normal)
+        };
+
+        let icon_state = if self.state.get() == State::OnBreak {
+            IconState::OnBreak
+        } else if self.degraded.get() {
+            IconState::degraded(progress)
+        } else {
+            self.countdown(icon_state)
+        };
+
+        let text = format::timer(elapsed);
+        if self.time_label.text() != text {
+            self.time_label.set_text(&text);
+            self.update_tip();
+        }
+        let position = (progress.min(1.0) * PROGRESS_RANGE as f32) as u32;
+        self.progress_bar.set_state(bar_state);
+        self.progress_bar.set_pos(position);
+        self.mini.update(&text, bar_state, position);
+
+        self.update_tray_icon(icon_state);
+        if let Some(taskbar) = &self.taskbar {
+            taskbar.set(&self.window, taskbar_state, progress);
+        }
+        self.update_flash(taskbar_state == taskbar::TBPF_ERROR);
+    }
+
+    /// Flashes the window once the break is overdue by the grace period, and stops once
+    /// the break is taken.
+    fn update_flash(&self, overdue: bool) {
+        let elapsed = self.elapsed.get();
+        let config = self.config.borrow().flash.clone();
+        let grace = Duration::from_secs(config.grace_minutes.saturating_mul(60));
+        if elapsed < BREAK_TIME {
+            if self.flashed.replace(false) {
+                taskbar::flash(&self.window, false);
+            }
+        } else if config.enabled && overdue && elapsed >= BREAK_TIME.saturating_add(grace) && !self.flashed.get() {
+            info!("Break overdue, flashing the window");
+            taskbar::flash(&self.window, true);
+            self.flashed.set(true);
+        }
+    }
+
+    /// Has the tray icon pulse in the last minutes before the break, with a timer that
+    /// runs only then.
+    fn countdown(&self, state: IconState) -> IconState {
+        let minutes = self.config.borrow().display.countdown_minutes;
+        let left = BREAK_TIME.saturating_sub(self.elapsed.get());
+        let step = match state {
+            IconState::Working(step) if minutes > 0 && left <= Duration::from_secs(minutes * 60) && !self.low_power.get() => step,
+            _ => {
+                self.pulse_timer.stop();
+                return state;
+            }
+        };
+        let frame = match self.icon_state.get() {
+            Some(IconState::Countdown(_, frame)) => frame,
+            _ => {
+                self.pulse_timer.start();
+                0
+            }
+        };
+        IconState::Countdown(step, frame)
+    }
+
+    fn on_pulse(&self) {
+        match self.icon_state.get() {
+            Some(IconState::Countdown(step, frame)) => {
+                self.update_tray_icon(IconState::Countdown(step, (frame + 1) % PULSE_FRAMES));
+            },
+            _ => self.pulse_timer.stop()
+        }
+    }
+
+    fn update_tray_icon(&self, state: IconState) {
+        let current = self.icon_state.get();
+        if current == Some(state) {
+            return;
+        }
+        // In low power mode the icon only changes with the state, not with every step of progress.
+        if self.low_power.get() && current.is_some_and(|current| mem::discriminant(&current) == mem::discriminant(&state)) {
+            return;
+        }
+        match tray_icon::render(state, &self.theme.get(), privacy::enabled()) {
+            Ok(icon) => {
+                self.tray.set_icon(&icon.icon);
+                self.icon_state.set(Some(state));
+                *self.state_icon.borrow_mut() = Some(icon);
+            },
+            Err(e) => {
+                error!("{}", e);
+            }
+        }
+    }
+
+    fn update_state(&self, state: State) {
+        self.state.set(state);
+        let text = tr(match state {
+            State::Working => "state-working",
+            State::Paused => "state-paused",
+            State::OnBreak => "state-on-break"
+        });
+        let reason = match (self.quiet.borrow().as_deref(), self.low_power.get(), self.degraded.get()) {
+            (Some("game"), _, _) => Some(tr("reason-game")),
+            (Some("fullscreen app"), _, _) => Some(tr("reason-fullscreen")),
+            (Some(process), _, _) => Some(process.to_string()),
+            (None, true, _) => Some(tr("reason-low-power")),
+            (None, false, true) => Some(tr("reason-degraded")),
+            (None, false, false) => None
+        };
+        let text = match reason {
+            Some(reason) => tr_args("state-with-reason", &[("state", &text), ("reason", &reason)]),
+            None => text
+        };
+        if self.state_label.text() != text {
+            self.state_label.set_text(&text);
+            accessibility::announce(&self.state_label.handle);
+        }
+        self.update_progress();
+    }
+
+    fn count_break(&self) {
+        let today = civil::now().date();
+        if *self.break_day.borrow() != today {
+            *self.break_day.borrow_mut() = today;
+            self.breaks_today.set(0);
+        }
+        self.breaks_today.set(self.breaks_today.get() + 1);
+        self.update_breaks_label();
+    }
+
+    fn update_breaks_label(&self) {
+        self.breaks_label.set_text(&tr_args("breaks-today", &[("count", &self.breaks_today.get())]));
+    }
+
+    fn set_undo_available(&self, available: bool) {
+        self.undo_available.set(available);
+        self.tray_undo.set_enabled(available);
+    }
+
+    fn acknowledge_break(&self) {
+        if self.state.get() != State::OnBreak {
+            self.requests.send(Request::AcknowledgeBreak).unwrap();
+        }
+    }
+
+    /// Does what a click on a notification or the tray icon is set to do.
+    fn run_click_action(&self, action: ClickAction) {
+        info!(?action, "Click");
+        match action {
+            ClickAction::ShowWindow => {
+                self.window.set_visible(true);
+                self.window.set_focus();
+            },
+            ClickAction::Stats => self.show_stats(),
+            ClickAction::StartBreak => self.acknowledge_break(),
+            ClickAction::Snooze => self.requests.send(Request::Snooze).unwrap(),
+            ClickAction::Nothing => {}
+        }
+    }
+
+    fn undo_reset(&self) {
+        if self.undo_available.get() {
+            self.requests.send(Request::UndoReset).unwrap();
+        }
+    }
+
+    /// Clicking the reset notification undoes the reset while that is still possible,
+    /// and clicking the update notification opens the release.
+    fn on_notification_click(&self) {
+        self.notifications.borrow_mut().clear();
+        match &*self.on_click.borrow() {
+            Click::Nothing => {},
+            Click::UndoReset => self.undo_reset(),
+            Click::Action(action) => self.run_click_action(*action),
+            Click::Open(url) => open(url)
+        }
+    }
+
+    fn on_power_event(&self, event: usize) {
+        let request = match event {
+            PBT_APMSUSPEND => Request::Suspend,
+            PBT_APMRESUMEAUTOMATIC => Request::Resume,
+            _ => return
+        };
+        self.requests.send(request).unwrap();
+    }
+
+    fn on_session_change(&self, event: usize) {
+        if let Some(request) = session::request_for(event) {
+            if request == Request::Connect {
+                self.update_session_kind();
+            }
+            self.requests.send(request).unwrap();
+        }
+    }
+
+    fn on_hotkey(&self, id: usize) {
+        let action = match self.hotkeys.borrow().as_ref().and_then(|hotkeys| hotkeys.action(id)) {
+            Some(action) => action,
+            None => return
+        };
+        info!(?action, "Hotkey");
+        let request = match action {
+            Action::BreakNow => Request::BreakNow,
+            Action::TogglePause if self.state.get() == State::Paused => Request::ResumeTimer,
+            Action::TogglePause => Request::PauseTimer(None),
+            Action::ShowWindow => {
+                self.window.set_visible(true);
+                self.window.set_focus();
+                return;
+            },
+            Action::TogglePrivacy => {
+                self.toggle_privacy();
+                return;
+            }
+        };
+        self.requests.send(request).unwrap();
+    }
+
+    fn update_session_kind(&self) {
+        let kind = session::current_kind();
+        info!(?kind, "Session");
+        self.session_kind.set(kind);
+        self.update_tip();
+    }
+
+    /// The tooltip of the tray icon, with the time until the break as the window shows it.
+    fn update_tip(&self) {
+        let tip = match (self.session_kind.get(), self.degraded.get()) {
+            (_, true) => tr("tip-degraded"),
+            (SessionKind::Console, false) => "Pauza".to_string(),
+            (SessionKind::Remote, false) => tr("tip-remote")
+        };
+        let mut tip = format!("{}\n{}", tip, self.time_label.text());
+        if privacy::enabled() {
+            tip = format!("{}\n{}", tip, tr("tip-private"));
+        }
+        self.tray.set_tip(&tip);
+    }
+
+    fn toggle_window(&self) {
+        let visible = !self.window.visible();
+        self.window.set_visible(visible);
+        if visible {
+            self.window.set_focus();
+        }
+    }
+
+    /// A click on the tray icon: the second within the double-click time runs the double-click
+    /// action, and a first one toggles the window once no second one follows.
+    fn on_tray_click(&self) {
+        if self.tray_click_pending.replace(false) {
+            self.tray_click_timer.stop();
+            let action = self.config.borrow().clicks.tray_double_click;
+            self.run_click_action(action);
+        } else {
+            self.tray_click_pending.set(true);
+            self.tray_click_timer.start();
+        }
+    }
+
+    fn on_tray_click_timeout(&self) {
+        self.tray_click_timer.stop();
+        if self.tray_click_pending.replace(false) {
+            self.toggle_window();
+        }
+    }
+
+    fn show_stats(&self) {
+        self.stats.show(&self.config.borrow().usage.categories);
+        if let Some(accessibility) = &self.accessibility {
+            accessibility.set_description(&self.stats.window.handle, &self.stats.description());
+        }
+    }
+
+    fn open_dashboard(&self) {
+        let config = self.config.borrow();
+        open(&format!("http://127.0.0.1:{}/?token={}", config.api.port, config.api.token.as_deref().unwrap_or("")));
+    }
+
+    fn show_tray_menu(&self) {
+        let (x, y) = nwg::GlobalCursor::position();
+        self.tray_menu.popup(x, y);
+    }
+
+    fn labels(&self) -> [&nwg::Label; 3] {
+        [&self.state_label, &self.time_label, &self.breaks_label]
+    }
+
+    fn paint_theme(&self) -> Result<(), nwg::NwgError> {
+        let theme = self.theme.get();
+        let mut painters = self.theme_painters.borrow_mut();
+        painters.push(ThemePainter::apply(&self.window, &self.labels(), theme)?);
+        painters.push(ThemePainter::apply(&self.mini.window, &[&self.mini.label], theme)?);
+        painters.push(ThemePainter::apply(&self.overlay.window, &self.overlay.labels(), theme)?);
+        Ok(())
+    }
+
+    fn toggle_time_shown(&self) {
+        let mut config = self.config.borrow_mut();
+        config.display.time_shown = config.display.time_shown.toggled();
+        format::set_time_shown(config.display.time_shown);
+        if let Err(e) = config.save() {
+            error!("{}", e);
         }
+        drop(config);
+        self.update_progress();
+    }
+
+    fn toggle_privacy(&self) {
+        let private = !privacy::enabled();
+        privacy::set(private);
+        self.tray_privacy.set_checked(private);
+        self.icon_state.set(None);
+        self.update_progress();
+        self.update_tip();
+        let mut config = self.config.borrow_mut();
+        config.privacy = private;
+        if let Err(e) = config.save() {
+            error!("{}", e);
+        }
+    }
+
+    fn toggle_mini(&self) {
+        let visible = !self.mini.window.visible();
+        self.mini.window.set_visible(visible);
+        self.tray_mini.set_checked(visible);
+        self.save_window_geometry();
+    }
+
+    /// Repaints everything in the colors of the current theme, as when high contrast is
+    /// turned on or off.
+    fn on_theme_changed(&self) {
+        let theme = Theme::from_config(&self.config.borrow().theme);
+        if theme == self.theme.get() {
+            return;
+        }
+        info!(high_contrast = theme.high_contrast, "Theme changed");
+        self.theme.set(theme);
+        for mut painter in self.theme_painters.borrow_mut().drain(..) {
+            painter.unbind();
+        }
+        if let Err(e) = self.paint_theme() {
+            error!("{}", e);
+        }
+        self.icon_state.set(None);
+        self.update_progress();
+        self.stats.window.invalidate();
+    }
+
+    /// Rescales fonts and layout spacing for the DPI of the monitor the window is on.
+    fn apply_dpi(&self, dpi: u32) {
+        self.dpi.set(dpi);
+
+        let build_font = |size| {
+            let mut font = nwg::Font::default();
+            nwg::Font::builder()
+                .family("Segoe UI")
+                .size(dpi::scale(size, dpi))
+                .build(&mut font)
+                .map(|()| font)
+        };
+        let (font, big_font) = match (build_font(FONT_SIZE), build_font(BIG_FONT_SIZE)) {
+            (Ok(font), Ok(big_font)) => (font, big_font),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("{}", e);
+                return;
+            }
+        };
+        for label in self.labels() {
+            label.set_font(Some(&font));
+        }
+        self.mini.set_font(&font);
+        if self.config.borrow().display.big_timer {
+            self.time_label.set_font(Some(&big_font));
+        }
+        *self.font.borrow_mut() = font;
+        *self.big_font.borrow_mut() = big_font;
+
+        let margin = dpi::scale(LAYOUT_MARGIN, dpi);
+        self.layout.margin([margin; 4]);
+        self.layout.spacing(margin);
+    }
+
+    fn on_dpi_changed(&self, dpi: u32, suggested: &RECT) {
+        dpi::apply_suggested_rect(&self.window, suggested);
+        self.apply_dpi(dpi);
+    }
+
+    fn save_window_geometry(&self) {
+        let mut config = self.config.borrow_mut();
+        let (x, y) = self.window.position();
+        let (width, height) = self.window.size();
+        let dpi = self.dpi.get();
+        config.window = WindowConfig {
+            x,
+            y,
+            width: dpi::unscale(width, dpi),
+            height: dpi::unscale(height, dpi),
+            visible: self.window.visible(),
+        };
+        config.mini = self.mini.config();
+        if let Err(e) = config.save() {
+            error!("{}", e);
+        }
+    }
+
+    /// Saves what the window knows, in case the monitor doesn't get to save the timer.
+    fn save_state(&self) {
+        self.save_window_geometry();
+        if let Err(e) = TimerState::new(self.elapsed.get(), self.has_break.get()).save() {
+            error!("{}", e);
+        }
+    }
+
+    /// Saves everything and lets go of what was registered with the system before leaving
+    /// the message loop, since the session may end as soon as this returns.
+    fn exit(&self) {
+        self.save_state();
+        if self.requests.send(Request::Shutdown).is_ok()
+            && self.stopped.recv_timeout(SHUTDOWN_TIMEOUT) == Err(RecvTimeoutError::Timeout) {
+            warn!("Timed out waiting for the timer and history to be saved");
+        }
+        session::unregister(&self.window);
+        if let Some(hotkeys) = self.hotkeys.borrow_mut().take() {
+            hotkeys.unregister(&self.window);
+        }
+        self.tray.set_visibility(false);
+        nwg::stop_thread_dispatch();
     }
 
     fn reset_notification(&self) {
-        let flags = nwg::TrayNotificationFlags::USER_ICON
-            | nwg::TrayNotificationFlags::LARGE_ICON;
-        self.tray.show("Back to work!",
-                       Some("Get back to work"),
-                       Some(flags),
-                       Some(&self.icon));
+        let message = match self.content.borrow_mut().tip() {
+            Some(tip) => format!("{}\n{}", tr("reset-message"), tip),
+            None => tr("reset-message")
+        };
+        self.notify(Topic::Reset, Some(SoundEvent::Reset), tr("reset-title"), message, Click::UndoReset);
+    }
+
+    /// Plays the event's own sound, if it has one, returning whether it did.
+    fn play_event_sound(&self, event: SoundEvent) -> bool {
+        let sound = self.config.borrow().sounds.get(event).clone();
+        sound.enabled && sound::play(&sound, false)
+    }
+
+    /// Delivers a notification the way set for its topic.
+    fn notify(&self, topic: Topic, sound: Option<SoundEvent>, title: String, message: String, click: Click) {
+        let delivery = self.config.borrow().notifications.get(topic);
+        self.deliver(delivery, Notification { topic, title, message }, sound, click, None);
+    }
+
+    /// Delivers a notification together with those shown just before, unless it is shown
+    /// already. Clicking it does what the most pressing of them does. The button is for
+    /// toasts, a label and the `pauza:` command it sends.
+    fn deliver(&self, delivery: Delivery, notification: Notification, sound: Option<SoundEvent>, click: Click, button: Option<(&str, &str)>) {
+        // Nothing is shown to fold it into.
+        if delivery == Delivery::Log {
+            let _ = LogOnly.show(&notification);
+            return;
+        }
+        let click = match click {
+            Click::Nothing | Click::Action(ClickAction::Nothing) => None,
+            click => Some(click)
+        };
+        let (shown, click) = match self.notifications.borrow_mut().add(notification, click, Instant::now()) {
+            Some(shown) => shown,
+            None => return
+        };
+        let click = click.unwrap_or(Click::Nothing);
+        let action = match click {
+            Click::Action(action) => action,
+            _ => ClickAction::ShowWindow
+        };
+        *self.on_click.borrow_mut() = click;
+        // The event's own sound plays in place of the balloon's, or none when it's turned off.
+        let silent = sound.is_some_and(|event| !self.config.borrow().sounds.get(event).enabled || self.play_event_sound(event));
+        let balloon = Balloon { tray: &self.tray, icon: &self.icon, silent };
+        let backend: &dyn NotificationBackend = match delivery {
+            Delivery::Balloon => &balloon,
+            Delivery::Toast => &Toast { click: action, button },
+            Delivery::Overlay => &self.overlay,
+            Delivery::Log => &LogOnly
+        };
+        if let Err(e) = backend.show(&shown) {
+            warn!("Failed to show a notification as a {}, showing a balloon instead: {}", backend.name(), e);
+            let _ = balloon.show(&shown);
+        }
+    }
+
+    /// Reminds of the break in the style the reminder policy has for this many repeats.
+    fn remind(&self, repeats: u32) {
+        let style = self.config.borrow().reminders.style(repeats);
+        let breaks = self.config.borrow().notifications.breaks;
+        match style {
+            ReminderStyle::Balloon => self.break_notification(breaks.unwrap_or(Delivery::Balloon)),
+            ReminderStyle::Toast => self.break_notification(breaks.unwrap_or(Delivery::Toast)),
+            ReminderStyle::Flash => {
+                self.play_event_sound(SoundEvent::BreakPrompt);
+                taskbar::flash(&self.window, true);
+                self.flashed.set(true);
+            },
+            ReminderStyle::Window => {
+                self.play_event_sound(SoundEvent::BreakPrompt);
+                self.window.set_visible(true);
+                self.window.set_focus();
+            }
+        }
+    }
+
+    /// With acknowledgment required, clicking the balloon or the button of the toast
+    /// starts the break; clicks on toasts come back through `pauza pauza:...`.
+    fn break_notification(&self, delivery: Delivery) {
+        let acknowledge = self.config.borrow().idle.require_acknowledgment;
+        let click = self.config.borrow().clicks.break_notification
+            .unwrap_or(if acknowledge { ClickAction::StartBreak } else { ClickAction::Nothing });
+        let mut message = {
+            let mut content = self.content.borrow_mut();
+            match content.tip() {
+                Some(tip) => format!("{}\n{}", content.break_message(), tip),
+                None => content.break_message()
+            }
+        };
+        let label = tr("menu-start-break");
+        let button = if acknowledge && delivery == Delivery::Toast {
+            Some((label.as_str(), "start-break"))
+        } else {
+            if click == ClickAction::StartBreak {
+                message = format!("{}\n{}", message, tr("break-acknowledge"));
+            }
+            None
+        };
+        let notification = Notification { topic: Topic::Break, title: tr("break-title"), message };
+        self.deliver(delivery, notification, Some(SoundEvent::BreakPrompt), Click::Action(click), button);
+    }
+
+    fn check_weekly_report(&self) {
+        let now = civil::now();
+        let today = now.date();
+        if self.report_checked.borrow().as_deref() == Some(today.as_str()) {
+            return;
+        }
+        let config = self.config.borrow().report.clone();
+        let email = self.config.borrow().email.clone();
+        if !(config.weekly || email.enabled) || now.weekday() != config.weekday.number() || now.hour < config.hour as i64 {
+            return;
+        }
+        *self.report_checked.borrow_mut() = Some(today.clone());
+
+        if let Err(e) = self.weekly_report(&today, config.weekly, email) {
+            error!("{}", e);
+        }
+    }
+
+    /// Shows the weekly summary, and sends it by email when that is set up, unless that
+    /// was already done on `day`, even in an earlier run.
+    fn weekly_report(&self, day: &str, show: bool, email: EmailConfig) -> rusqlite::Result<()> {
+        let history = History::open()?;
+        if history.meta(WEEKLY_REPORT_KEY)?.as_deref() == Some(day) {
+            return Ok(());
+        }
+        let report = WeeklyReport::generate(&history, SystemTime::now())?;
+        if show {
+            self.notify(Topic::Report, None, report.title(), report.message(), Click::Nothing);
+        }
+        if email.enabled && privacy::sharing() {
+            task::spawn_blocking(move || {
+                if let Err(e) = email::send_report(&email, &report) {
+                    error!("Failed to email the weekly summary: {}", e);
+                }
+            });
+        }
+        history.set_meta(WEEKLY_REPORT_KEY, day)
+    }
+
+    /// Has update checks handed to the GUI thread.
+    fn update_reporter(&self, manual: bool) -> impl Fn(Checked) + Send + Sync + 'static {
+        let s = self.update_s.clone();
+        let sender = self.update_notice.sender();
+        move |checked| {
+            let _ = s.send((checked, manual));
+            sender.notice();
+        }
+    }
+
+    /// Has settings synced from other machines handed to the GUI thread.
+    fn sync_reporter(&self) -> impl Fn(Config) + Send + Sync + 'static {
+        let s = self.sync_s.clone();
+        let sender = self.sync_notice.sender();
+        move |config| {
+            let _ = s.send(config);
+            sender.notice();
+        }
     }
 
-    fn break_notification(&self) {
-        let flags = nwg::TrayNotificationFlags::USER_ICON
-            | nwg::TrayNotificationFlags::LARGE_ICON;
-        self.tray.show("Break Time!",
-                       Some("Time to take a break!"),
-                       Some(flags),
-                       Some(&self.icon));
+    /// Keeps the synced settings, so saving what the window changes doesn't undo them.
+    fn on_settings_synced(&self) {
+        for config in self.sync_r.try_iter() {
+            *self.config.borrow_mut() = config;
+        }
+    }
+
+    fn toggle_autostart(&self) {
+        let enable = !self.tray_autostart.checked();
+        let result = if enable { autostart::enable() } else { autostart::disable() };
+        match result {
+            Ok(()) => self.tray_autostart.set_checked(enable),
+            Err(e) => {
+                error!("Failed to change the autostart entry: {}", e);
+                nwg::error_message("Pauza", &tr_args("autostart-failed", &[("error", &e)]));
+            }
+        }
+    }
+
+    fn check_for_updates(&self) {
+        update::check_now(self.config.borrow().updates.url.clone(), self.update_reporter(true));
+    }
+
+    fn on_update_checked(&self) {
+        for (checked, manual) in self.update_r.try_iter() {
+            match checked {
+                Checked::Newer(release) if manual => {
+                    let content = tr_args("update-download", &[("version", &release.version)]);
+                    let params = nwg::MessageParams {
+                        title: "Pauza",
+                        content: &content,
+                        buttons: nwg::MessageButtons::YesNo,
+                        icons: nwg::MessageIcons::Info,
+                    };
+                    if nwg::message(&params) == nwg::MessageChoice::Yes {
+                        open(release.download.as_ref().unwrap_or(&release.url));
+                    }
+                },
+                Checked::Newer(release) => {
+                    if let Err(e) = self.update_notification(&release) {
+                        error!("{}", e);
+                    }
+                },
+                Checked::UpToDate if manual => {
+                    nwg::simple_message("Pauza", &tr("update-none"));
+                },
+                Checked::Failed(e) if manual => {
+                    nwg::error_message("Pauza", &tr_args("update-failed", &[("error", &e)]));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Tells about a new release once, even across runs.
+    fn update_notification(&self, release: &Release) -> rusqlite::Result<()> {
+        let history = History::open()?;
+        if history.meta(UPDATE_KEY)?.as_deref() == Some(release.version.as_str()) {
+            return Ok(());
+        }
+        self.notify(Topic::Update, None, tr_args("update-title", &[("version", &release.version)]), tr("update-message"),
+                    Click::Open(release.url.clone()));
+        history.set_meta(UPDATE_KEY, &release.version)
+    }
+
+    fn check_achievements(&self) {
+        if self.achievements_checked.get().is_some_and(|t| t.elapsed() < ACHIEVEMENTS_INTERVAL) {
+            return;
+        }
+        self.achievements_checked.set(Some(Instant::now()));
+        if let Err(e) = self.achievements_notification() {
+            error!("{}", e);
+        }
+    }
+
+    /// Congratulates on badges earned since the last check, each only once.
+    fn achievements_notification(&self) -> rusqlite::Result<()> {
+        let history = History::open()?;
+        let achievements = Achievements::load(&history)?;
+        let announced = history.meta(BADGES_KEY)?.unwrap_or_default();
+        let announced: Vec<&str> = announced.split(',').collect();
+        let new: Vec<_> = achievements.badges.iter()
+            .filter(|badge| !announced.contains(&badge.key()))
+            .collect();
+        if new.is_empty() {
+            return Ok(());
+        }
+
+        let message = new.iter()
+            .map(|badge| format!("{}: {}", badge.name(), badge.description()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.notify(Topic::Badges, None, tr("badge-title"), message, Click::Nothing);
+
+        let keys: Vec<_> = achievements.badges.iter().map(|badge| badge.key()).collect();
+        history.set_meta(BADGES_KEY, &keys.join(","))
+    }
+
+    fn check_budgets(&self) {
+        let usage = &self.config.borrow().usage;
+        if !usage.enabled || usage.categories.iter().all(|category| category.budget_minutes.is_none()) {
+            return;
+        }
+        if self.budgets_checked.get().is_some_and(|t| t.elapsed() < BUDGETS_INTERVAL) {
+            return;
+        }
+        self.budgets_checked.set(Some(Instant::now()));
+        if let Err(e) = self.budgets_notification(&usage.categories) {
+            error!("{}", e);
+        }
+    }
+
+    /// Says when the time in a category of apps goes over its budget, once a day each.
+    fn budgets_notification(&self, categories: &[AppCategory]) -> rusqlite::Result<()> {
+        let history = History::open()?;
+        let today = civil::now().date();
+        let announced = history.meta(BUDGETS_KEY)?.unwrap_or_default();
+        let mut announced: Vec<&str> = announced.lines().collect();
+        if announced.first() != Some(&today.as_str()) {
+            announced = vec![&today];
+        }
+        let totals = usage::category_totals(categories, &history.app_usage(SystemTime::now())?);
+        let over: Vec<_> = totals.iter()
+            .filter(|total| total.over_budget() && !announced[1..].contains(&total.name.as_str()))
+            .collect();
+        if over.is_empty() {
+            return Ok(());
+        }
+
+        let message = over.iter()
+            .map(|total| tr_args("budget-message", &[("category", &total.name),
+                                                     ("time", &format::duration(total.duration))]))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.notify(Topic::Budgets, None, tr("budget-title"), message, Click::Nothing);
+
+        announced.extend(over.iter().map(|total| total.name.as_str()));
+        history.set_meta(BUDGETS_KEY, &announced.join("\n"))
+    }
+
+    /// Warns about the day's active time, in words that get more pressing past the limit.
+    fn daily_limit_notification(&self, warning: DailyWarning) {
+        let message = match warning {
+            DailyWarning::Approaching(left) => tr_args("daily-limit-approaching", &[("time", &format::duration(left))]),
+            DailyWarning::Reached(limit) => tr_args("daily-limit-reached", &[("time", &format::duration(limit))]),
+            DailyWarning::Over(over) => tr_args("daily-limit-over", &[("time", &format::duration(over))])
+        };
+        self.notify(Topic::DailyLimit, Some(SoundEvent::DailyLimit), tr("daily-limit-title"), message, Click::Nothing);
     }
 
     fn on_timer_tick(&self) {
         loop {
             match self.r.try_recv() {
-                Ok(event) => match event {
-                    Event::UpdateTime(duration) => {
-                        let text = format!("{:?}", duration);
-                        self.label.set_text(&text);
-                    },
-                    Event::NotifyReset => {
-                        self.reset_notification();
-                    },
-                    Event::NotifyBreak => {
-                        self.break_notification();
+                Ok(event) => {
+                    trace!(?event, "Monitor event");
+                    match event {
+                        Event::UpdateTime(duration) => {
+                            self.elapsed.set(duration);
+                            self.update_progress();
+                            self.check_weekly_report();
+                            self.check_achievements();
+                            self.check_budgets();
+                        },
+                        Event::UpdateState(state) => {
+                            self.update_state(state);
+                        },
+                        Event::NotifyReset => {
+                            self.has_break.set(false);
+                            self.count_break();
+                            self.reset_notification();
+                        },
+                        Event::NotifyBreak => {
+                            self.has_break.set(true);
+                            self.remind(0);
+                        },
+                        Event::RemindAgain(repeats) => {
+                            self.remind(repeats);
+                        },
+                        Event::BreakAcknowledged => {
+                            self.has_break.set(false);
+                            self.count_break();
+                        },
+                        Event::Interval(_) => {},
+                        Event::Quiet(reason) => {
+                            *self.quiet.borrow_mut() = reason;
+                            self.update_state(self.state.get());
+                        },
+                        Event::LowPower(low_power) => {
+                            self.low_power.set(low_power);
+                            self.update_state(self.state.get());
+                        },
+                        Event::Degraded(degraded) => {
+                            self.degraded.set(degraded);
+                            self.update_tip();
+                            self.update_state(self.state.get());
+                        },
+                        Event::UndoAvailable(available) => {
+                            self.set_undo_available(available);
+                        },
+                        Event::DailyLimit(warning) => {
+                            self.daily_limit_notification(warning);
+                        },
+                        Event::ResetUndone(_) => {
+                            self.set_undo_available(false);
+                            self.breaks_today.set(self.breaks_today.get().saturating_sub(1));
+                            self.update_breaks_label();
+                        }
                     }
                 },
                 Err(TryRecvError::Empty) => {
                     break;
                 },
                 Err(e) => {
-                    println!("ERROR: {}", e);
+                    error!("{}", e);
                     break;
                 }
             }
@@ -100,32 +1325,102 @@ mod basic_app_ui {
     use super::*;
     use std::cell::RefCell;
     use std::ops::Deref;
+    use winapi::shared::minwindef::{LOWORD, LPARAM, WPARAM};
+    use winapi::um::winuser::{
+        HTCAPTION,
+        WM_CLOSE,
+        WM_DPICHANGED,
+        WM_ENDSESSION,
+        WM_EXITSIZEMOVE,
+        WM_HOTKEY,
+        WM_LBUTTONUP,
+        WM_NCHITTEST,
+        WM_NCRBUTTONUP,
+        WM_POWERBROADCAST,
+        WM_QUERYENDSESSION,
+        WM_SETTINGCHANGE,
+        WM_SYSCOLORCHANGE,
+        WM_WTSSESSION_CHANGE,
+    };
+
+    const DPI_HANDLER_ID: usize = 0x10002;
+    const SYSTEM_HANDLER_ID: usize = 0x10003;
+    const MINI_HANDLER_ID: usize = 0x10005;
+    const OVERLAY_HANDLER_ID: usize = 0x10007;
 
     pub struct BasicAppUi {
         inner: Rc<BasicApp>,
-        default_handler: RefCell<Option<nwg::EventHandler>>
+        default_handler: RefCell<Option<nwg::EventHandler>>,
+        stats_handler: RefCell<Option<nwg::EventHandler>>,
+        about_handler: RefCell<Option<nwg::EventHandler>>,
+        dpi_handler: RefCell<Option<nwg::RawEventHandler>>,
+        mini_handler: RefCell<Option<nwg::RawEventHandler>>,
+        overlay_handler: RefCell<Option<nwg::RawEventHandler>>,
+        system_handler: RefCell<Option<nwg::RawEventHandler>>
     }
 
     impl nwg::NativeUi<BasicAppUi> for BasicApp {
         fn build_ui(mut data: BasicApp) -> Result<BasicAppUi, nwg::NwgError> {
             use nwg::Event as E;
 
-            nwg::Icon::builder()
-                .source_file(Some("./pauza.ico"))
-                .build(&mut data.icon)?;
+            let icon_file = data.config.borrow().icon_file.clone();
+            load_icon(&mut data.icon, icon_file.as_deref())?;
+
+            data.theme.set(Theme::from_config(&data.config.borrow().theme));
+            let background = Some(data.theme.get().background);
+
+            let geometry = data.config.borrow().window.clone();
+            let defaults = WindowConfig::default();
+            let position = if is_on_screen(geometry.x, geometry.y, geometry.width) {
+                (geometry.x, geometry.y)
+            } else {
+                (defaults.x, defaults.y)
+            };
+            let mut flags = nwg::WindowFlags::WINDOW | nwg::WindowFlags::RESIZABLE;
+            if geometry.visible {
+                flags |= nwg::WindowFlags::VISIBLE;
+            }
 
             // Controls
             nwg::Window::builder()
-                .flags(nwg::WindowFlags::WINDOW | nwg::WindowFlags::VISIBLE)
-                .size((300, 115))
-                .position((300, 300))
+                .flags(flags)
+                .size((geometry.width.max(150) as i32, geometry.height.max(100) as i32))
+                .position(position)
                 .title("Pauza")
                 .build(&mut data.window)?;
 
             nwg::Label::builder()
-                .text("Starting...")
+                .text(&tr("state-working"))
+                .background_color(background)
+                .parent(&data.window)
+                .build(&mut data.state_label)?;
+
+            nwg::Label::builder()
+                .text(&tr("starting"))
+                .background_color(background)
+                .parent(&data.window)
+                .build(&mut data.time_label)?;
+
+            nwg::ProgressBar::builder()
+                .range(0..PROGRESS_RANGE)
                 .parent(&data.window)
-                .build(&mut data.label)?;
+                .build(&mut data.progress_bar)?;
+
+            nwg::Label::builder()
+                .text(&tr_args("breaks-today", &[("count", &0)]))
+                .background_color(background)
+                .parent(&data.window)
+                .build(&mut data.breaks_label)?;
+
+            let time_rows = if data.config.borrow().display.big_timer { BIG_TIMER_ROWS } else { 1 };
+            nwg::GridLayout::builder()
+                .parent(&data.window)
+                .max_column(Some(1))
+                .child(0, 0, &data.state_label)
+                .child_item(nwg::GridLayoutItem::new(&data.time_label, 0, 1, 1, time_rows))
+                .child(0, 1 + time_rows, &data.progress_bar)
+                .child(0, 2 + time_rows, &data.breaks_label)
+                .build(&data.layout)?;
 
             nwg::TrayNotification::builder()
                 .parent(&data.window)
@@ -133,27 +1428,229 @@ mod basic_app_ui {
                 .tip(Some("Pauza"))
                 .build(&mut data.tray)?;
 
+            nwg::Menu::builder()
+                .popup(true)
+                .parent(&data.window)
+                .build(&mut data.tray_menu)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-toggle"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_toggle)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-statistics"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_stats)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-dashboard"))
+                .disabled(!data.config.borrow().api.enabled)
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_dashboard)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-mini-timer"))
+                .check(data.config.borrow().mini.visible)
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_mini)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-start-break"))
+                .disabled(!data.config.borrow().idle.require_acknowledgment)
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_start_break)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-undo-reset"))
+                .disabled(true)
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_undo)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-log-folder"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_logs)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-check-updates"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_update)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-autostart"))
+                .check(autostart::is_enabled())
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_autostart)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-privacy"))
+                .check(privacy::enabled())
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_privacy)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-about"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_about)?;
+
+            nwg::MenuItem::builder()
+                .text(&tr("menu-quit"))
+                .parent(&data.tray_menu)
+                .build(&mut data.tray_exit)?;
+
             nwg::Notice::builder()
                 .parent(&data.window)
                 .build(&mut data.notice)?;
 
+            nwg::Notice::builder()
+                .parent(&data.window)
+                .build(&mut data.update_notice)?;
+
+            nwg::Notice::builder()
+                .parent(&data.window)
+                .build(&mut data.sync_notice)?;
+
+            nwg::Timer::builder()
+                .parent(&data.window)
+                .interval(PULSE_INTERVAL_MS)
+                .stopped(true)
+                .build(&mut data.pulse_timer)?;
+
+            nwg::Timer::builder()
+                .parent(&data.window)
+                .interval(unsafe { GetDoubleClickTime() })
+                .stopped(true)
+                .build(&mut data.tray_click_timer)?;
+
+            data.stats.build(&data.window)?;
+            data.about.build(&data.window)?;
+
+            let mini = data.config.borrow().mini.clone();
+            let mini_defaults = MiniConfig::default();
+            let mini = if is_on_screen(mini.x, mini.y, 1) {
+                mini
+            } else {
+                MiniConfig { x: mini_defaults.x, y: mini_defaults.y, ..mini }
+            };
+            data.mini.build(&mini, PROGRESS_RANGE, data.theme.get().background)?;
+            data.overlay.build(data.theme.get().background)?;
+
+            data.paint_theme()?;
+
+            data.taskbar = match TaskbarProgress::new() {
+                Ok(taskbar) => Some(taskbar),
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            };
+
+            // Only then is the app ID and the `pauza:` protocol written to the registry.
+            let toasts = {
+                let config = data.config.borrow();
+                config.notifications.uses_toasts(&config.reminders)
+            };
+            if toasts {
+                if let Err(e) = toast::register() {
+                    error!("Failed to register for toasts: {}", e);
+                }
+            }
+
+            data.accessibility = match Accessibility::new() {
+                Ok(accessibility) => {
+                    accessibility.set_name(&data.progress_bar.handle, &tr("progress-name"));
+                    accessibility.set_live(&data.state_label.handle);
+                    Some(accessibility)
+                },
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            };
+
             // Wrap-up
             let ui = BasicAppUi {
                 inner: Rc::new(data),
                 default_handler: Default::default(),
+                stats_handler: Default::default(),
+                about_handler: Default::default(),
+                dpi_handler: Default::default(),
+                mini_handler: Default::default(),
+                overlay_handler: Default::default(),
+                system_handler: Default::default(),
             };
 
+            let dpi = dpi::window_dpi(&ui.window);
+            ui.window.set_size(dpi::scale(geometry.width.max(150), dpi), dpi::scale(geometry.height.max(100), dpi));
+            ui.apply_dpi(dpi);
+
             // Events
             let evt_ui = Rc::downgrade(&ui.inner);
-            let handle_events = move |evt, _evt_data, handle| {
+            let handle_events = move |evt, evt_data, handle| {
+                let _span = tracing::trace_span!("gui", event = ?evt).entered();
                 if let Some(ui) = evt_ui.upgrade() {
                     match evt {
-                        E::OnWindowClose => if &handle == &ui.window {
-                            nwg::stop_thread_dispatch();
+                        E::OnWindowClose if handle == ui.window => {
+                            if let nwg::EventData::OnWindowClose(data) = evt_data {
+                                data.close(false);
+                            }
+                            ui.window.set_visible(false);
+                        },
+                        E::OnMousePress(nwg::MousePressEvent::MousePressLeftUp) if handle == ui.tray => {
+                            ui.on_tray_click();
+                        },
+                        E::OnLabelClick if handle == ui.time_label => {
+                            ui.toggle_time_shown();
+                        },
+                        E::OnContextMenu if handle == ui.tray => {
+                            ui.show_tray_menu();
+                        },
+                        E::OnTrayNotificationUserClose if handle == ui.tray => {
+                            ui.on_notification_click();
+                        },
+                        E::OnMenuItemSelected => {
+                            if handle == ui.tray_toggle {
+                                ui.toggle_window();
+                            } else if handle == ui.tray_stats {
+                                ui.show_stats();
+                            } else if handle == ui.tray_dashboard {
+                                ui.open_dashboard();
+                            } else if handle == ui.tray_mini {
+                                ui.toggle_mini();
+                            } else if handle == ui.tray_start_break {
+                                ui.acknowledge_break();
+                            } else if handle == ui.tray_undo {
+                                ui.undo_reset();
+                            } else if handle == ui.tray_logs {
+                                open_path(&data_dir());
+                            } else if handle == ui.tray_update {
+                                ui.check_for_updates();
+                            } else if handle == ui.tray_autostart {
+                                ui.toggle_autostart();
+                            } else if handle == ui.tray_privacy {
+                                ui.toggle_privacy();
+                            } else if handle == ui.tray_about {
+                                ui.about.show(&ui.config.borrow().input, ui.degraded.get());
+                            } else if handle == ui.tray_exit {
+                                ui.exit();
+                            }
+                        },
+                        E::OnNotice if handle == ui.update_notice => {
+                            ui.on_update_checked();
+                        },
+                        E::OnNotice if handle == ui.sync_notice => {
+                            ui.on_settings_synced();
                         },
                         E::OnNotice => {
                             ui.on_timer_tick();
                         },
+                        E::OnTimerTick if handle == ui.pulse_timer => {
+                            ui.on_pulse();
+                        },
+                        E::OnTimerTick if handle == ui.tray_click_timer => {
+                            ui.on_tray_click_timeout();
+                        },
                         _ => {}
                     }
                 }
@@ -161,13 +1658,216 @@ mod basic_app_ui {
 
             *ui.default_handler.borrow_mut() = Some(nwg::full_bind_event_handler(&ui.window.handle, handle_events));
 
-            return Ok(ui);
+            let stats_ui = Rc::downgrade(&ui.inner);
+            let handle_stats_events = move |evt, evt_data, handle| {
+                if let Some(ui) = stats_ui.upgrade() {
+                    match (evt, &evt_data) {
+                        (E::OnPaint, nwg::EventData::OnPaint(paint)) => {
+                            ui.stats.paint(paint, &ui.theme.get());
+                        },
+                        (E::OnButtonClick, _) if handle == ui.stats.export_button => {
+                            ui.stats.export();
+                        },
+                        (E::OnResize, _) => {
+                            ui.stats.window.invalidate();
+                        },
+                        (E::OnWindowClose, nwg::EventData::OnWindowClose(data)) => {
+                            data.close(false);
+                            ui.stats.hide();
+                        },
+                        _ => {}
+                    }
+                }
+            };
+            *ui.stats_handler.borrow_mut() = Some(nwg::full_bind_event_handler(&ui.stats.window.handle, handle_stats_events));
+
+            let about_ui = Rc::downgrade(&ui.inner);
+            let handle_about_events = move |evt, evt_data, handle| {
+                if let Some(ui) = about_ui.upgrade() {
+                    match (evt, &evt_data) {
+                        (E::OnButtonClick, _) if handle == ui.about.copy_button => {
+                            ui.about.copy();
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.folder_button => {
+                            ui.about.open_folder();
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.export_button => {
+                            ui.about.export_settings();
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.import_button => {
+                            if let Some(config) = ui.about.import_settings() {
+                                *ui.config.borrow_mut() = config;
+                            }
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.backup_button => {
+                            let config = ui.config.borrow().backup.clone();
+                            ui.about.back_up(&config);
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.restore_button => {
+                            ui.about.restore();
+                        },
+                        (E::OnButtonClick, _) if handle == ui.about.delete_button => {
+                            let config = ui.config.borrow().clone();
+                            if ui.about.delete_data(&config) {
+                                ui.requests.send(Request::ForgetData).unwrap();
+                            }
+                        },
+                        (E::OnWindowClose, nwg::EventData::OnWindowClose(data)) => {
+                            data.close(false);
+                            ui.about.hide();
+                        },
+                        _ => {}
+                    }
+                }
+            };
+            *ui.about_handler.borrow_mut() = Some(nwg::full_bind_event_handler(&ui.about.window.handle, handle_about_events));
+
+            let dpi_ui = Rc::downgrade(&ui.inner);
+            let handle_dpi = move |_hwnd, msg, w: WPARAM, l: LPARAM| {
+                if msg == WM_DPICHANGED {
+                    if let Some(ui) = dpi_ui.upgrade() {
+                        ui.on_dpi_changed(LOWORD(w as u32) as u32, unsafe { &*(l as *const RECT) });
+                    }
+                    return Some(0);
+                }
+                None
+            };
+            *ui.dpi_handler.borrow_mut() = Some(nwg::bind_raw_event_handler(&ui.window.handle, DPI_HANDLER_ID, handle_dpi)?);
+
+            let mini_ui = Rc::downgrade(&ui.inner);
+            let handle_mini = move |_hwnd, msg, w: WPARAM, _l: LPARAM| {
+                let ui = mini_ui.upgrade()?;
+                match msg {
+                    WM_NCHITTEST => Some(HTCAPTION),
+                    WM_NCRBUTTONUP => {
+                        ui.show_tray_menu();
+                        Some(0)
+                    },
+                    WM_EXITSIZEMOVE => {
+                        ui.save_window_geometry();
+                        Some(0)
+                    },
+                    WM_DPICHANGED => {
+                        ui.mini.apply_dpi(LOWORD(w as u32) as u32);
+                        Some(0)
+                    },
+                    WM_CLOSE => {
+                        ui.toggle_mini();
+                        Some(0)
+                    },
+                    _ => None
+                }
+            };
+            *ui.mini_handler.borrow_mut() = Some(nwg::bind_raw_event_handler(&ui.mini.window.handle, MINI_HANDLER_ID, handle_mini)?);
+
+            let overlay_ui = Rc::downgrade(&ui.inner);
+            let handle_overlay = move |_hwnd, msg, _w: WPARAM, _l: LPARAM| {
+                if msg == WM_LBUTTONUP {
+                    let ui = overlay_ui.upgrade()?;
+                    ui.overlay.window.set_visible(false);
+                    ui.on_notification_click();
+                    return Some(0);
+                }
+                None
+            };
+            *ui.overlay_handler.borrow_mut() = Some(nwg::bind_raw_event_handler(&ui.overlay.window.handle, OVERLAY_HANDLER_ID, handle_overlay)?);
+
+            let system_ui = Rc::downgrade(&ui.inner);
+            let activate = instance::activate_message();
+            let handle_system = move |_hwnd, msg, w: WPARAM, _l: LPARAM| {
+                match msg {
+                    WM_POWERBROADCAST => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            ui.on_power_event(w);
+                        }
+                        Some(1)
+                    },
+                    WM_WTSSESSION_CHANGE => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            ui.on_session_change(w);
+                        }
+                        Some(0)
+                    },
+                    _ if msg == activate => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            if w == instance::SHOW_STATS {
+                                ui.show_stats();
+                            } else {
+                                ui.window.set_visible(true);
+                                ui.window.set_focus();
+                            }
+                        }
+                        Some(0)
+                    },
+                    WM_HOTKEY => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            ui.on_hotkey(w);
+                        }
+                        Some(0)
+                    },
+                    WM_SETTINGCHANGE | WM_SYSCOLORCHANGE => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            ui.on_theme_changed();
+                        }
+                        None
+                    },
+                    WM_QUERYENDSESSION => {
+                        if let Some(ui) = system_ui.upgrade() {
+                            ui.save_state();
+                        }
+                        Some(1)
+                    },
+                    WM_ENDSESSION => {
+                        if w != 0 {
+                            if let Some(ui) = system_ui.upgrade() {
+                                ui.exit();
+                            }
+                        }
+                        Some(0)
+                    },
+                    _ => None
+                }
+            };
+            *ui.system_handler.borrow_mut() = Some(nwg::bind_raw_event_handler(&ui.window.handle, SYSTEM_HANDLER_ID, handle_system)?);
+            session::register(&ui.window);
+            ui.update_session_kind();
+            let hotkeys = Hotkeys::register(&ui.window, &ui.config.borrow().hotkeys);
+            *ui.hotkeys.borrow_mut() = Some(hotkeys);
+
+            Ok(ui)
         }
     }
 
     impl Drop for BasicAppUi {
         /// To make sure that everything is freed without issues, the default handler must be unbound.
         fn drop(&mut self) {
+            if let Some(handler) = self.system_handler.borrow_mut().take() {
+                session::unregister(&self.window);
+                if let Some(hotkeys) = self.hotkeys.borrow_mut().take() {
+                    hotkeys.unregister(&self.window);
+                }
+                let _ = nwg::unbind_raw_event_handler(&handler);
+            }
+            if let Some(handler) = self.dpi_handler.borrow_mut().take() {
+                let _ = nwg::unbind_raw_event_handler(&handler);
+            }
+            if let Some(handler) = self.mini_handler.borrow_mut().take() {
+                let _ = nwg::unbind_raw_event_handler(&handler);
+            }
+            if let Some(handler) = self.overlay_handler.borrow_mut().take() {
+                let _ = nwg::unbind_raw_event_handler(&handler);
+            }
+            self.mini.unbind();
+            self.overlay.unbind();
+            for painter in self.theme_painters.borrow_mut().iter_mut() {
+                painter.unbind();
+            }
+            if let Some(handler) = self.stats_handler.borrow().as_ref() {
+                nwg::unbind_event_handler(handler);
+            }
+            if let Some(handler) = self.about_handler.borrow().as_ref() {
+                nwg::unbind_event_handler(handler);
+            }
             let handler = self.default_handler.borrow();
             if handler.is_some() {
                 nwg::unbind_event_handler(handler.as_ref().unwrap());
@@ -184,26 +1884,54 @@ mod basic_app_ui {
     }
 }
 
-pub fn start(r: Receiver<Event>) {
+pub fn start(r: Receiver<Event>, requests: Sender<Request>, stopped: Receiver<()>, config: Config, problems: Vec<Problem>) {
+    dpi::enable_per_monitor_awareness();
     nwg::init().expect("Failed to init Native Windows GUI");
     nwg::Font::set_global_family("Segoe UI").expect("Failed to set default font");
 
     let (uis, uir) = unbounded();
-    let ui = BasicApp::build_ui(BasicApp::new(uir)).expect("Failed to build UI");
+    let ui = BasicApp::build_ui(BasicApp::new(uir, requests, stopped, config)).expect("Failed to build UI");
+
+    if let Some(report) = crash::take_report() {
+        let content = tr("crash-message");
+        let params = nwg::MessageParams {
+            title: "Pauza",
+            content: &content,
+            buttons: nwg::MessageButtons::YesNo,
+            icons: nwg::MessageIcons::Warning,
+        };
+        if nwg::message(&params) == nwg::MessageChoice::Yes {
+            open_path(&report);
+        }
+    }
+
+    if !problems.is_empty() {
+        let path = config_path();
+        let list: Vec<String> = problems.iter().map(|problem| format!("• {}", problem)).collect();
+        let content = format!("{}\n\n{}\n\n{}",
+                              tr_args("config-problems", &[("path", &path.display())]),
+                              list.join("\n"),
+                              tr("config-open"));
+        let params = nwg::MessageParams {
+            title: "Pauza",
+            content: &content,
+            buttons: nwg::MessageButtons::YesNo,
+            icons: nwg::MessageIcons::Warning,
+        };
+        if nwg::message(&params) == nwg::MessageChoice::Yes {
+            open_path(&path);
+        }
+    }
+
+    update::watch(ui.config.borrow().updates.clone(), ui.update_reporter(false));
+    sync::watch(ui.config.borrow().sync.clone(), ui.sync_reporter());
 
     let notice = &ui.notice;
     let sender = notice.sender();
     thread::spawn(move || {
-        loop {
-            match r.recv() {
-                Ok(event) => {
-                    uis.send(event).unwrap();
-                    sender.notice();
-                },
-                Err(_e) => {
-                    break;
-                }
-            }
+        while let Ok(event) = r.recv() {
+            uis.send(event).unwrap();
+            sender.notice();
         }
     });
 
@@ -0,0 +1,286 @@
+use std::env::consts::{ARCH, OS};
+use std::fs;
+use std::path::PathBuf;
+use crate::backup::{self, BackupError};
+use crate::config::{config_path, data_dir, BackupConfig, Config, InputConfig};
+use crate::history::History;
+use crate::i18n::{self, tr, tr_args};
+use crate::privacy;
+use crate::settings;
+use crate::sync;
+use crate::{logger, VERSION};
+
+const MARGIN: i32 = 16;
+const BUTTON_HEIGHT: i32 = 28;
+const BUTTON_WIDTH: i32 = 150;
+const WIDTH: i32 = 680;
+const HEIGHT: i32 = 344;
+
+/// How idle time is being detected.
+fn backend(input: &InputConfig, degraded: bool) -> String {
+    let mut backend = if degraded {
+        "cursor position (GetLastInputInfo is failing)".to_string()
+    } else {
+        "GetLastInputInfo".to_string()
+    };
+    if input.weighted() && !degraded {
+        backend += &format!(" + raw input (keyboard x{}, mouse x{})", input.keyboard_weight, input.mouse_weight);
+    }
+    if input.gamepad {
+        backend += " + gamepads";
+    }
+    if input.audio {
+        backend += " + audio output";
+    }
+    backend
+}
+
+/// What support needs to know about this build and where it keeps its files.
+pub fn diagnostics(input: &InputConfig, degraded: bool) -> String {
+    [
+        format!("Pauza {}", VERSION),
+        format!("Platform: {} {}", OS, ARCH),
+        format!("Language: {}", i18n::language()),
+        format!("Idle detection: {}", backend(input, degraded)),
+        format!("Config: {}", config_path().display()),
+        format!("History: {}", History::file().display()),
+        format!("Log: {}", logger::path().display()),
+    ].join("\r\n")
+}
+
+#[derive(Default)]
+pub struct AboutWindow {
+    pub window: nwg::Window,
+    text: nwg::TextBox,
+    pub copy_button: nwg::Button,
+    pub folder_button: nwg::Button,
+    pub export_button: nwg::Button,
+    pub import_button: nwg::Button,
+    pub backup_button: nwg::Button,
+    pub restore_button: nwg::Button,
+    pub delete_button: nwg::Button,
+    export_dialog: nwg::FileDialog,
+    import_dialog: nwg::FileDialog,
+    backup_dialog: nwg::FileDialog,
+    restore_dialog: nwg::FileDialog,
+}
+
+impl AboutWindow {
+    pub fn build(&mut self, parent: &nwg::Window) -> Result<(), nwg::NwgError> {
+        nwg::Window::builder()
+            .flags(nwg::WindowFlags::WINDOW)
+            .size((WIDTH, HEIGHT))
+            .title(&tr("about-title"))
+            .parent(Some(parent))
+            .build(&mut self.window)?;
+
+        nwg::TextBox::builder()
+            .flags(nwg::TextBoxFlags::VISIBLE | nwg::TextBoxFlags::VSCROLL | nwg::TextBoxFlags::AUTOVSCROLL)
+            .readonly(true)
+            .position((MARGIN, MARGIN))
+            .size((WIDTH - MARGIN * 2, HEIGHT - MARGIN * 4 - BUTTON_HEIGHT * 2))
+            .parent(&self.window)
+            .build(&mut self.text)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-copy"))
+            .position((MARGIN, HEIGHT - MARGIN * 2 - BUTTON_HEIGHT * 2))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.copy_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-open-folder"))
+            .position((MARGIN * 2 + BUTTON_WIDTH, HEIGHT - MARGIN * 2 - BUTTON_HEIGHT * 2))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.folder_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-back-up"))
+            .position((MARGIN * 3 + BUTTON_WIDTH * 2, HEIGHT - MARGIN * 2 - BUTTON_HEIGHT * 2))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.backup_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-export-settings"))
+            .position((MARGIN, HEIGHT - MARGIN - BUTTON_HEIGHT))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.export_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-import-settings"))
+            .position((MARGIN * 2 + BUTTON_WIDTH, HEIGHT - MARGIN - BUTTON_HEIGHT))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.import_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-restore"))
+            .position((MARGIN * 3 + BUTTON_WIDTH * 2, HEIGHT - MARGIN - BUTTON_HEIGHT))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.restore_button)?;
+
+        nwg::Button::builder()
+            .text(&tr("about-delete-data"))
+            .position((MARGIN * 4 + BUTTON_WIDTH * 3, HEIGHT - MARGIN - BUTTON_HEIGHT))
+            .size((BUTTON_WIDTH, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.delete_button)?;
+
+        nwg::FileDialog::builder()
+            .title(tr("about-export-settings-title"))
+            .action(nwg::FileDialogAction::Save)
+            .filters("TOML(*.toml)")
+            .build(&mut self.export_dialog)?;
+
+        nwg::FileDialog::builder()
+            .title(tr("about-import-settings-title"))
+            .action(nwg::FileDialogAction::Open)
+            .filters("TOML(*.toml)")
+            .build(&mut self.import_dialog)?;
+
+        nwg::FileDialog::builder()
+            .title(tr("about-back-up-title"))
+            .action(nwg::FileDialogAction::OpenDirectory)
+            .build(&mut self.backup_dialog)?;
+
+        nwg::FileDialog::builder()
+            .title(tr("about-restore-title"))
+            .action(nwg::FileDialogAction::OpenDirectory)
+            .build(&mut self.restore_dialog)
+    }
+
+    pub fn show(&self, input: &InputConfig, degraded: bool) {
+        self.text.set_text(&diagnostics(input, degraded));
+        self.window.set_visible(true);
+        self.window.set_focus();
+    }
+
+    pub fn hide(&self) {
+        self.window.set_visible(false);
+    }
+
+    pub fn copy(&self) {
+        nwg::Clipboard::set_data_text(&self.window, &self.text.text());
+    }
+
+    pub fn open_folder(&self) {
+        super::open_path(&data_dir());
+    }
+
+    fn selected(&self, dialog: &nwg::FileDialog) -> Option<String> {
+        if !dialog.run(Some(&self.window)) {
+            return None;
+        }
+        dialog.get_selected_item()
+            .map_err(|e| log::error!("{}", e))
+            .ok()
+    }
+
+    pub fn export_settings(&self) {
+        let path = match self.selected(&self.export_dialog) {
+            Some(path) => path,
+            None => return
+        };
+        match settings::export(path.as_ref()) {
+            Ok(()) => nwg::simple_message(&tr("about-export-settings-title"), &tr_args("about-export-settings-done", &[("path", &path)])),
+            Err(e) => nwg::error_message(&tr("about-export-settings-title"), &tr_args("about-settings-failed", &[("error", &e)]))
+        };
+    }
+
+    /// Returns the imported config, for the running app to save from now on.
+    pub fn import_settings(&self) -> Option<Config> {
+        let path = self.selected(&self.import_dialog)?;
+        match settings::import(path.as_ref()) {
+            Ok((config, problems)) => {
+                let mut message = tr("about-import-settings-done");
+                for problem in problems {
+                    message += &format!("\n{}", problem);
+                }
+                nwg::simple_message(&tr("about-import-settings-title"), &message);
+                Some(config)
+            },
+            Err(e) => {
+                nwg::error_message(&tr("about-import-settings-title"), &tr_args("about-settings-failed", &[("error", &e)]));
+                None
+            }
+        }
+    }
+
+    /// Backs up to the folder set in the config, or else to one chosen now.
+    pub fn back_up(&self, config: &BackupConfig) {
+        let folder = match config.folder.clone().or_else(|| self.selected(&self.backup_dialog)) {
+            Some(folder) => PathBuf::from(folder),
+            None => return
+        };
+        let result = fs::create_dir_all(&folder)
+            .map_err(BackupError::from)
+            .and_then(|()| backup::back_up(&folder, config.keep));
+        match result {
+            Ok(backup) => nwg::simple_message(&tr("about-back-up-title"), &tr_args("about-back-up-done", &[("path", &backup.display())])),
+            Err(e) => nwg::error_message(&tr("about-back-up-title"), &tr_args("about-settings-failed", &[("error", &e)]))
+        };
+    }
+
+    /// Sets a chosen backup to be restored on the next start, once the user confirms.
+    pub fn restore(&self) {
+        let folder = match self.selected(&self.restore_dialog) {
+            Some(folder) => folder,
+            None => return
+        };
+        let title = tr("about-restore-title");
+        let content = tr_args("about-restore-confirm", &[("path", &folder)]);
+        let params = nwg::MessageParams {
+            title: &title,
+            content: &content,
+            buttons: nwg::MessageButtons::YesNo,
+            icons: nwg::MessageIcons::Warning,
+        };
+        if nwg::message(&params) != nwg::MessageChoice::Yes {
+            return;
+        }
+        match backup::restore(folder.as_ref()) {
+            Ok(()) => nwg::simple_message(&title, &tr("about-restore-done")),
+            Err(e) => nwg::error_message(&title, &tr_args("about-settings-failed", &[("error", &e)]))
+        };
+    }
+    /// Deletes the history once the user confirms, with the backups of it, the copy shared
+    /// through sync, and the saved timer and daily count, keeping the settings. Returns
+    /// whether anything was deleted, for the timer to start over.
+    pub fn delete_data(&self, config: &Config) -> bool {
+        let title = tr("about-delete-data-title");
+        let content = tr("about-delete-data-confirm");
+        let params = nwg::MessageParams {
+            title: &title,
+            content: &content,
+            buttons: nwg::MessageButtons::YesNo,
+            icons: nwg::MessageIcons::Warning,
+        };
+        if nwg::message(&params) != nwg::MessageChoice::Yes {
+            return false;
+        }
+        let mut errors = Vec::new();
+        if let Err(e) = History::open().and_then(|history| history.delete_all()) {
+            errors.push(e.to_string());
+        }
+        // A guest's history is all there is of theirs.
+        if !privacy::guest() {
+            if let Err(e) = backup::delete_all(&config.backup) {
+                errors.push(e.to_string());
+            }
+            if let Err(e) = sync::forget_history(&config.sync) {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            nwg::simple_message(&title, &tr("about-delete-data-done"));
+        } else {
+            nwg::error_message(&title, &tr_args("about-settings-failed", &[("error", &errors.join("\r\n"))]));
+        }
+        true
+    }
+}
@@ -0,0 +1,155 @@
+// The names follow oleacc.h.
+#![allow(non_snake_case, non_upper_case_globals, clippy::upper_case_acronyms)]
+
+use winapi::{DEFINE_GUID, RIDL};
+use winapi::Interface;
+use winapi::ctypes::c_int;
+use winapi::shared::guiddef::GUID;
+use winapi::shared::minwindef::{BYTE, DWORD};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::{HRESULT, SUCCEEDED};
+use winapi::shared::wtypes::{VARTYPE, VT_I4};
+use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+use winapi::um::combaseapi::CoCreateInstance;
+use winapi::um::oaidl::VARIANT;
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::LPCWSTR;
+use winapi::um::winuser::{NotifyWinEvent, CHILDID_SELF, EVENT_OBJECT_LIVEREGIONCHANGED, OBJID_CLIENT};
+use std::mem::zeroed;
+use std::ptr;
+use super::to_wide;
+
+type MSAAPROPID = GUID;
+
+DEFINE_GUID!{CLSID_AccPropServices,
+    0xb5f8350b, 0x0548, 0x48b1, 0xa6, 0xee, 0x88, 0xbd, 0x00, 0xb4, 0xa5, 0xe7}
+DEFINE_GUID!{PROPID_ACC_NAME,
+    0x608d3df8, 0x8128, 0x4aa7, 0xa4, 0x28, 0xf5, 0x5e, 0x49, 0x26, 0x72, 0x91}
+DEFINE_GUID!{PROPID_ACC_DESCRIPTION,
+    0x4d48dfe4, 0xbd3f, 0x491f, 0xa6, 0x48, 0x49, 0x2d, 0x6f, 0x20, 0xc5, 0x88}
+DEFINE_GUID!{LiveSetting_Property_GUID,
+    0xc12bcd8e, 0x2a8e, 0x4950, 0x8a, 0xe7, 0x36, 0x25, 0x11, 0x1d, 0x58, 0xeb}
+
+/// UI Automation's `LiveSetting` value for changes read out once the reader is idle.
+const POLITE: i32 = 1;
+
+RIDL!{#[uuid(0x6e26e776, 0x04f0, 0x495d, 0x80, 0xe4, 0x33, 0x30, 0x35, 0x2e, 0x31, 0x69)]
+interface IAccPropServices(IAccPropServicesVtbl): IUnknown(IUnknownVtbl) {
+    fn SetPropValue(
+        pIDString: *const BYTE,
+        dwIDStringLen: DWORD,
+        idProp: MSAAPROPID,
+        var: VARIANT,
+    ) -> HRESULT,
+    fn SetPropServer(
+        pIDString: *const BYTE,
+        dwIDStringLen: DWORD,
+        paProps: *const MSAAPROPID,
+        cProps: c_int,
+        pServer: *mut IUnknown,
+        annoScope: c_int,
+    ) -> HRESULT,
+    fn ClearProps(
+        pIDString: *const BYTE,
+        dwIDStringLen: DWORD,
+        paProps: *const MSAAPROPID,
+        cProps: c_int,
+    ) -> HRESULT,
+    fn SetHwndProp(
+        hwnd: HWND,
+        idObject: DWORD,
+        idChild: DWORD,
+        idProp: MSAAPROPID,
+        var: VARIANT,
+    ) -> HRESULT,
+    fn SetHwndPropStr(
+        hwnd: HWND,
+        idObject: DWORD,
+        idChild: DWORD,
+        idProp: MSAAPROPID,
+        str: LPCWSTR,
+    ) -> HRESULT,
+}}
+
+/// Tells screen readers about controls beyond what Windows finds out from the controls
+/// themselves, through Dynamic Annotation.
+///
+/// COM must already be initialized on the calling thread, which `nwg::init` takes care of.
+pub struct Accessibility {
+    services: *mut IAccPropServices,
+}
+
+impl Accessibility {
+    pub fn new() -> Result<Accessibility, nwg::NwgError> {
+        let mut services: *mut IAccPropServices = ptr::null_mut();
+        let result = unsafe {
+            CoCreateInstance(&CLSID_AccPropServices,
+                             ptr::null_mut(),
+                             CLSCTX_INPROC_SERVER,
+                             &IAccPropServices::uuidof(),
+                             &mut services as *mut _ as *mut _)
+        };
+        if !SUCCEEDED(result) || services.is_null() {
+            return Err(nwg::NwgError::initialization("Failed to create IAccPropServices"));
+        }
+        Ok(Accessibility { services })
+    }
+
+    fn set_text(&self, handle: &nwg::ControlHandle, property: MSAAPROPID, text: &str) {
+        let hwnd = match handle.hwnd() {
+            Some(hwnd) => hwnd,
+            None => return
+        };
+        let text = to_wide(text);
+        let result = unsafe {
+            (*self.services).SetHwndPropStr(hwnd, OBJID_CLIENT as DWORD, CHILDID_SELF as DWORD, property, text.as_ptr())
+        };
+        if !SUCCEEDED(result) {
+            log::warn!("Failed to annotate a control for screen readers: {:#010x}", result);
+        }
+    }
+
+    /// What a control is read out as, for controls like progress bars that have no text.
+    pub fn set_name(&self, handle: &nwg::ControlHandle, name: &str) {
+        self.set_text(handle, PROPID_ACC_NAME, name);
+    }
+
+    /// Read out after the name, for content that is painted rather than made of controls.
+    pub fn set_description(&self, handle: &nwg::ControlHandle, description: &str) {
+        self.set_text(handle, PROPID_ACC_DESCRIPTION, description);
+    }
+
+    /// Has changes to the text of a control read out whenever `announce` is called,
+    /// wherever the focus is.
+    pub fn set_live(&self, handle: &nwg::ControlHandle) {
+        let hwnd = match handle.hwnd() {
+            Some(hwnd) => hwnd,
+            None => return
+        };
+        let mut polite: VARIANT = unsafe { zeroed() };
+        unsafe {
+            let variant = polite.n1.n2_mut();
+            variant.vt = VT_I4 as VARTYPE;
+            *variant.n3.lVal_mut() = POLITE;
+        }
+        let result = unsafe {
+            (*self.services).SetHwndProp(hwnd, OBJID_CLIENT as DWORD, CHILDID_SELF as DWORD, LiveSetting_Property_GUID, polite)
+        };
+        if !SUCCEEDED(result) {
+            log::warn!("Failed to make a control live for screen readers: {:#010x}", result);
+        }
+    }
+}
+
+/// Tells screen readers the text of a control made live with `set_live` has changed.
+pub fn announce(handle: &nwg::ControlHandle) {
+    if let Some(hwnd) = handle.hwnd() {
+        unsafe { NotifyWinEvent(EVENT_OBJECT_LIVEREGIONCHANGED, hwnd, OBJID_CLIENT, CHILDID_SELF) };
+    }
+}
+
+impl Drop for Accessibility {
+    fn drop(&mut self) {
+        unsafe { (*self.services).Release() };
+    }
+}
@@ -0,0 +1,261 @@
+use crossbeam::channel::Receiver;
+use winapi::Interface;
+use winapi::shared::minwindef::{BOOL, FALSE, TRUE};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx};
+use winapi::um::endpointvolume::{IAudioEndpointVolume, IAudioMeterInformation};
+use winapi::um::mmdeviceapi::{eConsole, eRender, CLSID_MMDeviceEnumerator, IMMDevice, IMMDeviceEnumerator};
+use winapi::um::objbase::COINIT_MULTITHREADED;
+use winapi::um::winuser::{keybd_event, KEYEVENTF_KEYUP, VK_MEDIA_PLAY_PAUSE};
+use std::ptr;
+use std::time::{Duration, Instant};
+use crate::break_audio::{Quieted, Restore, Sound};
+use crate::config::{BreakAudio, BreakAudioConfig};
+use crate::{Event, State};
+
+/// Peak level, out of 1.0, below which the speakers count as silent.
+const SILENCE: f32 = 0.01;
+
+/// An interface of the default playback device.
+fn activate<T: Interface>() -> Option<*mut T> {
+    let mut enumerator: *mut IMMDeviceEnumerator = ptr::null_mut();
+    let result = unsafe {
+        CoCreateInstance(&CLSID_MMDeviceEnumerator,
+                         ptr::null_mut(),
+                         CLSCTX_INPROC_SERVER,
+                         &IMMDeviceEnumerator::uuidof(),
+                         &mut enumerator as *mut _ as *mut _)
+    };
+    if !SUCCEEDED(result) || enumerator.is_null() {
+        return None;
+    }
+
+    let mut device: *mut IMMDevice = ptr::null_mut();
+    let result = unsafe { (*enumerator).GetDefaultAudioEndpoint(eRender, eConsole, &mut device) };
+    unsafe { (*enumerator).Release() };
+    if !SUCCEEDED(result) || device.is_null() {
+        return None;
+    }
+
+    let mut interface: *mut T = ptr::null_mut();
+    let result = unsafe {
+        (*device).Activate(&T::uuidof(),
+                           CLSCTX_INPROC_SERVER,
+                           ptr::null_mut(),
+                           &mut interface as *mut _ as *mut _)
+    };
+    unsafe { (*device).Release() };
+    if !SUCCEEDED(result) || interface.is_null() {
+        return None;
+    }
+    Some(interface)
+}
+
+/// The peak meter of the default playback device.
+struct Meter {
+    meter: *mut IAudioMeterInformation,
+}
+
+impl Meter {
+    fn open() -> Option<Meter> {
+        activate().map(|meter| Meter { meter })
+    }
+
+    fn peak(&self) -> Option<f32> {
+        let mut peak = 0.0;
+        if SUCCEEDED(unsafe { (*self.meter).GetPeakValue(&mut peak) }) {
+            Some(peak)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        unsafe { (*self.meter).Release() };
+    }
+}
+
+/// Notices sound playing, so watching a video without touching anything isn't idle.
+///
+/// Initializes COM on the calling thread, so it must stay on the thread that created it.
+pub struct AudioMonitor {
+    meter: Option<Meter>,
+    last_sound: Option<Instant>,
+}
+
+impl AudioMonitor {
+    pub fn new() -> AudioMonitor {
+        unsafe { CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED) };
+        AudioMonitor {
+            meter: None,
+            last_sound: None,
+        }
+    }
+
+    /// Time since sound last played, or `None` if it hasn't yet.
+    pub fn idle_time(&mut self) -> Option<Duration> {
+        if self.meter.is_none() {
+            self.meter = Meter::open();
+        }
+        // The default device can change or go away; open it again on the next call.
+        match self.meter.as_ref().and_then(|meter| meter.peak()) {
+            Some(peak) if peak > SILENCE => self.last_sound = Some(Instant::now()),
+            Some(_) => {},
+            None => self.meter = None
+        }
+        self.last_sound.map(|sound| sound.elapsed())
+    }
+}
+
+/// The master volume of the default playback device.
+struct Volume {
+    volume: *mut IAudioEndpointVolume,
+}
+
+impl Volume {
+    fn open() -> Option<Volume> {
+        activate().map(|volume| Volume { volume })
+    }
+
+    fn level(&self) -> Option<f32> {
+        let mut level = 0.0;
+        if SUCCEEDED(unsafe { (*self.volume).GetMasterVolumeLevelScalar(&mut level) }) {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    fn set_level(&self, level: f32) -> bool {
+        SUCCEEDED(unsafe { (*self.volume).SetMasterVolumeLevelScalar(level, ptr::null()) })
+    }
+
+    fn muted(&self) -> Option<bool> {
+        let mut muted: BOOL = FALSE;
+        if SUCCEEDED(unsafe { (*self.volume).GetMute(&mut muted) }) {
+            Some(muted != FALSE)
+        } else {
+            None
+        }
+    }
+
+    fn set_muted(&self, muted: bool) -> bool {
+        SUCCEEDED(unsafe { (*self.volume).SetMute(if muted { TRUE } else { FALSE }, ptr::null()) })
+    }
+}
+
+impl Drop for Volume {
+    fn drop(&mut self) {
+        unsafe { (*self.volume).Release() };
+    }
+}
+
+fn press_play_pause() {
+    unsafe {
+        keybd_event(VK_MEDIA_PLAY_PAUSE as u8, 0, 0, 0);
+        keybd_event(VK_MEDIA_PLAY_PAUSE as u8, 0, KEYEVENTF_KEYUP, 0);
+    }
+}
+
+fn quiet(config: &BreakAudioConfig) -> Option<Quieted> {
+    match config.action {
+        BreakAudio::Nothing => None,
+        BreakAudio::Duck => {
+            let volume = Volume::open()?;
+            let before = volume.level()?;
+            let after = before * config.duck_percent as f32 / 100.0;
+            if volume.set_level(after) {
+                Some(Quieted::Ducked { before, after })
+            } else {
+                None
+            }
+        },
+        // Left alone if already muted, so the break doesn't unmute it.
+        BreakAudio::Mute => {
+            let volume = Volume::open()?;
+            if volume.muted()? || !volume.set_muted(true) {
+                return None;
+            }
+            Some(Quieted::Muted)
+        },
+        // Pressing play/pause with nothing playing would start something at the end.
+        BreakAudio::Pause => {
+            let peak = Meter::open()?.peak()?;
+            if peak <= SILENCE {
+                return None;
+            }
+            press_play_pause();
+            Some(Quieted::Paused)
+        }
+    }
+}
+
+/// Puts the sound back, unless it was changed during the break, and forgets about it.
+fn restore(quieted: Quieted) {
+    let volume = Volume::open();
+    let sound = Sound {
+        level: volume.as_ref().and_then(Volume::level),
+        muted: volume.as_ref().and_then(Volume::muted),
+        playing: Meter::open().and_then(|meter| meter.peak()).map(|peak| peak > SILENCE),
+    };
+    let restored = match (quieted.restore(sound), volume) {
+        (Some(Restore::SetLevel(level)), Some(volume)) => volume.set_level(level),
+        (Some(Restore::Unmute), Some(volume)) => volume.set_muted(false),
+        (Some(Restore::PressPlay), _) => {
+            press_play_pause();
+            true
+        },
+        (Some(Restore::Nothing), _) => true,
+        _ => false
+    };
+    if !restored {
+        log::warn!("Couldn't restore the sound after the break");
+    }
+    Quieted::forget();
+}
+
+/// Puts back the sound a break left down when pauza stopped before the break was over.
+/// Playback is only resumed right away, as from the crash handler: on the next start it
+/// could be long after, when starting it again would come out of the blue.
+pub fn restore_sound(resume_playback: bool) {
+    let quieted = match Quieted::load() {
+        Some(quieted) => quieted,
+        None => return
+    };
+    unsafe { CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED) };
+    if quieted == Quieted::Paused && !resume_playback {
+        Quieted::forget();
+        return;
+    }
+    log::info!("Restoring the sound after a break that didn't end: {:?}", quieted);
+    restore(quieted);
+}
+
+/// Ducks, mutes or pauses the sound while on a break, as `config` says.
+pub fn quiet_breaks(config: BreakAudioConfig, r: Receiver<Event>) {
+    unsafe { CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED) };
+    let mut on_break = false;
+    let mut quieted = None;
+    for event in r.iter() {
+        if let Event::UpdateState(state) = event {
+            let now_on_break = state == State::OnBreak;
+            if now_on_break && !on_break {
+                quieted = quiet(&config);
+                if let Some(Err(e)) = quieted.map(|quieted| quieted.save()) {
+                    log::error!("{}", e);
+                }
+            } else if !now_on_break && on_break {
+                if let Some(quieted) = quieted.take() {
+                    restore(quieted);
+                }
+            }
+            on_break = now_on_break;
+        }
+    }
+    if let Some(quieted) = quieted {
+        restore(quieted);
+    }
+}
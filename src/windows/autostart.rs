@@ -0,0 +1,94 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+use winapi::um::winnt::REG_SZ;
+use winapi::um::winreg::{RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, RRF_RT_REG_SZ};
+use std::env;
+use std::io;
+use std::path::Path;
+use std::ptr;
+use super::to_wide;
+
+/// Programs listed here are started when the user logs in.
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+const VALUE_NAME: &str = "Pauza";
+
+fn check(result: i32) -> io::Result<()> {
+    if result == ERROR_SUCCESS as i32 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(result))
+    }
+}
+
+fn command_line() -> io::Result<String> {
+    Ok(format!("\"{}\"", env::current_exe()?.display()))
+}
+
+/// The executable of a command line, which is quoted when the path has spaces.
+fn executable(command: &str) -> &str {
+    let command = command.trim();
+    match command.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(rest),
+        None => command
+    }
+}
+
+/// The command line pauza is started at login with, if it is.
+fn registered() -> Option<String> {
+    let key = to_wide(RUN_KEY);
+    let name = to_wide(VALUE_NAME);
+    let mut size: DWORD = 0;
+    let get = |data: *mut u16, size: &mut DWORD| unsafe {
+        RegGetValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr(), RRF_RT_REG_SZ, ptr::null_mut(), data as *mut _, size)
+    };
+    check(get(ptr::null_mut(), &mut size)).ok()?;
+    let mut value = vec![0u16; size as usize / 2];
+    check(get(value.as_mut_ptr(), &mut size)).ok()?;
+    let length = value.iter().position(|&c| c == 0).unwrap_or(value.len());
+    Some(String::from_utf16_lossy(&value[..length]))
+}
+
+pub fn is_enabled() -> bool {
+    registered().is_some()
+}
+
+/// Starts this executable when the user logs in.
+pub fn enable() -> io::Result<()> {
+    let key = to_wide(RUN_KEY);
+    let name = to_wide(VALUE_NAME);
+    let value = to_wide(&command_line()?);
+    check(unsafe {
+        RegSetKeyValueW(HKEY_CURRENT_USER,
+                        key.as_ptr(),
+                        name.as_ptr(),
+                        REG_SZ,
+                        value.as_ptr() as *const _,
+                        (value.len() * 2) as DWORD)
+    })
+}
+
+pub fn disable() -> io::Result<()> {
+    let key = to_wide(RUN_KEY);
+    let name = to_wide(VALUE_NAME);
+    let result = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr()) };
+    if result == ERROR_FILE_NOT_FOUND as i32 {
+        return Ok(());
+    }
+    check(result)
+}
+
+/// Points the login entry at this executable if the one it names is gone, as when
+/// pauza was moved or reinstalled somewhere else.
+pub fn repair() {
+    let command = match registered() {
+        Some(command) => command,
+        None => return
+    };
+    if Path::new(executable(&command)).exists() {
+        return;
+    }
+    match enable() {
+        Ok(()) => log::info!("The autostart entry named {}, which is gone, and now names this executable", command),
+        Err(e) => log::error!("Failed to repair the autostart entry: {}", e)
+    }
+}
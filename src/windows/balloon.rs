@@ -0,0 +1,29 @@
+use crate::notifications::{Notification, NotificationBackend};
+
+/// Notifications from the tray icon, each taking the place of the one before.
+pub struct Balloon<'a> {
+    pub tray: &'a nwg::TrayNotification,
+    pub icon: &'a nwg::Icon,
+    /// Pauza played a sound of its own in place of the notification's, or the sound of its
+    /// event is turned off.
+    pub silent: bool,
+}
+
+impl NotificationBackend for Balloon<'_> {
+    fn name(&self) -> &'static str {
+        "balloon"
+    }
+
+    fn show(&self, notification: &Notification) -> Result<(), String> {
+        let mut flags = nwg::TrayNotificationFlags::USER_ICON
+            | nwg::TrayNotificationFlags::LARGE_ICON;
+        if self.silent {
+            flags |= nwg::TrayNotificationFlags::SILENT;
+        }
+        self.tray.show(&notification.message,
+                       Some(&notification.title),
+                       Some(flags),
+                       Some(self.icon));
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::winnt::KEY_READ;
+use winapi::um::winreg::{RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY_CURRENT_USER, RRF_RT_REG_QWORD};
+use std::mem::size_of;
+use std::ptr;
+use super::to_wide;
+
+/// Windows records when each app last started and stopped using a capability here.
+const CONSENT_STORE_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore";
+const CAPABILITIES: [&str; 2] = ["microphone", "webcam"];
+/// Desktop apps are listed one level further down than Store apps.
+const NON_PACKAGED: &str = "NonPackaged";
+const MAX_KEY_NAME: usize = 256;
+
+struct Key(HKEY);
+
+impl Key {
+    fn open(parent: HKEY, path: &str) -> Option<Key> {
+        let path = to_wide(path);
+        let mut key = ptr::null_mut();
+        let result = unsafe { RegOpenKeyExW(parent, path.as_ptr(), 0, KEY_READ, &mut key) };
+        if result == ERROR_SUCCESS as i32 {
+            Some(Key(key))
+        } else {
+            None
+        }
+    }
+
+    fn subkeys(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut name = [0u16; MAX_KEY_NAME];
+        for index in 0.. {
+            let mut length = MAX_KEY_NAME as DWORD;
+            let result = unsafe {
+                RegEnumKeyExW(self.0,
+                              index,
+                              name.as_mut_ptr(),
+                              &mut length,
+                              ptr::null_mut(),
+                              ptr::null_mut(),
+                              ptr::null_mut(),
+                              ptr::null_mut())
+            };
+            if result != ERROR_SUCCESS as i32 {
+                break;
+            }
+            names.push(String::from_utf16_lossy(&name[..length as usize]));
+        }
+        names
+    }
+
+    fn qword(&self, name: &str) -> Option<u64> {
+        let name = to_wide(name);
+        let mut value: u64 = 0;
+        let mut size = size_of::<u64>() as DWORD;
+        let result = unsafe {
+            RegGetValueW(self.0,
+                         ptr::null(),
+                         name.as_ptr(),
+                         RRF_RT_REG_QWORD,
+                         ptr::null_mut(),
+                         &mut value as *mut u64 as *mut _,
+                         &mut size)
+        };
+        if result == ERROR_SUCCESS as i32 {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// An app that started using the capability and hasn't stopped yet.
+    fn in_use(&self) -> bool {
+        self.qword("LastUsedTimeStart").unwrap_or(0) != 0 && self.qword("LastUsedTimeStop") == Some(0)
+    }
+
+    fn any_in_use(&self) -> bool {
+        self.subkeys().iter().any(|name| match Key::open(self.0, name) {
+            Some(app) if name == NON_PACKAGED => app.any_in_use(),
+            Some(app) => app.in_use(),
+            None => false
+        })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        unsafe { RegCloseKey(self.0) };
+    }
+}
+
+/// True while any app is recording from the microphone or the webcam, as in a call.
+#[tracing::instrument(level = "trace", ret)]
+pub fn is_capturing() -> bool {
+    CAPABILITIES.iter().any(|capability| {
+        Key::open(HKEY_CURRENT_USER, &format!("{}\\{}", CONSENT_STORE_KEY, capability))
+            .is_some_and(|key| key.any_in_use())
+    })
+}
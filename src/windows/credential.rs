@@ -0,0 +1,43 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::ERROR_NOT_FOUND;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::wincred::{CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC, PCREDENTIALW};
+use std::mem::zeroed;
+use std::ptr::null_mut;
+use std::slice;
+use super::to_wide;
+
+/// Keeps `secret` in the Windows credential store under `target`, for the current account.
+pub fn write_credential(target: &str, secret: &str) -> Result<(), String> {
+    let mut target = to_wide(target);
+    let mut blob = secret.as_bytes().to_vec();
+    let mut credential: CREDENTIALW = unsafe { zeroed() };
+    credential.Type = CRED_TYPE_GENERIC;
+    credential.TargetName = target.as_mut_ptr();
+    credential.CredentialBlobSize = blob.len() as DWORD;
+    credential.CredentialBlob = blob.as_mut_ptr();
+    credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+    if unsafe { CredWriteW(&mut credential, 0) } == 0 {
+        return Err(format!("CredWriteW failed: {}", unsafe { GetLastError() }));
+    }
+    Ok(())
+}
+
+/// The secret kept under `target`, if there is one.
+pub fn read_credential(target: &str) -> Result<Option<String>, String> {
+    let target = to_wide(target);
+    let mut credential: PCREDENTIALW = null_mut();
+    if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+        return match unsafe { GetLastError() } {
+            ERROR_NOT_FOUND => Ok(None),
+            e => Err(format!("CredReadW failed: {}", e))
+        };
+    }
+    let secret = unsafe {
+        let blob = slice::from_raw_parts((*credential).CredentialBlob, (*credential).CredentialBlobSize as usize);
+        let secret = String::from_utf8_lossy(blob).into_owned();
+        CredFree(credential as _);
+        secret
+    };
+    Ok(Some(secret))
+}
@@ -0,0 +1,42 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::um::dpapi::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::winbase::LocalFree;
+use winapi::um::wincrypt::DATA_BLOB;
+use std::ptr::null_mut;
+use std::slice;
+
+fn blob(data: &[u8]) -> DATA_BLOB {
+    DATA_BLOB { cbData: data.len() as DWORD, pbData: data.as_ptr() as *mut _ }
+}
+
+fn take(out: DATA_BLOB) -> Vec<u8> {
+    let data = unsafe { slice::from_raw_parts(out.pbData, out.cbData as usize) }.to_vec();
+    unsafe { LocalFree(out.pbData as _) };
+    data
+}
+
+/// Encrypts `data` so only the current Windows account can decrypt it.
+pub fn protect(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut input = blob(data);
+    let mut out = DATA_BLOB { cbData: 0, pbData: null_mut() };
+    let ok = unsafe {
+        CryptProtectData(&mut input, null_mut(), null_mut(), null_mut(), null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut out)
+    };
+    if ok == 0 {
+        return Err(format!("CryptProtectData failed: {}", unsafe { GetLastError() }));
+    }
+    Ok(take(out))
+}
+
+pub fn unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut input = blob(data);
+    let mut out = DATA_BLOB { cbData: 0, pbData: null_mut() };
+    let ok = unsafe {
+        CryptUnprotectData(&mut input, null_mut(), null_mut(), null_mut(), null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut out)
+    };
+    if ok == 0 {
+        return Err(format!("CryptUnprotectData failed: {}", unsafe { GetLastError() }));
+    }
+    Ok(take(out))
+}
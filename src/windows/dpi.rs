@@ -0,0 +1,53 @@
+use winapi::shared::windef::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, RECT};
+use winapi::um::winuser::{
+    GetDpiForWindow,
+    SetProcessDpiAwarenessContext,
+    SetWindowPos,
+    SWP_NOACTIVATE,
+    SWP_NOZORDER,
+    USER_DEFAULT_SCREEN_DPI,
+};
+use std::ptr;
+
+pub const DEFAULT_DPI: u32 = USER_DEFAULT_SCREEN_DPI as u32;
+
+/// Must run before any window is created. Sizes in the config stay in 96 DPI units and
+/// are scaled by hand, so nwg's own (system DPI only) `high-dpi` feature stays off.
+pub fn enable_per_monitor_awareness() {
+    if unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) } == 0 {
+        log::error!("Failed to enable per-monitor DPI awareness");
+    }
+}
+
+pub fn window_dpi(window: &nwg::Window) -> u32 {
+    match window.handle.hwnd() {
+        Some(hwnd) => match unsafe { GetDpiForWindow(hwnd) } {
+            0 => DEFAULT_DPI,
+            dpi => dpi
+        },
+        None => DEFAULT_DPI
+    }
+}
+
+pub fn scale(value: u32, dpi: u32) -> u32 {
+    value * dpi / DEFAULT_DPI
+}
+
+pub fn unscale(value: u32, dpi: u32) -> u32 {
+    value * DEFAULT_DPI / dpi
+}
+
+/// Moves the window to the rectangle suggested by `WM_DPICHANGED`.
+pub fn apply_suggested_rect(window: &nwg::Window, rect: &RECT) {
+    if let Some(hwnd) = window.handle.hwnd() {
+        unsafe {
+            SetWindowPos(hwnd,
+                         ptr::null_mut(),
+                         rect.left,
+                         rect.top,
+                         rect.right - rect.left,
+                         rect.bottom - rect.top,
+                         SWP_NOZORDER | SWP_NOACTIVATE);
+        }
+    }
+}
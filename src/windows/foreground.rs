@@ -0,0 +1,60 @@
+use winapi::shared::minwindef::{DWORD, FALSE, MAX_PATH};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::OpenProcess;
+use winapi::um::winbase::QueryFullProcessImageNameW;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{GetForegroundWindow, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId};
+use std::path::Path;
+
+/// The lowercase executable name, like `powerpnt.exe`, of the app owning the foreground window.
+#[tracing::instrument(level = "trace", ret)]
+pub fn foreground_process() -> Option<String> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return None;
+    }
+    process_name(hwnd)
+}
+
+/// The executable name and the title of the foreground window.
+pub fn foreground_window() -> Option<(String, String)> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return None;
+    }
+    Some((process_name(hwnd)?, window_title(hwnd)))
+}
+
+fn window_title(hwnd: HWND) -> String {
+    let length = unsafe { GetWindowTextLengthW(hwnd) };
+    if length <= 0 {
+        return String::new();
+    }
+    let mut title = vec![0u16; length as usize + 1];
+    let copied = unsafe { GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32) };
+    String::from_utf16_lossy(&title[..copied.max(0) as usize])
+}
+
+fn process_name(hwnd: HWND) -> Option<String> {
+    let mut pid: DWORD = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+    if pid == 0 {
+        return None;
+    }
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid) };
+    if process.is_null() {
+        return None;
+    }
+    let mut path = [0u16; MAX_PATH];
+    let mut size = MAX_PATH as DWORD;
+    let result = unsafe { QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut size) };
+    unsafe { CloseHandle(process) };
+    if result == 0 {
+        return None;
+    }
+
+    let path = String::from_utf16_lossy(&path[..size as usize]);
+    Path::new(&path).file_name().map(|name| name.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,45 @@
+// The names follow windows.gaming.ui.h.
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+use winapi::RIDL;
+use winapi::shared::ntdef::BOOLEAN;
+use winapi::shared::winerror::HRESULT;
+use winapi::um::unknwnbase::IUnknown;
+use winapi::winrt::inspectable::{IInspectable, IInspectableVtbl};
+use std::io;
+use super::toast::{check, factory, Com};
+
+// Handlers and tokens are only here to keep the places of the getters.
+RIDL!{#[uuid(0x1db9a292, 0xcc78, 0x4173, 0xbe, 0x45, 0xb6, 0x1e, 0x67, 0x28, 0x3e, 0xa7)]
+interface IGameBarStatics(IGameBarStaticsVtbl): IInspectable(IInspectableVtbl) {
+    fn add_VisibilityChanged(
+        handler: *mut IUnknown,
+        token: *mut i64,
+    ) -> HRESULT,
+    fn remove_VisibilityChanged(
+        token: i64,
+    ) -> HRESULT,
+    fn add_IsInputRedirectedChanged(
+        handler: *mut IUnknown,
+        token: *mut i64,
+    ) -> HRESULT,
+    fn remove_IsInputRedirectedChanged(
+        token: i64,
+    ) -> HRESULT,
+    fn get_Visible(
+        value: *mut BOOLEAN,
+    ) -> HRESULT,
+    fn get_IsInputRedirected(
+        value: *mut BOOLEAN,
+    ) -> HRESULT,
+}}
+
+/// Whether the Game Bar is open, as `Windows.Gaming.UI.GameBar` tells. Fails where
+/// there is no Game Bar, as on Windows N and Server. COM must already be initialized
+/// on the calling thread.
+pub fn is_visible() -> io::Result<bool> {
+    let statics: Com<IGameBarStatics> = factory("Windows.Gaming.UI.GameBar")?;
+    let mut visible = 0;
+    check(unsafe { (*statics.0).get_Visible(&mut visible) })?;
+    Ok(visible != 0)
+}
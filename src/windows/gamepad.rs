@@ -0,0 +1,32 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::xinput::{XInputGetState, XINPUT_STATE, XUSER_MAX_COUNT};
+use std::mem::zeroed;
+use std::time::{Duration, Instant};
+
+/// Notices controller input, which `GetLastInputInfo` doesn't count.
+#[derive(Default)]
+pub struct GamepadMonitor {
+    packets: [Option<DWORD>; XUSER_MAX_COUNT as usize],
+    last_input: Option<Instant>,
+}
+
+impl GamepadMonitor {
+    /// Time since any controller last changed state, or `None` if none has yet.
+    pub fn idle_time(&mut self) -> Option<Duration> {
+        for (index, packet) in self.packets.iter_mut().enumerate() {
+            let mut state: XINPUT_STATE = unsafe { zeroed() };
+            if unsafe { XInputGetState(index as DWORD, &mut state) } != ERROR_SUCCESS {
+                *packet = None;
+                continue;
+            }
+            // The packet number only changes with the controller's state; the first reading
+            // after a controller connects isn't input.
+            if packet.is_some_and(|packet| packet != state.dwPacketNumber) {
+                self.last_input = Some(Instant::now());
+            }
+            *packet = Some(state.dwPacketNumber);
+        }
+        self.last_input.map(|input| input.elapsed())
+    }
+}
@@ -0,0 +1,128 @@
+use winapi::um::winuser::{
+    RegisterHotKey,
+    UnregisterHotKey,
+    MOD_ALT,
+    MOD_CONTROL,
+    MOD_NOREPEAT,
+    MOD_SHIFT,
+    MOD_WIN,
+    VK_DELETE,
+    VK_END,
+    VK_F1,
+    VK_HOME,
+    VK_INSERT,
+    VK_NEXT,
+    VK_PAUSE,
+    VK_PRIOR,
+    VK_SPACE,
+};
+use crate::config::HotkeyConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    BreakNow,
+    /// Pauses the timer, or resumes it when it is paused.
+    TogglePause,
+    ShowWindow,
+    TogglePrivacy
+}
+
+/// Parses a key combination like `Ctrl+Alt+B` into `RegisterHotKey` modifiers and a virtual key.
+fn parse(text: &str) -> Option<(u32, u32)> {
+    let mut modifiers = 0;
+    let mut key = None;
+    for part in text.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "win" => modifiers |= MOD_WIN,
+            name => {
+                if key.is_some() {
+                    return None;
+                }
+                key = Some(virtual_key(name)?);
+            }
+        }
+    }
+    key.map(|key| (modifiers as u32, key))
+}
+
+fn virtual_key(name: &str) -> Option<u32> {
+    let key = match name {
+        "space" => VK_SPACE,
+        "pause" => VK_PAUSE,
+        "insert" => VK_INSERT,
+        "delete" => VK_DELETE,
+        "home" => VK_HOME,
+        "end" => VK_END,
+        "pageup" => VK_PRIOR,
+        "pagedown" => VK_NEXT,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase() as i32,
+                (Some('f'), Some(_)) => match name[1..].parse::<i32>() {
+                    Ok(n) if (1..=24).contains(&n) => VK_F1 + n - 1,
+                    _ => return None
+                },
+                _ => return None
+            }
+        }
+    };
+    Some(key as u32)
+}
+
+/// The system-wide hotkeys, registered to the window so it receives `WM_HOTKEY`
+/// with the index of the action as the id.
+pub struct Hotkeys {
+    actions: Vec<Action>,
+}
+
+impl Hotkeys {
+    pub fn register(window: &nwg::Window, config: &HotkeyConfig) -> Hotkeys {
+        let mut actions = Vec::new();
+        let hwnd = match window.handle.hwnd() {
+            Some(hwnd) => hwnd,
+            None => return Hotkeys { actions }
+        };
+        let bindings = [
+            (Action::BreakNow, &config.break_now),
+            (Action::TogglePause, &config.pause),
+            (Action::ShowWindow, &config.show_window),
+            (Action::TogglePrivacy, &config.privacy),
+        ];
+        for (action, text) in bindings.iter() {
+            if text.is_empty() {
+                continue;
+            }
+            let (modifiers, key) = match parse(text) {
+                Some(hotkey) => hotkey,
+                None => {
+                    log::error!("Invalid hotkey for {:?}: {}", action, text);
+                    continue;
+                }
+            };
+            let id = actions.len() as i32;
+            if unsafe { RegisterHotKey(hwnd, id, modifiers | MOD_NOREPEAT as u32, key) } == 0 {
+                log::error!("Failed to register hotkey {}, it may be used by another app", text);
+                continue;
+            }
+            log::info!("Registered hotkey {} for {:?}", text, action);
+            actions.push(*action);
+        }
+        Hotkeys { actions }
+    }
+
+    pub fn action(&self, id: usize) -> Option<Action> {
+        self.actions.get(id).copied()
+    }
+
+    pub fn unregister(&self, window: &nwg::Window) {
+        if let Some(hwnd) = window.handle.hwnd() {
+            for id in 0..self.actions.len() {
+                unsafe { UnregisterHotKey(hwnd, id as i32) };
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HHOOK;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::{
+    CallNextHookEx, DispatchMessageW, GetMessageW, SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx,
+    HC_ACTION, KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN,
+    WM_MBUTTONDOWN, WM_MOUSEHWHEEL, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN
+};
+use std::mem::zeroed;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::history::History;
+use crate::privacy;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+static KEYS: AtomicU32 = AtomicU32::new(0);
+static CLICKS: AtomicU32 = AtomicU32::new(0);
+/// The key held down, so its repeats count once.
+static HELD_KEY: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "system" fn keyboard_proc(code: i32, w: WPARAM, l: LPARAM) -> LRESULT {
+    if code == HC_ACTION && !privacy::enabled() {
+        let key = (*(l as *const KBDLLHOOKSTRUCT)).vkCode;
+        match w as UINT {
+            WM_KEYDOWN | WM_SYSKEYDOWN if HELD_KEY.swap(key, Ordering::Relaxed) != key => {
+                KEYS.fetch_add(1, Ordering::Relaxed);
+            },
+            WM_KEYUP | WM_SYSKEYUP => HELD_KEY.store(0, Ordering::Relaxed),
+            _ => {}
+        }
+    }
+    CallNextHookEx(null_mut(), code, w, l)
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, w: WPARAM, l: LPARAM) -> LRESULT {
+    if code == HC_ACTION && !privacy::enabled() {
+        if let WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN | WM_MOUSEWHEEL | WM_MOUSEHWHEEL = w as UINT {
+            CLICKS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    CallNextHookEx(null_mut(), code, w, l)
+}
+
+/// Writes the counts to the history each minute. Away from the hooks, whose thread has to
+/// answer every input at once: a slow disk there would hold up all input, and Windows
+/// takes off hooks that take too long.
+fn save_each_minute(history: History) {
+    loop {
+        thread::sleep(MINUTE);
+        let (keys, clicks) = (KEYS.swap(0, Ordering::Relaxed), CLICKS.swap(0, Ordering::Relaxed));
+        if keys + clicks > 0 {
+            if let Err(e) = history.record_input(SystemTime::now(), keys, clicks) {
+                log::error!("{}", e);
+            }
+        }
+    }
+}
+
+/// Counts key presses and mouse clicks, never which keys or where, and records how many
+/// there were each minute. Runs until the process exits, on a thread of its own.
+pub fn record_input_rate() {
+    let history = match History::open() {
+        Ok(history) => history,
+        Err(e) => {
+            log::error!("{}", e);
+            return;
+        }
+    };
+    thread::spawn(move || save_each_minute(history));
+    let hooks: Vec<HHOOK> = unsafe {
+        let module = GetModuleHandleW(null_mut());
+        vec![SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), module, 0),
+             SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), module, 0)]
+    };
+    if hooks.iter().any(|hook| hook.is_null()) {
+        log::error!("Failed to hook input to count it");
+    }
+    // The hooks are called through this thread's messages.
+    let mut msg: MSG = unsafe { zeroed() };
+    while unsafe { GetMessageW(&mut msg, null_mut(), 0, 0) } > 0 {
+        unsafe {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+
+    unsafe {
+        for hook in hooks.into_iter().filter(|hook| !hook.is_null()) {
+            UnhookWindowsHookEx(hook);
+        }
+    }
+}
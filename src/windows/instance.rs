@@ -0,0 +1,63 @@
+use winapi::shared::winerror::ERROR_ALREADY_EXISTS;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::synchapi::CreateMutexW;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winuser::{AllowSetForegroundWindow, PostMessageW, RegisterWindowMessageW, ASFW_ANY, HWND_BROADCAST};
+use std::ptr::null_mut;
+use super::to_wide;
+
+const MUTEX_NAME: &str = "Local\\pauza";
+const ACTIVATE_MESSAGE: &str = "pauza-activate";
+/// Sent with the activate message to have the statistics shown rather than the window.
+pub const SHOW_STATS: usize = 1;
+
+/// Held for as long as this is the running instance in the session.
+pub struct InstanceLock(HANDLE);
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+/// Takes the instance lock, or returns `None` when another instance already holds it.
+pub fn acquire() -> Option<InstanceLock> {
+    let name = to_wide(MUTEX_NAME);
+    let handle = unsafe { CreateMutexW(null_mut(), 0, name.as_ptr()) };
+    if handle.is_null() {
+        log::error!("Failed to create the instance mutex: {}", unsafe { GetLastError() });
+        // Running twice beats not running at all.
+        return Some(InstanceLock(handle));
+    }
+    if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+        unsafe { CloseHandle(handle) };
+        return None;
+    }
+    Some(InstanceLock(handle))
+}
+
+/// The message the running instance's window answers by coming to the foreground.
+pub fn activate_message() -> u32 {
+    let name = to_wide(ACTIVATE_MESSAGE);
+    unsafe { RegisterWindowMessageW(name.as_ptr()) }
+}
+
+fn post_activate(what: usize) {
+    unsafe {
+        AllowSetForegroundWindow(ASFW_ANY);
+        PostMessageW(HWND_BROADCAST, activate_message(), what, 0);
+    }
+}
+
+/// Asks the running instance to show its window, letting it take the foreground from us.
+pub fn activate_existing() {
+    post_activate(0);
+}
+
+/// Asks the running instance to show its statistics.
+pub fn show_existing_stats() {
+    post_activate(SHOW_STATS);
+}
@@ -0,0 +1,106 @@
+use winapi::shared::basetsd::LONG_PTR;
+use winapi::um::winuser::{GetWindowLongPtrW, SetWindowLongPtrW, GWL_EXSTYLE, HTTRANSPARENT, WM_NCHITTEST, WS_EX_TOOLWINDOW};
+use std::cell::RefCell;
+use crate::config::MiniConfig;
+use super::dpi;
+
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 34;
+const PROGRESS_HEIGHT: u32 = 4;
+const MARGIN: u32 = 4;
+const HANDLER_ID: usize = 0x10004;
+
+/// A borderless strip with the time until the break that stays on top of other windows
+/// and can be dragged anywhere by any part of it.
+#[derive(Default)]
+pub struct MiniTimer {
+    pub window: nwg::Window,
+    pub label: nwg::Label,
+    progress_bar: nwg::ProgressBar,
+    handlers: RefCell<Vec<nwg::RawEventHandler>>,
+}
+
+impl MiniTimer {
+    pub fn build(&mut self, config: &MiniConfig, range: u32, background: [u8; 3]) -> Result<(), nwg::NwgError> {
+        let mut flags = nwg::WindowFlags::POPUP;
+        if config.visible {
+            flags |= nwg::WindowFlags::VISIBLE;
+        }
+        nwg::Window::builder()
+            .flags(flags)
+            .topmost(true)
+            .size((WIDTH as i32, HEIGHT as i32))
+            .position((config.x, config.y))
+            .title("Pauza")
+            .build(&mut self.window)?;
+
+        // Keeps it out of the taskbar and Alt+Tab.
+        if let Some(hwnd) = self.window.handle.hwnd() {
+            unsafe {
+                let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+                SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_TOOLWINDOW as LONG_PTR);
+            }
+        }
+
+        nwg::Label::builder()
+            .text("")
+            .h_align(nwg::HTextAlign::Center)
+            .background_color(Some(background))
+            .parent(&self.window)
+            .build(&mut self.label)?;
+
+        nwg::ProgressBar::builder()
+            .range(0..range)
+            .parent(&self.window)
+            .build(&mut self.progress_bar)?;
+
+        // Clicks go through the controls to the window, which is dragged by them.
+        let mut handlers = self.handlers.borrow_mut();
+        for handle in [&self.label.handle, &self.progress_bar.handle] {
+            handlers.push(nwg::bind_raw_event_handler(handle, HANDLER_ID, |_hwnd, msg, _w, _l| {
+                if msg == WM_NCHITTEST {
+                    return Some(HTTRANSPARENT);
+                }
+                None
+            })?);
+        }
+        drop(handlers);
+
+        self.apply_dpi(dpi::window_dpi(&self.window));
+        Ok(())
+    }
+
+    pub fn apply_dpi(&self, dpi: u32) {
+        let (width, height) = (dpi::scale(WIDTH, dpi), dpi::scale(HEIGHT, dpi));
+        let (progress, margin) = (dpi::scale(PROGRESS_HEIGHT, dpi), dpi::scale(MARGIN, dpi));
+        self.window.set_size(width, height);
+        self.label.set_position(0, 0);
+        self.label.set_size(width, height - progress - margin);
+        self.progress_bar.set_position(margin as i32, (height - progress - margin) as i32);
+        self.progress_bar.set_size(width - margin * 2, progress);
+    }
+
+    pub fn set_font(&self, font: &nwg::Font) {
+        self.label.set_font(Some(font));
+    }
+
+    pub fn update(&self, text: &str, state: nwg::ProgressBarState, position: u32) {
+        if self.label.text() != text {
+            self.label.set_text(text);
+        }
+        self.progress_bar.set_state(state);
+        self.progress_bar.set_pos(position);
+    }
+
+    pub fn config(&self) -> MiniConfig {
+        let (x, y) = self.window.position();
+        MiniConfig { x, y, visible: self.window.visible() }
+    }
+
+    /// Must be called while the window still exists.
+    pub fn unbind(&self) {
+        for handler in self.handlers.borrow_mut().drain(..) {
+            let _ = nwg::unbind_raw_event_handler(&handler);
+        }
+    }
+}
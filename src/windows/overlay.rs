@@ -0,0 +1,133 @@
+use winapi::shared::basetsd::LONG_PTR;
+use winapi::um::winuser::{
+    GetSystemMetrics, GetWindowLongPtrW, SetWindowLongPtrW, ShowWindow, GWL_EXSTYLE, HTTRANSPARENT, SM_CXSCREEN,
+    SM_CYSCREEN, SW_HIDE, WM_KEYDOWN, WM_NCHITTEST, WS_EX_TOOLWINDOW
+};
+use std::cell::RefCell;
+use crate::notifications::{Notification, NotificationBackend};
+use super::dpi;
+
+const TITLE_FONT_SIZE: u32 = 48;
+const MESSAGE_FONT_SIZE: u32 = 24;
+const MARGIN: u32 = 32;
+const HANDLER_ID: usize = 0x10006;
+
+/// A window over the whole primary screen for notifications that shouldn't be missed.
+/// A key dismisses it; a click is for its owner to handle, like one on a balloon.
+#[derive(Default)]
+pub struct Overlay {
+    pub window: nwg::Window,
+    title: nwg::Label,
+    message: nwg::Label,
+    fonts: RefCell<Option<(nwg::Font, nwg::Font)>>,
+    handlers: RefCell<Vec<nwg::RawEventHandler>>,
+}
+
+impl Overlay {
+    pub fn build(&mut self, background: [u8; 3]) -> Result<(), nwg::NwgError> {
+        nwg::Window::builder()
+            .flags(nwg::WindowFlags::POPUP)
+            .topmost(true)
+            .title("Pauza")
+            .build(&mut self.window)?;
+
+        // Keeps it out of the taskbar and Alt+Tab.
+        if let Some(hwnd) = self.window.handle.hwnd() {
+            unsafe {
+                let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+                SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_TOOLWINDOW as LONG_PTR);
+            }
+        }
+
+        for label in [&mut self.title, &mut self.message] {
+            nwg::Label::builder()
+                .text("")
+                .h_align(nwg::HTextAlign::Center)
+                .background_color(Some(background))
+                .parent(&self.window)
+                .build(label)?;
+        }
+
+        // Clicks go through the labels to the window.
+        let mut handlers = self.handlers.borrow_mut();
+        for handle in [&self.title.handle, &self.message.handle] {
+            handlers.push(nwg::bind_raw_event_handler(handle, HANDLER_ID, |_hwnd, msg, _w, _l| {
+                if msg == WM_NCHITTEST {
+                    return Some(HTTRANSPARENT);
+                }
+                None
+            })?);
+        }
+        handlers.push(nwg::bind_raw_event_handler(&self.window.handle, HANDLER_ID, |hwnd, msg, _w, _l| {
+            if msg == WM_KEYDOWN {
+                unsafe { ShowWindow(hwnd, SW_HIDE) };
+                return Some(0);
+            }
+            None
+        })?);
+        Ok(())
+    }
+
+    pub fn labels(&self) -> [&nwg::Label; 2] {
+        [&self.title, &self.message]
+    }
+
+    /// Covers the primary screen, with the fonts sized for its DPI.
+    fn lay_out(&self) {
+        let (width, height) = unsafe { (GetSystemMetrics(SM_CXSCREEN).max(1) as u32, GetSystemMetrics(SM_CYSCREEN).max(1) as u32) };
+        self.window.set_position(0, 0);
+        self.window.set_size(width, height);
+
+        let dpi = dpi::window_dpi(&self.window);
+        let (title_size, message_size) = (dpi::scale(TITLE_FONT_SIZE, dpi), dpi::scale(MESSAGE_FONT_SIZE, dpi));
+        let build_font = |size| {
+            let mut font = nwg::Font::default();
+            nwg::Font::builder()
+                .family("Segoe UI")
+                .size(size)
+                .build(&mut font)
+                .map(|()| font)
+        };
+        match (build_font(title_size), build_font(message_size)) {
+            (Ok(title_font), Ok(message_font)) => {
+                self.title.set_font(Some(&title_font));
+                self.message.set_font(Some(&message_font));
+                *self.fonts.borrow_mut() = Some((title_font, message_font));
+            },
+            (Err(e), _) | (_, Err(e)) => log::error!("{}", e)
+        }
+
+        let margin = dpi::scale(MARGIN, dpi).min(width / 4);
+        let title_height = title_size * 2;
+        let top = height / 3;
+        self.title.set_position(margin as i32, top as i32);
+        self.title.set_size(width - margin * 2, title_height);
+        self.message.set_position(margin as i32, (top + title_height) as i32);
+        self.message.set_size(width - margin * 2, height.saturating_sub(top + title_height + margin));
+    }
+
+    /// Must be called while the window still exists.
+    pub fn unbind(&self) {
+        for handler in self.handlers.borrow_mut().drain(..) {
+            let _ = nwg::unbind_raw_event_handler(&handler);
+        }
+    }
+}
+
+impl NotificationBackend for Overlay {
+    fn name(&self) -> &'static str {
+        "overlay"
+    }
+
+    fn show(&self, notification: &Notification) -> Result<(), String> {
+        if self.window.handle.hwnd().is_none() {
+            return Err("the overlay window wasn't created".to_string());
+        }
+        self.title.set_text(&notification.title);
+        self.message.set_text(&notification.message);
+        self.lay_out();
+        self.window.set_visible(true);
+        self.window.set_focus();
+        Ok(())
+    }
+}
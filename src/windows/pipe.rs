@@ -0,0 +1,209 @@
+use winapi::shared::minwindef::{DWORD, FALSE};
+use winapi::shared::sddl::{ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use winapi::shared::winerror::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::fileapi::{FlushFileBuffers, ReadFile, WriteFile};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::minwinbase::SECURITY_ATTRIBUTES;
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe};
+use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentProcessId, OpenProcessToken, ProcessIdToSessionId};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::winbase::{
+    LocalFree,
+    FILE_FLAG_FIRST_PIPE_INSTANCE,
+    PIPE_ACCESS_DUPLEX,
+    PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE,
+    PIPE_WAIT,
+};
+use winapi::um::winnt::{TokenUser, HANDLE, PSECURITY_DESCRIPTOR, TOKEN_QUERY, TOKEN_USER};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+use std::ptr;
+use std::slice;
+use std::thread;
+use std::time::Duration;
+use super::to_wide;
+
+const BUFFER_SIZE: DWORD = 4096;
+const CONNECT_ATTEMPTS: u32 = 20;
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The pipe of this session's pauza. Pipe names are shared by every session on the
+/// machine, so without the session another user's commands would reach our timer.
+fn pipe_name() -> String {
+    let mut session: DWORD = 0;
+    if unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session) } == FALSE {
+        log::warn!("Failed to get the session: {}", io::Error::last_os_error());
+    }
+    format!("\\\\.\\pipe\\pauza-{}", session)
+}
+
+/// The SID of the user running pauza, like S-1-5-21-...
+fn current_user_sid() -> io::Result<String> {
+    let mut token: HANDLE = ptr::null_mut();
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == FALSE {
+        return Err(io::Error::last_os_error());
+    }
+    let mut size: DWORD = 0;
+    unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut size) };
+    // In u64s, for the alignment of the TOKEN_USER at its start.
+    let mut buffer = vec![0u64; (size as usize).max(size_of::<TOKEN_USER>()) / 8 + 1];
+    let result = unsafe {
+        GetTokenInformation(token, TokenUser, buffer.as_mut_ptr() as *mut _, (buffer.len() * 8) as DWORD, &mut size)
+    };
+    unsafe { CloseHandle(token) };
+    if result == FALSE {
+        return Err(io::Error::last_os_error());
+    }
+    let user = unsafe { &*(buffer.as_ptr() as *const TOKEN_USER) };
+    let mut text = ptr::null_mut();
+    if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut text) } == FALSE {
+        return Err(io::Error::last_os_error());
+    }
+    let sid = unsafe {
+        let length = (0..).take_while(|&i| *text.add(i) != 0).count();
+        String::from_utf16_lossy(slice::from_raw_parts(text, length))
+    };
+    unsafe { LocalFree(text as _) };
+    Ok(sid)
+}
+
+/// A security descriptor that lets only the user running pauza open the pipe. The
+/// default one lets anyone on the machine read from it.
+struct OwnerOnly(PSECURITY_DESCRIPTOR);
+
+impl OwnerOnly {
+    fn new() -> io::Result<OwnerOnly> {
+        let sddl = to_wide(&format!("D:P(A;;GA;;;{})", current_user_sid()?));
+        let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+        let result = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl.as_ptr(),
+                                                                 SDDL_REVISION_1 as DWORD,
+                                                                 &mut descriptor,
+                                                                 ptr::null_mut())
+        };
+        if result == FALSE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(OwnerOnly(descriptor))
+    }
+}
+
+impl Drop for OwnerOnly {
+    fn drop(&mut self) {
+        unsafe { LocalFree(self.0) };
+    }
+}
+
+/// The server end of the pipe, serving one client at a time. Creating it fails while
+/// another pauza already owns the pipe.
+pub struct PipeServer {
+    handle: HANDLE,
+}
+
+impl PipeServer {
+    pub fn create() -> io::Result<PipeServer> {
+        let name = to_wide(&pipe_name());
+        let security = OwnerOnly::new()?;
+        let mut attributes = SECURITY_ATTRIBUTES {
+            nLength: size_of::<SECURITY_ATTRIBUTES>() as DWORD,
+            lpSecurityDescriptor: security.0,
+            bInheritHandle: FALSE,
+        };
+        let handle = unsafe {
+            CreateNamedPipeW(name.as_ptr(),
+                             PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                             PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                             1,
+                             BUFFER_SIZE,
+                             BUFFER_SIZE,
+                             0,
+                             &mut attributes)
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PipeServer { handle })
+    }
+
+    /// Blocks until a client connects. The client is disconnected when the returned
+    /// connection is dropped.
+    pub fn accept(&mut self) -> io::Result<PipeConnection<'_>> {
+        if unsafe { ConnectNamedPipe(self.handle, ptr::null_mut()) } == 0
+            && unsafe { GetLastError() } != ERROR_PIPE_CONNECTED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PipeConnection { server: self })
+    }
+}
+
+impl Drop for PipeServer {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
+}
+
+pub struct PipeConnection<'a> {
+    server: &'a mut PipeServer,
+}
+
+impl Read for PipeConnection<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read: DWORD = 0;
+        let result = unsafe {
+            ReadFile(self.server.handle, buf.as_mut_ptr() as *mut _, buf.len() as DWORD, &mut read, ptr::null_mut())
+        };
+        if result == FALSE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(read as usize)
+    }
+}
+
+impl Write for PipeConnection<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written: DWORD = 0;
+        let result = unsafe {
+            WriteFile(self.server.handle, buf.as_ptr() as *const _, buf.len() as DWORD, &mut written, ptr::null_mut())
+        };
+        if result == FALSE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if unsafe { FlushFileBuffers(self.server.handle) } == FALSE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PipeConnection<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            FlushFileBuffers(self.server.handle);
+            DisconnectNamedPipe(self.server.handle);
+        }
+    }
+}
+
+/// Opens the client end of the pipe, waiting a little while the server is busy with
+/// another client.
+pub fn connect() -> io::Result<File> {
+    let name = pipe_name();
+    let mut attempt = 0;
+    loop {
+        match OpenOptions::new().read(true).write(true).open(&name) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) && attempt < CONNECT_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(CONNECT_RETRY_DELAY);
+            },
+            result => return result
+        }
+    }
+}
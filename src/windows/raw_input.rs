@@ -0,0 +1,95 @@
+use winapi::shared::hidusage::{HID_USAGE_GENERIC_KEYBOARD, HID_USAGE_GENERIC_MOUSE, HID_USAGE_PAGE_GENERIC};
+use winapi::shared::minwindef::UINT;
+use winapi::um::winuser::{
+    CreateWindowExW, DestroyWindow, DispatchMessageW, GetMessageW, GetRawInputData, RegisterRawInputDevices,
+    TranslateMessage, HRAWINPUT, HWND_MESSAGE, MSG, RAWINPUTDEVICE, RAWINPUTHEADER, RIDEV_INPUTSINK, RID_HEADER,
+    RIM_TYPEKEYBOARD, RIM_TYPEMOUSE, WM_INPUT
+};
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use super::to_wide;
+
+/// Milliseconds from `start` to the last input of one kind.
+#[derive(Default)]
+struct LastInput {
+    keyboard: AtomicU64,
+    mouse: AtomicU64,
+}
+
+/// Tells keyboard input from mouse input, which `GetLastInputInfo` lumps together, from raw
+/// input read on a thread of its own.
+pub struct RawInputMonitor {
+    start: Instant,
+    last: Arc<LastInput>,
+}
+
+impl RawInputMonitor {
+    pub fn new() -> RawInputMonitor {
+        let start = Instant::now();
+        let last = Arc::new(LastInput::default());
+        let thread_last = last.clone();
+        thread::spawn(move || read_raw_input(start, &thread_last));
+        RawInputMonitor { start, last }
+    }
+
+    /// Time since the last key press and since the last mouse input, counted from when
+    /// monitoring started for a kind there was none of yet.
+    pub fn idle_times(&self) -> (Duration, Duration) {
+        let idle = |last: &AtomicU64| self.start.elapsed().saturating_sub(Duration::from_millis(last.load(Ordering::Relaxed)));
+        (idle(&self.last.keyboard), idle(&self.last.mouse))
+    }
+}
+
+fn read_raw_input(start: Instant, last: &LastInput) {
+    // Raw input goes to a window, even in the background with `RIDEV_INPUTSINK`; one that
+    // only gets messages will do.
+    let class = to_wide("STATIC");
+    let window = unsafe {
+        CreateWindowExW(0, class.as_ptr(), null_mut(), 0, 0, 0, 0, 0, HWND_MESSAGE, null_mut(), null_mut(), null_mut())
+    };
+    if window.is_null() {
+        log::error!("Failed to create a window for raw input");
+        return;
+    }
+    let device = |usage| RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: usage,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: window,
+    };
+    let devices = [device(HID_USAGE_GENERIC_KEYBOARD), device(HID_USAGE_GENERIC_MOUSE)];
+    if unsafe { RegisterRawInputDevices(devices.as_ptr(), devices.len() as UINT, size_of::<RAWINPUTDEVICE>() as UINT) } == 0 {
+        log::error!("Failed to register for raw input");
+        unsafe { DestroyWindow(window) };
+        return;
+    }
+
+    let mut msg: MSG = unsafe { zeroed() };
+    while unsafe { GetMessageW(&mut msg, null_mut(), 0, 0) } > 0 {
+        if msg.message == WM_INPUT {
+            let mut header: RAWINPUTHEADER = unsafe { zeroed() };
+            let mut size = size_of::<RAWINPUTHEADER>() as UINT;
+            let read = unsafe {
+                GetRawInputData(msg.lParam as HRAWINPUT, RID_HEADER, &mut header as *mut _ as *mut _, &mut size, size_of::<RAWINPUTHEADER>() as UINT)
+            };
+            if read != UINT::MAX {
+                let now = start.elapsed().as_millis() as u64;
+                match header.dwType {
+                    RIM_TYPEKEYBOARD => last.keyboard.store(now, Ordering::Relaxed),
+                    RIM_TYPEMOUSE => last.mouse.store(now, Ordering::Relaxed),
+                    _ => {}
+                }
+            }
+        }
+        // Raw input has to reach the default window procedure to be cleaned up.
+        unsafe {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    unsafe { DestroyWindow(window) };
+}
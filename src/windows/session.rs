@@ -0,0 +1,65 @@
+use winapi::shared::minwindef::{BOOL, DWORD};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    GetSystemMetrics,
+    SM_REMOTESESSION,
+    WTS_CONSOLE_CONNECT,
+    WTS_CONSOLE_DISCONNECT,
+    WTS_REMOTE_CONNECT,
+    WTS_REMOTE_DISCONNECT,
+    WTS_SESSION_LOCK,
+    WTS_SESSION_UNLOCK,
+};
+use crate::Request;
+
+const NOTIFY_FOR_THIS_SESSION: DWORD = 0;
+
+#[link(name = "wtsapi32")]
+extern "system" {
+    fn WTSRegisterSessionNotification(hwnd: HWND, flags: DWORD) -> BOOL;
+    fn WTSUnRegisterSessionNotification(hwnd: HWND) -> BOOL;
+}
+
+/// Whether this session is shown on the machine's own console or through Remote Desktop.
+/// Idle time only counts input to this session, so a session nobody is connected to
+/// looks idle whatever happens on the machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionKind {
+    Console,
+    Remote
+}
+
+pub fn current_kind() -> SessionKind {
+    if unsafe { GetSystemMetrics(SM_REMOTESESSION) } != 0 {
+        SessionKind::Remote
+    } else {
+        SessionKind::Console
+    }
+}
+
+/// What the monitor should do about a `WM_WTSSESSION_CHANGE` event.
+pub fn request_for(event: usize) -> Option<Request> {
+    match event {
+        WTS_SESSION_LOCK => Some(Request::Lock),
+        WTS_SESSION_UNLOCK => Some(Request::Unlock),
+        WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => Some(Request::Disconnect),
+        WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => Some(Request::Connect),
+        _ => None
+    }
+}
+
+/// Has `WM_WTSSESSION_CHANGE` sent to the window when this session is locked, unlocked,
+/// connected or disconnected.
+pub fn register(window: &nwg::Window) {
+    if let Some(hwnd) = window.handle.hwnd() {
+        if unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } == 0 {
+            log::error!("Failed to register for session notifications");
+        }
+    }
+}
+
+pub fn unregister(window: &nwg::Window) {
+    if let Some(hwnd) = window.handle.hwnd() {
+        unsafe { WTSUnRegisterSessionNotification(hwnd) };
+    }
+}
@@ -0,0 +1,31 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::um::mmeapi::waveOutSetVolume;
+use winapi::um::mmsystem::MMSYSERR_NOERROR;
+use winapi::um::playsoundapi::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT, SND_SYNC};
+use std::ptr::null_mut;
+use crate::config::Sound;
+use super::to_wide;
+
+/// Played for sounds without a file of their own.
+const DEFAULT_ALIAS: &str = "Notification.Default";
+
+/// Plays a sound at its volume, in the background unless `wait` is set.
+///
+/// The volume is that of pauza's own output, so it can be set apart from the system volume.
+pub fn play(sound: &Sound, wait: bool) -> bool {
+    let level = DWORD::from(sound.volume.min(100)) * 0xFFFF / 100;
+    let result = unsafe { waveOutSetVolume(null_mut(), (level << 16) | level) };
+    if result != MMSYSERR_NOERROR {
+        log::warn!("Failed to set the volume: {}", result);
+    }
+    let (name, source) = match &sound.file {
+        Some(file) => (to_wide(file), SND_FILENAME),
+        None => (to_wide(DEFAULT_ALIAS), SND_ALIAS)
+    };
+    let timing = if wait { SND_SYNC } else { SND_ASYNC };
+    let played = unsafe { PlaySoundW(name.as_ptr(), null_mut(), source | timing | SND_NODEFAULT) } != 0;
+    if !played {
+        log::error!("Failed to play {}", sound.file.as_deref().unwrap_or(DEFAULT_ALIAS));
+    }
+    played
+}
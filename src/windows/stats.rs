@@ -0,0 +1,342 @@
+use winapi::shared::windef::{HDC, RECT};
+use winapi::um::wingdi::{CreateSolidBrush, DeleteObject, SetBkMode, SetTextColor, TextOutW, RGB, TRANSPARENT};
+use winapi::um::winuser::{FillRect, GetClientRect};
+use std::cell::RefCell;
+use std::mem::zeroed;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use crate::achievements::Achievements;
+use crate::config::AppCategory;
+use crate::export::{self, Format};
+use crate::format;
+use crate::history::{AppUsage, Heatmap, History, Intensity, Period, Summary};
+use crate::i18n::{tr, tr_args};
+use crate::usage::{self, CategoryTotal};
+use super::theme::Theme;
+
+const DAYS_SHOWN: u64 = 7;
+const WEEKS_SHOWN: u64 = 8;
+const APPS_SHOWN: usize = 5;
+const MARGIN: i32 = 16;
+const LINE_HEIGHT: i32 = 20;
+const BUTTON_HEIGHT: i32 = 28;
+
+#[derive(Default)]
+struct StatsData {
+    days: Vec<Summary>,
+    weeks: Vec<Summary>,
+    achievements: Achievements,
+    /// Today's, when app usage is recorded.
+    apps: Vec<AppUsage>,
+    categories: Vec<CategoryTotal>,
+    /// Today's, when input is counted.
+    intensity: Vec<Intensity>,
+    heatmap: Heatmap,
+}
+
+fn load_data(categories: &[AppCategory]) -> rusqlite::Result<StatsData> {
+    let history = History::open()?;
+    let now = SystemTime::now();
+    let day = Duration::from_secs(24 * 60 * 60);
+    let apps = history.app_usage(now)?;
+    Ok(StatsData {
+        days: history.summaries(Period::Day, now - day * DAYS_SHOWN as u32)?,
+        weeks: history.summaries(Period::Week, now - day * (7 * WEEKS_SHOWN) as u32)?,
+        achievements: Achievements::load(&history)?,
+        categories: usage::category_totals(categories, &apps),
+        apps,
+        intensity: history.input_intensity(now)?,
+        heatmap: history.active_minutes(now - day * DAYS_SHOWN as u32)?,
+    })
+}
+
+fn rgb(color: [u8; 3]) -> u32 {
+    RGB(color[0], color[1], color[2])
+}
+
+fn fill(hdc: HDC, rect: RECT, color: [u8; 3]) {
+    unsafe {
+        let brush = CreateSolidBrush(rgb(color));
+        FillRect(hdc, &rect, brush);
+        DeleteObject(brush as _);
+    }
+}
+
+fn text(hdc: HDC, x: i32, y: i32, text: &str) {
+    let wide: Vec<u16> = text.encode_utf16().collect();
+    unsafe { TextOutW(hdc, x, y, wide.as_ptr(), wide.len() as i32) };
+}
+
+fn percent(value: Option<f32>) -> String {
+    match value {
+        Some(value) => format!("{:.0}%", value * 100.0),
+        None => "-".to_string()
+    }
+}
+
+/// The totals shown above the charts.
+fn summary_lines(data: &StatsData) -> Vec<String> {
+    let total = Summary::total(&data.days);
+    let overdue = total.average_overdue().map(format::duration).unwrap_or_else(|| "-".to_string());
+    let achievements = &data.achievements;
+    let badges: Vec<_> = achievements.badges.iter().map(|badge| badge.name()).collect();
+    let badges = if badges.is_empty() { tr("stats-no-badges") } else { badges.join(", ") };
+    let mut lines = vec![
+        tr_args("stats-total", &[("days", &DAYS_SHOWN), ("work", &format::duration(total.work)), ("rest", &format::duration(total.rest))]),
+        tr_args("stats-compliance", &[("percent", &percent(total.compliance())), ("delay", &overdue)]),
+        tr_args("stats-longest", &[("time", &format::duration(total.longest))]),
+        tr_args("stats-quality", &[("percent", &percent(total.quality()))]),
+        tr_args("stats-streak", &[("days", &achievements.streak), ("best", &achievements.best_streak)]),
+        tr_args("stats-badges", &[("badges", &badges)]),
+    ];
+    if !data.apps.is_empty() {
+        let apps: Vec<_> = data.apps.iter()
+            .take(APPS_SHOWN)
+            .map(|usage| format!("{} {}", usage.app, format::duration(usage.duration)))
+            .collect();
+        lines.push(tr_args("stats-apps", &[("apps", &apps.join(", "))]));
+    }
+    if !data.categories.is_empty() {
+        let categories: Vec<_> = data.categories.iter()
+            .map(|total| match total.budget {
+                Some(budget) => format!("{} {} / {}", total.name, format::duration(total.duration), format::duration(budget)),
+                None => format!("{} {}", total.name, format::duration(total.duration))
+            })
+            .collect();
+        lines.push(tr_args("stats-categories", &[("categories", &categories.join(", "))]));
+    }
+    lines
+}
+
+/// A chart as text, for screen readers.
+fn chart_lines(title: &str, summaries: &[Summary]) -> Vec<String> {
+    let mut lines = vec![title.to_string()];
+    if summaries.is_empty() {
+        lines.push(tr("chart-empty"));
+    }
+    for summary in summaries {
+        lines.push(tr_args("chart-row", &[("label", &summary.label),
+                                          ("work", &format::duration(summary.work)),
+                                          ("rest", &format::duration(summary.rest)),
+                                          ("percent", &percent(summary.compliance()))]));
+    }
+    lines
+}
+
+fn intensity_lines(intensity: &[Intensity]) -> Vec<String> {
+    let mut lines = vec![tr("chart-intensity")];
+    for hour in intensity {
+        lines.push(tr_args("chart-intensity-row", &[("hour", &hour.hour), ("rate", &format!("{:.0}", hour.per_minute()))]));
+    }
+    lines
+}
+
+fn heatmap_lines(heatmap: &Heatmap) -> Vec<String> {
+    let mut lines = vec![tr("chart-heatmap")];
+    let days = tr("chart-heatmap-days");
+    for (day, hours) in days.split_whitespace().zip(heatmap) {
+        let hours: Vec<_> = hours.iter().enumerate()
+            .filter(|(_, minutes)| **minutes > 0)
+            .map(|(hour, minutes)| tr_args("chart-heatmap-hour", &[("hour", &hour), ("time", &format::duration(Duration::from_secs(u64::from(*minutes) * 60)))]))
+            .collect();
+        if !hours.is_empty() {
+            lines.push(tr_args("chart-heatmap-row", &[("day", &day), ("hours", &hours.join(", "))]));
+        }
+    }
+    if lines.len() == 1 {
+        lines.push(tr("chart-empty"));
+    }
+    lines
+}
+
+/// `from` blended into `to` by `amount`, from 0 to 1.
+fn blend(from: [u8; 3], to: [u8; 3], amount: f32) -> [u8; 3] {
+    let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount.clamp(0.0, 1.0)).round() as u8;
+    [mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2])]
+}
+
+/// A cell for each hour of each weekday, the more accent colored the longer it was worked,
+/// so unbroken stretches show up as long runs.
+fn draw_heatmap(hdc: HDC, area: RECT, heatmap: &Heatmap, theme: &Theme) {
+    text(hdc, area.left, area.top, &tr("chart-heatmap"));
+    let label_width = 40;
+    let top = area.top + LINE_HEIGHT;
+    let row = ((area.bottom - LINE_HEIGHT - top) / 7).max(2);
+    let column = ((area.right - area.left - label_width) / 24).max(2);
+    let days = tr("chart-heatmap-days");
+    for (i, (day, hours)) in days.split_whitespace().zip(heatmap).enumerate() {
+        let y = top + i as i32 * row;
+        text(hdc, area.left, y, day);
+        for (hour, minutes) in hours.iter().enumerate() {
+            let x = area.left + label_width + hour as i32 * column;
+            let color = blend(theme.track, theme.accent, *minutes as f32 / 60.0);
+            fill(hdc, RECT { left: x, top: y, right: x + column - 1, bottom: y + row - 1 }, color);
+        }
+    }
+    for hour in (0..24).step_by(6) {
+        text(hdc, area.left + label_width + hour * column, top + 7 * row + 2, &hour.to_string());
+    }
+}
+
+/// A bar for each hour with input, as high as the input was intense.
+fn draw_intensity(hdc: HDC, area: RECT, intensity: &[Intensity], theme: &Theme) {
+    text(hdc, area.left, area.top, &tr("chart-intensity"));
+    let chart_top = area.top + LINE_HEIGHT;
+    let chart_bottom = area.bottom - LINE_HEIGHT;
+    let max = intensity.iter().map(Intensity::per_minute).fold(1.0, f32::max);
+    let slot = (area.right - area.left) / intensity.len().max(1) as i32;
+    let bar = (slot * 2 / 3).max(2);
+    for (i, hour) in intensity.iter().enumerate() {
+        let x = area.left + i as i32 * slot;
+        let height = (hour.per_minute() / max * (chart_bottom - chart_top) as f32) as i32;
+        fill(hdc, RECT { left: x, top: chart_bottom - height, right: x + bar, bottom: chart_bottom }, theme.accent);
+        text(hdc, x, chart_bottom + 2, &hour.hour.to_string());
+    }
+}
+
+/// Side-by-side work and break bars for each summary, scaled to the largest total.
+fn draw_chart(hdc: HDC, area: RECT, title: &str, summaries: &[Summary], theme: &Theme) {
+    text(hdc, area.left, area.top, title);
+    let chart_top = area.top + LINE_HEIGHT;
+    let chart_bottom = area.bottom - LINE_HEIGHT * 2;
+    if summaries.is_empty() {
+        text(hdc, area.left, chart_top, &tr("chart-empty"));
+        return;
+    }
+
+    let max = summaries.iter()
+        .map(|s| s.work.max(s.rest).as_secs())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let slot = (area.right - area.left) / summaries.len() as i32;
+    let bar = (slot / 3).max(2);
+    let height = (chart_bottom - chart_top) as u64;
+
+    for (i, summary) in summaries.iter().enumerate() {
+        let x = area.left + i as i32 * slot;
+        let work = (summary.work.as_secs() * height / max) as i32;
+        let rest = (summary.rest.as_secs() * height / max) as i32;
+        fill(hdc, RECT { left: x, top: chart_bottom - work, right: x + bar, bottom: chart_bottom }, theme.accent);
+        fill(hdc, RECT { left: x + bar, top: chart_bottom - rest, right: x + 2 * bar, bottom: chart_bottom }, theme.rest);
+        text(hdc, x, chart_bottom + 2, &summary.label);
+        text(hdc, x, chart_bottom + 2 + LINE_HEIGHT, &percent(summary.compliance()));
+    }
+}
+
+/// A window charting work and break time over the last days and weeks.
+#[derive(Default)]
+pub struct StatsWindow {
+    pub window: nwg::Window,
+    pub export_button: nwg::Button,
+    export_dialog: nwg::FileDialog,
+    data: RefCell<StatsData>,
+}
+
+impl StatsWindow {
+    pub fn build(&mut self, parent: &nwg::Window) -> Result<(), nwg::NwgError> {
+        nwg::Window::builder()
+            .flags(nwg::WindowFlags::WINDOW | nwg::WindowFlags::RESIZABLE)
+            .size((560, 600))
+            .title(&tr("stats-title"))
+            .parent(Some(parent))
+            .build(&mut self.window)?;
+
+        nwg::Button::builder()
+            .text(&tr("stats-export"))
+            .position((MARGIN, MARGIN))
+            .size((100, BUTTON_HEIGHT))
+            .parent(&self.window)
+            .build(&mut self.export_button)?;
+
+        nwg::FileDialog::builder()
+            .title(tr("stats-export-title"))
+            .action(nwg::FileDialogAction::Save)
+            .filters("CSV(*.csv)|JSON(*.json)|iCalendar(*.ics)")
+            .build(&mut self.export_dialog)
+    }
+
+    pub fn export(&self) {
+        if !self.export_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let path = match self.export_dialog.get_selected_item() {
+            Ok(path) => path,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+        let path = Path::new(&path);
+        match export::export_to_file(Format::for_path(path), None, path) {
+            Ok(count) => nwg::simple_message(&tr("stats-export-title"), &tr_args("stats-export-done", &[("count", &count), ("path", &path.display())])),
+            Err(e) => nwg::error_message(&tr("stats-export-title"), &tr_args("stats-export-failed", &[("error", &e)]))
+        };
+    }
+
+    pub fn show(&self, categories: &[AppCategory]) {
+        match load_data(categories) {
+            Ok(data) => *self.data.borrow_mut() = data,
+            Err(e) => log::error!("{}", e)
+        }
+        self.window.set_visible(true);
+        self.window.set_focus();
+        self.window.invalidate();
+    }
+
+    /// Everything the window paints, as text.
+    pub fn description(&self) -> String {
+        let data = self.data.borrow();
+        let mut lines = summary_lines(&data);
+        lines.extend(chart_lines(&tr("chart-daily"), &data.days));
+        lines.extend(chart_lines(&tr("chart-weekly"), &data.weeks));
+        if !data.intensity.is_empty() {
+            lines.extend(intensity_lines(&data.intensity));
+        }
+        lines.extend(heatmap_lines(&data.heatmap));
+        lines.join("\n")
+    }
+
+    pub fn hide(&self) {
+        self.window.set_visible(false);
+    }
+
+    pub fn paint(&self, paint: &nwg::PaintData, theme: &Theme) {
+        let ps = paint.begin_paint();
+        let hdc = ps.hdc;
+        let data = self.data.borrow();
+
+        let mut client: RECT = unsafe { zeroed() };
+        if let Some(hwnd) = self.window.handle.hwnd() {
+            unsafe { GetClientRect(hwnd, &mut client) };
+        }
+        fill(hdc, client, theme.background);
+        unsafe {
+            SetBkMode(hdc, TRANSPARENT as i32);
+            SetTextColor(hdc, rgb(theme.text));
+        }
+
+        let left = client.left + MARGIN;
+        let right = client.right - MARGIN;
+        let mut y = client.top + MARGIN * 2 + BUTTON_HEIGHT;
+        for line in summary_lines(&data) {
+            text(hdc, left, y, &line);
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+
+        let charts = if data.intensity.is_empty() { 3 } else { 4 };
+        let chart_height = (client.bottom - MARGIN - y) / charts;
+        draw_chart(hdc, RECT { left, top: y, right, bottom: y + chart_height }, &tr("chart-daily"), &data.days, theme);
+        y += chart_height;
+        draw_chart(hdc, RECT { left, top: y, right, bottom: y + chart_height }, &tr("chart-weekly"), &data.weeks, theme);
+        if !data.intensity.is_empty() {
+            y += chart_height;
+            draw_intensity(hdc, RECT { left, top: y, right, bottom: y + chart_height }, &data.intensity, theme);
+        }
+        y += chart_height;
+        draw_heatmap(hdc, RECT { left, top: y, right, bottom: y + chart_height }, &data.heatmap, theme);
+
+        paint.end_paint(&ps);
+    }
+}
@@ -0,0 +1,77 @@
+use winapi::Interface;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+use winapi::um::combaseapi::CoCreateInstance;
+use winapi::um::shobjidl_core::{CLSID_TaskbarList, ITaskbarList3};
+pub use winapi::um::shobjidl_core::{TBPFLAG, TBPF_ERROR, TBPF_NORMAL, TBPF_PAUSED};
+use winapi::um::winuser::{FlashWindowEx, FLASHWINFO, FLASHW_ALL, FLASHW_STOP, FLASHW_TIMERNOFG};
+use std::mem::size_of;
+use std::ptr;
+
+const PROGRESS_TOTAL: u64 = 1000;
+
+/// Break progress shown on the window's taskbar button.
+///
+/// COM must already be initialized on the calling thread, which `nwg::init` takes care of.
+pub struct TaskbarProgress {
+    list: *mut ITaskbarList3,
+}
+
+impl TaskbarProgress {
+    pub fn new() -> Result<TaskbarProgress, nwg::NwgError> {
+        let mut list: *mut ITaskbarList3 = ptr::null_mut();
+        let result = unsafe {
+            CoCreateInstance(&CLSID_TaskbarList,
+                             ptr::null_mut(),
+                             CLSCTX_INPROC_SERVER,
+                             &ITaskbarList3::uuidof(),
+                             &mut list as *mut _ as *mut _)
+        };
+        if !SUCCEEDED(result) || list.is_null() {
+            return Err(nwg::NwgError::initialization("Failed to create ITaskbarList3"));
+        }
+
+        let taskbar = TaskbarProgress { list };
+        if !SUCCEEDED(unsafe { (*taskbar.list).HrInit() }) {
+            return Err(nwg::NwgError::initialization("Failed to initialize ITaskbarList3"));
+        }
+        Ok(taskbar)
+    }
+
+    /// Failures are ignored: the taskbar button doesn't exist while the window is hidden,
+    /// and the next update will catch up once it is shown again.
+    pub fn set(&self, window: &nwg::Window, state: TBPFLAG, progress: f32) {
+        let hwnd = match window.handle.hwnd() {
+            Some(hwnd) => hwnd,
+            None => return
+        };
+        let completed = (progress.clamp(0.0, 1.0) * PROGRESS_TOTAL as f32) as u64;
+        unsafe {
+            (*self.list).SetProgressState(hwnd, state);
+            (*self.list).SetProgressValue(hwnd, completed, PROGRESS_TOTAL);
+        }
+    }
+}
+
+/// Flashes the window and its taskbar button until it comes to the front, or stops.
+/// A hidden window has no taskbar button to flash.
+pub fn flash(window: &nwg::Window, on: bool) {
+    let hwnd = match window.handle.hwnd() {
+        Some(hwnd) => hwnd,
+        None => return
+    };
+    let mut info = FLASHWINFO {
+        cbSize: size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: if on { FLASHW_ALL | FLASHW_TIMERNOFG } else { FLASHW_STOP },
+        uCount: 0,
+        dwTimeout: 0,
+    };
+    unsafe { FlashWindowEx(&mut info) };
+}
+
+impl Drop for TaskbarProgress {
+    fn drop(&mut self) {
+        unsafe { (*self.list).Release() };
+    }
+}
@@ -0,0 +1,254 @@
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, LRESULT, TRUE, FALSE, UINT, WPARAM};
+use winapi::shared::windef::{HBRUSH, HDC, HWND, RECT};
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::dwmapi::DwmSetWindowAttribute;
+use winapi::um::wingdi::{CreateSolidBrush, DeleteObject, ExcludeClipRect, SetBkColor, SetTextColor, RGB};
+use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+use winapi::um::winuser::{
+    FillRect,
+    GetClientRect,
+    GetSysColor,
+    GetWindowDC,
+    GetWindowRect,
+    MapWindowPoints,
+    RedrawWindow,
+    ReleaseDC,
+    SystemParametersInfoW,
+    COLOR_GRAYTEXT,
+    COLOR_HIGHLIGHT,
+    COLOR_WINDOW,
+    COLOR_WINDOWTEXT,
+    HCF_HIGHCONTRASTON,
+    HIGHCONTRASTW,
+    RDW_ALLCHILDREN,
+    RDW_ERASE,
+    RDW_FRAME,
+    RDW_INVALIDATE,
+    SPI_GETHIGHCONTRAST,
+    WM_CTLCOLORSTATIC,
+    WM_ERASEBKGND,
+    WM_NCPAINT,
+};
+use std::mem::{size_of, zeroed};
+use std::ptr;
+use crate::config::{ThemeConfig, ThemeMode};
+use super::to_wide;
+
+const PERSONALIZE_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
+const DWMWA_USE_IMMERSIVE_DARK_MODE: DWORD = 20;
+const THEME_HANDLER_ID: usize = 0x10001;
+
+const DEFAULT_ACCENT: [u8; 3] = [0x2e, 0x9e, 0x44];
+const REST_COLOR: [u8; 3] = [0x5b, 0x8d, 0xd6];
+const TRACK_COLOR: [u8; 3] = [0xd8, 0xd8, 0xd8];
+const OVERDUE_COLOR: [u8; 3] = [0xd0, 0x30, 0x30];
+const PAUSED_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub dark: bool,
+    /// Made of the system colors of a high contrast theme, which the user picked to be
+    /// able to tell things apart.
+    pub high_contrast: bool,
+    pub background: [u8; 3],
+    pub text: [u8; 3],
+    pub accent: [u8; 3],
+    /// Break time in the statistics charts.
+    pub rest: [u8; 3],
+    /// The part of the tray icon work time hasn't filled yet.
+    pub track: [u8; 3],
+    pub overdue: [u8; 3],
+    pub paused: [u8; 3],
+}
+
+fn system_color(index: i32) -> [u8; 3] {
+    let color = unsafe { GetSysColor(index) };
+    [color as u8, (color >> 8) as u8, (color >> 16) as u8]
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme {
+            dark: false,
+            high_contrast: false,
+            background: [0xff, 0xff, 0xff],
+            text: [0x1a, 0x1a, 0x1a],
+            accent: DEFAULT_ACCENT,
+            rest: REST_COLOR,
+            track: TRACK_COLOR,
+            overdue: OVERDUE_COLOR,
+            paused: PAUSED_COLOR,
+        }
+    }
+
+    pub fn dark() -> Theme {
+        Theme {
+            dark: true,
+            background: [0x20, 0x20, 0x20],
+            text: [0xf0, 0xf0, 0xf0],
+            ..Theme::light()
+        }
+    }
+
+    /// The colors of the high contrast theme in use, with only as many of them as the
+    /// user can tell apart: highlight for work, text for breaks and overdue work.
+    pub fn high_contrast() -> Theme {
+        let background = system_color(COLOR_WINDOW);
+        let text = system_color(COLOR_WINDOWTEXT);
+        let brightness = background.iter().map(|&c| c as u32).sum::<u32>();
+        Theme {
+            dark: brightness < 3 * 0x80,
+            high_contrast: true,
+            background,
+            text,
+            accent: system_color(COLOR_HIGHLIGHT),
+            rest: text,
+            track: background,
+            overdue: text,
+            paused: system_color(COLOR_GRAYTEXT),
+        }
+    }
+
+    /// A high contrast theme wins over the config, which can't know its colors.
+    pub fn from_config(config: &ThemeConfig) -> Theme {
+        if high_contrast_enabled() {
+            return Theme::high_contrast();
+        }
+        let dark = match config.mode {
+            ThemeMode::System => system_uses_dark_mode(),
+            ThemeMode::Light => false,
+            ThemeMode::Dark => true
+        };
+        let mut theme = if dark { Theme::dark() } else { Theme::light() };
+        if let Some(accent) = &config.accent {
+            match parse_color(accent) {
+                Some(color) => theme.accent = color,
+                None => log::error!("invalid accent color {:?}", accent)
+            }
+        }
+        theme
+    }
+}
+
+/// Parses `#rrggbb` colors.
+pub fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn high_contrast_enabled() -> bool {
+    let mut contrast: HIGHCONTRASTW = unsafe { zeroed() };
+    contrast.cbSize = size_of::<HIGHCONTRASTW>() as UINT;
+    let result = unsafe {
+        SystemParametersInfoW(SPI_GETHIGHCONTRAST, contrast.cbSize, &mut contrast as *mut _ as *mut _, 0)
+    };
+    result != 0 && contrast.dwFlags & HCF_HIGHCONTRASTON != 0
+}
+
+/// Reads the "Choose your app mode" setting, defaulting to light when it's missing.
+pub fn system_uses_dark_mode() -> bool {
+    let key = to_wide(PERSONALIZE_KEY);
+    let value_name = to_wide("AppsUseLightTheme");
+    let mut value: DWORD = 1;
+    let mut size = size_of::<DWORD>() as DWORD;
+    let result = unsafe {
+        RegGetValueW(HKEY_CURRENT_USER,
+                     key.as_ptr(),
+                     value_name.as_ptr(),
+                     RRF_RT_REG_DWORD,
+                     ptr::null_mut(),
+                     &mut value as *mut DWORD as *mut _,
+                     &mut size)
+    };
+    result == ERROR_SUCCESS as i32 && value == 0
+}
+
+fn rgb(color: [u8; 3]) -> u32 {
+    RGB(color[0], color[1], color[2])
+}
+
+/// Fills the margins a label keeps around its text, which it paints itself.
+unsafe fn paint_frame(hwnd: HWND, brush: HBRUSH) {
+    let mut window: RECT = zeroed();
+    let mut client: RECT = zeroed();
+    GetWindowRect(hwnd, &mut window);
+    GetClientRect(hwnd, &mut client);
+    MapWindowPoints(hwnd, ptr::null_mut(), &mut client as *mut RECT as *mut _, 2);
+    let dc = GetWindowDC(hwnd);
+    ExcludeClipRect(dc, client.left - window.left, client.top - window.top,
+                    client.right - window.left, client.bottom - window.top);
+    let frame = RECT { left: 0, top: 0, right: window.right - window.left, bottom: window.bottom - window.top };
+    FillRect(dc, &frame, brush);
+    ReleaseDC(hwnd, dc);
+}
+
+/// Paints the window background and the static controls on it with the theme colors.
+pub struct ThemePainter {
+    brush: HBRUSH,
+    handlers: Vec<nwg::RawEventHandler>,
+}
+
+impl ThemePainter {
+    /// Can be applied again with another theme once the previous painter is unbound.
+    pub fn apply(window: &nwg::Window, labels: &[&nwg::Label], theme: Theme) -> Result<ThemePainter, nwg::NwgError> {
+        let brush = unsafe { CreateSolidBrush(rgb(theme.background)) };
+        let mut painter = ThemePainter { brush, handlers: Vec::new() };
+        if let Some(hwnd) = window.handle.hwnd() {
+            let dark: BOOL = if theme.dark { TRUE } else { FALSE };
+            unsafe {
+                DwmSetWindowAttribute(hwnd,
+                                      DWMWA_USE_IMMERSIVE_DARK_MODE,
+                                      &dark as *const BOOL as *const _,
+                                      size_of::<BOOL>() as DWORD);
+            }
+        }
+
+        painter.handlers.push(nwg::bind_raw_event_handler(&window.handle, THEME_HANDLER_ID, move |hwnd: HWND, msg, w: WPARAM, _l: LPARAM| {
+            match msg {
+                WM_ERASEBKGND => unsafe {
+                    let mut rect: RECT = zeroed();
+                    GetClientRect(hwnd, &mut rect);
+                    FillRect(w as HDC, &rect, brush);
+                    Some(1)
+                },
+                WM_CTLCOLORSTATIC => unsafe {
+                    SetTextColor(w as HDC, rgb(theme.text));
+                    SetBkColor(w as HDC, rgb(theme.background));
+                    Some(brush as LRESULT)
+                },
+                _ => None
+            }
+        })?);
+        for label in labels {
+            painter.handlers.push(nwg::bind_raw_event_handler(&label.handle, THEME_HANDLER_ID, move |hwnd: HWND, msg, _w, _l| {
+                if msg == WM_NCPAINT {
+                    unsafe { paint_frame(hwnd, brush) };
+                    return Some(0);
+                }
+                None
+            })?);
+        }
+
+        if let Some(hwnd) = window.handle.hwnd() {
+            unsafe { RedrawWindow(hwnd, ptr::null(), ptr::null_mut(), RDW_INVALIDATE | RDW_ERASE | RDW_FRAME | RDW_ALLCHILDREN) };
+        }
+        Ok(painter)
+    }
+
+    /// Must be called while the window still exists.
+    pub fn unbind(&mut self) {
+        for handler in self.handlers.drain(..) {
+            let _ = nwg::unbind_raw_event_handler(&handler);
+        }
+    }
+}
+
+impl Drop for ThemePainter {
+    fn drop(&mut self) {
+        unsafe { DeleteObject(self.brush as _) };
+    }
+}
@@ -0,0 +1,243 @@
+// The names follow windows.ui.notifications.h and windows.data.xml.dom.h.
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+use winapi::RIDL;
+use winapi::Interface;
+use winapi::shared::minwindef::{DWORD, UINT};
+use winapi::shared::winerror::{HRESULT, SUCCEEDED};
+use winapi::um::unknwnbase::IUnknown;
+use winapi::um::winnt::{PCWSTR, REG_SZ};
+use winapi::um::winreg::{RegSetKeyValueW, HKEY_CURRENT_USER};
+use winapi::winrt::hstring::HSTRING;
+use winapi::winrt::inspectable::{IInspectable, IInspectableVtbl};
+use winapi::winrt::roapi::{RoActivateInstance, RoGetActivationFactory};
+use winapi::winrt::winstring::{WindowsCreateString, WindowsDeleteString};
+use std::env;
+use std::fs;
+use std::io;
+use std::ptr;
+use crate::config::{data_dir, ClickAction};
+use crate::notifications::{Notification, NotificationBackend};
+use super::to_wide;
+
+/// Ties the toasts to pauza in the Action Center, with the name and icon registered for it.
+const APP_ID: &str = "Pauza";
+const APP_ID_KEY: &str = "Software\\Classes\\AppUserModelId\\Pauza";
+/// Clicks on toasts start `pauza pauza:...`, which passes them on to the running instance.
+const PROTOCOL_KEY: &str = "Software\\Classes\\pauza";
+const PROTOCOL: &str = "pauza:";
+/// Each reminder replaces the one before in the Action Center.
+const TAG: &str = "break";
+const ICON: &[u8] = include_bytes!("../../pauza.ico");
+
+RIDL!{#[uuid(0xf7f3a506, 0x1e87, 0x42d6, 0xbc, 0xfb, 0xb8, 0xc8, 0x09, 0xfa, 0x54, 0x94)]
+interface IXmlDocument(IXmlDocumentVtbl): IInspectable(IInspectableVtbl) {}}
+
+RIDL!{#[uuid(0x6cd0e74e, 0xee65, 0x4489, 0x9e, 0xbf, 0xca, 0x43, 0xe8, 0x7b, 0xa6, 0x37)]
+interface IXmlDocumentIO(IXmlDocumentIOVtbl): IInspectable(IInspectableVtbl) {
+    fn LoadXml(
+        xml: HSTRING,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x997e2675, 0x059e, 0x4e60, 0x8b, 0x06, 0x17, 0x60, 0x91, 0x7c, 0x8b, 0x80)]
+interface IToastNotification(IToastNotificationVtbl): IInspectable(IInspectableVtbl) {}}
+
+RIDL!{#[uuid(0x9dfb9fd1, 0x143a, 0x490e, 0x90, 0xbf, 0xb9, 0xfb, 0xa7, 0x13, 0x2d, 0xe7)]
+interface IToastNotification2(IToastNotification2Vtbl): IInspectable(IInspectableVtbl) {
+    fn put_Tag(
+        value: HSTRING,
+    ) -> HRESULT,
+    fn get_Tag(
+        value: *mut HSTRING,
+    ) -> HRESULT,
+    fn put_Group(
+        value: HSTRING,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x04124b20, 0x82c6, 0x4229, 0xb1, 0x09, 0xfd, 0x9e, 0xd4, 0x66, 0x2b, 0x53)]
+interface IToastNotificationFactory(IToastNotificationFactoryVtbl): IInspectable(IInspectableVtbl) {
+    fn CreateToastNotification(
+        content: *mut IXmlDocument,
+        value: *mut *mut IToastNotification,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x75927b93, 0x03f3, 0x41ec, 0x91, 0xd3, 0x6e, 0x5b, 0xac, 0x1b, 0x38, 0xe7)]
+interface IToastNotifier(IToastNotifierVtbl): IInspectable(IInspectableVtbl) {
+    fn Show(
+        notification: *mut IToastNotification,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x50ac103f, 0xd235, 0x4598, 0xbb, 0xef, 0x98, 0xfe, 0x4d, 0x1a, 0x3a, 0xd4)]
+interface IToastNotificationManagerStatics(IToastNotificationManagerStaticsVtbl): IInspectable(IInspectableVtbl) {
+    fn CreateToastNotifier(
+        value: *mut *mut IToastNotifier,
+    ) -> HRESULT,
+    fn CreateToastNotifierWithId(
+        applicationId: HSTRING,
+        value: *mut *mut IToastNotifier,
+    ) -> HRESULT,
+}}
+
+#[link(name = "shell32")]
+extern "system" {
+    fn SetCurrentProcessExplicitAppUserModelID(id: PCWSTR) -> HRESULT;
+}
+
+pub(super) fn check(result: HRESULT) -> io::Result<()> {
+    if SUCCEEDED(result) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("HRESULT {:#010x}", result)))
+    }
+}
+
+struct HString(HSTRING);
+
+impl HString {
+    fn new(text: &str) -> io::Result<HString> {
+        let wide: Vec<u16> = text.encode_utf16().collect();
+        let mut string = ptr::null_mut();
+        check(unsafe { WindowsCreateString(wide.as_ptr(), wide.len() as UINT, &mut string) })?;
+        Ok(HString(string))
+    }
+}
+
+impl Drop for HString {
+    fn drop(&mut self) {
+        unsafe { WindowsDeleteString(self.0) };
+    }
+}
+
+/// Owns a reference to a COM object.
+pub(super) struct Com<T: Interface>(pub(super) *mut T);
+
+impl<T: Interface> Com<T> {
+    fn cast<U: Interface>(&self) -> io::Result<Com<U>> {
+        let mut other: *mut U = ptr::null_mut();
+        let unknown = self.0 as *mut IUnknown;
+        check(unsafe { (*unknown).QueryInterface(&U::uuidof(), &mut other as *mut _ as *mut _) })?;
+        Ok(Com(other))
+    }
+}
+
+impl<T: Interface> Drop for Com<T> {
+    fn drop(&mut self) {
+        unsafe { (*(self.0 as *mut IUnknown)).Release() };
+    }
+}
+
+pub(super) fn factory<T: Interface>(class: &str) -> io::Result<Com<T>> {
+    let class = HString::new(class)?;
+    let mut factory: *mut T = ptr::null_mut();
+    check(unsafe { RoGetActivationFactory(class.0, &T::uuidof(), &mut factory as *mut _ as *mut _) })?;
+    Ok(Com(factory))
+}
+
+fn set_value(key: &str, name: Option<&str>, value: &str) -> io::Result<()> {
+    let key = to_wide(key);
+    let name = name.map(to_wide);
+    let value = to_wide(value);
+    let result = unsafe {
+        RegSetKeyValueW(HKEY_CURRENT_USER,
+                        key.as_ptr(),
+                        name.as_ref().map_or(ptr::null(), |name| name.as_ptr()),
+                        REG_SZ,
+                        value.as_ptr() as *const _,
+                        (value.len() * 2) as DWORD)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(result))
+    }
+}
+
+/// Registers the AppUserModelID toasts are shown under, which keeps them in the Action
+/// Center until dismissed, and the `pauza:` protocol clicks on them are sent through.
+pub fn register() -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let icon = data_dir().join("pauza.ico");
+    if !icon.exists() {
+        fs::create_dir_all(data_dir())?;
+        fs::write(&icon, ICON)?;
+    }
+    set_value(APP_ID_KEY, Some("DisplayName"), "Pauza")?;
+    set_value(APP_ID_KEY, Some("IconUri"), &icon.to_string_lossy())?;
+    set_value(PROTOCOL_KEY, None, "URL:Pauza")?;
+    set_value(PROTOCOL_KEY, Some("URL Protocol"), "")?;
+    set_value(&format!("{}\\shell\\open\\command", PROTOCOL_KEY), None, &format!("\"{}\" \"%1\"", exe.display()))?;
+    let id = to_wide(APP_ID);
+    check(unsafe { SetCurrentProcessExplicitAppUserModelID(id.as_ptr()) })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A toast with a title and a message, sending `launch` when clicked. The button, if any,
+/// is a label and the protocol arguments it is sent with, like `start-break`.
+/// COM must already be initialized on the calling thread.
+fn show(title: &str, message: &str, launch: &str, button: Option<(&str, &str)>) -> io::Result<()> {
+    let actions = match button {
+        Some((label, arguments)) => format!(
+            "<actions><action content=\"{}\" arguments=\"{}{}\" activationType=\"protocol\"/></actions>",
+            escape(label), PROTOCOL, escape(arguments)),
+        None => String::new()
+    };
+    // Toasts have room for two lines under the title.
+    let lines: String = message.lines().take(2)
+        .map(|line| format!("<text>{}</text>", escape(line)))
+        .collect();
+    // The sound is played with pauza's own sound settings.
+    let xml = format!(
+        "<toast launch=\"{}{}\" activationType=\"protocol\"><visual><binding template=\"ToastGeneric\">\
+         <text>{}</text>{}</binding></visual>{}<audio silent=\"true\"/></toast>",
+        PROTOCOL, escape(launch), escape(title), lines, actions);
+
+    let class = HString::new("Windows.Data.Xml.Dom.XmlDocument")?;
+    let mut instance: *mut IInspectable = ptr::null_mut();
+    check(unsafe { RoActivateInstance(class.0, &mut instance) })?;
+    let document = Com(instance);
+    let xml = HString::new(&xml)?;
+    let io: Com<IXmlDocumentIO> = document.cast()?;
+    check(unsafe { (*io.0).LoadXml(xml.0) })?;
+    let document: Com<IXmlDocument> = document.cast()?;
+
+    let toasts: Com<IToastNotificationFactory> = factory("Windows.UI.Notifications.ToastNotification")?;
+    let mut toast = ptr::null_mut();
+    check(unsafe { (*toasts.0).CreateToastNotification(document.0, &mut toast) })?;
+    let toast = Com(toast);
+    let tag = HString::new(TAG)?;
+    let group = HString::new(APP_ID)?;
+    let tagged: Com<IToastNotification2> = toast.cast()?;
+    check(unsafe { (*tagged.0).put_Tag(tag.0) })?;
+    check(unsafe { (*tagged.0).put_Group(group.0) })?;
+
+    let manager: Com<IToastNotificationManagerStatics> = factory("Windows.UI.Notifications.ToastNotificationManager")?;
+    let id = HString::new(APP_ID)?;
+    let mut notifier = ptr::null_mut();
+    check(unsafe { (*manager.0).CreateToastNotifierWithId(id.0, &mut notifier) })?;
+    let notifier = Com(notifier);
+    check(unsafe { (*notifier.0).Show(toast.0) })
+}
+
+/// Toasts that do `click` when clicked, with a button if given, as a label and the
+/// protocol arguments it sends.
+pub struct Toast<'a> {
+    pub click: ClickAction,
+    pub button: Option<(&'a str, &'a str)>,
+}
+
+impl NotificationBackend for Toast<'_> {
+    fn name(&self) -> &'static str {
+        "toast"
+    }
+
+    fn show(&self, notification: &Notification) -> Result<(), String> {
+        show(&notification.title, &notification.message, self.click.arguments(), self.button).map_err(|e| e.to_string())
+    }
+}
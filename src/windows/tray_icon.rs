@@ -0,0 +1,209 @@
+use winapi::shared::minwindef::TRUE;
+use winapi::shared::windef::HBITMAP;
+use winapi::um::wingdi::{
+    CreateBitmap,
+    CreateDIBSection,
+    DeleteObject,
+    BITMAPINFO,
+    BITMAPINFOHEADER,
+    BI_RGB,
+    DIB_RGB_COLORS,
+};
+use winapi::um::winuser::{
+    CreateIconIndirect,
+    DestroyIcon,
+    GetSystemMetrics,
+    ICONINFO,
+    SM_CXSMICON,
+};
+use std::f32::consts::PI;
+use std::mem::{size_of, zeroed};
+use std::ptr;
+use super::theme::Theme;
+
+const PROGRESS_STEPS: u32 = 24;
+const OUTLINE_WIDTH: f32 = 1.5;
+/// The radius of the hole punched in the middle in privacy mode, out of the icon's.
+const PRIVACY_HOLE: f32 = 0.45;
+/// Frames of one pulse of the countdown, each shown for `PULSE_INTERVAL_MS`. Twice a second
+/// is enough for the eye, and keeps rendering and swapping the icon from waking the CPU more.
+pub const PULSE_FRAMES: u32 = 4;
+pub const PULSE_INTERVAL_MS: u32 = 500;
+/// How far the pie fades toward the track at the low of a pulse.
+const PULSE_DEPTH: f32 = 0.6;
+/// Half the width of the strokes of the marks punched out of the icon, out of its radius.
+const STROKE: f32 = 0.16;
+
+/// Each state but working and on a break has its own mark punched out of the icon, so
+/// it can be told apart by shape as well as by color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IconState {
+    Working(u32),
+    /// Working in the last minutes before the break, at a frame of the pulse.
+    Countdown(u32, u32),
+    /// The break is due, marked with an exclamation mark.
+    Overdue,
+    /// Marked with the two bars of a pause button.
+    Paused(u32),
+    /// A full disc in the color of breaks.
+    OnBreak,
+    /// Idle time can't be told reliably, marked with a bar across.
+    Degraded(u32),
+}
+
+impl IconState {
+    pub fn working(progress: f32) -> IconState {
+        IconState::Working(quantize(progress))
+    }
+
+    pub fn paused(progress: f32) -> IconState {
+        IconState::Paused(quantize(progress))
+    }
+
+    pub fn degraded(progress: f32) -> IconState {
+        IconState::Degraded(quantize(progress))
+    }
+
+    fn fill(&self, theme: &Theme) -> (f32, [u8; 3]) {
+        match *self {
+            IconState::Working(step) => (step as f32 / PROGRESS_STEPS as f32, theme.accent),
+            IconState::Countdown(step, frame) => {
+                let fade = (1.0 - (2.0 * PI * frame as f32 / PULSE_FRAMES as f32).cos()) / 2.0 * PULSE_DEPTH;
+                (step as f32 / PROGRESS_STEPS as f32, blend(theme.accent, theme.track, fade))
+            },
+            IconState::Overdue => (1.0, theme.overdue),
+            IconState::Paused(step) => (step as f32 / PROGRESS_STEPS as f32, theme.paused),
+            IconState::OnBreak => (1.0, theme.rest),
+            IconState::Degraded(step) => (step as f32 / PROGRESS_STEPS as f32, theme.overdue),
+        }
+    }
+
+    /// Whether a point, from -1 to 1 across the icon and down it, is punched out by the
+    /// state's mark.
+    fn is_marked(&self, x: f32, y: f32) -> bool {
+        match self {
+            IconState::Working(_) | IconState::Countdown(..) | IconState::OnBreak => false,
+            IconState::Overdue => x.abs() < STROKE && ((-0.6..0.15).contains(&y) || (0.35..0.6).contains(&y)),
+            IconState::Paused(_) => (STROKE..STROKE * 3.0).contains(&x.abs()) && y.abs() < 0.5,
+            IconState::Degraded(_) => y.abs() < STROKE && x.abs() < 0.6,
+        }
+    }
+}
+
+fn blend(from: [u8; 3], to: [u8; 3], amount: f32) -> [u8; 3] {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    [mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2])]
+}
+
+fn quantize(progress: f32) -> u32 {
+    (progress.clamp(0.0, 1.0) * PROGRESS_STEPS as f32).round() as u32
+}
+
+/// An icon rendered at runtime, destroyed when dropped.
+pub struct StateIcon {
+    pub icon: nwg::Icon,
+}
+
+impl Drop for StateIcon {
+    fn drop(&mut self) {
+        if !self.icon.handle.is_null() {
+            unsafe { DestroyIcon(self.icon.handle as _) };
+        }
+    }
+}
+
+/// Draws a pie filling up clockwise from the top as the work time progresses, outlined
+/// in high contrast so it stands out from the taskbar, and a ring in privacy mode, with
+/// the mark of the state punched out.
+fn render_pixels(state: IconState, theme: &Theme, private: bool, size: usize) -> Vec<u8> {
+    let (progress, color) = state.fill(theme);
+    let mut pixels = vec![0u8; size * size * 4];
+    let center = size as f32 / 2.0;
+    let radius = center - 0.5;
+
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f32 + 0.5 - center;
+            let dy = y as f32 + 0.5 - center;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let mut coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            if private {
+                coverage = coverage.min((distance - radius * PRIVACY_HOLE + 0.5).clamp(0.0, 1.0));
+            }
+            if state.is_marked(dx / radius, dy / radius) {
+                coverage = 0.0;
+            }
+            if coverage == 0.0 {
+                continue;
+            }
+
+            let mut angle = dx.atan2(-dy);
+            if angle < 0.0 {
+                angle += 2.0 * PI;
+            }
+            let rgb = if theme.high_contrast && distance > radius - OUTLINE_WIDTH {
+                theme.text
+            } else if angle / (2.0 * PI) < progress {
+                color
+            } else {
+                theme.track
+            };
+
+            let i = (y * size + x) * 4;
+            pixels[i] = rgb[2];
+            pixels[i + 1] = rgb[1];
+            pixels[i + 2] = rgb[0];
+            pixels[i + 3] = (coverage * 255.0) as u8;
+        }
+    }
+
+    pixels
+}
+
+pub fn render(state: IconState, theme: &Theme, private: bool) -> Result<StateIcon, nwg::NwgError> {
+    let size = match unsafe { GetSystemMetrics(SM_CXSMICON) } {
+        s if s > 0 => s as usize,
+        _ => 16
+    };
+    let pixels = render_pixels(state, theme, private, size);
+
+    unsafe {
+        let mut info: BITMAPINFO = zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = size as i32;
+        info.bmiHeader.biHeight = -(size as i32);
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+
+        let mut bits = ptr::null_mut();
+        let color: HBITMAP = CreateDIBSection(ptr::null_mut(), &info, DIB_RGB_COLORS, &mut bits, ptr::null_mut(), 0);
+        if color.is_null() || bits.is_null() {
+            return Err(nwg::NwgError::resource_create("Failed to create tray icon bitmap"));
+        }
+        ptr::copy_nonoverlapping(pixels.as_ptr(), bits as *mut u8, pixels.len());
+
+        let mask_bits = vec![0u8; size.div_ceil(16) * 2 * size];
+        let mask = CreateBitmap(size as i32, size as i32, 1, 1, mask_bits.as_ptr() as _);
+
+        let mut icon_info = ICONINFO {
+            fIcon: TRUE,
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let handle = CreateIconIndirect(&mut icon_info);
+
+        DeleteObject(color as _);
+        DeleteObject(mask as _);
+
+        if handle.is_null() {
+            return Err(nwg::NwgError::resource_create("Failed to create tray icon"));
+        }
+
+        let mut icon = nwg::Icon::default();
+        icon.handle = handle as _;
+        Ok(StateIcon { icon })
+    }
+}